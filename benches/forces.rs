@@ -0,0 +1,84 @@
+//! Force computation timings vs body count, run with `cargo bench`.
+//! The systems are run on a bare `World`, without Bevy's render loop.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use nbody::plugins::nbody::{
+    compute_accelerations, update_acceleration, BodyBundle, ExtendedBody, ExternalField,
+    ForceCutoff, ForceMode, Gravity, GroupIsolation, Mass, MaxAcceleration, SofteningKernel,
+};
+
+const BODY_COUNTS: [usize; 4] = [100, 500, 1000, 2000];
+const WARMUP: Duration = Duration::from_millis(500);
+const MEASURE: Duration = Duration::from_secs(2);
+
+/// Random bodies in a 100-unit cube
+fn random_world(n: usize) -> World {
+    let mut rng = rand::thread_rng();
+    let mut world = World::default();
+    world.insert_resource(Gravity(1.0));
+    world.insert_resource(SofteningKernel::default());
+    world.insert_resource(ForceCutoff::default());
+    world.insert_resource(ForceMode::default());
+    world.insert_resource(MaxAcceleration::default());
+    world.insert_resource(ExtendedBody::default());
+    world.insert_resource(GroupIsolation::default());
+    world.insert_resource(ExternalField::default());
+    for _ in 0..n {
+        let pos = Vec3::new(
+            rng.gen_range(-50.0..50.0),
+            rng.gen_range(-50.0..50.0),
+            rng.gen_range(-50.0..50.0),
+        );
+        world
+            .spawn()
+            .insert_bundle(BodyBundle::new(rng.gen_range(1.0..10.0), pos, Vec3::ZERO));
+    }
+    world
+}
+
+/// Runs `f` for the warmup period, then returns the mean duration of a run
+fn bench<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    while start.elapsed() < WARMUP {
+        f();
+    }
+
+    let mut iterations = 0;
+    let start = Instant::now();
+    while start.elapsed() < MEASURE {
+        f();
+        iterations += 1;
+    }
+    start.elapsed() / iterations
+}
+
+fn main() {
+    println!("{:<24} {:>8} {:>14}", "benchmark", "bodies", "time/step");
+    for &n in BODY_COUNTS.iter() {
+        let mut world = random_world(n);
+
+        let (masses, positions): (Vec<f32>, Vec<Vec3>) = world
+            .query::<(&Mass, &Transform)>()
+            .iter(&world)
+            .map(|(mass, transform)| (mass.0, transform.translation))
+            .unzip();
+        let mean = bench(|| {
+            black_box(compute_accelerations(
+                &masses,
+                &positions,
+                1.0,
+                SofteningKernel::None,
+                None,
+            ));
+        });
+        println!("{:<24} {:>8} {:>14?}", "compute_accelerations", n, mean);
+
+        let mut stage = SystemStage::single_threaded().with_system(update_acceleration.system());
+        let mean = bench(|| stage.run(&mut world));
+        println!("{:<24} {:>8} {:>14?}", "update_acceleration", n, mean);
+    }
+}
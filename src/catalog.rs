@@ -0,0 +1,145 @@
+//! Physical data of the named bodies of the Solar System, e.g. to reference them by name in a scene:
+//! ```ignore
+//! let mut earth = catalog_body("Earth").unwrap();
+//! earth.position = (1.0, 0.0, 0.0);
+//! ```
+//! The bodies are described in the units of the `solar` startup: masses in 10^24 kg
+//! and radii in units of `RADIUS_SCALE` km.
+use crate::scene::BodyDescription;
+
+/// Kilometers per rendered unit of radius
+pub const RADIUS_SCALE: f32 = 10_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CatalogEntry {
+    pub name: &'static str,
+    /// Mass in 10^24 kg
+    pub mass: f32,
+    /// Mean radius in km
+    pub radius_km: f32,
+    /// Representative RGB color
+    pub color: (f32, f32, f32),
+    pub star: bool,
+}
+
+/// Masses and mean radii from JPL Horizons
+pub const CATALOG: &[CatalogEntry] = &[
+    CatalogEntry {
+        name: "Sun",
+        mass: 1_988_500.0,
+        radius_km: 695_700.0,
+        color: (1.0, 1.0, 0.0),
+        star: true,
+    },
+    CatalogEntry {
+        name: "Mercury",
+        mass: 0.3302,
+        radius_km: 2440.0,
+        color: (1.0, 0.27, 0.0),
+        star: false,
+    },
+    CatalogEntry {
+        name: "Venus",
+        mass: 4.868,
+        radius_km: 6051.84,
+        color: (1.0, 0.65, 0.0),
+        star: false,
+    },
+    CatalogEntry {
+        name: "Earth",
+        mass: 5.97219,
+        radius_km: 6371.01,
+        color: (0.0, 0.0, 1.0),
+        star: false,
+    },
+    CatalogEntry {
+        name: "Moon",
+        mass: 0.07349,
+        radius_km: 1737.53,
+        color: (0.75, 0.75, 0.75),
+        star: false,
+    },
+    CatalogEntry {
+        name: "Mars",
+        mass: 0.64171,
+        radius_km: 3389.92,
+        color: (1.0, 0.0, 0.0),
+        star: false,
+    },
+    CatalogEntry {
+        name: "Jupiter",
+        mass: 1898.187,
+        radius_km: 69911.0,
+        color: (1.0, 0.89, 0.77),
+        star: false,
+    },
+    CatalogEntry {
+        name: "Saturn",
+        mass: 568.34,
+        radius_km: 58232.0,
+        color: (1.0, 0.84, 0.0),
+        star: false,
+    },
+    CatalogEntry {
+        name: "Uranus",
+        mass: 86.813,
+        radius_km: 25362.0,
+        color: (0.5, 1.0, 0.83),
+        star: false,
+    },
+    CatalogEntry {
+        name: "Neptune",
+        mass: 102.4126,
+        radius_km: 24622.0,
+        color: (0.0, 0.0, 1.0),
+        star: false,
+    },
+    CatalogEntry {
+        name: "Pluto",
+        mass: 0.013030,
+        radius_km: 1188.3,
+        color: (0.5, 0.5, 0.5),
+        star: false,
+    },
+];
+
+/// Catalog entry of the body named `name`, case insensitive
+pub fn catalog_entry(name: &str) -> Option<&'static CatalogEntry> {
+    CATALOG
+        .iter()
+        .find(|entry| entry.name.eq_ignore_ascii_case(name))
+}
+
+/// Body named `name` at rest at the origin, with its mass, radius and color from the catalog
+pub fn catalog_body(name: &str) -> Option<BodyDescription> {
+    catalog_entry(name).map(|entry| BodyDescription {
+        name: Some(entry.name.to_string()),
+        mass: entry.mass,
+        position: (0.0, 0.0, 0.0),
+        velocity: (0.0, 0.0, 0.0),
+        radius: entry.radius_km / RADIUS_SCALE,
+        color: Some(entry.color),
+        texture: None,
+        star: entry.star,
+        comet: false,
+        accretor: false,
+        trail: None,
+        temperature: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jupiter_from_the_catalog() {
+        let jupiter = catalog_body("jupiter").unwrap();
+        assert_eq!(jupiter.name.as_deref(), Some("Jupiter"));
+        assert_eq!(jupiter.mass, 1898.187);
+        assert!((jupiter.radius - 6.9911).abs() < 1e-6);
+        assert!(!jupiter.star);
+        assert!(catalog_body("Sun").unwrap().star);
+        assert_eq!(catalog_body("Vulcan"), None);
+    }
+}
@@ -0,0 +1,1014 @@
+//! Integration of a system of bodies outside of Bevy's ECS,
+//! e.g. for trajectory predictions or batch experiments.
+use bevy::math::Vec3;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::StandardNormal;
+
+use crate::plugins::diagnostics::{kinetic_energy, potential_energy};
+use crate::plugins::nbody::{
+    accelerations_of, block_levels, block_step, compute_accelerations, implicit_midpoint_step,
+    ExternalField, Integrator, SofteningKernel,
+};
+
+/// Force law parameters, mirroring the `Gravity`, `SofteningKernel`, `ForceCutoff` and `ExternalField` resources
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForceParams {
+    pub g: f32,
+    pub softening: SofteningKernel,
+    pub cutoff: Option<f32>,
+    pub external: ExternalField,
+}
+
+impl ForceParams {
+    /// Accelerations of the bodies of masses `masses` at `positions`
+    pub fn accelerations(&self, masses: &[f32], positions: &[Vec3]) -> Vec<Vec3> {
+        let mut accelerations =
+            compute_accelerations(masses, positions, self.g, self.softening, self.cutoff);
+        self.external.apply(positions, &mut accelerations);
+        accelerations
+    }
+}
+
+/// Snapshot of the bodies of a simulation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SystemState {
+    pub masses: Vec<f32>,
+    pub positions: Vec<Vec3>,
+    pub velocities: Vec<Vec3>,
+}
+
+impl SystemState {
+    pub fn len(&self) -> usize {
+        self.masses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.masses.is_empty()
+    }
+
+    pub fn push(&mut self, mass: f32, pos: Vec3, vel: Vec3) {
+        self.masses.push(mass);
+        self.positions.push(pos);
+        self.velocities.push(vel);
+    }
+
+    pub fn accelerations(&self, params: &ForceParams) -> Vec<Vec3> {
+        params.accelerations(&self.masses, &self.positions)
+    }
+
+    fn kick(&mut self, accelerations: &[Vec3], dt: f32) {
+        for (vel, acc) in self.velocities.iter_mut().zip(accelerations) {
+            *vel += *acc * dt;
+        }
+    }
+
+    fn drift(&mut self, dt: f32) {
+        for (pos, vel) in self.positions.iter_mut().zip(self.velocities.iter()) {
+            *pos += *vel * dt;
+        }
+    }
+
+    fn drift_by(&mut self, velocities: &[Vec3], dt: f32) {
+        for (pos, vel) in self.positions.iter_mut().zip(velocities) {
+            *pos += *vel * dt;
+        }
+    }
+
+    /// Advances the system by `dt`.
+    /// Unlike the physics systems, the leapfrog velocities are kept in sync with the positions,
+    /// at the cost of a second force computation.
+    pub fn step(&mut self, params: &ForceParams, integrator: Integrator, dt: f32) {
+        match integrator {
+            Integrator::SemiImplicitEuler => {
+                self.kick(&self.accelerations(params), dt);
+                self.drift(dt);
+            }
+            Integrator::Leapfrog => {
+                self.kick(&self.accelerations(params), dt / 2.0);
+                self.drift(dt);
+                self.kick(&self.accelerations(params), dt / 2.0);
+            }
+            Integrator::VelocityVerlet => {
+                let accelerations = self.accelerations(params);
+                for ((pos, vel), acc) in self
+                    .positions
+                    .iter_mut()
+                    .zip(self.velocities.iter())
+                    .zip(&accelerations)
+                {
+                    *pos += *vel * dt + *acc * dt * dt / 2.0;
+                }
+                let new_accelerations = self.accelerations(params);
+                for ((vel, acc), new_acc) in self
+                    .velocities
+                    .iter_mut()
+                    .zip(accelerations)
+                    .zip(new_accelerations)
+                {
+                    *vel += (acc + new_acc) * dt / 2.0;
+                }
+            }
+            Integrator::RungeKutta4 => self.runge_kutta_step(params, dt),
+            Integrator::BlockStep { min_dt, max_levels } => {
+                self.block_step(params, dt, min_dt, max_levels);
+            }
+            Integrator::ImplicitMidpoint { iterations } => {
+                let accelerations = self.accelerations(params);
+                let masses = &self.masses;
+                implicit_midpoint_step(
+                    &mut self.positions,
+                    &mut self.velocities,
+                    &accelerations,
+                    &vec![false; masses.len()],
+                    dt,
+                    iterations,
+                    |positions| params.accelerations(masses, positions),
+                );
+            }
+        }
+    }
+
+    /// `Integrator::BlockStep` step, returning the number of steps each body took
+    pub fn block_step(
+        &mut self,
+        params: &ForceParams,
+        dt: f32,
+        min_dt: f32,
+        max_levels: u32,
+    ) -> Vec<u32> {
+        let mut accelerations = self.accelerations(params);
+        let levels = block_levels(&self.positions, &accelerations, dt, min_dt, max_levels);
+        let masses = &self.masses;
+        block_step(
+            &mut self.positions,
+            &mut self.velocities,
+            &mut accelerations,
+            &levels,
+            &vec![false; masses.len()],
+            dt,
+            |positions, active| {
+                let accelerations = accelerations_of(
+                    masses,
+                    positions,
+                    active,
+                    params.g,
+                    params.softening,
+                    params.cutoff,
+                );
+                active
+                    .iter()
+                    .zip(accelerations)
+                    .map(|(i, acc)| acc + params.external.acceleration(positions[*i]))
+                    .collect()
+            },
+        )
+    }
+
+    fn runge_kutta_step(&mut self, params: &ForceParams, dt: f32) {
+        let stage = |state: &SystemState, dx: &[Vec3], dv: &[Vec3], h: f32| {
+            let mut next = state.clone();
+            next.drift_by(dx, h);
+            next.kick(dv, h);
+            next
+        };
+        let a1 = self.accelerations(params);
+        let s2 = stage(self, &self.velocities, &a1, dt / 2.0);
+        let a2 = s2.accelerations(params);
+        let s3 = stage(self, &s2.velocities, &a2, dt / 2.0);
+        let a3 = s3.accelerations(params);
+        let s4 = stage(self, &s3.velocities, &a3, dt);
+        let a4 = s4.accelerations(params);
+
+        let weighted = |k1: &[Vec3], k2: &[Vec3], k3: &[Vec3], k4: &[Vec3]| -> Vec<Vec3> {
+            (0..k1.len())
+                .map(|i| (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]) / 6.0)
+                .collect()
+        };
+        let dx = weighted(
+            &self.velocities,
+            &s2.velocities,
+            &s3.velocities,
+            &s4.velocities,
+        );
+        self.drift_by(&dx, dt);
+        self.kick(&weighted(&a1, &a2, &a3, &a4), dt);
+    }
+
+    pub fn energy(&self, params: &ForceParams) -> f32 {
+        kinetic_energy(&self.masses, &self.velocities)
+            + potential_energy(&self.masses, &self.positions, params.g, params.softening)
+    }
+}
+
+/// Condition ending a headless run, checked after each step
+#[derive(Debug, Clone, PartialEq)]
+pub enum EndCondition {
+    /// After the given number of steps
+    Steps(usize),
+    /// Once the simulated time reaches the given time
+    SimulatedTime(f32),
+    /// Once the total energy drifted from its initial value by more than the given relative change
+    EnergyDrift(f32),
+    /// Once two bodies come closer than the given distance
+    Collision { distance: f32 },
+    /// Once at most one body is left within the given distance of the center of mass, the others having escaped
+    SingleBody { escape_distance: f32 },
+    /// Once any of the conditions is met, e.g. a collision or a maximum number of steps
+    Any(Vec<EndCondition>),
+}
+
+impl EndCondition {
+    fn uses_energy(&self) -> bool {
+        match self {
+            EndCondition::EnergyDrift(_) => true,
+            EndCondition::Any(conditions) => conditions.iter().any(EndCondition::uses_energy),
+            _ => false,
+        }
+    }
+
+    /// Whether the condition is met after `step` steps of `dt`,
+    /// `initial_energy` being the energy of the system before the first one
+    pub fn is_met(
+        &self,
+        state: &SystemState,
+        params: &ForceParams,
+        step: usize,
+        dt: f32,
+        initial_energy: f32,
+    ) -> bool {
+        match self {
+            EndCondition::Steps(steps) => step >= *steps,
+            EndCondition::SimulatedTime(time) => step as f32 * dt >= *time,
+            EndCondition::EnergyDrift(drift) => {
+                initial_energy != 0.0
+                    && ((state.energy(params) - initial_energy) / initial_energy).abs() > *drift
+            }
+            EndCondition::Collision { distance } => (0..state.len()).any(|i| {
+                (i + 1..state.len())
+                    .any(|j| state.positions[i].distance(state.positions[j]) < *distance)
+            }),
+            EndCondition::SingleBody { escape_distance } => {
+                let total_mass: f32 = state.masses.iter().sum();
+                let center = state
+                    .masses
+                    .iter()
+                    .zip(&state.positions)
+                    .fold(Vec3::ZERO, |center, (mass, pos)| center + *mass * *pos)
+                    / total_mass;
+                state
+                    .positions
+                    .iter()
+                    .filter(|pos| pos.distance(center) <= *escape_distance)
+                    .count()
+                    <= 1
+            }
+            EndCondition::Any(conditions) => conditions
+                .iter()
+                .any(|condition| condition.is_met(state, params, step, dt, initial_energy)),
+        }
+    }
+}
+
+/// Integrates `state` with steps of `dt` until the `end` condition is met,
+/// calling `on_step` with the step number after each of them. Returns the number of steps.
+pub fn run_headless(
+    state: &mut SystemState,
+    params: &ForceParams,
+    integrator: Integrator,
+    dt: f32,
+    end: &EndCondition,
+    mut on_step: impl FnMut(usize, &SystemState),
+) -> usize {
+    let initial_energy = if end.uses_energy() {
+        state.energy(params)
+    } else {
+        0.0
+    };
+    let mut step = 0;
+    while !end.is_met(state, params, step, dt, initial_energy) {
+        state.step(params, integrator, dt);
+        step += 1;
+        on_step(step, state);
+    }
+    step
+}
+
+/// Largest relative change of the total energy while integrating a copy of `state` for `steps` steps of `dt`
+pub fn estimate_energy_drift(
+    state: &SystemState,
+    params: &ForceParams,
+    integrator: Integrator,
+    dt: f32,
+    steps: usize,
+) -> f32 {
+    let initial = state.energy(params);
+    if initial == 0.0 {
+        return 0.0;
+    }
+    let mut drift: f32 = 0.0;
+    run_headless(
+        &mut state.clone(),
+        params,
+        integrator,
+        dt,
+        &EndCondition::Steps(steps),
+        |_, state| {
+            drift = drift.max(((state.energy(params) - initial) / initial).abs());
+        },
+    );
+    drift
+}
+
+/// Shadow integration estimating the largest Lyapunov exponent of a system (Benettin's method):
+/// a copy perturbed by `delta` is integrated along with the system, and their separation in the
+/// phase space is renormalized back to `delta` from time to time, the exponent being the mean
+/// logarithmic growth rate of the separation:
+/// ```text
+/// λ = Σ ln(d_k / δ) / t
+/// ```
+/// It tends to 0 for a regular motion, the separation growing only linearly, and is positive for a chaotic one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LyapunovRun {
+    reference: SystemState,
+    shadow: SystemState,
+    delta: f32,
+    time: f32,
+    /// Sum of the logarithmic growths and time integrated up to the last renormalization
+    log_growth: f64,
+    renormalized_time: f32,
+}
+
+impl LyapunovRun {
+    /// The shadow copy has its first body offset by `delta` along x
+    pub fn new(state: SystemState, delta: f32) -> Self {
+        let mut shadow = state.clone();
+        if let Some(pos) = shadow.positions.first_mut() {
+            pos.x += delta;
+        }
+        Self {
+            reference: state,
+            shadow,
+            delta,
+            time: 0.0,
+            log_growth: 0.0,
+            renormalized_time: 0.0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.reference.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reference.is_empty()
+    }
+
+    pub fn step(&mut self, params: &ForceParams, integrator: Integrator, dt: f32) {
+        self.reference.step(params, integrator, dt);
+        self.shadow.step(params, integrator, dt);
+        self.time += dt;
+    }
+
+    /// Distance between the system and its shadow in the phase space:
+    /// ```text
+    /// d = sqrt(Σ |x_i - x'_i|^2 + |v_i - v'_i|^2)
+    /// ```
+    pub fn separation(&self) -> f32 {
+        let positions = self.reference.positions.iter().zip(&self.shadow.positions);
+        let velocities = self
+            .reference
+            .velocities
+            .iter()
+            .zip(&self.shadow.velocities);
+        positions
+            .chain(velocities)
+            .map(|(a, b)| a.distance_squared(*b))
+            .sum::<f32>()
+            .sqrt()
+    }
+
+    /// Accumulates the growth of the separation and brings the shadow back to `delta` from the system,
+    /// along the direction the separation grew in
+    pub fn renormalize(&mut self) {
+        let d = self.separation();
+        if d == 0.0 || !d.is_finite() {
+            return;
+        }
+        self.log_growth += (d as f64 / self.delta as f64).ln();
+        self.renormalized_time = self.time;
+        let scale = self.delta / d;
+        let reference = &self.reference;
+        let shadow = &mut self.shadow;
+        for (pos, reference) in shadow.positions.iter_mut().zip(&reference.positions) {
+            *pos = *reference + (*pos - *reference) * scale;
+        }
+        for (vel, reference) in shadow.velocities.iter_mut().zip(&reference.velocities) {
+            *vel = *reference + (*vel - *reference) * scale;
+        }
+    }
+
+    /// Running estimate of the exponent, `None` before the first renormalization
+    pub fn exponent(&self) -> Option<f32> {
+        if self.renormalized_time > 0.0 {
+            Some((self.log_growth / self.renormalized_time as f64) as f32)
+        } else {
+            None
+        }
+    }
+}
+
+/// Largest Lyapunov exponent of `state` estimated over `steps` steps of `dt`,
+/// the shadow copy being renormalized every `renorm_interval` steps, see `LyapunovRun`
+pub fn lyapunov_exponent(
+    state: &SystemState,
+    params: &ForceParams,
+    integrator: Integrator,
+    dt: f32,
+    steps: usize,
+    renorm_interval: usize,
+    delta: f32,
+) -> Option<f32> {
+    let mut run = LyapunovRun::new(state.clone(), delta);
+    for step in 1..=steps {
+        run.step(params, integrator, dt);
+        if step.is_multiple_of(renorm_interval.max(1)) {
+            run.renormalize();
+        }
+    }
+    run.exponent()
+}
+
+/// Random perturbations of the trials of `monte_carlo_run`, as standard deviations of gaussian noise
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Perturbation {
+    /// Relative to `G`, e.g. 0.01 for a 1% uncertainty
+    pub g: f32,
+    /// Added to each coordinate of the initial positions
+    pub position: f32,
+    /// Added to each coordinate of the initial velocities
+    pub velocity: f32,
+    /// Seed of the noise, for the same trials to be drawn on every run
+    pub seed: u64,
+}
+
+impl Perturbation {
+    /// Perturbed copy of the scene
+    pub fn apply(
+        &self,
+        state: &SystemState,
+        params: &ForceParams,
+        rng: &mut impl Rng,
+    ) -> (SystemState, ForceParams) {
+        let mut noise = |std_dev: f32| {
+            std_dev
+                * Vec3::new(
+                    rng.sample(StandardNormal),
+                    rng.sample(StandardNormal),
+                    rng.sample(StandardNormal),
+                )
+        };
+        let g = params.g * (1.0 + noise(self.g).x);
+        let mut state = state.clone();
+        for pos in state.positions.iter_mut() {
+            *pos += noise(self.position);
+        }
+        for vel in state.velocities.iter_mut() {
+            *vel += noise(self.velocity);
+        }
+        (state, ForceParams { g, ..*params })
+    }
+}
+
+/// Result of a trial of `monte_carlo_run`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    /// Perturbed gravitational constant of the trial
+    pub g: f32,
+    /// Bodies at the end of the trial
+    pub state: SystemState,
+}
+
+impl Outcome {
+    /// Final distance between the bodies `i` and `j`
+    pub fn separation(&self, i: usize, j: usize) -> f32 {
+        self.state.positions[i].distance(self.state.positions[j])
+    }
+}
+
+/// Integrates `trials` perturbed copies of the scene for `steps` steps of `dt`, e.g. to look at
+/// the distribution of the final separations and quantify the sensitivity to the initial conditions.
+pub fn monte_carlo_run(
+    state: &SystemState,
+    params: &ForceParams,
+    integrator: Integrator,
+    dt: f32,
+    trials: usize,
+    perturbation: &Perturbation,
+    steps: usize,
+) -> Vec<Outcome> {
+    let mut rng = StdRng::seed_from_u64(perturbation.seed);
+    (0..trials)
+        .map(|_| {
+            let (mut state, params) = perturbation.apply(state, params, &mut rng);
+            run_headless(
+                &mut state,
+                &params,
+                integrator,
+                dt,
+                &EndCondition::Steps(steps),
+                |_, _| {},
+            );
+            Outcome { g: params.g, state }
+        })
+        .collect()
+}
+
+/// Checksum of `integration_checksum` for the canonical two-body scene,
+/// to be updated along with any intended change of the force computation or the integrators
+pub const TWO_BODY_CHECKSUM: u64 = 0xfca4_2fdd_dde0_dd39;
+
+/// Number of steps of `DT` the canonical scene is integrated for
+pub const CHECKSUM_STEPS: usize = 1000;
+
+/// Canonical scene of the checksum: two unit masses on a circular orbit of separation 1, with `G = 1`
+pub fn canonical_two_body() -> (SystemState, ForceParams) {
+    let speed = 0.5f32.sqrt();
+    let mut state = SystemState::default();
+    state.push(1.0, Vec3::new(-0.5, 0.0, 0.0), Vec3::new(0.0, -speed, 0.0));
+    state.push(1.0, Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.0, speed, 0.0));
+    let params = ForceParams {
+        g: 1.0,
+        softening: SofteningKernel::None,
+        cutoff: None,
+        external: ExternalField::default(),
+    };
+    (state, params)
+}
+
+/// FNV-1a hash of the final positions and velocities after integrating a copy of `state` for `steps` steps,
+/// summarizing a run in a single number, e.g. to catch any change of the physics in CI.
+/// The values are rounded to 1e-3 so that the last bits of the floating point operations,
+/// which may differ across platforms, don't change the checksum.
+pub fn integration_checksum(
+    state: &SystemState,
+    params: &ForceParams,
+    integrator: Integrator,
+    dt: f32,
+    steps: usize,
+) -> u64 {
+    const ROUNDING: f32 = 1000.0;
+    let mut state = state.clone();
+    run_headless(
+        &mut state,
+        params,
+        integrator,
+        dt,
+        &EndCondition::Steps(steps),
+        |_, _| {},
+    );
+
+    state
+        .positions
+        .iter()
+        .zip(&state.velocities)
+        .flat_map(|(pos, vel)| vec![pos.x, pos.y, pos.z, vel.x, vel.y, vel.z])
+        .flat_map(|x| ((x * ROUNDING).round() as i64).to_le_bytes())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::nbody::DT;
+
+    /// Seeds of the random configurations of the property tests, fixed for the failures to be reproducible
+    const SEEDS: std::ops::Range<u64> = 0..32;
+
+    /// Random configuration of 2 to 8 bodies in a unit cube, with Plummer softening for the close
+    /// encounters not to blow the energy up
+    fn random_configuration(seed: u64) -> (SystemState, ForceParams) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let vec3 = |rng: &mut StdRng, extent: f32| {
+            Vec3::new(
+                rng.gen_range(-extent..extent),
+                rng.gen_range(-extent..extent),
+                rng.gen_range(-extent..extent),
+            )
+        };
+        let mut state = SystemState::default();
+        for _ in 0..rng.gen_range(2..=8) {
+            let mass = rng.gen_range(0.1..1.0);
+            let pos = vec3(&mut rng, 1.0);
+            let vel = vec3(&mut rng, 0.3);
+            state.push(mass, pos, vel);
+        }
+        let params = ForceParams {
+            g: 1.0,
+            softening: SofteningKernel::Plummer { eps: 0.1 },
+            cutoff: None,
+            external: ExternalField::default(),
+        };
+        (state, params)
+    }
+
+    fn momentum(state: &SystemState) -> Vec3 {
+        state
+            .masses
+            .iter()
+            .zip(&state.velocities)
+            .fold(Vec3::ZERO, |p, (m, vel)| p + *m * *vel)
+    }
+
+    /// Sum of the magnitudes of the momenta, the scale of the rounding errors of the total momentum
+    fn momentum_scale(state: &SystemState) -> f32 {
+        state
+            .masses
+            .iter()
+            .zip(&state.velocities)
+            .map(|(m, vel)| m * vel.length())
+            .sum()
+    }
+
+    /// The canonical two-body scene slowed down to an eccentric orbit (e = 0.36) of period ~2.8
+    fn eccentric_two_body() -> (SystemState, ForceParams) {
+        let (mut state, params) = canonical_two_body();
+        state.velocities.iter_mut().for_each(|vel| *vel *= 0.8);
+        (state, params)
+    }
+
+    #[test]
+    fn every_integrator_conserves_momentum() {
+        let integrators = [
+            Integrator::SemiImplicitEuler,
+            Integrator::Leapfrog,
+            Integrator::VelocityVerlet,
+            Integrator::RungeKutta4,
+            Integrator::BlockStep {
+                min_dt: 1e-5,
+                max_levels: 4,
+            },
+            Integrator::ImplicitMidpoint { iterations: 4 },
+        ];
+        for seed in SEEDS {
+            let (initial, params) = random_configuration(seed);
+            for integrator in integrators.iter() {
+                let mut state = initial.clone();
+                let mut scale = momentum_scale(&state);
+                run_headless(
+                    &mut state,
+                    &params,
+                    *integrator,
+                    1e-3,
+                    &EndCondition::Steps(500),
+                    |_, state| scale = scale.max(momentum_scale(state)),
+                );
+                let error = (momentum(&state) - momentum(&initial)).length();
+                assert!(
+                    error <= 1e-5 * scale.max(1.0),
+                    "{:?} changed the momentum by {} with seed {}: {:?}",
+                    integrator,
+                    error,
+                    seed,
+                    initial
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn symplectic_integrators_bound_the_energy_drift() {
+        for seed in SEEDS {
+            let (state, params) = random_configuration(seed);
+            for integrator in [Integrator::Leapfrog, Integrator::VelocityVerlet].iter() {
+                let drift = estimate_energy_drift(&state, &params, *integrator, 1e-3, 2000);
+                assert!(
+                    drift < 1e-2,
+                    "{:?} drifted by {} with seed {}: {:?}",
+                    integrator,
+                    drift,
+                    seed,
+                    state
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn leapfrog_drifts_less_than_euler_over_an_orbit() {
+        let (state, params) = eccentric_two_body();
+        let drift = |integrator| estimate_energy_drift(&state, &params, integrator, 1e-2, 280);
+        let (euler, leapfrog) = (
+            drift(Integrator::SemiImplicitEuler),
+            drift(Integrator::Leapfrog),
+        );
+        assert!(leapfrog > 0.0);
+        assert!(euler > 10.0 * leapfrog, "{} vs {}", euler, leapfrog);
+    }
+
+    /// Position error and energy drift after one period of the eccentric Kepler orbit, for each integrator
+    mod accuracy {
+        use super::*;
+        use crate::plugins::kepler::OrbitalElements;
+
+        const STEPS: usize = 250;
+
+        /// Largest distance of the relative position of the two bodies to the analytic one over a period,
+        /// and the largest relative energy drift meanwhile
+        fn period_error(integrator: Integrator) -> (f32, f32) {
+            let (state, params) = eccentric_two_body();
+            let mu = params.g * (state.masses[0] + state.masses[1]);
+            let relative = |state: &SystemState| {
+                (
+                    state.positions[1] - state.positions[0],
+                    state.velocities[1] - state.velocities[0],
+                )
+            };
+            let (pos, vel) = relative(&state);
+            let elements = OrbitalElements::from_state(pos, vel, mu).unwrap();
+            let dt = elements.period(mu) / STEPS as f32;
+
+            let drift = estimate_energy_drift(&state, &params, integrator, dt, STEPS);
+            let mut error: f32 = 0.0;
+            run_headless(
+                &mut state.clone(),
+                &params,
+                integrator,
+                dt,
+                &EndCondition::Steps(STEPS),
+                |step, state| {
+                    let expected = elements.advanced(mu, step as f32 * dt).state(mu).0;
+                    error = error.max(relative(state).0.distance(expected));
+                },
+            );
+            (error, drift)
+        }
+
+        #[test]
+        fn integrators_are_ordered_by_accuracy() {
+            let [euler, leapfrog, verlet, rk4] = [
+                Integrator::SemiImplicitEuler,
+                Integrator::Leapfrog,
+                Integrator::VelocityVerlet,
+                Integrator::RungeKutta4,
+            ]
+            .map(|integrator| {
+                let (error, drift) = period_error(integrator);
+                println!(
+                    "{:?}: position error {:e}, energy drift {:e}",
+                    integrator, error, drift
+                );
+                error
+            });
+            assert!(rk4 < verlet, "{} vs {}", rk4, verlet);
+            assert!(
+                (verlet - leapfrog).abs() < 0.1 * leapfrog,
+                "{} vs {}",
+                verlet,
+                leapfrog
+            );
+            assert!(leapfrog < euler, "{} vs {}", leapfrog, euler);
+        }
+    }
+
+    #[test]
+    fn canonical_two_body_checksum() {
+        let (state, params) = canonical_two_body();
+        assert_eq!(
+            integration_checksum(&state, &params, Integrator::default(), DT, CHECKSUM_STEPS),
+            TWO_BODY_CHECKSUM
+        );
+    }
+
+    #[test]
+    fn block_steps_advance_the_close_binary_more_often() {
+        // Binary of separation 0.1 (period ~0.14) and a light planet at 5 (period ~50)
+        let mut state = SystemState::default();
+        let speed = 0.5 * (2.0f32 / 0.1).sqrt();
+        state.push(1.0, Vec3::new(-0.05, 0.0, 0.0), Vec3::new(0.0, -speed, 0.0));
+        state.push(1.0, Vec3::new(0.05, 0.0, 0.0), Vec3::new(0.0, speed, 0.0));
+        state.push(
+            1e-3,
+            Vec3::new(5.0, 0.0, 0.0),
+            Vec3::new(0.0, (2.0f32 / 5.0).sqrt(), 0.0),
+        );
+        let params = ForceParams {
+            g: 1.0,
+            softening: SofteningKernel::None,
+            cutoff: None,
+            external: ExternalField::default(),
+        };
+
+        let initial = state.energy(&params);
+        let mut steps = vec![0; 3];
+        let mut max_error: f32 = 0.0;
+        for _ in 0..300 {
+            let taken = state.block_step(&params, DT, 1e-5, 6);
+            steps
+                .iter_mut()
+                .zip(taken)
+                .for_each(|(total, n)| *total += n);
+            max_error = max_error.max(((state.energy(&params) - initial) / initial).abs());
+        }
+
+        assert_eq!(steps[0], steps[1]);
+        assert!(steps[0] >= 4 * steps[2], "{:?}", steps);
+        assert_eq!(steps[2], 300);
+        assert!(max_error < 1e-2, "relative energy error {}", max_error);
+    }
+
+    #[test]
+    fn runs_end_after_the_given_number_of_steps() {
+        let (mut state, params) = canonical_two_body();
+        let mut calls = Vec::new();
+        let steps = run_headless(
+            &mut state,
+            &params,
+            Integrator::Leapfrog,
+            DT,
+            &EndCondition::Steps(42),
+            |step, _| calls.push(step),
+        );
+        assert_eq!(steps, 42);
+        assert_eq!(calls, (1..=42).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn runs_end_when_a_single_body_remains() {
+        // Light body leaving the star at 4 times its escape speed
+        let mut state = SystemState::default();
+        state.push(1.0, Vec3::ZERO, Vec3::ZERO);
+        state.push(1e-3, Vec3::X, 4.0 * 2.0f32.sqrt() * Vec3::Y);
+        let (_, params) = canonical_two_body();
+        let end = EndCondition::SingleBody {
+            escape_distance: 10.0,
+        };
+
+        let steps = run_headless(
+            &mut state,
+            &params,
+            Integrator::Leapfrog,
+            DT,
+            &end,
+            |_, _| {},
+        );
+        // ~5.3 of distance per unit of time past the star
+        assert!((150..250).contains(&steps), "{}", steps);
+        assert!(state.positions[1].length() > 10.0);
+        assert!(state.positions[0].length() < 0.1);
+    }
+
+    #[test]
+    fn runs_end_at_the_first_collision() {
+        // Head-on fall of two bodies at rest, separated by 2
+        let mut state = SystemState::default();
+        state.push(1.0, -Vec3::X, Vec3::ZERO);
+        state.push(1.0, Vec3::X, Vec3::ZERO);
+        let (_, params) = canonical_two_body();
+        let end = EndCondition::Any(vec![
+            EndCondition::Collision { distance: 0.2 },
+            EndCondition::Steps(10_000),
+        ]);
+
+        let steps = run_headless(
+            &mut state,
+            &params,
+            Integrator::Leapfrog,
+            DT,
+            &end,
+            |_, _| {},
+        );
+        let separation = state.positions[0].distance(state.positions[1]);
+        assert!(steps < 10_000);
+        assert!(separation < 0.2 && separation > 0.1, "{}", separation);
+        // Free fall time of the pair, π/2 · sqrt(r³/2GM) with r = 2, M = 2
+        assert!((steps as f32 * DT - 2.22).abs() < 0.1, "{}", steps);
+    }
+
+    #[test]
+    fn unperturbed_trials_have_identical_outcomes() {
+        let (state, params) = canonical_two_body();
+        let perturbation = Perturbation {
+            seed: 7,
+            ..Default::default()
+        };
+        let outcomes = monte_carlo_run(
+            &state,
+            &params,
+            Integrator::Leapfrog,
+            DT,
+            4,
+            &perturbation,
+            500,
+        );
+
+        let mut expected = state.clone();
+        run_headless(
+            &mut expected,
+            &params,
+            Integrator::Leapfrog,
+            DT,
+            &EndCondition::Steps(500),
+            |_, _| {},
+        );
+        assert_eq!(outcomes.len(), 4);
+        for outcome in &outcomes {
+            assert_eq!(outcome.g, params.g);
+            assert_eq!(outcome.state.positions, expected.positions);
+            assert_eq!(outcome.state.velocities, expected.velocities);
+        }
+    }
+
+    #[test]
+    fn perturbed_trials_are_reproducible_from_their_seed() {
+        let (state, params) = canonical_two_body();
+        let perturbation = Perturbation {
+            g: 0.01,
+            position: 0.01,
+            velocity: 0.01,
+            seed: 7,
+        };
+        let run = || {
+            monte_carlo_run(
+                &state,
+                &params,
+                Integrator::Leapfrog,
+                DT,
+                4,
+                &perturbation,
+                500,
+            )
+        };
+
+        let outcomes = run();
+        assert_eq!(outcomes, run());
+        assert_ne!(outcomes[0].separation(0, 1), outcomes[1].separation(0, 1));
+        assert_ne!(outcomes[0].g, outcomes[1].g);
+    }
+
+    #[test]
+    fn implicit_midpoint_bounds_the_energy_error_like_leapfrog() {
+        // Binary on an eccentric orbit with a large step at the pericenter
+        let (mut state, params) = canonical_two_body();
+        state.velocities.iter_mut().for_each(|vel| *vel *= 0.5);
+        let drift =
+            |integrator, steps| estimate_energy_drift(&state, &params, integrator, 1e-2, steps);
+        let midpoint = Integrator::ImplicitMidpoint { iterations: 4 };
+
+        // No secular growth over tens of orbits, unlike Runge-Kutta
+        let (short, long) = (drift(midpoint, 1000), drift(midpoint, 10_000));
+        assert!(long < 1.1 * short, "{} then {}", short, long);
+        let runge_kutta = Integrator::RungeKutta4;
+        assert!(drift(runge_kutta, 10_000) > 2.0 * drift(runge_kutta, 1000));
+
+        let leapfrog = drift(Integrator::Leapfrog, 10_000);
+        assert!(long < 2.0 * leapfrog, "{} vs {}", long, leapfrog);
+        assert!(long < 0.1);
+    }
+
+    #[test]
+    fn regular_orbits_have_a_vanishing_lyapunov_exponent() {
+        let (state, params) = canonical_two_body();
+        // The separation only grows linearly, the estimate decreasing as ln(t) / t
+        let exponent = lyapunov_exponent(
+            &state,
+            &params,
+            Integrator::Leapfrog,
+            1e-2,
+            10_000,
+            10,
+            1e-3,
+        );
+        let exponent = exponent.unwrap();
+        assert!(exponent.abs() < 0.05, "{}", exponent);
+    }
+
+    #[test]
+    fn three_body_problem_has_a_positive_lyapunov_exponent() {
+        // Pythagorean three-body problem, softened for the close encounters to be integrated accurately
+        let mut state = SystemState::default();
+        state.push(3.0, Vec3::new(1.0, 3.0, 0.0), Vec3::ZERO);
+        state.push(4.0, Vec3::new(-2.0, -1.0, 0.0), Vec3::ZERO);
+        state.push(5.0, Vec3::new(1.0, -1.0, 0.0), Vec3::ZERO);
+        let (_, params) = canonical_two_body();
+        let params = ForceParams {
+            softening: SofteningKernel::Plummer { eps: 0.3 },
+            ..params
+        };
+        assert!(estimate_energy_drift(&state, &params, Integrator::Leapfrog, 1e-3, 50_000) < 1e-3);
+
+        let exponent = lyapunov_exponent(
+            &state,
+            &params,
+            Integrator::Leapfrog,
+            1e-3,
+            50_000,
+            10,
+            1e-3,
+        );
+        let exponent = exponent.unwrap();
+        assert!(exponent > 0.2, "{}", exponent);
+    }
+}
@@ -0,0 +1,6 @@
+pub mod catalog;
+pub mod headless;
+pub mod plugins;
+pub mod scene;
+#[cfg(test)]
+mod testing;
@@ -9,9 +9,72 @@ use bevy::{
 use rand::Rng;
 use rand_distr::{Distribution, UnitSphere};
 
-mod plugins;
-use plugins::nbody::{BodyBundle, Gravity, NBody};
-use plugins::pan_orbit_camera::{PanOrbitCamera, PanOrbitCameraPlugin};
+use nbody::catalog::{catalog_body, catalog_entry};
+#[cfg(feature = "audio")]
+use nbody::plugins::audio::{AudioConfig, CollisionAudioPlugin};
+use nbody::plugins::axis_gizmo::AxisGizmoPlugin;
+use nbody::plugins::barnes_hut::MultipoleOrder;
+use nbody::plugins::barycenter::BarycenterPlugin;
+use nbody::plugins::camera_bookmarks::CameraBookmarksPlugin;
+use nbody::plugins::census::CensusPlugin;
+use nbody::plugins::chaos::{ChaosPair, ChaosPairPlugin};
+use nbody::plugins::collision::{
+    CentralAccretor, CollisionMode, CollisionPlugin, ContinuousCollision, Radius, TidalDisruption,
+};
+use nbody::plugins::comet::CometPlugin;
+use nbody::plugins::contours::{PotentialContours, PotentialContoursPlugin};
+#[cfg(feature = "ui")]
+use nbody::plugins::dashboard::DashboardPlugin;
+use nbody::plugins::deflection::DeflectionRingsPlugin;
+use nbody::plugins::diagnostics::{
+    DiagnosticsRecorder, EnergyCorrection, PhysicsDiagnosticsPlugin,
+};
+use nbody::plugins::distances::DistanceListPlugin;
+use nbody::plugins::encounters::{CloseEncounterPlugin, CloseEncounterStats};
+use nbody::plugins::extremes::ExtremesPlugin;
+use nbody::plugins::frame_rate::{FrameRatePlugin, MinimizedBehavior, PauseOnFocusLoss};
+use nbody::plugins::gravity_control::GravityControlPlugin;
+use nbody::plugins::grid::{Plane, ReferenceGrid, ReferenceGridPlugin};
+use nbody::plugins::heatmap::{EncounterHeatmap, HeatmapPlugin};
+use nbody::plugins::help::HelpPlugin;
+use nbody::plugins::hill_sphere::HillSpherePlugin;
+use nbody::plugins::hud::HudPlugin;
+use nbody::plugins::impulse::ImpulsePlugin;
+use nbody::plugins::influence::SphereOfInfluencePlugin;
+use nbody::plugins::kepler::OnRailsPlugin;
+use nbody::plugins::key_bindings::{KeyBindings, KeyRemap};
+use nbody::plugins::launcher::{LauncherPlugin, MaxBodies};
+use nbody::plugins::lensing::{LensedBackground, LensingPlugin};
+use nbody::plugins::lyapunov::{LyapunovEstimator, LyapunovPlugin};
+use nbody::plugins::measure::{MeasurePlugin, TimeUnit, UnitSystem};
+use nbody::plugins::nbody::{
+    BodyBundle, BodyClass, ConstrainToPlane, ExtendedBody, ExternalField, ForceMode, Gravity,
+    Integrator, NBody, PhysicsThreads, SofteningKernel, Universe,
+};
+use nbody::plugins::orbit_ellipses::OrbitEllipsesPlugin;
+use nbody::plugins::orbit_info::OrbitInfoPlugin;
+use nbody::plugins::orbit_view::OrbitViewPlugin;
+use nbody::plugins::pan_orbit_camera::{PanOrbitCamera, PanOrbitCameraPlugin, WorldUp};
+use nbody::plugins::performance::{AdaptivePerformance, AdaptivePerformancePlugin};
+use nbody::plugins::prediction::{PredictionModel, PredictionPlugin, TrajectoryPrediction};
+use nbody::plugins::quality::{QualityPlugin, QualityPreset};
+use nbody::plugins::render_radius::{MinRenderRadius, RenderRadiusPlugin};
+use nbody::plugins::reset::SceneResetPlugin;
+use nbody::plugins::resonance::ResonancePlugin;
+use nbody::plugins::restricted_three_body::{
+    corotating_velocity, triangular_points, trojan_swarm, EscapeRadius, Primaries,
+    RestrictedThreeBodyPlugin, TestParticle,
+};
+use nbody::plugins::selection::SelectionPlugin;
+use nbody::plugins::self_check::{IntegratorSelfCheck, SelfCheckPlugin};
+use nbody::plugins::star_light::{StarLightPlugin, StarLights, StarTemperature};
+use nbody::plugins::timeline::TimelinePlugin;
+use nbody::plugins::tint::TintPlugin;
+use nbody::plugins::trail::{Trail, TrailPlugin};
+use nbody::scene::{
+    validate_scene, BodyDescription, DuplicatePolicy, SceneDescription, ValidationIssue,
+    DUPLICATE_EPSILON,
+};
 
 #[derive(FromArgs)]
 /** 
@@ -21,30 +84,212 @@ Several `--startup` options:
 * solar: planets of the Solar System - and Pluto :'( - with data from JPL Horizons as-of 2021-04-18.
 * figure8: stable figure-8 three-body solution
 * random: generates random bodies around a large star
+* restricted: grid of test particles around two primaries (restricted three-body problem)
+* chaos: the `--scene` (figure-8 by default) next to a replica with its first body offset by `--perturbation`
+* trojans: the Sun, Jupiter and swarms of asteroids librating around its L4 and L5 Lagrange points
+
+Or load a `--scene` file in RON or JSON, e.g. `assets/scenes/figure8.ron`.
 
 Mouse controls: 
 * right-click & drag to orbit the camera 
 * scroll to zoom
 
+Keyboard controls:
+* Tab: select the next body (Shift + Tab to add it to the selection)
+* Escape: clear the selection
+* P: pin/unpin the selected bodies
+* Z: freeze/unfreeze the selected bodies in time
+* L: toggle the gravitational lensing effect around the stars
+* Space: launch a projectile from the camera
+* F: toggle the frame rate cap (60 FPS)
+* R: save the diagnostics recorded with `--record`
+* F2: toggle the heatmap of the bodies in the orbital plane (Shift + F2 to reset it)
+* G: toggle the grid on the orbital plane
+* M: toggle the measurement mode, left-click two points to measure their distance
+* I: toggle the Hill sphere of the selected body
+* Q: cycle the rendering quality presets
+* C: toggle the gravitational potential contours on the orbital plane
+* V: toggle the coloring of the trails by speed
+* X: export the trails as seen from the camera to `trails.svg`
+* [ / ]: hold to decrease / increase the gravitational constant (Backspace to restore it)
+* O / E: view the orbit of the selected body face-on / edge-on
+* D: toggle the deflection rings of the selected body
+* Arrow keys: kick the selected bodies up / down / left / right on screen
+* K: pause / resume the simulation
+* , / .: step through the recorded history while paused (Shift to step 10 frames)
+* B: toggle the coloring of the bodies by the star dominating their gravity
+* N: toggle the list of the distances from the selected body to the others
+* J: toggle the osculating orbit of the bodies around their primary
+* T: toggle the highlight of the most massive body
+* Y: toggle the highlight of the fastest body
+* A: hold to capture the path of the selected bodies (Shift + A to clear the captured paths)
+* F5: reset the scene to its initial state
+* U: toggle the marker of the center of mass
+* Ctrl + Q / Ctrl + E: hold to roll the camera left / right
+* 0-9: jump to the camera viewpoint saved with Ctrl + 0-9
+* H: toggle the list of the keyboard controls
+
+The actions can be bound to other keys with `--bind`, e.g. `--bind toggle-pause=pause`.
+
 */
 struct Flags {
-    /// startup system [solar (default)|figure8|random]
+    /// startup system [solar (default)|figure8|random|restricted|chaos|trojans]
     #[argh(option, default = "Startup::SolarSystem")]
     startup: Startup,
 
+    /// scene file (.ron or .json) to load instead of a startup system, or to replicate with the chaos one
+    #[argh(option)]
+    scene: Option<String>,
+
+    /// handling of the bodies of a scene file at the same position [merge (default)|warn|error]
+    #[argh(option, default = "DuplicatePolicy::Merge")]
+    duplicates: DuplicatePolicy,
+
+    /// outcome of the bodies touching each other [merge (default)|ignore|stick|fragment:<energy threshold>[:<pieces>]]
+    #[argh(option)]
+    collisions: Option<CollisionMode>,
+
+    /// detect the collisions along the paths of the bodies during a step, for the fast ones not to pass through each other
+    #[argh(switch)]
+    continuous_collisions: bool,
+
+    /// break up the bodies crossing the Roche limit of a heavier body into fragments drifting apart along the tidal axis, as <pieces>[:<spread>], the spread being a multiple of the escape speed of the body [default: off]
+    #[argh(option)]
+    tidal_disruption: Option<TidalDisruption>,
+
     /// speed of the simulation [default: 1.0x]
     #[argh(option, default = "1.0")]
     speed: f32,
 
+    /// integration scheme [euler (default)|leapfrog|verlet|rk4|block[:<max levels>[:<min dt>]]|midpoint[:<iterations>]]
+    #[argh(option)]
+    integrator: Option<Integrator>,
+
+    /// softening of the gravity [none (default)|<plummer length>|plummer:<length>|spline:<length>]
+    #[argh(option)]
+    softening: Option<SofteningKernel>,
+
+    /// offset along x of the first body of the replica of the chaos startup [default: 1e-4]
+    #[argh(option, default = "1e-4")]
+    perturbation: f32,
+
+    /// number of bodies of the random startup [default: 10]
+    #[argh(option, default = "10")]
+    bodies: usize,
+
+    /// time budget of the force computation in ms, distant bodies stop interacting when exceeded
+    #[argh(option)]
+    force_budget: Option<f32>,
+
+    /// compute the forces with a Barnes-Hut tree of the given opening angle, e.g. 0.5, instead of all the pairs
+    #[argh(option)]
+    barnes_hut: Option<f32>,
+
+    /// expand the Barnes-Hut tree nodes up to their quadrupole moment
+    #[argh(switch)]
+    quadrupole: bool,
+
+    /// treat the bodies as uniform-density spheres, the force decreasing linearly inside them
+    #[argh(switch)]
+    extended_bodies: bool,
+
+    /// uniform external gravitational field accelerating all the bodies alike, as <x>,<y>,<z>
+    #[argh(option)]
+    external_field: Option<ExternalField>,
+
+    /// confine the physics to a plane through the initial position of each body, still rendered in 3D [xy|xz|yz]
+    #[argh(option)]
+    constrain_to_plane: Option<Plane>,
+
+    /// simulated time the path of the selected body is predicted over [default: 50]
+    #[argh(option)]
+    prediction_horizon: Option<f32>,
+
+    /// predict the path of the selected body along its Kepler orbit around its primary instead of with all the bodies
+    #[argh(switch)]
+    two_body_prediction: bool,
+
+    /// number of threads running the systems [default: all the cores]
+    #[argh(option)]
+    threads: Option<usize>,
+
+    /// pause the simulation while the window is unfocused
+    #[argh(switch)]
+    pause_on_focus_loss: bool,
+
+    /// physics while the window is minimized [continue (default)|throttle:<steps per second>|pause]
+    #[argh(option, default = "MinimizedBehavior::Continue")]
+    when_minimized: MinimizedBehavior,
+
+    /// maximum number of bodies, the oldest launched projectiles are despawned past it
+    #[argh(option)]
+    max_bodies: Option<usize>,
+
+    /// file (.ron or .json) the energy, momentum and virial ratio are recorded to, saved on exit
+    #[argh(option)]
+    record: Option<String>,
+
+    /// sound played on each collision, relative to the assets directory
+    #[cfg(feature = "audio")]
+    #[argh(option)]
+    collision_sound: Option<String>,
+
+    /// impact energy of the collisions played at full volume, the weaker ones being quieter
+    #[cfg(feature = "audio")]
+    #[argh(option)]
+    full_volume_energy: Option<f32>,
+
+    /// rescale the velocities every given number of steps to restore the initial energy (non-physical)
+    #[argh(option)]
+    energy_correction: Option<u64>,
+
+    /// record the closest approach of the pairs of bodies coming closer than this distance
+    #[argh(option)]
+    encounters: Option<f32>,
+
+    /// check the energy drift over an orbital period at startup, to catch a time step too large for the scene
+    #[argh(switch)]
+    self_check: bool,
+
+    /// estimate the largest Lyapunov exponent of the scene with a perturbed shadow copy, logged every second
+    #[argh(switch)]
+    lyapunov: bool,
+
+    /// unit the simulated time is displayed in, for the scenes with physical units [seconds|hours|days|years]
+    #[argh(option)]
+    time_unit: Option<TimeUnit>,
+
+    /// rendering quality, sets the MSAA, the mesh subdivisions and the starfield [low|medium|high (default)]
+    #[argh(option, default = "QualityPreset::High")]
+    quality: QualityPreset,
+
+    /// smallest rendered radius of the bodies, for the tiny ones to stay visible [default: 0]
+    #[argh(option, default = "0.0")]
+    min_render_radius: f32,
+
+    /// axis the camera orbits around when dragged horizontally, e.g. z for the ecliptic in the xy plane [x|y (default)|z]
+    #[argh(option, default = "WorldUp::default()")]
+    camera_up: WorldUp,
+
+    /// remap an action to a key as <action>=<key>, e.g. toggle-pause=pause, repeatable
+    #[argh(option)]
+    bind: Vec<KeyRemap>,
+
     /// enable diagnostics in the console
     #[argh(switch, short = 'd')]
     debug: bool,
 }
 
+/// Number of bodies generated by the random startup
+pub struct BodyCount(usize);
+
 enum Startup {
     SolarSystem,
     Figure8,
     Random,
+    Restricted,
+    ChaosPair,
+    Trojans,
 }
 
 impl FromStr for Startup {
@@ -55,8 +300,11 @@ impl FromStr for Startup {
             "solar" => Ok(Self::SolarSystem),
             "figure8" => Ok(Self::Figure8),
             "random" => Ok(Self::Random),
+            "restricted" => Ok(Self::Restricted),
+            "chaos" => Ok(Self::ChaosPair),
+            "trojans" => Ok(Self::Trojans),
             _ => Err(String::from(
-                "Invalid input. Should be: solar[system], figure8 or random",
+                "Invalid input. Should be: solar[system], figure8, random, restricted, chaos or trojans",
             )),
         }
     }
@@ -64,14 +312,69 @@ impl FromStr for Startup {
 
 fn main() {
     let args: Flags = argh::from_env();
+    let scene = args.scene.as_ref().map(|path| {
+        let mut scene = SceneDescription::load(path).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            std::process::exit(1)
+        });
+        // The errors are rejected by the loader, the duplicates are reported below with their handling
+        for issue in validate_scene(&scene)
+            .iter()
+            .filter(|issue| !matches!(issue, ValidationIssue::Coincident(..)))
+        {
+            eprintln!("{}: {}", path, issue);
+        }
+        match scene.sanitize(DUPLICATE_EPSILON, args.duplicates) {
+            Ok(duplicates) => {
+                let outcome = match args.duplicates {
+                    DuplicatePolicy::Merge => "merged",
+                    _ => "kept",
+                };
+                for (i, j) in duplicates {
+                    eprintln!(
+                        "{}: bodies {} and {} are at the same position, {}",
+                        path, i, j, outcome
+                    );
+                }
+            }
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                std::process::exit(1)
+            }
+        }
+        scene
+    });
+
+    let mut key_bindings = KeyBindings::default();
+    for remap in &args.bind {
+        for other in key_bindings.remap(remap.action, remap.key) {
+            eprintln!("{:?} is also bound to {}", remap.key, other.name());
+        }
+    }
+
+    // Inserted before the `NBody` plugin, which fixes the rate of the physics steps
+    let time_step = scene
+        .as_ref()
+        .and_then(|scene| scene.simulation.as_ref()?.time_step())
+        .unwrap_or_default();
 
     let mut app = App::build();
+    app.insert_resource(key_bindings)
+        .insert_resource(time_step);
+    if let Some(threads) = args.threads {
+        let threads = PhysicsThreads(threads);
+        app.insert_resource(threads.task_pool_options())
+            .insert_resource(threads);
+    }
     app.insert_resource(ClearColor(Color::rgb(0.1, 0.1, 0.1)))
         .insert_resource(AmbientLight {
             color: Color::WHITE,
             brightness: 2.0,
         })
-        .insert_resource(Msaa { samples: 4 })
+        .insert_resource(Msaa {
+            samples: args.quality.msaa_samples(),
+        })
+        .insert_resource(args.quality)
         .add_plugins(DefaultPlugins);
 
     if args.debug {
@@ -80,15 +383,181 @@ fn main() {
     };
 
     app.add_plugin(PanOrbitCameraPlugin)
+        .add_plugin(HudPlugin)
         .add_plugin(NBody {
             speed_factor: args.speed,
         })
+        .add_plugin(StarLightPlugin)
+        .add_plugin(SelectionPlugin)
+        .add_plugin(RestrictedThreeBodyPlugin)
+        .add_plugin(TrailPlugin)
+        .add_plugin(CollisionPlugin)
+        .add_plugin(PredictionPlugin)
+        .add_plugin(LensingPlugin)
+        .add_plugin(LauncherPlugin)
+        .add_plugin(AdaptivePerformancePlugin)
+        .add_plugin(FrameRatePlugin)
+        .add_plugin(PhysicsDiagnosticsPlugin)
+        .add_plugin(ResonancePlugin)
+        .add_plugin(SelfCheckPlugin)
+        .add_plugin(HeatmapPlugin)
+        .add_plugin(ReferenceGridPlugin)
+        .add_plugin(MeasurePlugin)
+        .add_plugin(OnRailsPlugin)
+        .add_plugin(HillSpherePlugin)
+        .add_plugin(QualityPlugin)
+        .add_plugin(RenderRadiusPlugin)
+        .add_plugin(GravityControlPlugin)
+        .add_plugin(PotentialContoursPlugin)
+        .add_plugin(CometPlugin)
+        .add_plugin(CloseEncounterPlugin)
+        .add_plugin(LyapunovPlugin)
+        .add_plugin(AxisGizmoPlugin)
+        .add_plugin(OrbitViewPlugin)
+        .add_plugin(OrbitInfoPlugin)
+        .add_plugin(DeflectionRingsPlugin)
+        .add_plugin(ImpulsePlugin)
+        .add_plugin(TimelinePlugin)
+        .add_plugin(TintPlugin)
+        .add_plugin(SphereOfInfluencePlugin)
+        .add_plugin(CensusPlugin)
+        .add_plugin(DistanceListPlugin)
+        .add_plugin(OrbitEllipsesPlugin)
+        .add_plugin(ExtremesPlugin)
+        .add_plugin(SceneResetPlugin)
+        .add_plugin(CameraBookmarksPlugin)
+        .add_plugin(BarycenterPlugin)
+        .add_plugin(HelpPlugin)
         .add_startup_system(add_starry_background.system());
 
-    match args.startup {
-        Startup::SolarSystem => app.add_startup_system(solar_system.system()),
-        Startup::Figure8 => app.add_startup_system(figure8_bodies.system()),
-        Startup::Random => app.add_startup_system(random_bodies.system()),
+    #[cfg(feature = "ui")]
+    app.add_plugin(DashboardPlugin);
+
+    #[cfg(feature = "audio")]
+    {
+        app.add_plugin(CollisionAudioPlugin);
+        if let Some(path) = &args.collision_sound {
+            let sound = app
+                .world_mut()
+                .get_resource::<AssetServer>()
+                .expect("the asset server is added with the default plugins")
+                .load(path.as_str());
+            app.insert_resource(AudioConfig {
+                collision_sound: Some(sound),
+                reference_energy: args.full_volume_energy,
+                ..Default::default()
+            });
+        }
+    }
+
+    if let Some(target_ms) = args.force_budget {
+        app.insert_resource(AdaptivePerformance {
+            enabled: true,
+            target_ms,
+            ..Default::default()
+        });
+    }
+
+    app.insert_resource(MaxBodies(args.max_bodies))
+        .insert_resource(MinRenderRadius(args.min_render_radius))
+        .insert_resource(args.camera_up)
+        .insert_resource(PauseOnFocusLoss(args.pause_on_focus_loss))
+        .insert_resource(args.when_minimized)
+        .insert_resource(UnitSystem {
+            display_time_unit: args.time_unit,
+            ..Default::default()
+        });
+
+    if let Some(theta) = args.barnes_hut {
+        app.insert_resource(ForceMode::BarnesHut {
+            theta,
+            multipole_order: if args.quadrupole {
+                MultipoleOrder::Quadrupole
+            } else {
+                MultipoleOrder::Monopole
+            },
+        });
+    }
+
+    app.insert_resource(ExtendedBody(args.extended_bodies))
+        .insert_resource(ConstrainToPlane(
+            args.constrain_to_plane.map(|plane| plane.normal()),
+        ))
+        .insert_resource(args.external_field.unwrap_or_default());
+
+    let mut prediction = TrajectoryPrediction::default();
+    if let Some(horizon) = args.prediction_horizon {
+        let points = prediction.steps / prediction.sample_every;
+        prediction = prediction.with_horizon(horizon, points, time_step.0);
+    }
+    if args.two_body_prediction {
+        prediction.model = PredictionModel::TwoBody;
+    }
+    app.insert_resource(prediction);
+
+    if let Some(integrator) = args.integrator {
+        app.insert_resource(integrator);
+    }
+    if let Some(softening) = args.softening {
+        app.insert_resource(softening);
+    }
+    if let Some(mode) = args.collisions {
+        app.insert_resource(mode);
+    }
+    if args.continuous_collisions {
+        app.insert_resource(ContinuousCollision(true));
+    }
+    if let Some(disruption) = args.tidal_disruption {
+        app.insert_resource(disruption);
+    }
+    app.insert_resource(BodyCount(args.bodies));
+
+    if let Some(interval) = args.energy_correction {
+        app.insert_resource(EnergyCorrection {
+            interval,
+            enabled: true,
+            ..Default::default()
+        });
+    }
+
+    if let Some(threshold) = args.encounters {
+        app.insert_resource(CloseEncounterStats::new(threshold));
+    }
+
+    if args.self_check {
+        app.insert_resource(IntegratorSelfCheck {
+            enabled: true,
+            ..Default::default()
+        });
+    }
+
+    if args.lyapunov {
+        app.insert_resource(LyapunovEstimator::new(100, 1e-4));
+    }
+
+    if let Some(path) = args.record {
+        app.insert_resource(DiagnosticsRecorder::new(100, path));
+    }
+
+    match (args.startup, scene) {
+        (Startup::ChaosPair, scene) => {
+            let scene = scene.unwrap_or_else(|| {
+                SceneDescription::from_ron(include_str!("../assets/scenes/figure8.ron"))
+                    .expect("the figure-8 scene is valid")
+            });
+            app.add_plugin(ChaosPairPlugin)
+                .insert_resource(scene)
+                .insert_resource(Perturbation(Vec3::X * args.perturbation))
+                .add_startup_system(chaos_pair_bodies.system())
+        }
+        (_, Some(scene)) => app
+            .insert_resource(scene)
+            .add_startup_system(scene_bodies.system()),
+        (Startup::SolarSystem, None) => app.add_startup_system(solar_system.system()),
+        (Startup::Figure8, None) => app.add_startup_system(figure8_bodies.system()),
+        (Startup::Random, None) => app.add_startup_system(random_bodies.system()),
+        (Startup::Restricted, None) => app.add_startup_system(restricted_three_body.system()),
+        (Startup::Trojans, None) => app.add_startup_system(trojan_asteroids.system()),
     };
 
     app.run()
@@ -123,26 +592,188 @@ fn add_starry_background(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    quality: Res<QualityPreset>,
 ) {
     let mut rng = rand::thread_rng();
 
     let stars: Vec<Vec3> = UnitSphere
         .sample_iter(&mut rng)
-        .take(1000)
+        .take(quality.star_count())
         .map(|xyz| 800.0 * Vec3::new(xyz[0], xyz[1], xyz[2]))
         .collect();
 
     stars.into_iter().for_each(|s| {
-        commands.spawn_bundle(PbrBundle {
-            transform: Transform::from_translation(s),
-            mesh: meshes.add(Mesh::from(shape::Icosphere {
-                radius: 1.0,
-                subdivisions: 2,
-            })),
-            material: materials.add(Color::WHITE.into()),
+        commands
+            .spawn_bundle(PbrBundle {
+                transform: Transform::from_translation(s),
+                mesh: meshes.add(Mesh::from(shape::Icosphere {
+                    radius: 1.0,
+                    subdivisions: quality.subdivisions().min(2),
+                })),
+                material: materials.add(Color::WHITE.into()),
+                ..Default::default()
+            })
+            .insert(LensedBackground { position: s });
+    })
+}
+
+/// Bodies and simulation parameters loaded from a scene file
+#[allow(clippy::too_many_arguments)]
+pub fn scene_bodies(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut g: ResMut<Gravity>,
+    mut softening: ResMut<SofteningKernel>,
+    mut integrator: ResMut<Integrator>,
+    mut collisions: ResMut<CollisionMode>,
+    mut continuous: ResMut<ContinuousCollision>,
+    mut tidal_disruption: ResMut<TidalDisruption>,
+    scene: Res<SceneDescription>,
+    quality: Res<QualityPreset>,
+    asset_server: Res<AssetServer>,
+) {
+    if let Some(params) = &scene.simulation {
+        params.apply(&mut g, &mut softening, &mut integrator);
+        params.apply_collisions(&mut collisions, &mut continuous, &mut tidal_disruption);
+    }
+
+    let scale = scene.scale();
+    let mut extent: f32 = 1.0;
+    for body in scene.bodies.iter() {
+        extent = extent.max(body.scaled_state(scale).0.length());
+        spawn_scene_body(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &asset_server,
+            body,
+            scale,
+            quality.subdivisions(),
+        );
+    }
+
+    spawn_z_camera(&mut commands, 5.0 * extent);
+    spawn_z_light(&mut commands, 5.0 * extent, 200.0 * extent, 20.0 * extent);
+}
+
+fn spawn_scene_body(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    asset_server: &AssetServer,
+    body: &BodyDescription,
+    scale: f32,
+    subdivisions: usize,
+) -> Entity {
+    let (pos, vel) = body.scaled_state(scale);
+    let color = body
+        .color
+        .map_or(Color::WHITE, |(r, g, b)| Color::rgb(r, g, b));
+
+    let mut entity = commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Icosphere {
+            radius: body.radius,
+            subdivisions,
+        })),
+        material: materials.add(StandardMaterial {
+            base_color: color,
+            base_color_texture: body
+                .texture
+                .as_ref()
+                .map(|path| asset_server.load(path.as_str())),
+            emissive: if body.star { color } else { Color::BLACK },
             ..Default::default()
+        }),
+        ..Default::default()
+    });
+    entity
+        .insert_bundle(BodyBundle::new(body.mass, pos, vel))
+        .insert(Radius(body.radius))
+        .insert(if body.star {
+            BodyClass::Star
+        } else if body.comet {
+            BodyClass::Comet
+        } else {
+            BodyClass::Planet
         });
-    })
+    if let Some(length) = body.trail {
+        entity.insert(Trail::new(length));
+    }
+    if let Some(kelvin) = body.temperature {
+        entity.insert(StarTemperature(kelvin));
+    }
+    if body.accretor {
+        entity.insert(CentralAccretor);
+    }
+    entity.id()
+}
+
+/// Offset of the first body of the perturbed replica of the chaos startup
+pub struct Perturbation(Vec3);
+
+/// Two replicas of the scene evolving side by side in their own universe,
+/// the first body of the second one being offset by the `Perturbation`.
+/// The replica is rendered in lighter colors.
+#[allow(clippy::too_many_arguments)]
+pub fn chaos_pair_bodies(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut g: ResMut<Gravity>,
+    mut softening: ResMut<SofteningKernel>,
+    mut integrator: ResMut<Integrator>,
+    mut collisions: ResMut<CollisionMode>,
+    mut continuous: ResMut<ContinuousCollision>,
+    mut tidal_disruption: ResMut<TidalDisruption>,
+    mut chaos: ResMut<ChaosPair>,
+    scene: Res<SceneDescription>,
+    perturbation: Res<Perturbation>,
+    quality: Res<QualityPreset>,
+    asset_server: Res<AssetServer>,
+) {
+    if let Some(params) = &scene.simulation {
+        params.apply(&mut g, &mut softening, &mut integrator);
+        params.apply_collisions(&mut collisions, &mut continuous, &mut tidal_disruption);
+    }
+    let mut replica = match scene.perturbed(0, perturbation.0) {
+        Some(replica) => replica,
+        None => return,
+    };
+    for body in replica.bodies.iter_mut() {
+        let (r, g, b) = body.color.unwrap_or((1.0, 1.0, 1.0));
+        body.color = Some(((1.0 + r) / 2.0, (1.0 + g) / 2.0, (1.0 + b) / 2.0));
+    }
+
+    let scale = scene.scale();
+    let mut extent: f32 = 1.0;
+    for (body, twin) in scene.bodies.iter().zip(replica.bodies.iter()) {
+        extent = extent.max(body.scaled_state(scale).0.length());
+        let subdivisions = quality.subdivisions();
+        let original = spawn_scene_body(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &asset_server,
+            body,
+            scale,
+            subdivisions,
+        );
+        let twin = spawn_scene_body(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &asset_server,
+            twin,
+            scale,
+            subdivisions,
+        );
+        commands.entity(twin).insert(Universe(1));
+        chaos.twins.push((original, twin));
+    }
+
+    spawn_z_camera(&mut commands, 5.0 * extent);
+    spawn_z_light(&mut commands, 5.0 * extent, 200.0 * extent, 20.0 * extent);
 }
 
 /// Figure-8 solution
@@ -157,6 +788,7 @@ pub fn figure8_bodies(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut g: ResMut<Gravity>,
+    quality: Res<QualityPreset>,
 ) {
     // Set G = 1.0
     g.0 = 1.0;
@@ -176,12 +808,13 @@ pub fn figure8_bodies(
             .spawn_bundle(PbrBundle {
                 mesh: meshes.add(Mesh::from(shape::Icosphere {
                     radius: 0.1,
-                    subdivisions: 5,
+                    subdivisions: quality.subdivisions(),
                 })),
                 material: materials.add(col.into()),
                 ..Default::default()
             })
-            .insert_bundle(BodyBundle::new(1.0, pos, vel));
+            .insert_bundle(BodyBundle::new(1.0, pos, vel))
+            .insert(Trail::new(600));
     }
 
     spawn_z_camera(&mut commands, 5.0);
@@ -194,6 +827,8 @@ pub fn random_bodies(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut g: ResMut<Gravity>,
+    count: Res<BodyCount>,
+    quality: Res<QualityPreset>,
 ) {
     // Set G = 1.0
     g.0 = 1.0;
@@ -204,18 +839,16 @@ pub fn random_bodies(
         .spawn_bundle(PbrBundle {
             mesh: meshes.add(Mesh::from(shape::Icosphere {
                 radius: 1.0,
-                subdivisions: 5,
+                subdivisions: quality.subdivisions(),
             })),
             material: materials.add(Color::YELLOW.into()),
             ..Default::default()
         })
         .insert_bundle(BodyBundle::new(10_000.0, Vec3::ZERO, Vec3::ZERO))
-        .insert(Light {
-            color: Color::WHITE,
-            ..Default::default()
-        });
+        .insert(BodyClass::Star)
+        .insert(Radius(1.0));
 
-    (0..10).for_each(|_| {
+    (0..count.0).for_each(|_| {
         let pos = Vec3::new(
             rng.gen_range(-10.0..10.0),
             rng.gen_range(-10.0..10.0),
@@ -232,7 +865,7 @@ pub fn random_bodies(
             .spawn_bundle(PbrBundle {
                 mesh: meshes.add(Mesh::from(shape::Icosphere {
                     radius: 0.5,
-                    subdivisions: 5,
+                    subdivisions: quality.subdivisions(),
                 })),
                 material: materials.add(
                     Color::rgb(
@@ -244,43 +877,253 @@ pub fn random_bodies(
                 ),
                 ..Default::default()
             })
-            .insert_bundle(BodyBundle::new(1.0, pos, vel));
+            .insert_bundle(BodyBundle::new(1.0, pos, vel))
+            .insert(Radius(0.5))
+            .insert(Trail::new(300));
     });
 
     spawn_z_camera(&mut commands, 50.0);
     spawn_z_light(&mut commands, 10.0, 2000.0, 50.0);
 }
 
-/// Add the sun and all the planets of the Solar system (+ Pluto)
-/// Units are scaled:
-/// Mass = 10^24 kg
-/// Distance = AU (= 1.5 x 10^11 m)
-/// Velocity = AU / Day
-/// Acceleration = AU / DAY^2
-pub fn solar_system(
+/// Restricted three-body problem: two primaries on circular orbits around their barycenter
+/// and a grid of massless test particles, initially at rest in the co-rotating frame.
+/// Test particles escaping the system turn red, the Jacobi constant of the selected ones is logged.
+pub fn restricted_three_body(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut g: ResMut<Gravity>,
+    quality: Res<QualityPreset>,
 ) {
-    // Scale for rendering: 1 unit = 0.1 AU
-    const AU_TO_UNIT_SCALE: f32 = 10.0;
-    const DAY: f32 = 86_400.0;
+    // Set G = 1.0
+    g.0 = 1.0;
+
+    const SEPARATION: f32 = 10.0;
+    const GRID_SIZE: i32 = 10;
+    const GRID_SPACING: f32 = 2.0;
+
+    let (m1, m2) = (900.0, 100.0);
+    // Kepler's third law
+    let omega = Vec3::Z * (g.0 * (m1 + m2) / SEPARATION.powi(3)).sqrt();
+
+    // Primaries on the x-axis with the barycenter at the origin
+    let primaries = [
+        (m1, -SEPARATION * m2 / (m1 + m2), 1.0, Color::YELLOW),
+        (m2, SEPARATION * m1 / (m1 + m2), 0.5, Color::ORANGE),
+    ];
+    let primary_ids: Vec<Entity> = primaries
+        .iter()
+        .map(|(mass, x, radius, col)| {
+            let pos = Vec3::new(*x, 0.0, 0.0);
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Icosphere {
+                        radius: *radius,
+                        subdivisions: quality.subdivisions(),
+                    })),
+                    material: materials.add((*col).into()),
+                    ..Default::default()
+                })
+                .insert_bundle(BodyBundle::new(*mass, pos, corotating_velocity(omega, pos)))
+                .insert(Radius(*radius))
+                .id()
+        })
+        .collect();
+    commands.insert_resource(Primaries(primary_ids[0], primary_ids[1]));
+
+    let particle_mesh = meshes.add(Mesh::from(shape::Icosphere {
+        radius: 0.1,
+        subdivisions: quality.subdivisions().min(2),
+    }));
+    for i in -GRID_SIZE..=GRID_SIZE {
+        for j in -GRID_SIZE..=GRID_SIZE {
+            let pos = GRID_SPACING * Vec3::new(i as f32, j as f32, 0.0);
+            // Skip the particles too close to a primary
+            if primaries
+                .iter()
+                .any(|(_, x, radius, _)| pos.distance(Vec3::new(*x, 0.0, 0.0)) < 2.0 * radius)
+            {
+                continue;
+            }
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: particle_mesh.clone(),
+                    material: materials.add(Color::GREEN.into()),
+                    ..Default::default()
+                })
+                .insert_bundle(BodyBundle::new(0.0, pos, corotating_velocity(omega, pos)))
+                .insert(TestParticle);
+        }
+    }
+    commands.insert_resource(EscapeRadius(3.0 * SEPARATION));
+
+    spawn_z_camera(&mut commands, 60.0);
+    spawn_z_light(&mut commands, 10.0, 2000.0, 50.0);
+}
+
+/// Scale for rendering of the Solar System startups: 1 unit = 0.1 AU
+const AU_TO_UNIT_SCALE: f32 = 10.0;
+const DAY: f32 = 86_400.0;
 
+/// Gravitational constant `g` in the units of the Solar System startups, see `solar_system`
+fn solar_gravity(g: f32) -> f32 {
     // Scale the gravitational constant accordingly to account for the units scaling
     // ```
     // G = m^3 / kg / s^2
     // G = (1.5^3 * 10^11 / 10 m)^3 / 10^24 kg / Day^2
     // G' = G * Day^2 * 10-6 / 1.5^3
     // ```
-    g.0 *= DAY * DAY * 10.0f32.powi(-6) / 1.5f32.powi(3);
+    g * DAY * DAY * 10.0f32.powi(-6) / 1.5f32.powi(3)
+}
 
-    let sun = BodyBundle::new(1_988_500.0, Vec3::ZERO, Vec3::ZERO);
+/// The Sun and Jupiter on a circular orbit, with swarms of massless Trojan asteroids
+/// librating around Jupiter's triangular Lagrange points L4 and L5, in the units of `solar_system`.
+/// As in the `restricted` startup, the Jacobi constant of the selected asteroids is logged
+/// and the asteroids escaping the system turn red.
+#[allow(clippy::too_many_arguments)]
+pub fn trojan_asteroids(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut g: ResMut<Gravity>,
+    mut star_lights: ResMut<StarLights>,
+    mut units: ResMut<UnitSystem>,
+    quality: Res<QualityPreset>,
+) {
+    const JUPITER_AU: f32 = 5.2;
+    const ASTEROIDS_PER_POINT: usize = 50;
+    g.0 = solar_gravity(g.0);
+
+    let sun = catalog_entry("Sun").expect("Sun missing from the catalog");
+    let jupiter = catalog_body("Jupiter").expect("Jupiter missing from the catalog");
+    let (m1, m2) = (sun.mass, jupiter.mass);
+    let separation = JUPITER_AU * AU_TO_UNIT_SCALE;
+    // Kepler's third law
+    let omega = Vec3::Z * (g.0 * (m1 + m2) / separation.powi(3)).sqrt();
+
+    // Primaries on the x-axis with the barycenter at the origin
+    let sun_pos = Vec3::new(-separation * m2 / (m1 + m2), 0.0, 0.0);
+    let jupiter_pos = Vec3::new(separation * m1 / (m1 + m2), 0.0, 0.0);
+    let sun_radius = 2.8;
+    let sun_id = commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Icosphere {
+                radius: sun_radius,
+                subdivisions: 2 * quality.subdivisions(),
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::YELLOW,
+                roughness: 0.6,
+                emissive: Color::YELLOW,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .insert_bundle(BodyBundle::new(
+            m1,
+            sun_pos,
+            corotating_velocity(omega, sun_pos),
+        ))
+        .insert(BodyClass::Star)
+        .insert(Radius(sun_radius))
+        .id();
+    let jupiter_color = jupiter
+        .color
+        .map_or(Color::WHITE, |(r, g, b)| Color::rgb(r, g, b));
+    let jupiter_id = commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Icosphere {
+                radius: jupiter.radius,
+                subdivisions: quality.subdivisions(),
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: jupiter_color,
+                roughness: 0.6,
+                reflectance: 0.1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .insert_bundle(BodyBundle::new(
+            m2,
+            jupiter_pos,
+            corotating_velocity(omega, jupiter_pos),
+        ))
+        .insert(BodyClass::Planet)
+        .insert(Radius(jupiter.radius))
+        .insert(Trail::new(2000))
+        .id();
+    commands.insert_resource(Primaries(sun_id, jupiter_id));
+    star_lights.intensity = 50_000.0;
+    star_lights.range = 2000.0;
+    units.length_unit = String::from("AU");
+    units.render_scale = AU_TO_UNIT_SCALE;
+    units.time_unit = Some(TimeUnit::Days);
+
+    // Within about 10° along the orbit and 0.5% of the radius and orbital speed of the triangular points
+    let mut rng = rand::thread_rng();
+    let (l4, l5) = triangular_points(sun_pos, jupiter_pos, omega);
+    let particle_mesh = meshes.add(Mesh::from(shape::Icosphere {
+        radius: 0.2,
+        subdivisions: quality.subdivisions().min(2),
+    }));
+    for (point, color) in [(l4, Color::GREEN), (l5, Color::CYAN)].iter() {
+        let material = materials.add((*color).into());
+        let swarm = trojan_swarm(
+            *point,
+            omega,
+            ASTEROIDS_PER_POINT,
+            0.2,
+            0.005 * separation,
+            0.005 * omega.length() * separation,
+            &mut rng,
+        );
+        for (pos, vel) in swarm {
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: particle_mesh.clone(),
+                    material: material.clone(),
+                    ..Default::default()
+                })
+                .insert_bundle(BodyBundle::new(0.0, pos, vel))
+                .insert(TestParticle);
+        }
+    }
+    commands.insert_resource(EscapeRadius(2.0 * separation));
+
+    spawn_z_camera(&mut commands, 150.0);
+}
+
+/// Add the sun and all the planets of the Solar system (+ Pluto)
+/// Units are scaled:
+/// Mass = 10^24 kg
+/// Distance = AU (= 1.5 x 10^11 m)
+/// Velocity = AU / Day
+/// Acceleration = AU / DAY^2
+#[allow(clippy::too_many_arguments)]
+pub fn solar_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut g: ResMut<Gravity>,
+    mut star_lights: ResMut<StarLights>,
+    mut heatmap: ResMut<EncounterHeatmap>,
+    mut grid: ResMut<ReferenceGrid>,
+    mut units: ResMut<UnitSystem>,
+    mut contours: ResMut<PotentialContours>,
+    quality: Res<QualityPreset>,
+) {
+    g.0 = solar_gravity(g.0);
+
+    let sun = catalog_entry("Sun").expect("Sun missing from the catalog");
+    // Shrunk so that Mercury stays well outside of it
+    let sun_radius = 2.8;
     commands
         .spawn_bundle(PbrBundle {
             mesh: meshes.add(Mesh::from(shape::Icosphere {
-                radius: 2.8,
-                subdivisions: 10,
+                radius: sun_radius,
+                subdivisions: 2 * quality.subdivisions(),
             })),
             material: materials.add(StandardMaterial {
                 base_color: Color::YELLOW.into(),
@@ -290,119 +1133,147 @@ pub fn solar_system(
             }),
             ..Default::default()
         })
-        .insert_bundle(sun)
-        .insert(Light {
-            color: Color::WHITE,
-            intensity: 50_000.0,
-            range: 2000.0,
-            ..Default::default()
-        });
+        .insert_bundle(BodyBundle::new(sun.mass, Vec3::ZERO, Vec3::ZERO))
+        .insert(BodyClass::Star)
+        .insert(Radius(sun_radius));
+    star_lights.intensity = 50_000.0;
+    star_lights.range = 2000.0;
+    // Up to Pluto
+    heatmap.extent = 400.0;
+    contours.extent = 400.0;
+    // 1 AU between the lines
+    grid.spacing = AU_TO_UNIT_SCALE;
+    grid.extent = 400.0;
+    units.length_unit = String::from("AU");
+    units.render_scale = AU_TO_UNIT_SCALE;
+    units.time_unit = Some(TimeUnit::Days);
 
     macro_rules! spawn_planet {
-    ($name:ident, m=$mass:literal, pos=($($pos:literal),+), vel=($($vel:literal),+), r=$radius:literal, col=$col:expr $(,)?) => {
-        let $name = BodyBundle::new($mass, AU_TO_UNIT_SCALE * Vec3::new($($pos),+), AU_TO_UNIT_SCALE * Vec3::new($($vel),+));
+    ($name:literal, pos=($($pos:literal),+), vel=($($vel:literal),+) $(,)?) => {
+        let planet = catalog_body($name).expect("Planet missing from the catalog");
+        let color = planet.color.map_or(Color::WHITE, |(r, g, b)| Color::rgb(r, g, b));
         commands
             .spawn_bundle(PbrBundle {
                 mesh: meshes.add(Mesh::from(shape::Icosphere {
-                    radius: $radius / 10_000.0,
-                    subdivisions: 5,
+                    radius: planet.radius,
+                    subdivisions: quality.subdivisions(),
                 })),
                 material: materials.add(StandardMaterial {
-                    base_color: $col.into(),
+                    base_color: color,
                     roughness: 0.6,
                     reflectance: 0.1,
                     ..Default::default()
                 }),
                 ..Default::default()
             })
-            .insert_bundle($name);
+            .insert_bundle(BodyBundle::new(planet.mass, AU_TO_UNIT_SCALE * Vec3::new($($pos),+), AU_TO_UNIT_SCALE * Vec3::new($($vel),+)))
+            .insert(BodyClass::Planet)
+            .insert(Radius(planet.radius))
+            .insert(Trail::new(2000));
     };
 }
     // Data pulled from JPL Horizons as of 2021-04-18
     // https://ssd.jpl.nasa.gov/horizons.cgi
     #[rustfmt::skip]
     spawn_planet!(
-        mercury,
-        m=0.3302,
+        "Mercury",
         pos=(3.044170697902298E-01, 1.295114876282963E-01, -1.734104195212369E-02),
         vel=(-1.648628006573339E-02, 2.713585294570181E-02, 3.729745700066048E-03),
-        r=2440.0,
-        col=Color::ORANGE_RED,
     );
 
     #[rustfmt::skip]
     spawn_planet!(
-        venus,
-        m=4.868,
+        "Venus",
         pos=(5.387247476293335E-01, 4.820230339302334E-01, -2.447215630265642E-02),
         vel=(-1.354845714410186E-02, 1.498631588335955E-02, 9.874886299710420E-04),
-        r=6051.84,
-        col=Color::ORANGE,
     );
 
     #[rustfmt::skip]
     spawn_planet!(
-        earth,
-        m=5.97219,
+        "Earth",
         pos=(-8.873674344461769E-01, -4.697992257377307E-01, 2.381003809013169E-05),
         vel=(7.775921491692710E-03, -1.526923260035268E-02, 1.329236295796724E-07),
-        r=6371.01,
-        col=Color::BLUE,
     );
     #[rustfmt::skip]
     spawn_planet!(
-        mars,
-        m=0.64171 ,
+        "Mars",
         pos=(-7.669365607923907E-01, 1.437715683938847E+00, 4.894216325150345E-02),
         vel=(-1.181841087219943E-02, -5.396860897762226E-03, 1.768153357356463E-04),
-        r=3389.92,
-        col=Color::RED,
     );
     #[rustfmt::skip]
     spawn_planet!(
-        jupiter,
-        m=1898.187,
+        "Jupiter",
         pos=(3.638338491378654E+00, -3.517196054099748E+00, -6.679350348303023E-02),
         vel=(5.159638546395391E-03, 5.787459942412818E-03, -1.394560955359292E-04),
-        r=69911.0,
-        col=Color::BISQUE,
     );
     #[rustfmt::skip]
     spawn_planet!(
-        saturn,
-        m=568.34,
+        "Saturn",
         pos=(5.946821461107053E+00, -8.000786524501104E+00, -9.757186586148088E-02),
         vel=(4.173453543382942E-03, 3.320093983241896E-03, -2.235785645393874E-04),
-        r=58232.0,
-        col=Color::GOLD,
     );
     #[rustfmt::skip]
     spawn_planet!(
-        uranus,
-        m=86.813,
+        "Uranus",
         pos=(1.507889019392361E+01, 1.276651492152234E+01, -1.479475386482554E-01),
         vel=(-2.565701401124483E-03, 2.824133197172000E-03, 4.363663945419187E-05),
-        r=25362.0,
-        col=Color::AQUAMARINE,
     );
     #[rustfmt::skip]
     spawn_planet!(
-        neptune,
-        m=102.4126,
+        "Neptune",
         pos=(2.951580077181258E+01, -4.898113153026739E+00, -5.794227616270428E-01),
         vel=(4.988324362083494E-04, 3.122660147661985E-03, -7.542919141146281E-05),
-        r=24622.0,
-        col=Color::BLUE
     );
     #[rustfmt::skip]
     spawn_planet!(
-        pluto,
-        m=0.013030,
+        "Pluto",
         pos=(1.437474170944128E+01, -3.109027718169479E+01, -8.297576366914019E-01),
         vel=(2.929346098298212E-03, 6.560315763737425E-04, -9.025427350060328E-04),
-        r=11880.3,
-        col=Color::GRAY,
     );
 
     spawn_z_camera(&mut commands, 200.0);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Flags, argh::EarlyExit> {
+        Flags::from_args(&["nbody"], args)
+    }
+
+    #[test]
+    fn arguments_map_to_the_configuration() {
+        let args = parse(&[
+            "--startup",
+            "random",
+            "--speed",
+            "20",
+            "--integrator",
+            "leapfrog",
+            "--softening",
+            "plummer:0.1",
+            "--bodies",
+            "500",
+        ])
+        .unwrap();
+        assert!(matches!(args.startup, Startup::Random));
+        assert_eq!(args.speed, 20.0);
+        assert_eq!(args.integrator, Some(Integrator::Leapfrog));
+        assert_eq!(args.softening, Some(SofteningKernel::Plummer { eps: 0.1 }));
+        assert_eq!(args.bodies, 500);
+
+        let args = parse(&[]).unwrap();
+        assert!(matches!(args.startup, Startup::SolarSystem));
+        assert_eq!(args.speed, 1.0);
+        assert_eq!(args.integrator, None);
+        assert_eq!(args.bodies, 10);
+    }
+
+    #[test]
+    fn invalid_values_explain_the_expected_ones() {
+        let error = parse(&["--integrator", "magic"]).err().unwrap();
+        assert!(error.status.is_err());
+        assert!(error.output.contains("euler, leapfrog, verlet, rk4"));
+    }
+}
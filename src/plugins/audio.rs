@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+
+use super::collision::CollisionEvent;
+
+/// Plays a short sound on each collision, e.g. the merges and the shattering of bodies
+pub struct CollisionAudioPlugin;
+
+impl Plugin for CollisionAudioPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AudioConfig>()
+            .add_system(play_collision_sounds.system());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioConfig {
+    /// Nothing plays without a sound
+    pub collision_sound: Option<Handle<AudioSource>>,
+    pub enabled: bool,
+    /// Impact energy played at full volume, the quieter collisions being scaled down;
+    /// all of them play at full volume if `None`
+    pub reference_energy: Option<f32>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            collision_sound: None,
+            enabled: true,
+            reference_energy: None,
+        }
+    }
+}
+
+/// Plays the sounds requested by the systems, for them to be checked without an audio output
+pub trait SoundPlayer {
+    /// `volume` ranges from 0 (silent) to 1 (full volume)
+    fn play_sound(&self, sound: Handle<AudioSource>, volume: f32);
+}
+
+impl SoundPlayer for Audio {
+    /// Bevy 0.5 plays every sound at full volume
+    fn play_sound(&self, sound: Handle<AudioSource>, _volume: f32) {
+        self.play(sound);
+    }
+}
+
+/// Volume of a collision of the given impact energy, following the perceived loudness:
+/// ```text
+/// volume = sqrt(E / E_ref)
+/// ```
+/// capped at full volume.
+pub fn collision_volume(energy: f32, reference_energy: Option<f32>) -> f32 {
+    match reference_energy {
+        Some(reference) if reference > 0.0 => (energy.max(0.0) / reference).sqrt().min(1.0),
+        _ => 1.0,
+    }
+}
+
+/// Requests a sound from the `player` for each collision, returning the number of sounds requested
+pub fn play_collisions<'a>(
+    config: &AudioConfig,
+    collisions: impl IntoIterator<Item = &'a CollisionEvent>,
+    player: &impl SoundPlayer,
+) -> usize {
+    let sound = match (&config.collision_sound, config.enabled) {
+        (Some(sound), true) => sound,
+        _ => return 0,
+    };
+    let mut played = 0;
+    for CollisionEvent(record) in collisions {
+        let volume = collision_volume(record.impact_energy(), config.reference_energy);
+        if volume > 0.0 {
+            player.play_sound(sound.clone(), volume);
+            played += 1;
+        }
+    }
+    played
+}
+
+fn play_collision_sounds(
+    config: Res<AudioConfig>,
+    audio: Res<Audio>,
+    mut collisions: EventReader<CollisionEvent>,
+) {
+    play_collisions(&config, collisions.iter(), &*audio);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::plugins::collision::CollisionRecord;
+
+    /// Records the play requests instead of playing them
+    #[derive(Default)]
+    struct MockPlayer {
+        requests: RefCell<Vec<(Handle<AudioSource>, f32)>>,
+    }
+
+    impl SoundPlayer for MockPlayer {
+        fn play_sound(&self, sound: Handle<AudioSource>, volume: f32) {
+            self.requests.borrow_mut().push((sound, volume));
+        }
+    }
+
+    fn collision(masses: (f32, f32), impact_speed: f32) -> CollisionEvent {
+        CollisionEvent(CollisionRecord {
+            time: 0.0,
+            bodies: (Entity::new(0), Entity::new(1)),
+            masses,
+            impact_speed,
+            resulting_mass: masses.0 + masses.1,
+        })
+    }
+
+    #[test]
+    fn collisions_request_a_sound_scaled_by_their_energy() {
+        let sound = Handle::<AudioSource>::default();
+        let config = AudioConfig {
+            collision_sound: Some(sound.clone()),
+            enabled: true,
+            reference_energy: Some(4.0),
+        };
+        // Impact energies of 1, 16 and 0
+        let collisions = [
+            collision((1.0, 1.0), 2.0),
+            collision((2.0, 2.0), 4.0),
+            collision((1.0, 0.0), 3.0),
+        ];
+        let player = MockPlayer::default();
+
+        assert_eq!(play_collisions(&config, collisions.iter(), &player), 2);
+        assert_eq!(
+            *player.requests.borrow(),
+            vec![(sound.clone(), 0.5), (sound, 1.0)]
+        );
+    }
+
+    #[test]
+    fn nothing_plays_when_disabled_or_without_a_sound() {
+        let collisions = [collision((1.0, 1.0), 2.0)];
+        let player = MockPlayer::default();
+        let disabled = AudioConfig {
+            collision_sound: Some(Handle::default()),
+            enabled: false,
+            reference_energy: None,
+        };
+
+        assert_eq!(play_collisions(&disabled, collisions.iter(), &player), 0);
+        assert_eq!(
+            play_collisions(&AudioConfig::default(), collisions.iter(), &player),
+            0
+        );
+        assert!(player.requests.borrow().is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use bevy::render::camera::PerspectiveProjection;
+
+use super::pan_orbit_camera::PanOrbitCamera;
+use super::prediction::line_mesh;
+
+/// Small X/Y/Z triad in the bottom-left corner of the screen, showing the orientation of the world axes
+/// as the camera orbits
+pub struct AxisGizmoPlugin;
+
+impl Plugin for AxisGizmoPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AxisGizmo>()
+            .add_startup_system(spawn_axis_gizmo.system())
+            .add_system(follow_camera.system());
+    }
+}
+
+pub struct AxisGizmo {
+    pub enabled: bool,
+    /// Length of the arrows, as a fraction of the screen height
+    pub size: f32,
+    /// Position of the center of the triad in normalized device coordinates, `(-1, -1)` being the bottom-left corner
+    pub anchor: Vec2,
+}
+
+impl Default for AxisGizmo {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            size: 0.08,
+            anchor: Vec2::new(-0.85, -0.8),
+        }
+    }
+}
+
+/// Distance of the triad in front of the camera, beyond the near plane
+const GIZMO_DISTANCE: f32 = 2.0;
+
+/// Directions the world X, Y and Z axes point to on screen, in the camera's frame
+/// (x to the right, y up, z toward the viewer)
+pub fn axis_directions(camera_rotation: Quat) -> [Vec3; 3] {
+    let inverse = camera_rotation.conjugate();
+    [inverse * Vec3::X, inverse * Vec3::Y, inverse * Vec3::Z]
+}
+
+/// Position in the camera's frame of the point at `ndc` on the screen, at `distance` in front of the camera
+pub fn screen_point(ndc: Vec2, distance: f32, fov: f32, aspect_ratio: f32) -> Vec3 {
+    let half_height = distance * (fov / 2.0).tan();
+    Vec3::new(
+        ndc.x * half_height * aspect_ratio,
+        ndc.y * half_height,
+        -distance,
+    )
+}
+
+struct AxisArrow;
+
+fn spawn_axis_gizmo(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (axis, color) in [
+        (Vec3::X, Color::RED),
+        (Vec3::Y, Color::GREEN),
+        (Vec3::Z, Color::BLUE),
+    ]
+    .iter()
+    {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: meshes.add(line_mesh(vec![[0.0; 3], (*axis).into()])),
+                material: materials.add(StandardMaterial {
+                    base_color: *color,
+                    unlit: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .insert(AxisArrow);
+    }
+}
+
+/// Keeps the triad at the same place on screen, aligned with the world axes
+#[allow(clippy::type_complexity)]
+fn follow_camera(
+    gizmo: Res<AxisGizmo>,
+    cameras: Query<(&Transform, &PerspectiveProjection), With<PanOrbitCamera>>,
+    mut arrows: Query<(&mut Transform, &mut Visible), (With<AxisArrow>, Without<PanOrbitCamera>)>,
+) {
+    let (camera, projection) = match cameras.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    let center = screen_point(
+        gizmo.anchor,
+        GIZMO_DISTANCE,
+        projection.fov,
+        projection.aspect_ratio,
+    );
+    let half_height = GIZMO_DISTANCE * (projection.fov / 2.0).tan();
+    for (mut transform, mut visible) in arrows.iter_mut() {
+        visible.is_visible = gizmo.enabled;
+        transform.translation = camera.translation + camera.rotation * center;
+        transform.scale = Vec3::splat(2.0 * half_height * gizmo.size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn axes_follow_the_camera_orientation() {
+        // Default camera looking down -Z: the axes are drawn as they are
+        let [x, y, z] = axis_directions(Quat::IDENTITY);
+        assert_close(x, Vec3::X);
+        assert_close(y, Vec3::Y);
+        assert_close(z, Vec3::Z);
+
+        // Camera on the +X axis looking back at the origin: X points toward the viewer, Z to the left
+        let camera = Transform::from_translation(Vec3::X).looking_at(Vec3::ZERO, Vec3::Y);
+        let [x, y, z] = axis_directions(camera.rotation);
+        assert_close(x, Vec3::Z);
+        assert_close(y, Vec3::Y);
+        assert_close(z, -Vec3::X);
+    }
+
+    #[test]
+    fn corners_of_the_screen_are_on_the_frustum() {
+        let fov = std::f32::consts::FRAC_PI_2;
+        assert_close(
+            screen_point(Vec2::ZERO, 2.0, fov, 1.5),
+            Vec3::new(0.0, 0.0, -2.0),
+        );
+        assert_close(
+            screen_point(Vec2::new(-1.0, 1.0), 2.0, fov, 1.5),
+            Vec3::new(-3.0, 2.0, -2.0),
+        );
+    }
+}
@@ -0,0 +1,302 @@
+//! Barnes-Hut tree code: the bodies are grouped in an octree and the distant groups
+//! are approximated by a multipole expansion around their center of mass,
+//! bringing the force computation down to `O(n log n)`.
+use std::ops::Range;
+
+use bevy::prelude::*;
+
+use super::nbody::SofteningKernel;
+
+/// Terms of the multipole expansion of the tree nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MultipoleOrder {
+    /// Mass at the center of mass only
+    #[default]
+    Monopole,
+    /// Mass and quadrupole moment: more accurate at a given opening angle, hence allowing a larger one.
+    /// The dipole vanishes around the center of mass.
+    Quadrupole,
+}
+
+/// Depth past which the bodies of a node are no longer split, e.g. when they all sit at the same position
+const MAX_DEPTH: usize = 32;
+
+struct Node {
+    /// Bodies of the node, as a range of `Octree::order`
+    range: Range<usize>,
+    half_size: f32,
+    mass: f32,
+    center_of_mass: Vec3,
+    /// Traceless quadrupole tensor around the center of mass:
+    /// ```text
+    /// Q = Σ m * (3 x xᵀ - |x|^2 I)
+    /// ```
+    quadrupole: Mat3,
+    children: Vec<usize>,
+}
+
+/// Octree of the bodies, the root being the first node
+pub struct Octree {
+    nodes: Vec<Node>,
+    /// Bodies sorted so that those of each node are contiguous
+    order: Vec<usize>,
+    /// Index in `order` of each body
+    rank: Vec<usize>,
+}
+
+impl Octree {
+    pub fn new(masses: &[f32], positions: &[Vec3]) -> Self {
+        let mut tree = Self {
+            nodes: Vec::new(),
+            order: (0..positions.len()).collect(),
+            rank: vec![0; positions.len()],
+        };
+        if positions.is_empty() {
+            return tree;
+        }
+        let (min, max) = positions.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), pos| (min.min(*pos), max.max(*pos)),
+        );
+        let half_size = (max - min).max_element() / 2.0;
+        tree.build(
+            masses,
+            positions,
+            0..positions.len(),
+            (min + max) / 2.0,
+            half_size,
+            0,
+        );
+        for (rank, body) in tree.order.iter().enumerate() {
+            tree.rank[*body] = rank;
+        }
+        tree
+    }
+
+    fn build(
+        &mut self,
+        masses: &[f32],
+        positions: &[Vec3],
+        range: Range<usize>,
+        center: Vec3,
+        half_size: f32,
+        depth: usize,
+    ) -> usize {
+        let bodies = &self.order[range.clone()];
+        let mass: f32 = bodies.iter().map(|i| masses[*i]).sum();
+        let center_of_mass = if mass > 0.0 {
+            bodies
+                .iter()
+                .fold(Vec3::ZERO, |acc, i| acc + masses[*i] * positions[*i])
+                / mass
+        } else {
+            center
+        };
+        let quadrupole = bodies.iter().fold(Mat3::ZERO, |q, i| {
+            let x = positions[*i] - center_of_mass;
+            let outer = Mat3::from_cols(x * x.x, x * x.y, x * x.z);
+            q + (outer * 3.0 - Mat3::IDENTITY * x.length_squared()) * masses[*i]
+        });
+
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            range: range.clone(),
+            half_size,
+            mass,
+            center_of_mass,
+            quadrupole,
+            children: Vec::new(),
+        });
+        if range.len() <= 1 || depth >= MAX_DEPTH || half_size <= 0.0 {
+            return index;
+        }
+
+        let octant = |pos: Vec3| {
+            (pos.x >= center.x) as usize
+                | ((pos.y >= center.y) as usize) << 1
+                | ((pos.z >= center.z) as usize) << 2
+        };
+        self.order[range.clone()].sort_by_key(|i| octant(positions[*i]));
+        let mut children = Vec::new();
+        let mut start = range.start;
+        for child in 0..8 {
+            let end = start
+                + self.order[start..range.end]
+                    .iter()
+                    .take_while(|i| octant(positions[**i]) == child)
+                    .count();
+            if end > start {
+                let sign = |bit: usize| if child & bit != 0 { 1.0 } else { -1.0 };
+                let offset = Vec3::new(sign(1), sign(2), sign(4)) * half_size / 2.0;
+                children.push(self.build(
+                    masses,
+                    positions,
+                    start..end,
+                    center + offset,
+                    half_size / 2.0,
+                    depth + 1,
+                ));
+            }
+            start = end;
+        }
+        self.nodes[index].children = children;
+        index
+    }
+
+    /// Acceleration of the body `body`, opening the nodes seen under an angle larger than `theta`
+    #[allow(clippy::too_many_arguments)]
+    pub fn acceleration(
+        &self,
+        body: usize,
+        masses: &[f32],
+        positions: &[Vec3],
+        g: f32,
+        softening: SofteningKernel,
+        theta: f32,
+        order: MultipoleOrder,
+    ) -> Vec3 {
+        let point = positions[body];
+        let rank = self.rank[body];
+        let mut acc = Vec3::ZERO;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = match self.nodes.get(index) {
+                Some(node) if node.mass > 0.0 => node,
+                _ => continue,
+            };
+            let diff = node.center_of_mass - point;
+            let distance = diff.length();
+            let contains_body = node.range.contains(&rank);
+            if !contains_body && 2.0 * node.half_size < theta * distance {
+                acc += node_acceleration(node, diff, g, softening, order);
+            } else if node.children.is_empty() {
+                // Leaf: direct summation over its bodies
+                for other in self.order[node.range.clone()].iter() {
+                    let diff = positions[*other] - point;
+                    if let Some(direction) = diff.try_normalize() {
+                        acc +=
+                            direction * g * masses[*other] * softening.force(diff.length_squared());
+                    }
+                }
+            } else {
+                stack.extend(node.children.iter());
+            }
+        }
+        acc
+    }
+}
+
+/// Acceleration toward the node at `diff` from the point, by its multipole expansion with `r = -diff`:
+/// ```text
+/// a = -G*M*r/r^3 + G*(Q r / r^5 - 5/2 * (rᵀQr) r / r^7)
+/// ```
+/// The quadrupole term isn't softened, the accepted nodes being far from the point.
+fn node_acceleration(
+    node: &Node,
+    diff: Vec3,
+    g: f32,
+    softening: SofteningKernel,
+    order: MultipoleOrder,
+) -> Vec3 {
+    let dist2 = diff.length_squared();
+    let direction = match diff.try_normalize() {
+        Some(direction) => direction,
+        None => return Vec3::ZERO,
+    };
+    let monopole = direction * g * node.mass * softening.force(dist2);
+    match order {
+        MultipoleOrder::Monopole => monopole,
+        MultipoleOrder::Quadrupole => {
+            // `r` points from the node to the point
+            let r = -diff;
+            let qr = node.quadrupole * r;
+            let r5 = dist2 * dist2 * dist2.sqrt();
+            monopole + g * (qr / r5 - 2.5 * r.dot(qr) * r / (r5 * dist2))
+        }
+    }
+}
+
+/// Accelerations of all the bodies computed with a Barnes-Hut tree, see `compute_accelerations`
+/// for the direct summation they approximate
+pub fn barnes_hut_accelerations(
+    masses: &[f32],
+    positions: &[Vec3],
+    g: f32,
+    softening: SofteningKernel,
+    theta: f32,
+    order: MultipoleOrder,
+) -> Vec<Vec3> {
+    let tree = Octree::new(masses, positions);
+    (0..positions.len())
+        .map(|body| tree.acceleration(body, masses, positions, g, softening, theta, order))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::nbody::compute_accelerations;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// 4 clusters of 50 bodies, the distant clusters being approximated by their multipoles
+    fn clustered_bodies() -> (Vec<f32>, Vec<Vec3>) {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut masses = Vec::new();
+        let mut positions = Vec::new();
+        for center in [
+            Vec3::new(-5.0, 0.0, 0.0),
+            Vec3::new(5.0, 1.0, 0.0),
+            Vec3::new(0.0, 6.0, -2.0),
+            Vec3::new(1.0, -4.0, 4.0),
+        ]
+        .iter()
+        {
+            for _ in 0..50 {
+                masses.push(rng.gen_range(0.1..1.0));
+                positions.push(
+                    *center
+                        + Vec3::new(
+                            rng.gen_range(-1.0..1.0),
+                            rng.gen_range(-1.0..1.0),
+                            rng.gen_range(-1.0..1.0),
+                        ),
+                );
+            }
+        }
+        (masses, positions)
+    }
+
+    /// Root mean square of the relative errors of the accelerations
+    fn rms_error(approx: &[Vec3], exact: &[Vec3]) -> f32 {
+        let sum: f32 = approx
+            .iter()
+            .zip(exact)
+            .map(|(a, e)| ((*a - *e).length() / e.length()).powi(2))
+            .sum();
+        (sum / exact.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn quadrupoles_are_more_accurate_than_monopoles() {
+        let (masses, positions) = clustered_bodies();
+        let softening = SofteningKernel::None;
+        let exact = compute_accelerations(&masses, &positions, 1.0, softening, None);
+        let error = |theta, order| {
+            let approx =
+                barnes_hut_accelerations(&masses, &positions, 1.0, softening, theta, order);
+            rms_error(&approx, &exact)
+        };
+
+        // Without opening angle, every node is opened down to the bodies
+        assert!(error(0.0, MultipoleOrder::Monopole) < 1e-4);
+        let monopole = error(0.7, MultipoleOrder::Monopole);
+        let quadrupole = error(0.7, MultipoleOrder::Quadrupole);
+        assert!(
+            quadrupole < 0.5 * monopole,
+            "quadrupole {} vs monopole {}",
+            quadrupole,
+            monopole
+        );
+    }
+}
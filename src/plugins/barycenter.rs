@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{center_of_mass, Mass};
+
+/// Marker at the center of mass of the bodies, which stays fixed unless external forces act on them,
+/// e.g. the pinned bodies or the kicks:
+/// * U: toggle the marker of the center of mass
+pub struct BarycenterPlugin;
+
+impl Plugin for BarycenterPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::ToggleBarycenter,
+            "toggle the marker of the center of mass",
+        )
+        .init_resource::<CenterOfMass>()
+        .init_resource::<ShowBarycenter>()
+        .add_startup_system(spawn_barycenter_marker.system())
+        .add_system(barycenter_keys.system())
+        .add_system(update_center_of_mass.system().label(BarycenterSystem))
+        .add_system(draw_barycenter.system().after(BarycenterSystem));
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct BarycenterSystem;
+
+/// Center of mass of the bodies, updated every frame
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CenterOfMass(pub Vec3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ShowBarycenter(pub bool);
+
+pub const BARYCENTER_COLOR: Color = Color::ORANGE_RED;
+
+/// Radius of the marker, independent of the size of the bodies
+const MARKER_RADIUS: f32 = 0.15;
+
+/// The marker has no `Mass` nor `Radius` for the gravity and the collisions to ignore it
+pub struct BarycenterMarker;
+
+fn spawn_barycenter_marker(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Icosphere {
+                radius: MARKER_RADIUS,
+                subdivisions: 2,
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: BARYCENTER_COLOR,
+                unlit: true,
+                ..Default::default()
+            }),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: false,
+            },
+            ..Default::default()
+        })
+        .insert(BarycenterMarker);
+}
+
+fn barycenter_keys(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut show: ResMut<ShowBarycenter>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleBarycenter) {
+        show.0 = !show.0;
+    }
+}
+
+fn update_center_of_mass(
+    mut center: ResMut<CenterOfMass>,
+    bodies: Query<(&Mass, &Transform), Without<BarycenterMarker>>,
+) {
+    let (masses, positions): (Vec<f32>, Vec<Vec3>) = bodies
+        .iter()
+        .map(|(mass, transform)| (mass.0, transform.translation))
+        .unzip();
+    center.0 = center_of_mass(&masses, &positions);
+}
+
+fn draw_barycenter(
+    show: Res<ShowBarycenter>,
+    center: Res<CenterOfMass>,
+    mut markers: Query<(&mut Transform, &mut Visible), With<BarycenterMarker>>,
+) {
+    for (mut transform, mut visible) in markers.iter_mut() {
+        visible.is_visible = show.0;
+        transform.translation = center.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::nbody::BodyBundle;
+    use crate::testing::run_system;
+
+    #[test]
+    fn marker_tracks_the_center_of_mass() {
+        let mut world = World::default();
+        world.insert_resource(CenterOfMass::default());
+        world.insert_resource(ShowBarycenter(true));
+        let heavy = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(3.0, Vec3::ZERO, Vec3::ZERO))
+            .id();
+        world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::new(4.0, 0.0, 0.0), Vec3::ZERO));
+        let marker = world
+            .spawn()
+            .insert_bundle((Transform::default(), Visible::default(), BarycenterMarker))
+            .id();
+        let update = |world: &mut World| {
+            run_system(world, update_center_of_mass.system());
+            run_system(world, draw_barycenter.system());
+            world.get::<Transform>(marker).unwrap().translation
+        };
+
+        assert_eq!(update(&mut world), Vec3::new(1.0, 0.0, 0.0));
+        world.get_mut::<Transform>(heavy).unwrap().translation = Vec3::new(0.0, 4.0, 0.0);
+        assert_eq!(update(&mut world), Vec3::new(1.0, 3.0, 0.0));
+        assert!(world.get::<Visible>(marker).unwrap().is_visible);
+        assert_eq!(
+            *world.get_resource::<CenterOfMass>().unwrap(),
+            CenterOfMass(Vec3::new(1.0, 3.0, 0.0))
+        );
+
+        world.insert_resource(ShowBarycenter(false));
+        run_system(&mut world, draw_barycenter.system());
+        assert!(!world.get::<Visible>(marker).unwrap().is_visible);
+    }
+}
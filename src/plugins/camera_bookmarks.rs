@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+
+use super::pan_orbit_camera::{is_upside_down, PanOrbitCamera, WorldUp};
+
+/// Saves viewpoints of the camera to the number keys to jump back to them:
+/// * Ctrl + 0-9: save the camera viewpoint
+/// * 0-9: restore the saved camera viewpoint
+///
+/// The bookmarks are kept when the scene is reset.
+pub struct CameraBookmarksPlugin;
+
+impl Plugin for CameraBookmarksPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CameraBookmarks>()
+            .add_system(camera_bookmarks.system());
+    }
+}
+
+/// Number keys of the bookmarks, the slot of each being its digit
+pub const BOOKMARK_KEYS: [KeyCode; 10] = [
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+/// Viewpoint of a `PanOrbitCamera`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraBookmark {
+    pub focus: Vec3,
+    pub radius: f32,
+    pub rotation: Quat,
+}
+
+impl CameraBookmark {
+    pub fn capture(camera: &PanOrbitCamera, transform: &Transform) -> Self {
+        Self {
+            focus: camera.focus,
+            radius: camera.radius,
+            rotation: transform.rotation,
+        }
+    }
+
+    /// Moves the camera to the viewpoint, orbiting around its focus and the `world_up` axis afterwards
+    pub fn apply(&self, camera: &mut PanOrbitCamera, transform: &mut Transform, world_up: Vec3) {
+        camera.focus = self.focus;
+        camera.radius = self.radius;
+        camera.upside_down = is_upside_down(self.rotation, world_up);
+        transform.rotation = self.rotation;
+        transform.translation = self.focus + self.rotation * Vec3::new(0.0, 0.0, self.radius);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CameraBookmarks {
+    slots: [Option<CameraBookmark>; 10],
+}
+
+impl CameraBookmarks {
+    /// Overwrites the bookmark of the `slot`, from 0 to 9
+    pub fn save(&mut self, slot: usize, bookmark: CameraBookmark) {
+        if let Some(saved) = self.slots.get_mut(slot) {
+            *saved = Some(bookmark);
+        }
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&CameraBookmark> {
+        self.slots.get(slot).and_then(Option::as_ref)
+    }
+}
+
+fn camera_bookmarks(
+    keys: Res<Input<KeyCode>>,
+    world_up: Res<WorldUp>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    mut cameras: Query<(&mut PanOrbitCamera, &mut Transform)>,
+) {
+    let slot = match BOOKMARK_KEYS.iter().position(|key| keys.just_pressed(*key)) {
+        Some(slot) => slot,
+        None => return,
+    };
+    let save = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+
+    for (mut camera, mut transform) in cameras.iter_mut() {
+        if save {
+            bookmarks.save(slot, CameraBookmark::capture(&camera, &transform));
+            info!("Camera viewpoint saved to {}", slot);
+        } else if let Some(bookmark) = bookmarks.get(slot) {
+            bookmark.apply(&mut camera, &mut transform, world_up.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::run_system;
+
+    /// Replaces the keyboard input by the given keys just pressed
+    fn press(world: &mut World, keys: &[KeyCode]) {
+        let mut input = Input::<KeyCode>::default();
+        for key in keys {
+            input.press(*key);
+        }
+        world.insert_resource(input);
+    }
+
+    #[test]
+    fn restoring_a_bookmark_reproduces_the_saved_viewpoint() {
+        let mut world = World::default();
+        world.insert_resource(WorldUp::default());
+        world.insert_resource(CameraBookmarks::default());
+        let rotation = Quat::from_rotation_y(0.7) * Quat::from_rotation_x(-0.4);
+        let (focus, radius) = (Vec3::new(1.0, 2.0, 3.0), 12.0);
+        let camera = PanOrbitCamera {
+            focus,
+            radius,
+            upside_down: false,
+        };
+        let transform = Transform {
+            translation: focus + rotation * Vec3::new(0.0, 0.0, radius),
+            rotation,
+            ..Default::default()
+        };
+        let entity = world.spawn().insert_bundle((camera, transform)).id();
+
+        press(&mut world, &[KeyCode::LControl, KeyCode::Key3]);
+        run_system(&mut world, camera_bookmarks.system());
+        assert!(world
+            .get_resource::<CameraBookmarks>()
+            .unwrap()
+            .get(3)
+            .is_some());
+
+        *world.get_mut::<PanOrbitCamera>(entity).unwrap() = PanOrbitCamera::default();
+        *world.get_mut::<Transform>(entity).unwrap() =
+            Transform::from_xyz(0.0, 0.0, 5.0).looking_at(Vec3::ZERO, Vec3::Y);
+        press(&mut world, &[KeyCode::Key3]);
+        run_system(&mut world, camera_bookmarks.system());
+
+        let restored = world.get::<PanOrbitCamera>(entity).unwrap();
+        assert_eq!(restored.focus, focus);
+        assert_eq!(restored.radius, radius);
+        assert!(!restored.upside_down);
+        let restored = world.get::<Transform>(entity).unwrap();
+        assert_eq!(restored.rotation, transform.rotation);
+        assert!(restored.translation.distance(transform.translation) < 1e-5);
+    }
+
+    #[test]
+    fn empty_slots_leave_the_camera_in_place() {
+        let mut world = World::default();
+        world.insert_resource(WorldUp::default());
+        world.insert_resource(CameraBookmarks::default());
+        let transform = Transform::from_xyz(0.0, 0.0, 5.0);
+        let entity = world
+            .spawn()
+            .insert_bundle((PanOrbitCamera::default(), transform))
+            .id();
+
+        press(&mut world, &[KeyCode::Key5]);
+        run_system(&mut world, camera_bookmarks.system());
+        assert_eq!(*world.get::<Transform>(entity).unwrap(), transform);
+    }
+}
@@ -0,0 +1,201 @@
+use bevy::prelude::*;
+
+use super::hud::{hud_text, set_hud_text, HudFont};
+use super::nbody::{BodyClass, Mass};
+
+/// Number and mass of the bodies of each `BodyClass`, exposed in `Census`, shown in the bottom left corner
+/// and logged whenever the number of bodies changes, e.g. after collisions or launches
+pub struct CensusPlugin;
+
+impl Plugin for CensusPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Census>()
+            .init_resource::<HudFont>()
+            .add_startup_system(spawn_census_text.system())
+            .add_system(update_census.system().label(CensusSystem))
+            .add_system(log_census.system().after(CensusSystem))
+            .add_system(show_census.system().after(CensusSystem));
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct CensusSystem;
+
+/// Number and total mass of a group of bodies
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ClassTally {
+    pub count: usize,
+    pub mass: f32,
+}
+
+impl ClassTally {
+    fn add(&mut self, mass: f32) {
+        self.count += 1;
+        self.mass += mass;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Census {
+    pub stars: ClassTally,
+    pub planets: ClassTally,
+    pub comets: ClassTally,
+    /// Bodies without a `BodyClass`, e.g. the random bodies or the test particles
+    pub unclassified: ClassTally,
+}
+
+impl Census {
+    /// Tallies the `(mass, class)` of the bodies
+    pub fn from_bodies(bodies: impl Iterator<Item = (f32, Option<BodyClass>)>) -> Self {
+        let mut census = Self::default();
+        for (mass, class) in bodies {
+            match class {
+                Some(BodyClass::Star) => census.stars.add(mass),
+                Some(BodyClass::Planet) => census.planets.add(mass),
+                Some(BodyClass::Comet) => census.comets.add(mass),
+                None => census.unclassified.add(mass),
+            }
+        }
+        census
+    }
+
+    pub fn count(&self) -> usize {
+        self.tallies().iter().map(|(tally, ..)| tally.count).sum()
+    }
+
+    pub fn total_mass(&self) -> f32 {
+        self.tallies().iter().map(|(tally, ..)| tally.mass).sum()
+    }
+
+    /// Each tally with its singular and plural names
+    fn tallies(&self) -> [(ClassTally, &'static str, &'static str); 4] {
+        [
+            (self.stars, "star", "stars"),
+            (self.planets, "planet", "planets"),
+            (self.comets, "comet", "comets"),
+            (self.unclassified, "other body", "other bodies"),
+        ]
+    }
+
+    /// One line overview, e.g. `1 star, 8 planets, 1 comet, total mass 1.99e6`, the empty classes being left out
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = self
+            .tallies()
+            .iter()
+            .filter(|(tally, ..)| tally.count > 0)
+            .map(|(tally, singular, plural)| {
+                let name = if tally.count == 1 { singular } else { plural };
+                format!("{} {}", tally.count, name)
+            })
+            .collect();
+        parts.push(format!("total mass {:.3e}", self.total_mass()));
+        parts.join(", ")
+    }
+}
+
+fn update_census(mut census: ResMut<Census>, bodies: Query<(&Mass, Option<&BodyClass>)>) {
+    let new_census =
+        Census::from_bodies(bodies.iter().map(|(mass, class)| (mass.0, class.copied())));
+    // Only flag a change when there is one
+    if *census != new_census {
+        *census = new_census;
+    }
+}
+
+/// Text of the census readout
+struct CensusText;
+
+fn spawn_census_text(mut commands: Commands, font: Res<HudFont>) {
+    let position = Rect {
+        left: Val::Px(10.0),
+        bottom: Val::Px(10.0),
+        ..Default::default()
+    };
+    commands
+        .spawn_bundle(hud_text(&font, position))
+        .insert(CensusText);
+}
+
+fn show_census(census: Res<Census>, mut texts: Query<&mut Text, With<CensusText>>) {
+    for mut text in texts.iter_mut() {
+        set_hud_text(&mut text, census.summary());
+    }
+}
+
+fn log_census(census: Res<Census>, mut last_count: Local<Option<usize>>) {
+    if *last_count != Some(census.count()) {
+        *last_count = Some(census.count());
+        info!("{}", census.summary());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::run_system;
+
+    #[test]
+    fn census_of_mixed_classes() {
+        let census = Census::from_bodies(
+            vec![
+                (1000.0, Some(BodyClass::Star)),
+                (1.0, Some(BodyClass::Planet)),
+                (2.0, Some(BodyClass::Planet)),
+                (0.5, None),
+                (0.0, None),
+                (0.25, Some(BodyClass::Planet)),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(
+            census.stars,
+            ClassTally {
+                count: 1,
+                mass: 1000.0
+            }
+        );
+        assert_eq!(
+            census.planets,
+            ClassTally {
+                count: 3,
+                mass: 3.25
+            }
+        );
+        assert_eq!(census.comets, ClassTally::default());
+        assert_eq!(
+            census.unclassified,
+            ClassTally {
+                count: 2,
+                mass: 0.5
+            }
+        );
+        assert_eq!(census.count(), 6);
+        assert_eq!(census.total_mass(), 1003.75);
+        assert_eq!(
+            census.summary(),
+            "1 star, 3 planets, 2 other bodies, total mass 1.004e3"
+        );
+    }
+
+    #[test]
+    fn readout_shows_the_census() {
+        let mut world = World::default();
+        world.insert_resource(Census::default());
+        world.spawn().insert_bundle((Mass(2.0), BodyClass::Comet));
+        world.spawn().insert_bundle((Mass(3.0), BodyClass::Star));
+        let text = world
+            .spawn()
+            .insert_bundle((
+                Text::with_section("", Default::default(), Default::default()),
+                CensusText,
+            ))
+            .id();
+
+        run_system(&mut world, update_census.system());
+        run_system(&mut world, show_census.system());
+        assert_eq!(
+            world.get::<Text>(text).unwrap().sections[0].value,
+            "1 star, 1 comet, total mass 5.000e0"
+        );
+    }
+}
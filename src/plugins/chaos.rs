@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+
+use super::nbody::SimulationTime;
+
+/// Divergence of two replicas of a scene, one of them slightly perturbed,
+/// to study the sensitivity to the initial conditions.
+/// The separation of the replicas is recorded as the simulation advances and logged every second.
+pub struct ChaosPairPlugin;
+
+impl Plugin for ChaosPairPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ChaosPair>()
+            .add_system(track_separation.system());
+    }
+}
+
+pub struct ChaosPair {
+    /// Each body of the original scene and its twin in the perturbed replica
+    pub twins: Vec<(Entity, Entity)>,
+    /// Simulated time and separation of the replicas
+    pub history: Vec<(f32, f32)>,
+    pub timer: Timer,
+}
+
+impl Default for ChaosPair {
+    fn default() -> Self {
+        Self {
+            twins: Vec::new(),
+            history: Vec::new(),
+            timer: Timer::from_seconds(1.0, true),
+        }
+    }
+}
+
+/// Separation of two replicas of a scene in the configuration space, given the positions of the twin bodies:
+/// ```text
+/// d = sqrt(Σ |x_i - x'_i|^2)
+/// ```
+pub fn separation(twins: &[(Vec3, Vec3)]) -> f32 {
+    twins
+        .iter()
+        .map(|(a, b)| a.distance_squared(*b))
+        .sum::<f32>()
+        .sqrt()
+}
+
+fn track_separation(
+    time: Res<Time>,
+    sim_time: Res<SimulationTime>,
+    mut chaos: ResMut<ChaosPair>,
+    bodies: Query<&Transform>,
+) {
+    if chaos.twins.is_empty() {
+        return;
+    }
+    // Twins despawned, e.g. by a collision, are left out
+    let positions: Vec<(Vec3, Vec3)> = chaos
+        .twins
+        .iter()
+        .filter_map(|(a, b)| match (bodies.get(*a), bodies.get(*b)) {
+            (Ok(a), Ok(b)) => Some((a.translation, b.translation)),
+            _ => None,
+        })
+        .collect();
+    let d = separation(&positions);
+    // Once per simulated step, e.g. not while paused
+    if !matches!(chaos.history.last(), Some((t, _)) if *t >= sim_time.0) {
+        chaos.history.push((sim_time.0, d));
+    }
+
+    if chaos.timer.tick(time.delta()).just_finished() {
+        info!(
+            "Separation of the replicas at t = {:.2}: {:e}",
+            sim_time.0, d
+        );
+    }
+}
@@ -0,0 +1,1181 @@
+use std::fmt::Write;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::Rng;
+use rand_distr::{Distribution, UnitSphere};
+use serde::{Deserialize, Serialize};
+
+use super::nbody::{
+    BodyBundle, Gravity, Mass, PhysicsStep, PhysicsSystem, SimulationTime, TimeStep, Universe,
+    Velocity,
+};
+use super::quality::QualityPreset;
+
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CollisionMode>()
+            .init_resource::<ContinuousCollision>()
+            .init_resource::<CollisionLog>()
+            .init_resource::<AccretionHistory>()
+            .init_resource::<TidalDisruption>()
+            .init_resource::<Disrupted>()
+            .add_event::<CollisionEvent>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(PhysicsStep)
+                    .with_system(
+                        disrupt_tidally
+                            .system()
+                            .label(TidalDisruptionSystem)
+                            .after(PhysicsSystem::Movement),
+                    )
+                    .with_system(
+                        resolve_collisions
+                            .system()
+                            .after(PhysicsSystem::Movement)
+                            .after(TidalDisruptionSystem),
+                    )
+                    .with_system(
+                        stick_collisions
+                            .system()
+                            .after(PhysicsSystem::Movement)
+                            .after(TidalDisruptionSystem),
+                    )
+                    .with_system(
+                        move_clusters
+                            .system()
+                            .after(PhysicsSystem::Circularization)
+                            .before(PhysicsSystem::Movement),
+                    ),
+            );
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct TidalDisruptionSystem;
+
+/// Physical radius of a body, only bodies with a radius collide
+pub struct Radius(pub f32);
+
+/// Outcome of two bodies touching each other
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum CollisionMode {
+    /// Bodies go through each other
+    Ignore,
+    /// The lightest body is absorbed by the heaviest, conserving mass, momentum and volume
+    #[default]
+    Merge,
+    /// Bodies colliding with a kinetic energy above `threshold` (in their center of mass frame)
+    /// shatter into `pieces` fragments, conserving mass, momentum and volume.
+    /// They merge below the threshold.
+    Fragment { threshold: f32, pieces: usize },
+    /// Bodies stick together into rigid clusters, moving as one while keeping their own render
+    Stick,
+}
+
+impl std::str::FromStr for CollisionMode {
+    type Err = String;
+
+    /// `ignore`, `merge`, `stick`, or `fragment` with its energy threshold and optional number of pieces,
+    /// e.g. `fragment:0.5:8`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            String::from(
+                "Invalid input. Should be: ignore, merge, stick or fragment:<threshold>[:<pieces>]",
+            )
+        };
+        let s = s.to_lowercase();
+        let mut parts = s.split(':');
+        match parts.next().unwrap_or_default() {
+            "ignore" => Ok(Self::Ignore),
+            "merge" => Ok(Self::Merge),
+            "stick" => Ok(Self::Stick),
+            "fragment" => {
+                let threshold = match parts.next() {
+                    Some(threshold) => threshold.parse().map_err(|_| invalid())?,
+                    None => return Err(invalid()),
+                };
+                let pieces = match parts.next() {
+                    Some(pieces) => pieces.parse().map_err(|_| invalid())?,
+                    None => 5,
+                };
+                Ok(Self::Fragment { threshold, pieces })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Tags a central body absorbing every body touching it whatever the `CollisionMode`,
+/// e.g. a star in a planet formation demo
+pub struct CentralAccretor;
+
+/// Mass absorbed by the `CentralAccretor` bodies over time
+#[derive(Debug, Clone, Default)]
+pub struct AccretionHistory {
+    /// Simulated time and mass of each absorbed body, chronologically
+    pub events: Vec<(f32, f32)>,
+    pub total_mass: f32,
+}
+
+impl AccretionHistory {
+    pub fn record(&mut self, time: f32, mass: f32) {
+        self.events.push((time, mass));
+        self.total_mass += mass;
+    }
+}
+
+/// Whether collisions are detected along the paths of the bodies during a step, see `time_to_collision`,
+/// rather than only between their end positions, letting fast bodies tunnel through each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ContinuousCollision(pub bool);
+
+/// Break up of the bodies crossing the Roche limit of a heavier body, see `tidal_fragments`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TidalDisruption {
+    pub enabled: bool,
+    pub pieces: usize,
+    /// Relative speed of the outermost fragments, as a multiple of the escape speed of the disrupted body
+    pub spread: f32,
+}
+
+impl Default for TidalDisruption {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pieces: 5,
+            spread: 1.0,
+        }
+    }
+}
+
+impl std::str::FromStr for TidalDisruption {
+    type Err = String;
+
+    /// Enabled with the number of pieces and optionally their spread, e.g. `8:0.5`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || String::from("Invalid input. Should be: <pieces>[:<spread>]");
+        let mut parts = s.split(':');
+        let pieces = parts
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| invalid())?;
+        let spread = match parts.next() {
+            Some(spread) => spread.parse().map_err(|_| invalid())?,
+            None => Self::default().spread,
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            enabled: true,
+            pieces,
+            spread,
+        })
+    }
+}
+
+/// Bodies disrupted during the current step, despawned along with the commands at the end of the stage:
+/// the collision systems leave them out not to resolve them twice
+#[derive(Default)]
+struct Disrupted(Vec<Entity>);
+
+/// Tags the fragments of a tidal disruption: they are as dense as their parent body and share its Roche limit,
+/// so they would be disrupted again right away
+pub struct TidalDebris;
+
+/// Membership of a body to a rigid cluster, identified by one of its members
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cluster(pub Entity);
+
+/// Common velocity of rigidly bound bodies, conserving their momentum:
+/// ```text
+/// v = Σ m_i*v_i / Σ m_i
+/// ```
+/// Clusters of massless bodies move at their average velocity.
+pub fn cluster_velocity(masses: &[f32], velocities: &[Vec3]) -> Vec3 {
+    let total_mass: f32 = masses.iter().sum();
+    if total_mass > 0.0 {
+        masses
+            .iter()
+            .zip(velocities)
+            .fold(Vec3::ZERO, |momentum, (m, vel)| momentum + *m * *vel)
+            / total_mass
+    } else if velocities.is_empty() {
+        Vec3::ZERO
+    } else {
+        velocities.iter().fold(Vec3::ZERO, |sum, vel| sum + *vel) / velocities.len() as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CollidingBody {
+    pub entity: Entity,
+    pub mass: f32,
+    pub pos: Vec3,
+    pub vel: Vec3,
+    pub radius: f32,
+}
+
+impl CollidingBody {
+    pub fn touches(&self, other: &CollidingBody) -> bool {
+        self.pos.distance(other.pos) <= self.radius + other.radius
+    }
+
+    /// Overlapping bodies moving away from each other, e.g. fragments, don't collide
+    pub fn approaches(&self, other: &CollidingBody) -> bool {
+        (other.pos - self.pos).dot(other.vel - self.vel) < 0.0
+    }
+
+    /// Whether the bodies collide at the end of the last step of `dt`,
+    /// or met during it when `continuous`
+    pub fn collides(&self, other: &CollidingBody, dt: f32, continuous: bool) -> bool {
+        (self.touches(other) && self.approaches(other))
+            || (continuous && {
+                let start = |body: &CollidingBody| CollidingBody {
+                    pos: body.pos - body.vel * dt,
+                    ..*body
+                };
+                time_to_collision(&start(self), &start(other)).is_some_and(|t| t <= dt)
+            })
+    }
+}
+
+/// Earliest time at which two spheres moving in straight lines touch,
+/// `Some(0.0)` if they already overlap while getting closer.
+/// Solves:
+/// ```text
+/// |Δp + Δv*t| = r1 + r2
+/// ```
+pub fn time_to_collision(a: &CollidingBody, b: &CollidingBody) -> Option<f32> {
+    let dp = b.pos - a.pos;
+    let dv = b.vel - a.vel;
+    let radius = a.radius + b.radius;
+    let a2 = dv.length_squared();
+    let b2 = dp.dot(dv);
+    if a2 == 0.0 || b2 >= 0.0 {
+        // Not getting closer, e.g. overlapping fragments moving apart
+        return None;
+    }
+    let c = dp.length_squared() - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    let discriminant = b2 * b2 - a2 * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    Some((-b2 - discriminant.sqrt()) / a2)
+}
+
+/// Kinetic energy of two bodies in their center of mass frame:
+/// ```text
+/// E = 1/2 * m1*m2/(m1 + m2) * v^2
+/// ```
+/// where `v` is their relative speed.
+pub fn collision_energy(a: &CollidingBody, b: &CollidingBody) -> f32 {
+    let mass = a.mass + b.mass;
+    if mass <= 0.0 {
+        return 0.0;
+    }
+    0.5 * a.mass * b.mass / mass * a.vel.distance_squared(b.vel)
+}
+
+/// Fragment of a shattered body
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Debris {
+    pub mass: f32,
+    pub pos: Vec3,
+    pub vel: Vec3,
+    pub radius: f32,
+}
+
+/// Shatters two bodies into `pieces` fragments of equal mass and volume, flying away from their center of mass
+/// in random directions.
+/// Mass and momentum are conserved, the kinetic energy of the collision is given back to the fragments.
+pub fn fragment(
+    a: &CollidingBody,
+    b: &CollidingBody,
+    pieces: usize,
+    rng: &mut impl Rng,
+) -> Vec<Debris> {
+    let merged = merge(a, b);
+    if pieces < 2 {
+        return vec![Debris {
+            mass: merged.mass,
+            pos: merged.pos,
+            vel: merged.vel,
+            radius: merged.radius,
+        }];
+    }
+
+    // Random directions, centered so that they don't carry any momentum
+    let mut directions: Vec<Vec3> = UnitSphere
+        .sample_iter(rng)
+        .take(pieces)
+        .map(|[x, y, z]: [f32; 3]| Vec3::new(x, y, z))
+        .collect();
+    let mean = directions.iter().fold(Vec3::ZERO, |sum, d| sum + *d) / pieces as f32;
+    directions.iter_mut().for_each(|d| *d -= mean);
+
+    let mass = merged.mass / pieces as f32;
+    let radius = merged.radius / (pieces as f32).cbrt();
+    // All the fragments have the same mass: E = 1/2 * m * speed^2 * Σ|d|^2
+    let spread: f32 = directions.iter().map(|d| d.length_squared()).sum();
+    let speed = if mass > 0.0 && spread > 0.0 {
+        (2.0 * collision_energy(a, b) / (mass * spread)).sqrt()
+    } else {
+        0.0
+    };
+    directions
+        .into_iter()
+        .map(|d| Debris {
+            mass,
+            pos: merged.pos + d * merged.radius,
+            vel: merged.vel + d * speed,
+            radius,
+        })
+        .collect()
+}
+
+/// Distance to a body of mass `m_primary` below which a rigid body of mass `m_body` and radius `r_body`
+/// is torn apart by the tides:
+/// ```text
+/// d = r * (2M / m)^(1/3)
+/// ```
+pub fn roche_limit(m_primary: f32, m_body: f32, r_body: f32) -> f32 {
+    r_body * (2.0 * m_primary / m_body).cbrt()
+}
+
+/// Tears `body` apart into `pieces` fragments of equal mass and volume, lined up along the tidal axis,
+/// i.e. the direction of the `primary`, and drifting apart along it at up to `speed` from the body.
+/// Mass and momentum are conserved, the fragments being symmetric around the body.
+pub fn tidal_fragments(
+    body: &CollidingBody,
+    primary: &CollidingBody,
+    pieces: usize,
+    speed: f32,
+) -> Vec<Debris> {
+    let axis = (body.pos - primary.pos).normalize_or_zero();
+    if pieces < 2 || axis == Vec3::ZERO {
+        return vec![Debris {
+            mass: body.mass,
+            pos: body.pos,
+            vel: body.vel,
+            radius: body.radius,
+        }];
+    }
+
+    let mass = body.mass / pieces as f32;
+    let radius = body.radius / (pieces as f32).cbrt();
+    let half = (pieces - 1) as f32 / 2.0;
+    (0..pieces)
+        .map(|k| {
+            // From -1 (the nearest to the primary) to 1
+            let offset = (k as f32 - half) / half;
+            Debris {
+                mass,
+                pos: body.pos + axis * offset * (body.radius - radius),
+                vel: body.vel + axis * offset * speed,
+                radius,
+            }
+        })
+        .collect()
+}
+
+/// Merges two bodies into the heaviest one:
+/// - at their center of mass
+/// - with their combined mass and momentum
+/// - with their combined volume
+pub fn merge(a: &CollidingBody, b: &CollidingBody) -> CollidingBody {
+    let (heaviest, lightest) = if a.mass >= b.mass { (a, b) } else { (b, a) };
+    let mass = a.mass + b.mass;
+    let (pos, vel) = if mass > 0.0 {
+        (
+            (a.mass * a.pos + b.mass * b.pos) / mass,
+            (a.mass * a.vel + b.mass * b.vel) / mass,
+        )
+    } else {
+        (heaviest.pos, heaviest.vel)
+    };
+    CollidingBody {
+        entity: heaviest.entity,
+        mass,
+        pos,
+        vel,
+        radius: (heaviest.radius.powi(3) + lightest.radius.powi(3)).cbrt(),
+    }
+}
+
+/// Absorbs `body` into the `accretor`, as `merge` does but keeping the accretor whatever the masses
+pub fn accrete(accretor: &CollidingBody, body: &CollidingBody) -> CollidingBody {
+    CollidingBody {
+        entity: accretor.entity,
+        ..merge(accretor, body)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollisionRecord {
+    /// Simulated time of the collision
+    pub time: f32,
+    pub bodies: (Entity, Entity),
+    pub masses: (f32, f32),
+    /// Relative speed of the bodies at impact
+    pub impact_speed: f32,
+    pub resulting_mass: f32,
+}
+
+impl CollisionRecord {
+    /// Kinetic energy of the bodies in their center of mass frame at impact, see `collision_energy`
+    pub fn impact_energy(&self) -> f32 {
+        let (m1, m2) = self.masses;
+        if m1 + m2 <= 0.0 {
+            return 0.0;
+        }
+        0.5 * m1 * m2 / (m1 + m2) * self.impact_speed * self.impact_speed
+    }
+}
+
+/// Sent on each collision, along with its record in the `CollisionLog`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollisionEvent(pub CollisionRecord);
+
+/// Chronological log of the collisions
+#[derive(Default)]
+pub struct CollisionLog {
+    pub records: Vec<CollisionRecord>,
+    /// Maximum number of records kept, the oldest being dropped first
+    pub max_records: Option<usize>,
+}
+
+impl CollisionLog {
+    pub fn push(&mut self, record: CollisionRecord) {
+        self.records.push(record);
+        if let Some(max) = self.max_records {
+            let excess = self.records.len().saturating_sub(max);
+            self.records.drain(..excess);
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("time,body1,body2,mass1,mass2,impact_speed,resulting_mass\n");
+        for r in self.records.iter() {
+            // Writing to a String can't fail
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{}",
+                r.time,
+                r.bodies.0.id(),
+                r.bodies.1.id(),
+                r.masses.0,
+                r.masses.1,
+                r.impact_speed,
+                r.resulting_mass
+            );
+        }
+        csv
+    }
+
+    pub fn export_csv(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_csv())
+    }
+}
+
+fn record_collision(
+    log: &mut CollisionLog,
+    events: &mut EventWriter<CollisionEvent>,
+    record: CollisionRecord,
+) {
+    events.send(CollisionEvent(record.clone()));
+    log.push(record);
+}
+
+fn spawn_debris<'a, 'b>(
+    commands: &'b mut Commands<'a>,
+    meshes: &mut Assets<Mesh>,
+    quality: &QualityPreset,
+    material: &Option<Handle<StandardMaterial>>,
+    debris: &Debris,
+) -> bevy::ecs::system::EntityCommands<'a, 'b> {
+    let mut entity = commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Icosphere {
+            radius: debris.radius,
+            subdivisions: quality.subdivisions().min(2),
+        })),
+        material: material.clone().unwrap_or_default(),
+        ..Default::default()
+    });
+    entity
+        .insert_bundle(BodyBundle::new(debris.mass, debris.pos, debris.vel))
+        .insert(Radius(debris.radius));
+    entity
+}
+
+/// Breaks up the bodies within the Roche limit of a heavier body, before they touch it
+#[allow(clippy::type_complexity)]
+fn disrupt_tidally(
+    mut commands: Commands,
+    disruption: Res<TidalDisruption>,
+    mut disrupted: ResMut<Disrupted>,
+    gravity: Res<Gravity>,
+    quality: Res<QualityPreset>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    query: Query<(
+        Entity,
+        &Mass,
+        &Transform,
+        &Velocity,
+        &Radius,
+        Option<&Handle<StandardMaterial>>,
+        Option<&Universe>,
+        Option<&TidalDebris>,
+    )>,
+) {
+    disrupted.0.clear();
+    if !disruption.enabled || disruption.pieces < 2 {
+        return;
+    }
+    let bodies: Vec<(CollidingBody, Universe)> = query
+        .iter()
+        .map(|(entity, mass, transform, vel, radius, _, universe, _)| {
+            let body = CollidingBody {
+                entity,
+                mass: mass.0,
+                pos: transform.translation,
+                vel: vel.0,
+                radius: radius.0,
+            };
+            (body, universe.copied().unwrap_or_default())
+        })
+        .collect();
+    for ((body, universe), (.., material, _, debris)) in bodies.iter().zip(query.iter()) {
+        if debris.is_some() || body.mass <= 0.0 || body.radius <= 0.0 {
+            continue;
+        }
+        let primary = bodies.iter().find(|(primary, primary_universe)| {
+            primary_universe == universe
+                && primary.mass > body.mass
+                && !primary.touches(body)
+                && primary.pos.distance(body.pos)
+                    < roche_limit(primary.mass, body.mass, body.radius)
+        });
+        if let Some((primary, _)) = primary {
+            let escape_speed = (2.0 * gravity.0 * body.mass / body.radius).sqrt();
+            let speed = disruption.spread * escape_speed;
+            let material = material.cloned();
+            for debris in tidal_fragments(body, primary, disruption.pieces, speed) {
+                spawn_debris(&mut commands, &mut meshes, &quality, &material, &debris)
+                    .insert(TidalDebris);
+            }
+            commands.entity(body.entity).despawn();
+            disrupted.0.push(body.entity);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn resolve_collisions(
+    mut commands: Commands,
+    mode: Res<CollisionMode>,
+    continuous: Res<ContinuousCollision>,
+    dt: Res<TimeStep>,
+    disrupted: Res<Disrupted>,
+    time: Res<SimulationTime>,
+    mut log: ResMut<CollisionLog>,
+    mut events: EventWriter<CollisionEvent>,
+    mut accretion: ResMut<AccretionHistory>,
+    quality: Res<QualityPreset>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<(
+        Entity,
+        &mut Mass,
+        &mut Transform,
+        &mut Velocity,
+        &mut Radius,
+        Option<&Handle<StandardMaterial>>,
+        Option<&CentralAccretor>,
+        Option<&Universe>,
+    )>,
+) {
+    let mut materials = Vec::new();
+    let mut accretors = Vec::new();
+    let mut universes = Vec::new();
+    let mut bodies: Vec<Option<CollidingBody>> = query
+        .iter_mut()
+        .map(
+            |(entity, mass, transform, vel, radius, material, accretor, universe)| {
+                materials.push(material.cloned());
+                accretors.push(accretor.is_some());
+                universes.push(universe.copied().unwrap_or_default());
+                if disrupted.0.contains(&entity) {
+                    return None;
+                }
+                Some(CollidingBody {
+                    entity,
+                    mass: mass.0,
+                    pos: transform.translation,
+                    vel: vel.0,
+                    radius: radius.0,
+                })
+            },
+        )
+        .collect();
+    let colliding = *mode != CollisionMode::Ignore && *mode != CollisionMode::Stick;
+    if !colliding && !accretors.contains(&true) {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut merged = HashMap::default();
+    for i in 0..bodies.len() {
+        for j in (i + 1)..bodies.len() {
+            if universes[i] != universes[j] {
+                continue;
+            }
+            let (a, b) = match (bodies[i], bodies[j]) {
+                (Some(a), Some(b)) if a.collides(&b, dt.0, continuous.0) => (a, b),
+                _ => continue,
+            };
+
+            // Accretors take precedence over the collision mode, between two of them the heaviest wins
+            let accreting = match (accretors[i], accretors[j]) {
+                (true, false) => Some((i, j, a, b)),
+                (false, true) => Some((j, i, b, a)),
+                _ => None,
+            };
+            if let Some((kept, absorbed, accretor, body)) = accreting {
+                let result = accrete(&accretor, &body);
+                record_collision(
+                    &mut log,
+                    &mut events,
+                    CollisionRecord {
+                        time: time.0,
+                        bodies: (accretor.entity, body.entity),
+                        masses: (accretor.mass, body.mass),
+                        impact_speed: accretor.vel.distance(body.vel),
+                        resulting_mass: result.mass,
+                    },
+                );
+                accretion.record(time.0, body.mass);
+                commands.entity(body.entity).despawn();
+                merged.remove(&body.entity);
+                bodies[absorbed] = None;
+                bodies[kept] = Some(result);
+                merged.insert(result.entity, result);
+                continue;
+            }
+            if !colliding {
+                continue;
+            }
+
+            let shattering = match *mode {
+                CollisionMode::Fragment { threshold, pieces }
+                    if pieces > 1 && collision_energy(&a, &b) > threshold =>
+                {
+                    Some(pieces)
+                }
+                _ => None,
+            };
+            if let Some(pieces) = shattering {
+                record_collision(
+                    &mut log,
+                    &mut events,
+                    CollisionRecord {
+                        time: time.0,
+                        bodies: (a.entity, b.entity),
+                        masses: (a.mass, b.mass),
+                        impact_speed: a.vel.distance(b.vel),
+                        resulting_mass: a.mass + b.mass,
+                    },
+                );
+                // Fragments look like the heaviest body
+                let material = if a.mass >= b.mass {
+                    materials[i].clone()
+                } else {
+                    materials[j].clone()
+                };
+                for debris in fragment(&a, &b, pieces, &mut rng) {
+                    spawn_debris(&mut commands, &mut meshes, &quality, &material, &debris);
+                }
+                for body in [bodies[i].take(), bodies[j].take()].iter().flatten() {
+                    commands.entity(body.entity).despawn();
+                    merged.remove(&body.entity);
+                }
+                continue;
+            }
+
+            let result = merge(&a, &b);
+            record_collision(
+                &mut log,
+                &mut events,
+                CollisionRecord {
+                    time: time.0,
+                    bodies: (a.entity, b.entity),
+                    masses: (a.mass, b.mass),
+                    impact_speed: a.vel.distance(b.vel),
+                    resulting_mass: result.mass,
+                },
+            );
+
+            // The merged body replaces the heaviest one
+            let (kept, absorbed) = if result.entity == a.entity {
+                (i, j)
+            } else {
+                (j, i)
+            };
+            if let Some(absorbed) = bodies[absorbed].take() {
+                commands.entity(absorbed.entity).despawn();
+                merged.remove(&absorbed.entity);
+            }
+            bodies[kept] = Some(result);
+            merged.insert(result.entity, result);
+        }
+    }
+
+    for (entity, mut mass, mut transform, mut vel, mut radius, _, _, _) in query.iter_mut() {
+        if let Some(body) = merged.get(&entity) {
+            // Grow the rendered mesh along with the body
+            transform.scale *= body.radius / radius.0;
+            transform.translation = body.pos;
+            mass.0 = body.mass;
+            vel.0 = body.vel;
+            radius.0 = body.radius;
+        }
+    }
+}
+
+/// Links the touching bodies into clusters, the accretors absorbing them instead
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn stick_collisions(
+    mut commands: Commands,
+    mode: Res<CollisionMode>,
+    continuous: Res<ContinuousCollision>,
+    dt: Res<TimeStep>,
+    disrupted: Res<Disrupted>,
+    time: Res<SimulationTime>,
+    mut log: ResMut<CollisionLog>,
+    mut events: EventWriter<CollisionEvent>,
+    mut query: Query<
+        (
+            Entity,
+            &Mass,
+            &Transform,
+            &mut Velocity,
+            &Radius,
+            Option<&Cluster>,
+            Option<&Universe>,
+        ),
+        Without<CentralAccretor>,
+    >,
+) {
+    if *mode != CollisionMode::Stick {
+        return;
+    }
+
+    let mut bodies = Vec::new();
+    let mut clusters = Vec::new();
+    let mut universes = Vec::new();
+    for (entity, mass, transform, vel, radius, cluster, universe) in query.iter_mut() {
+        bodies.push(CollidingBody {
+            entity,
+            mass: mass.0,
+            pos: transform.translation,
+            vel: vel.0,
+            radius: radius.0,
+        });
+        clusters.push(cluster.map_or(entity, |cluster| cluster.0));
+        universes.push(universe.copied().unwrap_or_default());
+    }
+
+    let mut stuck = false;
+    for i in 0..bodies.len() {
+        for j in (i + 1)..bodies.len() {
+            let (a, b) = (&bodies[i], &bodies[j]);
+            if clusters[i] == clusters[j]
+                || universes[i] != universes[j]
+                || disrupted.0.contains(&a.entity)
+                || disrupted.0.contains(&b.entity)
+                || !(a.touches(b) || a.collides(b, dt.0, continuous.0))
+            {
+                continue;
+            }
+            record_collision(
+                &mut log,
+                &mut events,
+                CollisionRecord {
+                    time: time.0,
+                    bodies: (a.entity, b.entity),
+                    masses: (a.mass, b.mass),
+                    impact_speed: a.vel.distance(b.vel),
+                    resulting_mass: a.mass + b.mass,
+                },
+            );
+            let (kept, absorbed) = (clusters[i], clusters[j]);
+            clusters
+                .iter_mut()
+                .filter(|cluster| **cluster == absorbed)
+                .for_each(|cluster| *cluster = kept);
+            stuck = true;
+        }
+    }
+    if !stuck {
+        return;
+    }
+
+    let mut sizes: HashMap<Entity, usize> = HashMap::default();
+    for cluster in clusters.iter() {
+        *sizes.entry(*cluster).or_default() += 1;
+    }
+    let velocities = cluster_velocities(
+        bodies
+            .iter()
+            .zip(clusters.iter())
+            .map(|(body, cluster)| (*cluster, body.mass, body.vel)),
+    );
+    for ((entity, _, _, mut vel, _, cluster, _), new_cluster) in query.iter_mut().zip(clusters) {
+        if sizes[&new_cluster] > 1 && cluster != Some(&Cluster(new_cluster)) {
+            commands.entity(entity).insert(Cluster(new_cluster));
+        }
+        if let Some(cluster_vel) = velocities.get(&new_cluster) {
+            vel.0 = *cluster_vel;
+        }
+    }
+}
+
+/// Common velocity of each cluster, given the `(cluster, mass, velocity)` of its members
+fn cluster_velocities(members: impl Iterator<Item = (Entity, f32, Vec3)>) -> HashMap<Entity, Vec3> {
+    let mut clusters: HashMap<Entity, (Vec<f32>, Vec<Vec3>)> = HashMap::default();
+    for (cluster, mass, vel) in members {
+        let (masses, velocities) = clusters.entry(cluster).or_default();
+        masses.push(mass);
+        velocities.push(vel);
+    }
+    clusters
+        .into_iter()
+        .map(|(cluster, (masses, velocities))| (cluster, cluster_velocity(&masses, &velocities)))
+        .collect()
+}
+
+/// Gives the bodies of each cluster their common velocity so that they move rigidly
+fn move_clusters(mut query: Query<(&Mass, &mut Velocity, &Cluster)>) {
+    let velocities = cluster_velocities(
+        query
+            .iter_mut()
+            .map(|(mass, vel, cluster)| (cluster.0, mass.0, vel.0)),
+    );
+    for (_, mut vel, cluster) in query.iter_mut() {
+        if let Some(cluster_vel) = velocities.get(&cluster.0) {
+            vel.0 = *cluster_vel;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::Events;
+
+    use super::*;
+    use crate::plugins::nbody::DT;
+    use crate::testing::{run_system, world_with_assets};
+
+    fn collision_world(mode: CollisionMode) -> World {
+        let mut world = world_with_assets();
+        world.insert_resource(mode);
+        world.insert_resource(ContinuousCollision::default());
+        world.insert_resource(Disrupted::default());
+        world.insert_resource(SimulationTime(2.0));
+        world.insert_resource(TimeStep::default());
+        world.insert_resource(CollisionLog::default());
+        world.insert_resource(AccretionHistory::default());
+        world.insert_resource(Events::<CollisionEvent>::default());
+        world.insert_resource(QualityPreset::default());
+        world
+    }
+
+    fn spawn_body(world: &mut World, mass: f32, pos: Vec3, vel: Vec3, radius: f32) -> Entity {
+        world
+            .spawn()
+            .insert_bundle(BodyBundle::new(mass, pos, vel))
+            .insert(Radius(radius))
+            .id()
+    }
+
+    fn bodies(world: &mut World) -> Vec<(f32, Vec3, Vec3)> {
+        world
+            .query::<(&Mass, &Transform, &Velocity)>()
+            .iter(world)
+            .map(|(mass, transform, vel)| (mass.0, transform.translation, vel.0))
+            .collect()
+    }
+
+    #[test]
+    fn merging_logs_a_single_record() {
+        let mut world = collision_world(CollisionMode::Merge);
+        let heavy = spawn_body(&mut world, 3.0, Vec3::ZERO, Vec3::X, 0.5);
+        let light = spawn_body(&mut world, 1.0, Vec3::new(0.8, 0.0, 0.0), -Vec3::X, 0.5);
+
+        run_system(&mut world, resolve_collisions.system());
+
+        let log = world.get_resource::<CollisionLog>().unwrap();
+        assert_eq!(log.records.len(), 1);
+        let record = &log.records[0];
+        assert_eq!(record.time, 2.0);
+        assert_eq!(record.bodies, (heavy, light));
+        assert_eq!(record.masses, (3.0, 1.0));
+        assert_eq!(record.resulting_mass, 4.0);
+        assert_eq!(record.impact_speed, 2.0);
+        assert_eq!(
+            bodies(&mut world),
+            vec![(4.0, Vec3::new(0.2, 0.0, 0.0), Vec3::new(0.5, 0.0, 0.0))]
+        );
+    }
+
+    #[test]
+    fn accretor_absorbs_the_bodies_touching_it() {
+        // Accretors absorb the bodies even when the collisions are ignored
+        let mut world = collision_world(CollisionMode::Ignore);
+        let star = spawn_body(&mut world, 10.0, Vec3::ZERO, Vec3::ZERO, 1.0);
+        world.entity_mut(star).insert(CentralAccretor);
+        let test_body = spawn_body(&mut world, 0.5, Vec3::new(1.2, 0.0, 0.0), -Vec3::X, 0.3);
+        spawn_body(&mut world, 0.5, Vec3::new(5.0, 0.0, 0.0), Vec3::ZERO, 0.3);
+
+        run_system(&mut world, resolve_collisions.system());
+
+        assert!(world.get_entity(test_body).is_none());
+        assert_eq!(world.get::<Mass>(star).unwrap().0, 10.5);
+        let vel = world.get::<Velocity>(star).unwrap().0;
+        assert!((vel - Vec3::new(-0.5 / 10.5, 0.0, 0.0)).length() < 1e-6);
+        assert_eq!(bodies(&mut world).len(), 2);
+        let accretion = world.get_resource::<AccretionHistory>().unwrap();
+        assert_eq!(accretion.events, vec![(2.0, 0.5)]);
+        assert_eq!(accretion.total_mass, 0.5);
+    }
+
+    #[test]
+    fn fragmenting_conserves_mass_momentum_and_energy() {
+        let mut world = collision_world(CollisionMode::Fragment {
+            threshold: 0.1,
+            pieces: 6,
+        });
+        let a = (3.0, Vec3::ZERO, Vec3::new(2.0, 1.0, 0.0));
+        let b = (1.0, Vec3::new(0.8, 0.0, 0.0), Vec3::new(-3.0, 0.0, 0.5));
+        for &(mass, pos, vel) in [a, b].iter() {
+            spawn_body(&mut world, mass, pos, vel, 0.5);
+        }
+
+        run_system(&mut world, resolve_collisions.system());
+
+        let fragments = bodies(&mut world);
+        assert_eq!(fragments.len(), 6);
+        let total = |bodies: &[(f32, Vec3, Vec3)]| {
+            bodies
+                .iter()
+                .fold((0.0, Vec3::ZERO, 0.0), |(m, p, e), (mass, _, vel)| {
+                    (
+                        m + mass,
+                        p + *mass * *vel,
+                        e + 0.5 * mass * vel.length_squared(),
+                    )
+                })
+        };
+        let (mass, momentum, energy) = total(&fragments);
+        let (initial_mass, initial_momentum, initial_energy) = total(&[a, b]);
+        assert!((mass - initial_mass).abs() < 1e-5);
+        assert!((momentum - initial_momentum).length() < 1e-4);
+        assert!((energy - initial_energy).abs() < 1e-3 * initial_energy);
+    }
+
+    #[test]
+    fn collision_mode_from_str() {
+        assert_eq!("merge".parse(), Ok(CollisionMode::Merge));
+        assert_eq!("Ignore".parse(), Ok(CollisionMode::Ignore));
+        assert_eq!(
+            "fragment:0.5:8".parse(),
+            Ok(CollisionMode::Fragment {
+                threshold: 0.5,
+                pieces: 8
+            })
+        );
+        assert_eq!(
+            "fragment:2".parse(),
+            Ok(CollisionMode::Fragment {
+                threshold: 2.0,
+                pieces: 5
+            })
+        );
+        assert_eq!("stick".parse(), Ok(CollisionMode::Stick));
+        assert!("fragment".parse::<CollisionMode>().is_err());
+    }
+
+    #[test]
+    fn tidal_disruption_from_str() {
+        assert_eq!(
+            "8:0.5".parse(),
+            Ok(TidalDisruption {
+                enabled: true,
+                pieces: 8,
+                spread: 0.5
+            })
+        );
+        assert_eq!(
+            "3".parse(),
+            Ok(TidalDisruption {
+                enabled: true,
+                pieces: 3,
+                spread: 1.0
+            })
+        );
+        assert!("".parse::<TidalDisruption>().is_err());
+        assert!("3:1:2".parse::<TidalDisruption>().is_err());
+    }
+
+    #[test]
+    fn body_inside_the_roche_limit_is_disrupted() {
+        let mut world = collision_world(CollisionMode::Merge);
+        world.insert_resource(TidalDisruption {
+            enabled: true,
+            ..Default::default()
+        });
+        world.insert_resource(Gravity(1.0));
+        // Roche limit of the planet at 0.58 from the star
+        let star = (100.0, Vec3::ZERO, Vec3::ZERO, 0.2);
+        let planet = (
+            1.0,
+            Vec3::new(0.5, 0.0, 0.0),
+            Vec3::new(0.0, 14.0, 0.0),
+            0.1,
+        );
+        // Dense moon touching the planet, with a Roche limit of 0.13 only
+        let moon = (
+            0.1,
+            Vec3::new(0.5, 0.1, 0.0),
+            Vec3::new(1.0, 14.0, 0.0),
+            0.01,
+        );
+        for &(mass, pos, vel, radius) in [star, planet, moon].iter() {
+            spawn_body(&mut world, mass, pos, vel, radius);
+        }
+
+        // The disrupted planet doesn't merge with the moon in the same step
+        SystemStage::single_threaded()
+            .with_system(disrupt_tidally.system().label(TidalDisruptionSystem))
+            .with_system(resolve_collisions.system().after(TidalDisruptionSystem))
+            .run(&mut world);
+
+        let debris: Vec<(f32, Vec3, Vec3)> = world
+            .query_filtered::<(&Mass, &Transform, &Velocity), With<TidalDebris>>()
+            .iter(&world)
+            .map(|(mass, transform, vel)| (mass.0, transform.translation, vel.0))
+            .collect();
+        assert_eq!(debris.len(), 5);
+        for (_, pos, _) in debris.iter() {
+            // Lined up along the tidal axis
+            assert!(pos.y.abs() < 1e-6 && pos.z.abs() < 1e-6);
+        }
+        assert_eq!(bodies(&mut world).len(), 7);
+        let total = |bodies: &[(f32, Vec3, Vec3)]| {
+            bodies
+                .iter()
+                .fold((0.0, Vec3::ZERO), |(m, p), (mass, _, vel)| {
+                    (m + mass, p + *mass * *vel)
+                })
+        };
+        let (mass, momentum) = total(&debris);
+        assert!((mass - 1.0).abs() < 1e-5);
+        assert!((momentum - planet.2).length() < 1e-4);
+        let all = bodies(&mut world);
+        let (mass, momentum) = total(&all);
+        assert!((mass - 101.1).abs() < 1e-4);
+        assert!((momentum - Vec3::new(0.1, 15.4, 0.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn stuck_bodies_move_together_with_their_combined_momentum() {
+        let mut world = collision_world(CollisionMode::Stick);
+        let heavy = spawn_body(&mut world, 3.0, Vec3::ZERO, Vec3::X, 0.5);
+        let light = spawn_body(&mut world, 1.0, Vec3::new(0.8, 0.0, 0.0), -Vec3::Y, 0.5);
+        let momentum = 3.0 * Vec3::X - Vec3::Y;
+
+        run_system(&mut world, stick_collisions.system());
+
+        let offset = Vec3::new(0.8, 0.0, 0.0);
+        for _ in 0..100 {
+            // Opposite internal forces, e.g. their mutual attraction
+            world.get_mut::<Velocity>(heavy).unwrap().0 += Vec3::new(0.1, 0.0, 0.0) / 3.0;
+            world.get_mut::<Velocity>(light).unwrap().0 -= Vec3::new(0.1, 0.0, 0.0);
+            run_system(&mut world, move_clusters.system());
+            for (mut transform, vel) in world
+                .query::<(&mut Transform, &Velocity)>()
+                .iter_mut(&mut world)
+            {
+                transform.translation += vel.0 * DT;
+            }
+
+            let position = |world: &World, body| world.get::<Transform>(body).unwrap().translation;
+            let separation = position(&world, light) - position(&world, heavy);
+            assert!((separation - offset).length() < 1e-5);
+            let total = bodies(&mut world)
+                .iter()
+                .fold(Vec3::ZERO, |sum, (mass, _, vel)| sum + *mass * *vel);
+            assert!((total - momentum).length() < 1e-5);
+        }
+        assert_eq!(world.get::<Cluster>(light), Some(&Cluster(heavy)));
+    }
+
+    fn colliding_body(pos: Vec3, vel: Vec3) -> CollidingBody {
+        CollidingBody {
+            entity: Entity::new(0),
+            mass: 1.0,
+            pos,
+            vel,
+            radius: 0.1,
+        }
+    }
+
+    #[test]
+    fn time_to_collision_of_approaching_and_receding_bodies() {
+        let target = colliding_body(Vec3::ZERO, Vec3::ZERO);
+        let approaching = colliding_body(Vec3::new(-1.2, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0));
+        let t = time_to_collision(&approaching, &target).unwrap();
+        assert!((t - 0.5).abs() < 1e-6);
+        let receding = colliding_body(Vec3::new(-1.2, 0.0, 0.0), Vec3::new(-2.0, 0.0, 0.0));
+        assert_eq!(time_to_collision(&receding, &target), None);
+
+        // Overlapping: only a collision while getting closer
+        let closing = colliding_body(Vec3::new(-0.1, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(time_to_collision(&closing, &target), Some(0.0));
+        let separating = colliding_body(Vec3::new(-0.1, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(time_to_collision(&separating, &target), None);
+        assert_eq!(time_to_collision(&target, &target), None);
+    }
+
+    #[test]
+    fn fast_body_does_not_tunnel_through_a_stationary_one() {
+        // Crossing 10 units in a step, from 5 units before the target to 5 units past it
+        let speed = 10.0 / DT;
+        for &continuous in [false, true].iter() {
+            let mut world = collision_world(CollisionMode::Merge);
+            world.insert_resource(ContinuousCollision(continuous));
+            spawn_body(&mut world, 10.0, Vec3::ZERO, Vec3::ZERO, 0.5);
+            spawn_body(
+                &mut world,
+                1.0,
+                Vec3::new(5.0, 0.0, 0.0),
+                Vec3::new(speed, 0.0, 0.0),
+                0.1,
+            );
+
+            run_system(&mut world, resolve_collisions.system());
+
+            let log = world.get_resource::<CollisionLog>().unwrap();
+            assert_eq!(log.records.len(), continuous as usize);
+            assert_eq!(bodies(&mut world).len(), if continuous { 1 } else { 2 });
+        }
+    }
+}
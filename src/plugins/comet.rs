@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+
+use super::nbody::BodyClass;
+
+/// Tails of the `BodyClass::Comet` bodies, pointing away from the nearest star
+/// and growing as the comet gets closer to it.
+/// The tails are purely cosmetic, they don't carry any mass.
+pub struct CometPlugin;
+
+impl Plugin for CometPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CometTails>()
+            .add_system(spawn_comet_tails.system())
+            .add_system(update_comet_tails.system());
+    }
+}
+
+pub struct CometTails {
+    /// Length of the tail at a unit distance from the star, the length scales as the inverse distance
+    pub length_scale: f32,
+    pub max_length: f32,
+    pub width: f32,
+    pub color: Color,
+}
+
+impl Default for CometTails {
+    fn default() -> Self {
+        Self {
+            length_scale: 10.0,
+            max_length: 5.0,
+            width: 0.1,
+            color: Color::rgba(0.6, 0.8, 1.0, 0.4),
+        }
+    }
+}
+
+/// Tail of the `comet` entity
+struct CometTail {
+    comet: Entity,
+}
+
+/// Tags a comet whose tail is spawned
+struct WithTail;
+
+/// Direction pointing away from the star nearest to `pos`, `None` without any star
+/// or when the comet sits on the star
+pub fn tail_direction(pos: Vec3, stars: &[Vec3]) -> Option<Vec3> {
+    let nearest = stars.iter().min_by(|a, b| {
+        a.distance_squared(pos)
+            .partial_cmp(&b.distance_squared(pos))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    })?;
+    (pos - *nearest).try_normalize()
+}
+
+/// Length of the tail at `distance` from the star:
+/// ```text
+/// l = min(length_scale / d, max_length)
+/// ```
+pub fn tail_length(distance: f32, length_scale: f32, max_length: f32) -> f32 {
+    if distance <= 0.0 {
+        return max_length;
+    }
+    (length_scale / distance).min(max_length)
+}
+
+/// Rotation turning the y axis, the long axis of the tail mesh, into the unit vector `direction`
+fn rotation_from_y(direction: Vec3) -> Quat {
+    match Vec3::Y.cross(direction).try_normalize() {
+        Some(axis) => Quat::from_axis_angle(axis, Vec3::Y.dot(direction).clamp(-1.0, 1.0).acos()),
+        None if direction.y < 0.0 => Quat::from_rotation_x(std::f32::consts::PI),
+        None => Quat::IDENTITY,
+    }
+}
+
+fn spawn_comet_tails(
+    mut commands: Commands,
+    settings: Res<CometTails>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    comets: Query<(Entity, &BodyClass), Without<WithTail>>,
+) {
+    for (comet, class) in comets.iter() {
+        if *class != BodyClass::Comet {
+            continue;
+        }
+        commands
+            .spawn_bundle(PbrBundle {
+                // Unit sphere stretched along the tail direction by `update_comet_tails`
+                mesh: meshes.add(Mesh::from(shape::Icosphere {
+                    radius: 0.5,
+                    subdivisions: 3,
+                })),
+                material: materials.add(StandardMaterial {
+                    base_color: settings.color,
+                    emissive: settings.color,
+                    unlit: true,
+                    ..Default::default()
+                }),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(CometTail { comet });
+        commands.entity(comet).insert(WithTail);
+    }
+}
+
+fn update_comet_tails(
+    mut commands: Commands,
+    settings: Res<CometTails>,
+    bodies: Query<(&BodyClass, &Transform), Without<CometTail>>,
+    mut tails: Query<(Entity, &CometTail, &mut Transform, &mut Visible)>,
+) {
+    let stars: Vec<Vec3> = bodies
+        .iter()
+        .filter(|(class, _)| **class == BodyClass::Star)
+        .map(|(_, transform)| transform.translation)
+        .collect();
+
+    for (entity, tail, mut transform, mut visible) in tails.iter_mut() {
+        let pos = match bodies.get(tail.comet) {
+            Ok((_, comet)) => comet.translation,
+            Err(_) => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        let direction = match tail_direction(pos, &stars) {
+            Some(direction) => direction,
+            None => {
+                visible.is_visible = false;
+                continue;
+            }
+        };
+        let distance = stars
+            .iter()
+            .map(|star| star.distance(pos))
+            .fold(f32::INFINITY, f32::min);
+        let length = tail_length(distance, settings.length_scale, settings.max_length);
+
+        visible.is_visible = true;
+        // The tail starts at the comet
+        *transform = Transform {
+            translation: pos + 0.5 * length * direction,
+            rotation: rotation_from_y(direction),
+            scale: Vec3::new(settings.width, length, settings.width),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tail_points_away_from_the_nearest_star() {
+        let stars = [Vec3::new(-10.0, 0.0, 0.0), Vec3::new(0.0, 3.0, 0.0)];
+        let direction = tail_direction(Vec3::ZERO, &stars).unwrap();
+        assert!((direction - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-6);
+        assert_eq!(tail_direction(Vec3::ZERO, &[]), None);
+        assert_eq!(tail_direction(Vec3::ZERO, &[Vec3::ZERO]), None);
+    }
+
+    #[test]
+    fn tail_grows_closer_to_the_star() {
+        assert_eq!(tail_length(10.0, 20.0, 5.0), 2.0);
+        assert_eq!(tail_length(5.0, 20.0, 5.0), 4.0);
+        assert_eq!(tail_length(1.0, 20.0, 5.0), 5.0);
+        assert_eq!(tail_length(0.0, 20.0, 5.0), 5.0);
+    }
+
+    #[test]
+    fn tail_mesh_is_rotated_along_the_direction() {
+        for direction in [Vec3::X, -Vec3::Y, Vec3::new(1.0, 1.0, -1.0).normalize()].iter() {
+            let rotated = rotation_from_y(*direction) * Vec3::Y;
+            assert!((rotated - *direction).length() < 1e-5, "{:?}", direction);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use bevy::prelude::*;
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{Gravity, Mass, SofteningKernel};
+use super::prediction::line_mesh;
+
+/// Iso-potential lines of the bodies' gravity in the orbital plane (z = 0):
+/// * C: toggle the contours
+pub struct PotentialContoursPlugin;
+
+impl Plugin for PotentialContoursPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::ToggleContours,
+            "toggle the gravitational potential contours on the orbital plane",
+        )
+        .init_resource::<PotentialContours>()
+        .add_startup_system(spawn_contours.system())
+        .add_system(toggle_contours.system())
+        .add_system(draw_contours.system());
+    }
+}
+
+pub struct PotentialContours {
+    /// Potentials of the drawn lines, when empty `AUTO_LEVELS` levels are spread over the sampled range
+    pub levels: Vec<f32>,
+    pub enabled: bool,
+    /// Number of cells along each side of the sampled grid
+    pub resolution: usize,
+    /// Half the side length of the sampled square, centered on the origin
+    pub extent: f32,
+}
+
+impl Default for PotentialContours {
+    fn default() -> Self {
+        Self {
+            levels: Vec::new(),
+            enabled: false,
+            resolution: 64,
+            extent: 50.0,
+        }
+    }
+}
+
+/// Number of levels drawn when none are configured
+pub const AUTO_LEVELS: usize = 8;
+
+pub const CONTOUR_COLOR: Color = Color::rgba(0.2, 0.8, 1.0, 0.5);
+
+/// Gravitational potential at `point`, with the same softening as the forces:
+/// ```text
+/// Φ = -Σ G*m_i / r_i
+/// ```
+pub fn potential_at(
+    point: Vec3,
+    bodies: &[(f32, Vec3)],
+    g: f32,
+    softening: SofteningKernel,
+) -> f32 {
+    bodies
+        .iter()
+        .map(|(mass, pos)| (mass, pos.distance_squared(point)))
+        .filter(|(_, dist2)| *dist2 > 0.0)
+        .map(|(mass, dist2)| g * mass * softening.potential(dist2))
+        .sum()
+}
+
+/// Potential at the `resolution + 1` x `resolution + 1` corners of the grid covering `[-extent, extent]`
+/// along x and y, row by row from the bottom (-y) of the plane
+pub fn sample_potential(
+    bodies: &[(f32, Vec3)],
+    g: f32,
+    softening: SofteningKernel,
+    resolution: usize,
+    extent: f32,
+) -> Vec<f32> {
+    let coord = |i: usize| -extent + 2.0 * extent * i as f32 / resolution as f32;
+    (0..=resolution)
+        .flat_map(|row| (0..=resolution).map(move |col| Vec3::new(coord(col), coord(row), 0.0)))
+        .map(|point| potential_at(point, bodies, g, softening))
+        .collect()
+}
+
+/// `count` levels spaced geometrically between the extreme `values`, all of the same sign as the potential,
+/// so that the lines tighten around the bodies
+pub fn auto_levels(values: &[f32], count: usize) -> Vec<f32> {
+    let (min, max) = values
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| {
+            (min.min(*v), max.max(*v))
+        });
+    // Potentials are negative, the deepest one is the largest in magnitude
+    let (deepest, shallowest) = (-min, -max);
+    if count == 0 || shallowest <= 0.0 || deepest <= shallowest {
+        return Vec::new();
+    }
+    let ratio = (deepest / shallowest).powf(1.0 / (count + 1) as f32);
+    (1..=count)
+        .map(|i| -shallowest * ratio.powi(i as i32))
+        .collect()
+}
+
+/// Marching squares on a single unit cell: segments of the `level` line through the cell,
+/// given the values at its corners `[bottom-left, bottom-right, top-right, top-left]`.
+/// The crossings are linearly interpolated along the edges,
+/// the saddle cells are disambiguated with the average of the corners.
+pub fn cell_segments(corners: [f32; 4], level: f32) -> Vec<(Vec2, Vec2)> {
+    let positions = [
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+    ];
+    let above = |v: f32| v >= level;
+
+    // Crossings on the bottom, right, top and left edges
+    let crossings: Vec<Option<Vec2>> = (0..4)
+        .map(|i| {
+            let (a, b) = (corners[i], corners[(i + 1) % 4]);
+            if above(a) == above(b) {
+                return None;
+            }
+            let t = (level - a) / (b - a);
+            Some(positions[i].lerp(positions[(i + 1) % 4], t))
+        })
+        .collect();
+
+    match crossings.iter().flatten().collect::<Vec<_>>().as_slice() {
+        [a, b] => vec![(**a, **b)],
+        [bottom, right, top, left] => {
+            let center = corners.iter().sum::<f32>() / 4.0;
+            if above(center) == above(corners[0]) {
+                // The bottom-left and top-right corners are connected through the center
+                vec![(**bottom, **right), (**top, **left)]
+            } else {
+                vec![(**left, **bottom), (**right, **top)]
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Line list of the `level` line through the grid of `values` sampled by `sample_potential`
+pub fn contour_lines(values: &[f32], resolution: usize, extent: f32, level: f32) -> Vec<[f32; 3]> {
+    if values.len() != (resolution + 1) * (resolution + 1) {
+        return Vec::new();
+    }
+    let cell_size = 2.0 * extent / resolution as f32;
+    let value = |row: usize, col: usize| values[row * (resolution + 1) + col];
+    let mut vertices = Vec::new();
+    for row in 0..resolution {
+        for col in 0..resolution {
+            let corners = [
+                value(row, col),
+                value(row, col + 1),
+                value(row + 1, col + 1),
+                value(row + 1, col),
+            ];
+            let origin = Vec2::new(col as f32, row as f32);
+            for (a, b) in cell_segments(corners, level) {
+                for p in [a, b].iter() {
+                    let p = (origin + *p) * cell_size - Vec2::splat(extent);
+                    vertices.push([p.x, p.y, 0.0]);
+                }
+            }
+        }
+    }
+    vertices
+}
+
+struct ContourLines;
+
+fn spawn_contours(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(line_mesh(vec![[0.0; 3]; 2])),
+            material: materials.add(StandardMaterial {
+                base_color: CONTOUR_COLOR,
+                unlit: true,
+                ..Default::default()
+            }),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(ContourLines);
+}
+
+fn toggle_contours(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut contours: ResMut<PotentialContours>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleContours) {
+        contours.enabled = !contours.enabled;
+    }
+}
+
+fn draw_contours(
+    contours: Res<PotentialContours>,
+    g: Res<Gravity>,
+    softening: Res<SofteningKernel>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    bodies: Query<(&Mass, &Transform)>,
+    mut query: Query<(&Handle<Mesh>, &mut Visible), With<ContourLines>>,
+) {
+    let (mesh, mut visible) = match query.single_mut() {
+        Ok(contour) => contour,
+        Err(_) => return,
+    };
+    if !contours.enabled {
+        visible.is_visible = false;
+        return;
+    }
+
+    let bodies: Vec<(f32, Vec3)> = bodies
+        .iter()
+        .map(|(mass, transform)| (mass.0, transform.translation))
+        .collect();
+    let values = sample_potential(
+        &bodies,
+        g.0,
+        *softening,
+        contours.resolution,
+        contours.extent,
+    );
+    let levels = if contours.levels.is_empty() {
+        auto_levels(&values, AUTO_LEVELS)
+    } else {
+        contours.levels.clone()
+    };
+    let vertices: Vec<[f32; 3]> = levels
+        .iter()
+        .flat_map(|level| contour_lines(&values, contours.resolution, contours.extent, *level))
+        .collect();
+
+    visible.is_visible = !vertices.is_empty();
+    if let (Some(mesh), false) = (meshes.get_mut(mesh), vertices.is_empty()) {
+        *mesh = line_mesh(vertices);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec2, b: Vec2) -> bool {
+        (a - b).length() < 1e-5
+    }
+
+    #[test]
+    fn linear_field_crosses_the_cell_at_the_interpolated_points() {
+        // f(x, y) = x: the 0.25 line is vertical at x = 0.25
+        let segments = cell_segments([0.0, 1.0, 1.0, 0.0], 0.25);
+        assert_eq!(segments.len(), 1);
+        let (a, b) = segments[0];
+        assert!(close(a, Vec2::new(0.25, 0.0)), "{:?}", a);
+        assert!(close(b, Vec2::new(0.25, 1.0)), "{:?}", b);
+    }
+
+    #[test]
+    fn cells_outside_the_level_have_no_segment() {
+        assert!(cell_segments([0.0, 1.0, 1.0, 0.0], 2.0).is_empty());
+        assert!(cell_segments([0.0, 1.0, 1.0, 0.0], -1.0).is_empty());
+    }
+
+    #[test]
+    fn saddle_cells_follow_the_center_value() {
+        // High center: the bottom-left and top-right corners are connected through it
+        let connected = cell_segments([1.0, 0.0, 1.0, 0.2], 0.5);
+        assert_eq!(connected.len(), 2);
+        assert!(close(connected[0].0, Vec2::new(0.5, 0.0)));
+        assert!(close(connected[0].1, Vec2::new(1.0, 0.5)));
+        assert!(close(connected[1].0, Vec2::new(0.375, 1.0)));
+        assert!(close(connected[1].1, Vec2::new(0.0, 0.625)));
+        // Low center: the bottom-left and top-right corners are cut off
+        let separated = cell_segments([1.0, 0.0, 0.6, 0.0], 0.5);
+        assert_eq!(separated.len(), 2);
+        assert!(close(separated[0].0, Vec2::new(0.0, 0.5)));
+        assert!(close(separated[0].1, Vec2::new(0.5, 0.0)));
+        assert!(close(separated[1].0, Vec2::new(1.0, 5.0 / 6.0)));
+        assert!(close(separated[1].1, Vec2::new(5.0 / 6.0, 1.0)));
+    }
+
+    #[test]
+    fn contour_lines_are_scaled_to_the_extent() {
+        // 2x2 cells of f(x, y) = x over [-1, 1]
+        let values: Vec<f32> = (0..9).map(|i| (i % 3) as f32).collect();
+        let vertices = contour_lines(&values, 2, 1.0, 0.5);
+        assert_eq!(vertices.len(), 4);
+        assert!(vertices.iter().all(|v| (v[0] + 0.5).abs() < 1e-5));
+        assert!(contour_lines(&values[1..], 2, 1.0, 0.5).is_empty());
+    }
+}
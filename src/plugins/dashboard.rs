@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use bevy_egui::egui::plot::{Legend, Line, Plot, Value, Values};
+use bevy_egui::egui::{self, Ui};
+use bevy_egui::{EguiContext, EguiPlugin};
+
+use super::diagnostics::{DiagnosticsHistory, DiagnosticsSample};
+use super::nbody::Gravity;
+use super::timeline::{Timeline, TimelineControl};
+
+/// egui panels over the simulation, behind the `ui` feature:
+/// * Diagnostics: live plots of the total energy and of the momentum magnitude vs the simulated time,
+///   drawn from the `DiagnosticsHistory`
+/// * Gravity: slider of the gravitational constant on a log scale, around the G of the scene
+/// * Timeline: play / pause / step buttons and a slider scrubbing through the recorded frames of the `Timeline`
+pub struct DashboardPlugin;
+
+impl Plugin for DashboardPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(EguiPlugin)
+            .add_system(plot_diagnostics.system())
+            .add_system(gravity_slider.system())
+            .add_system(timeline_panel.system());
+    }
+}
+
+/// Height of the plots, in logical pixels
+const PLOT_HEIGHT: f32 = 120.0;
+
+/// Factor G can be scaled by from the G of the scene, either way
+const GRAVITY_RANGE: f32 = 100.0;
+
+fn plot_line(
+    ui: &mut Ui,
+    id: &str,
+    history: &DiagnosticsHistory,
+    value: impl Fn(&DiagnosticsSample) -> f32,
+) {
+    let values = history
+        .samples()
+        .iter()
+        .map(|sample| Value::new(sample.time, value(sample)));
+    Plot::new(id)
+        .height(PLOT_HEIGHT)
+        .legend(Legend::default())
+        .show(ui, |plot| {
+            plot.line(Line::new(Values::from_values_iter(values)).name(id))
+        });
+}
+
+fn plot_diagnostics(egui: Res<EguiContext>, history: Res<DiagnosticsHistory>) {
+    egui::Window::new("Diagnostics")
+        .default_width(300.0)
+        .show(egui.ctx(), |ui| {
+            plot_line(ui, "total energy", &history, |sample| sample.total);
+            plot_line(ui, "|momentum|", &history, |sample| sample.momentum);
+        });
+}
+
+/// Only the future dynamics change, the positions and velocities are kept as is
+fn gravity_slider(egui: Res<EguiContext>, mut scene_g: Local<Option<f32>>, mut g: ResMut<Gravity>) {
+    // The scenes set G in their startup systems
+    let initial = *scene_g.get_or_insert(g.0);
+    if initial <= 0.0 {
+        return;
+    }
+    let mut value = g.0;
+    egui::Window::new("Gravity").show(egui.ctx(), |ui| {
+        ui.add(
+            egui::Slider::new(
+                &mut value,
+                initial / GRAVITY_RANGE..=initial * GRAVITY_RANGE,
+            )
+            .logarithmic(true)
+            .text("G"),
+        );
+        ui.label("Changing G breaks the conservation of energy: the orbits are no longer physical");
+    });
+    // Not to flag the resource as changed on every frame
+    if value != g.0 {
+        g.0 = value;
+    }
+}
+
+/// The controls are requested to the `Timeline`, applied along with the keyboard ones
+fn timeline_panel(egui: Res<EguiContext>, mut timeline: ResMut<Timeline>) {
+    let last = timeline.len().saturating_sub(1);
+    let mut index = timeline.cursor().unwrap_or(last);
+    let scrubbing = timeline.is_scrubbing();
+    let mut control = None;
+    egui::Window::new("Timeline").show(egui.ctx(), |ui| {
+        ui.horizontal(|ui| {
+            if ui.button("<").clicked() {
+                control = Some(TimelineControl::Step(-1));
+            }
+            if ui
+                .button(if scrubbing { "Play" } else { "Pause" })
+                .clicked()
+            {
+                control = Some(TimelineControl::TogglePause);
+            }
+            if ui.button(">").clicked() {
+                control = Some(TimelineControl::Step(1));
+            }
+        });
+        if ui
+            .add(egui::Slider::new(&mut index, 0..=last).text("frame"))
+            .changed()
+        {
+            control = Some(TimelineControl::Seek(index));
+        }
+    });
+    if let Some(control) = control {
+        timeline.request(control);
+    }
+}
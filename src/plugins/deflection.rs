@@ -0,0 +1,201 @@
+use bevy::prelude::*;
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{Gravity, Mass};
+use super::prediction::line_mesh;
+use super::selection::Selected;
+
+/// Focus rings of the selected body: the impact parameters at which test particles coming in
+/// at a reference relative velocity are deflected by given angles on their hyperbolic flyby:
+/// * D: toggle the rings, their deflection angles are logged
+///
+/// The rings lie in the plane perpendicular to the incoming velocity, centered on the body,
+/// the widest ones deflecting the least.
+pub struct DeflectionRingsPlugin;
+
+impl Plugin for DeflectionRingsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::ToggleDeflectionRings,
+            "toggle the deflection rings of the selected body",
+        )
+        .init_resource::<DeflectionRings>()
+        .add_startup_system(spawn_deflection_rings.system())
+        .add_system(toggle_deflection_rings.system())
+        .add_system(draw_deflection_rings.system());
+    }
+}
+
+pub struct DeflectionRings {
+    pub enabled: bool,
+    /// Velocity of the incoming particles relative to the body, far from it
+    pub velocity: Vec3,
+    /// Deflection angles of the rings in degrees, read at startup
+    pub angles: Vec<f32>,
+}
+
+impl Default for DeflectionRings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            // Toward the default camera, the rings face it
+            velocity: Vec3::new(0.0, 0.0, -10.0),
+            angles: vec![90.0, 60.0, 30.0, 10.0],
+        }
+    }
+}
+
+/// Number of segments of a ring
+const RING_SEGMENTS: usize = 96;
+
+/// Deflection angle in radians of a hyperbolic flyby with impact parameter `b` at relative speed `v`
+/// far from a body of gravitational parameter `mu = G*M`:
+/// ```text
+/// e = sqrt(1 + b^2 * v^4 / mu^2)
+/// δ = 2 * asin(1 / e)
+/// ```
+pub fn deflection_angle(impact_parameter: f32, speed: f32, mu: f32) -> f32 {
+    let ratio = impact_parameter * speed * speed / mu;
+    let eccentricity = (1.0 + ratio * ratio).sqrt();
+    2.0 * (1.0 / eccentricity).asin()
+}
+
+/// Impact parameter deflecting by `angle` radians, inverse of `deflection_angle`:
+/// ```text
+/// b = mu / v^2 * cot(δ / 2)
+/// ```
+/// `None` outside of `(0, π]`
+pub fn impact_parameter(angle: f32, speed: f32, mu: f32) -> Option<f32> {
+    if angle <= 0.0 || angle > std::f32::consts::PI || speed <= 0.0 {
+        return None;
+    }
+    Some(mu / (speed * speed) / (angle / 2.0).tan())
+}
+
+/// Color of the ring of `angle` radians, from red for a backward deflection to blue for a grazing one
+pub fn ring_color(angle: f32) -> Color {
+    let t = (angle / std::f32::consts::PI).clamp(0.0, 1.0);
+    Color::rgba(t, 0.4, 1.0 - t, 0.8)
+}
+
+/// Unit circle in the xy plane
+fn circle_vertices(segments: usize) -> Vec<[f32; 3]> {
+    let point = |i: usize| {
+        let theta = std::f32::consts::TAU * i as f32 / segments as f32;
+        [theta.cos(), theta.sin(), 0.0]
+    };
+    (0..segments)
+        .flat_map(|i| vec![point(i), point(i + 1)])
+        .collect()
+}
+
+/// Ring deflecting by `angle` radians
+struct DeflectionRing {
+    angle: f32,
+}
+
+fn spawn_deflection_rings(
+    mut commands: Commands,
+    rings: Res<DeflectionRings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(line_mesh(circle_vertices(RING_SEGMENTS)));
+    for angle in rings.angles.iter().map(|angle| angle.to_radians()) {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: mesh.clone(),
+                material: materials.add(StandardMaterial {
+                    base_color: ring_color(angle),
+                    unlit: true,
+                    ..Default::default()
+                }),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(DeflectionRing { angle });
+    }
+}
+
+fn toggle_deflection_rings(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut rings: ResMut<DeflectionRings>,
+) {
+    if !bindings.just_pressed(&keys, Action::ToggleDeflectionRings) {
+        return;
+    }
+    rings.enabled = !rings.enabled;
+    if rings.enabled {
+        info!(
+            "Deflection rings of {:?} degrees at the relative speed {}",
+            rings.angles,
+            rings.velocity.length()
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn draw_deflection_rings(
+    rings: Res<DeflectionRings>,
+    g: Res<Gravity>,
+    selected: Query<(&Mass, &Transform), (With<Selected>, Without<DeflectionRing>)>,
+    mut query: Query<(&DeflectionRing, &mut Transform, &mut Visible)>,
+) {
+    let body = selected.iter().next().filter(|_| rings.enabled);
+    let normal = rings.velocity.try_normalize();
+    for (ring, mut transform, mut visible) in query.iter_mut() {
+        visible.is_visible = false;
+        let ((mass, body_transform), normal) = match (body, normal) {
+            (Some(body), Some(normal)) => (body, normal),
+            _ => continue,
+        };
+        let radius = match impact_parameter(ring.angle, rings.velocity.length(), g.0 * mass.0) {
+            Some(radius) if radius.is_finite() => radius,
+            _ => continue,
+        };
+        transform.translation = body_transform.translation;
+        transform.rotation = rotation_from_z(normal);
+        transform.scale = Vec3::splat(radius);
+        visible.is_visible = true;
+    }
+}
+
+/// Rotation turning the z axis, the normal of the ring mesh, into the unit vector `direction`
+fn rotation_from_z(direction: Vec3) -> Quat {
+    match Vec3::Z.cross(direction).try_normalize() {
+        Some(axis) => Quat::from_axis_angle(axis, Vec3::Z.dot(direction).clamp(-1.0, 1.0).acos()),
+        // A ring is symmetric, facing either way is the same
+        None => Quat::IDENTITY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    #[test]
+    fn impact_parameter_maps_to_the_deflection_angle() {
+        // G*M = 4 at a speed of 2: b = mu / v^2 = 1 makes e = sqrt(2), a right-angle deflection
+        assert!((deflection_angle(1.0, 2.0, 4.0) - FRAC_PI_2).abs() < 1e-6);
+        // Head-on flybys turn back, distant ones go straight
+        assert!((deflection_angle(0.0, 2.0, 4.0) - PI).abs() < 1e-6);
+        assert!(deflection_angle(1e4, 2.0, 4.0) < 1e-3);
+
+        for degrees in [10.0f32, 45.0, 90.0, 170.0].iter() {
+            let angle = degrees.to_radians();
+            let b = impact_parameter(angle, 2.0, 4.0).unwrap();
+            assert!(
+                (deflection_angle(b, 2.0, 4.0) - angle).abs() < 1e-5,
+                "{}°",
+                degrees
+            );
+        }
+        assert_eq!(impact_parameter(0.0, 2.0, 4.0), None);
+        assert_eq!(impact_parameter(4.0, 2.0, 4.0), None);
+    }
+}
@@ -0,0 +1,690 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::collision::Radius;
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{
+    ExtendedBody, Gravity, Mass, PhysicsStep, PhysicsSystem, SimulationTime, SofteningKernel,
+    Velocity,
+};
+
+/// Conserved quantities of the simulation, updated after each physics step,
+/// and their optional recording with a `DiagnosticsRecorder`:
+/// * R: save the recorded diagnostics
+pub struct PhysicsDiagnosticsPlugin;
+
+impl Plugin for PhysicsDiagnosticsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::SaveDiagnostics,
+            "save the diagnostics recorded with `--record`",
+        )
+        .init_resource::<PhysicsDiagnostics>()
+        .init_resource::<DiagnosticsHistory>()
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(PhysicsStep)
+                .with_system(
+                    update_diagnostics
+                        .system()
+                        .label(DiagnosticsSystem::Update)
+                        .after(PhysicsSystem::Movement),
+                )
+                .with_system(record_diagnostics.system().after(DiagnosticsSystem::Update))
+                .with_system(update_history.system().after(DiagnosticsSystem::Update))
+                .with_system(
+                    correct_energy_drift
+                        .system()
+                        .after(DiagnosticsSystem::Update),
+                ),
+        )
+        .add_system(save_diagnostics_on_demand.system())
+        .add_system_to_stage(CoreStage::Last, save_diagnostics_on_exit.system());
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum DiagnosticsSystem {
+    Update,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PhysicsDiagnostics {
+    /// Number of physics steps since the start
+    pub step: u64,
+    pub kinetic: f32,
+    pub potential: f32,
+    pub momentum: Vec3,
+    /// Virial ratio `2K/|U|`, 1 for a system in equilibrium
+    pub virial: f32,
+}
+
+impl PhysicsDiagnostics {
+    pub fn total_energy(&self) -> f32 {
+        self.kinetic + self.potential
+    }
+}
+
+/// ```text
+/// K = Σ 1/2 * m_i * v_i^2
+/// ```
+pub fn kinetic_energy(masses: &[f32], velocities: &[Vec3]) -> f32 {
+    masses
+        .iter()
+        .zip(velocities)
+        .map(|(m, vel)| 0.5 * m * vel.length_squared())
+        .sum()
+}
+
+/// Gravitational potential energy, with the same softening as the forces:
+/// ```text
+/// U = -Σ G*m_i*m_j / r_ij
+/// ```
+/// Coincident bodies don't contribute, as they don't exert any force on each other.
+pub fn potential_energy(
+    masses: &[f32],
+    positions: &[Vec3],
+    g: f32,
+    softening: SofteningKernel,
+) -> f32 {
+    pairwise_potential_energy(masses, None, positions, g, softening)
+}
+
+/// Same as `potential_energy` for uniform spheres of radii `radii`, matching the forces of
+/// `compute_extended_accelerations` so that the total energy is conserved through contacts.
+/// The self-energy of each sphere is left out, being constant.
+pub fn extended_potential_energy(
+    masses: &[f32],
+    radii: &[f32],
+    positions: &[Vec3],
+    g: f32,
+    softening: SofteningKernel,
+) -> f32 {
+    pairwise_potential_energy(masses, Some(radii), positions, g, softening)
+}
+
+fn pairwise_potential_energy(
+    masses: &[f32],
+    radii: Option<&[f32]>,
+    positions: &[Vec3],
+    g: f32,
+    softening: SofteningKernel,
+) -> f32 {
+    let pair_radius = |i: usize, j: usize| radii.map_or(0.0, |radii| radii[i].max(radii[j]));
+    let mut energy = 0.0;
+    for i in 0..positions.len() {
+        for j in (i + 1)..positions.len() {
+            let dist2 = positions[i].distance_squared(positions[j]);
+            if dist2 > 0.0 {
+                energy += g
+                    * masses[i]
+                    * masses[j]
+                    * softening.sphere_potential(dist2, pair_radius(i, j));
+            }
+        }
+    }
+    energy
+}
+
+pub fn total_momentum(masses: &[f32], velocities: &[Vec3]) -> Vec3 {
+    masses
+        .iter()
+        .zip(velocities)
+        .fold(Vec3::ZERO, |momentum, (m, vel)| momentum + *m * *vel)
+}
+
+/// Inertia tensor of the bodies about `center`, e.g. their center of mass:
+/// ```text
+/// I = Σ m_i * (|r_i|^2 * Id - r_i ⊗ r_i)
+/// ```
+/// where `r_i` is the position of the body relative to `center`.
+pub fn inertia_tensor(masses: &[f32], positions: &[Vec3], center: Vec3) -> Mat3 {
+    masses
+        .iter()
+        .zip(positions)
+        .fold(Mat3::ZERO, |inertia, (m, pos)| {
+            let r = *pos - center;
+            let outer = Mat3::from_cols(r * r.x, r * r.y, r * r.z);
+            inertia + (Mat3::IDENTITY * r.length_squared() - outer) * *m
+        })
+}
+
+/// Principal moments of inertia and their axes, by increasing moment,
+/// from the eigen-decomposition of the symmetric `inertia` tensor with Jacobi rotations
+pub fn principal_axes(inertia: Mat3) -> [(f32, Vec3); 3] {
+    let mut a: [[f32; 3]; 3] = [
+        inertia.x_axis.into(),
+        inertia.y_axis.into(),
+        inertia.z_axis.into(),
+    ];
+    // Rotation diagonalizing the tensor, its columns being the axes
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    for _ in 0..50 {
+        let off_diagonal = a[0][1].abs() + a[0][2].abs() + a[1][2].abs();
+        if off_diagonal <= f32::EPSILON * (a[0][0].abs() + a[1][1].abs() + a[2][2].abs()) {
+            break;
+        }
+        for (p, q) in [(0, 1), (0, 2), (1, 2)].iter().copied() {
+            if a[p][q] == 0.0 {
+                continue;
+            }
+            // Rotation in the (p, q) plane zeroing a[p][q]
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+            for row in a.iter_mut().chain(v.iter_mut()) {
+                let (rp, rq) = (row[p], row[q]);
+                row[p] = c * rp - s * rq;
+                row[q] = s * rp + c * rq;
+            }
+            let (row_p, row_q) = (a[p], a[q]);
+            for (k, (apk, aqk)) in row_p.iter().zip(row_q.iter()).enumerate() {
+                a[p][k] = c * apk - s * aqk;
+                a[q][k] = s * apk + c * aqk;
+            }
+        }
+    }
+    let axis = |i: usize| {
+        (
+            a[i][i],
+            Vec3::new(v[0][i], v[1][i], v[2][i]).normalize_or_zero(),
+        )
+    };
+    let mut axes = [axis(0), axis(1), axis(2)];
+    axes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    axes
+}
+
+/// ```text
+/// Q = 2K / |U|
+/// ```
+pub fn virial_ratio(kinetic: f32, potential: f32) -> f32 {
+    if potential == 0.0 {
+        return 0.0;
+    }
+    2.0 * kinetic / potential.abs()
+}
+
+/// Non-physical stabilizer periodically rescaling the velocities to restore a total energy,
+/// for demos where the long-term look of the orbits matters more than the dynamics.
+/// The rescaling is not a physical process: it hides the integration errors instead of reducing them,
+/// and the trajectories are no longer solutions of the equations of motion.
+pub struct EnergyCorrection {
+    /// Total energy to restore, the one of the first step when `None`
+    pub target_energy: Option<f32>,
+    /// Number of physics steps between two corrections
+    pub interval: u64,
+    pub enabled: bool,
+}
+
+impl Default for EnergyCorrection {
+    fn default() -> Self {
+        Self {
+            target_energy: None,
+            interval: 100,
+            enabled: false,
+        }
+    }
+}
+
+/// Scales the velocities relative to the center of mass so that `K + potential = target`,
+/// leaving the total momentum unchanged.
+/// Returns `false`, without touching the velocities, when the target cannot be reached,
+/// e.g. when it is below the potential energy or when the bodies are at rest relative to each other.
+pub fn correct_energy(
+    masses: &[f32],
+    velocities: &mut [Vec3],
+    potential: f32,
+    target: f32,
+) -> bool {
+    let total_mass: f32 = masses.iter().sum();
+    if total_mass <= 0.0 {
+        return false;
+    }
+    let bulk_velocity = total_momentum(masses, velocities) / total_mass;
+    let bulk = 0.5 * total_mass * bulk_velocity.length_squared();
+    let internal = kinetic_energy(masses, velocities) - bulk;
+    let wanted = target - potential - bulk;
+    if internal <= 0.0 || wanted < 0.0 {
+        return false;
+    }
+    let factor = (wanted / internal).sqrt();
+    for vel in velocities.iter_mut() {
+        *vel = bulk_velocity + factor * (*vel - bulk_velocity);
+    }
+    true
+}
+
+/// Diagnostics at a given step, as written by the `DiagnosticsRecorder`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticsSample {
+    pub step: u64,
+    pub time: f32,
+    pub kinetic: f32,
+    pub potential: f32,
+    pub total: f32,
+    pub momentum: f32,
+    pub virial: f32,
+}
+
+impl DiagnosticsSample {
+    pub fn new(diagnostics: &PhysicsDiagnostics, time: f32) -> Self {
+        Self {
+            step: diagnostics.step,
+            time,
+            kinetic: diagnostics.kinetic,
+            potential: diagnostics.potential,
+            total: diagnostics.total_energy(),
+            momentum: diagnostics.momentum.length(),
+            virial: diagnostics.virial,
+        }
+    }
+}
+
+/// Most recent diagnostics, sampled every `interval` physics steps, e.g. for live plots
+pub struct DiagnosticsHistory {
+    pub interval: u64,
+    capacity: usize,
+    samples: VecDeque<DiagnosticsSample>,
+}
+
+impl Default for DiagnosticsHistory {
+    fn default() -> Self {
+        Self::new(10, 1000)
+    }
+}
+
+impl DiagnosticsHistory {
+    pub fn new(interval: u64, capacity: usize) -> Self {
+        Self {
+            interval,
+            capacity,
+            samples: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Samples, the oldest first
+    pub fn samples(&self) -> &VecDeque<DiagnosticsSample> {
+        &self.samples
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes the number of samples kept, dropping the oldest ones if needed
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        let excess = self.samples.len().saturating_sub(capacity);
+        self.samples.drain(..excess);
+    }
+
+    pub fn push(&mut self, sample: DiagnosticsSample) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+}
+
+/// Records the diagnostics every `interval` physics steps,
+/// saved to `path` on exit or on demand, in RON or JSON depending on its extension
+pub struct DiagnosticsRecorder {
+    pub interval: u64,
+    pub path: PathBuf,
+    pub samples: Vec<DiagnosticsSample>,
+}
+
+impl DiagnosticsRecorder {
+    pub fn new(interval: u64, path: impl Into<PathBuf>) -> Self {
+        Self {
+            interval,
+            path: path.into(),
+            samples: Vec::new(),
+        }
+    }
+
+    /// Writes the samples to `path`, as JSON for a `.json` file and RON otherwise
+    pub fn save(&self) -> std::io::Result<()> {
+        let to_io_error = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+        let content = match self.path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::to_string_pretty(&self.samples)
+                .map_err(|e| to_io_error(e.to_string()))?,
+            _ => ron::ser::to_string_pretty(&self.samples, Default::default())
+                .map_err(|e| to_io_error(e.to_string()))?,
+        };
+        std::fs::write(&self.path, content)
+    }
+}
+
+fn update_diagnostics(
+    g: Res<Gravity>,
+    softening: Res<SofteningKernel>,
+    extended: Res<ExtendedBody>,
+    mut diagnostics: ResMut<PhysicsDiagnostics>,
+    query: Query<(&Mass, &Transform, &Velocity, Option<&Radius>)>,
+) {
+    let mut masses = Vec::new();
+    let mut positions = Vec::new();
+    let mut velocities = Vec::new();
+    let mut radii = Vec::new();
+    for (mass, transform, vel, radius) in query.iter() {
+        masses.push(mass.0);
+        positions.push(transform.translation);
+        velocities.push(vel.0);
+        radii.push(radius.map_or(0.0, |radius| radius.0));
+    }
+
+    let kinetic = kinetic_energy(&masses, &velocities);
+    // Matching the force law of the physics systems
+    let potential = if extended.0 {
+        extended_potential_energy(&masses, &radii, &positions, g.0, *softening)
+    } else {
+        potential_energy(&masses, &positions, g.0, *softening)
+    };
+    *diagnostics = PhysicsDiagnostics {
+        step: diagnostics.step + 1,
+        kinetic,
+        potential,
+        momentum: total_momentum(&masses, &velocities),
+        virial: virial_ratio(kinetic, potential),
+    };
+}
+
+fn record_diagnostics(
+    diagnostics: Res<PhysicsDiagnostics>,
+    time: Res<SimulationTime>,
+    recorder: Option<ResMut<DiagnosticsRecorder>>,
+) {
+    if let Some(mut recorder) = recorder {
+        if diagnostics.step.is_multiple_of(recorder.interval.max(1)) {
+            recorder
+                .samples
+                .push(DiagnosticsSample::new(&diagnostics, time.0));
+        }
+    }
+}
+
+fn update_history(
+    diagnostics: Res<PhysicsDiagnostics>,
+    time: Res<SimulationTime>,
+    mut history: ResMut<DiagnosticsHistory>,
+) {
+    if diagnostics.step.is_multiple_of(history.interval.max(1)) {
+        history.push(DiagnosticsSample::new(&diagnostics, time.0));
+    }
+}
+
+fn correct_energy_drift(
+    diagnostics: Res<PhysicsDiagnostics>,
+    correction: Option<ResMut<EnergyCorrection>>,
+    mut query: Query<(&Mass, &mut Velocity)>,
+) {
+    let mut correction = match correction {
+        Some(correction) if correction.enabled => correction,
+        _ => return,
+    };
+    let target = match correction.target_energy {
+        Some(target) => target,
+        None => {
+            correction.target_energy = Some(diagnostics.total_energy());
+            return;
+        }
+    };
+    if !diagnostics.step.is_multiple_of(correction.interval.max(1)) {
+        return;
+    }
+
+    let masses: Vec<f32> = query.iter_mut().map(|(mass, _)| mass.0).collect();
+    let mut velocities: Vec<Vec3> = query.iter_mut().map(|(_, vel)| vel.0).collect();
+    if correct_energy(&masses, &mut velocities, diagnostics.potential, target) {
+        for ((_, mut vel), new_vel) in query.iter_mut().zip(velocities) {
+            vel.0 = new_vel;
+        }
+    }
+}
+
+fn save(recorder: &DiagnosticsRecorder) {
+    match recorder.save() {
+        Ok(()) => info!(
+            "{} diagnostics samples saved to {}",
+            recorder.samples.len(),
+            recorder.path.display()
+        ),
+        Err(e) => error!("Cannot save the diagnostics: {}", e),
+    }
+}
+
+fn save_diagnostics_on_demand(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    recorder: Option<Res<DiagnosticsRecorder>>,
+) {
+    if let (true, Some(recorder)) = (
+        bindings.just_pressed(&keys, Action::SaveDiagnostics),
+        recorder,
+    ) {
+        save(&recorder);
+    }
+}
+
+fn save_diagnostics_on_exit(
+    mut exit: EventReader<AppExit>,
+    recorder: Option<Res<DiagnosticsRecorder>>,
+) {
+    if let (Some(_), Some(recorder)) = (exit.iter().next(), recorder) {
+        save(&recorder);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::run_system;
+
+    /// Samples recorded every other step over 6 steps
+    fn recorded_samples() -> Vec<DiagnosticsSample> {
+        let mut world = World::default();
+        world.insert_resource(DiagnosticsRecorder::new(2, "unused"));
+        for step in 1..=6 {
+            world.insert_resource(SimulationTime(0.1 * step as f32));
+            world.insert_resource(PhysicsDiagnostics {
+                step,
+                kinetic: step as f32,
+                potential: -2.0 * step as f32,
+                momentum: Vec3::new(3.0, 4.0, 0.0),
+                virial: 1.0,
+            });
+            run_system(&mut world, record_diagnostics.system());
+        }
+        world
+            .get_resource::<DiagnosticsRecorder>()
+            .unwrap()
+            .samples
+            .clone()
+    }
+
+    #[test]
+    fn recorded_diagnostics_round_trip() {
+        let samples = recorded_samples();
+        assert_eq!(
+            samples.iter().map(|sample| sample.step).collect::<Vec<_>>(),
+            vec![2, 4, 6]
+        );
+        assert_eq!(samples[0].total, -2.0);
+        assert_eq!(samples[0].momentum, 5.0);
+
+        for &extension in ["json", "ron"].iter() {
+            let path = std::env::temp_dir().join(format!("nbody_diagnostics.{}", extension));
+            let recorder = DiagnosticsRecorder {
+                samples: samples.clone(),
+                ..DiagnosticsRecorder::new(2, &path)
+            };
+            recorder.save().unwrap();
+            let content = std::fs::read_to_string(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            let parsed: Vec<DiagnosticsSample> = match extension {
+                "json" => serde_json::from_str(&content).unwrap(),
+                _ => ron::from_str(&content).unwrap(),
+            };
+            assert_eq!(parsed, samples);
+        }
+    }
+
+    fn sample(step: u64) -> DiagnosticsSample {
+        DiagnosticsSample::new(
+            &PhysicsDiagnostics {
+                step,
+                ..Default::default()
+            },
+            step as f32,
+        )
+    }
+
+    fn steps(history: &DiagnosticsHistory) -> Vec<u64> {
+        history.samples().iter().map(|sample| sample.step).collect()
+    }
+
+    #[test]
+    fn history_keeps_the_most_recent_samples() {
+        let mut history = DiagnosticsHistory::new(1, 3);
+        for step in 1..=5 {
+            history.push(sample(step));
+        }
+        assert_eq!(steps(&history), vec![3, 4, 5]);
+
+        history.set_capacity(2);
+        assert_eq!(steps(&history), vec![4, 5]);
+        history.push(sample(6));
+        assert_eq!(steps(&history), vec![5, 6]);
+
+        history.set_capacity(0);
+        history.push(sample(7));
+        assert!(history.samples().is_empty());
+    }
+
+    #[test]
+    fn corrected_energy_matches_the_target() {
+        let masses = [1.0, 2.0, 0.5];
+        let positions = [
+            Vec3::ZERO,
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+        ];
+        let mut velocities = [
+            Vec3::new(0.0, 0.3, 0.0),
+            Vec3::new(0.1, -0.2, 0.0),
+            Vec3::new(-0.4, 0.0, 0.1),
+        ];
+        let potential = potential_energy(&masses, &positions, 1.0, SofteningKernel::None);
+        let momentum = total_momentum(&masses, &velocities);
+        let target = potential + 0.5;
+
+        assert!(correct_energy(&masses, &mut velocities, potential, target));
+        let total = kinetic_energy(&masses, &velocities) + potential;
+        assert!((total - target).abs() < 1e-5, "{} != {}", total, target);
+        assert!((total_momentum(&masses, &velocities) - momentum).length() < 1e-5);
+
+        // Below the energy of the bulk motion
+        let before = velocities;
+        assert!(!correct_energy(
+            &masses,
+            &mut velocities,
+            potential,
+            potential - 1.0
+        ));
+        assert_eq!(velocities, before);
+    }
+
+    #[test]
+    fn extended_bodies_conserve_the_matching_energy() {
+        use crate::plugins::nbody::compute_extended_accelerations;
+
+        // Two spheres of radius 1 released at rest, falling through each other back and forth
+        let masses = [1.0, 2.0];
+        let radii = [1.0, 1.0];
+        let mut positions = [Vec3::new(-1.5, 0.0, 0.0), Vec3::new(1.0, 0.2, 0.0)];
+        let mut velocities = [Vec3::ZERO; 2];
+        let softening = SofteningKernel::None;
+        let energy = |positions: &[Vec3], velocities: &[Vec3]| {
+            kinetic_energy(&masses, velocities)
+                + extended_potential_energy(&masses, &radii, positions, 1.0, softening)
+        };
+        let accelerations = |positions: &[Vec3]| {
+            compute_extended_accelerations(&masses, &radii, positions, 1.0, softening, None)
+        };
+
+        let initial = energy(&positions, &velocities);
+        let dt = 1e-3;
+        let mut max_error: f32 = 0.0;
+        let mut min_distance = f32::INFINITY;
+        for _ in 0..5000 {
+            // Leapfrog
+            for (vel, acc) in velocities.iter_mut().zip(accelerations(&positions)) {
+                *vel += acc * dt / 2.0;
+            }
+            for (pos, vel) in positions.iter_mut().zip(&velocities) {
+                *pos += *vel * dt;
+            }
+            for (vel, acc) in velocities.iter_mut().zip(accelerations(&positions)) {
+                *vel += acc * dt / 2.0;
+            }
+            min_distance = min_distance.min(positions[0].distance(positions[1]));
+            max_error = max_error.max((energy(&positions, &velocities) - initial).abs());
+        }
+
+        // The spheres did overlap, where the point-mass potential no longer matches the force
+        assert!(min_distance < 0.5, "{}", min_distance);
+        assert!(
+            max_error < 1e-3 * initial.abs(),
+            "energy error {}",
+            max_error
+        );
+    }
+
+    #[test]
+    fn inertia_tensor_of_masses_on_the_axes() {
+        // Mass 2 at distance 3 on ±x and ±y, around a center at (1, 1, 1)
+        let center = Vec3::ONE;
+        let positions: Vec<Vec3> = [Vec3::X, -Vec3::X, Vec3::Y, -Vec3::Y]
+            .iter()
+            .map(|axis| center + 3.0 * *axis)
+            .collect();
+        let inertia = inertia_tensor(&[2.0; 4], &positions, center);
+        // The pair on the other in-plane axis contributes 2md², both pairs around z
+        assert_eq!(inertia, Mat3::from_diagonal(Vec3::new(36.0, 36.0, 72.0)));
+
+        // Same distribution of mass on every axis: spherical top
+        let positions = [Vec3::X, -Vec3::X, Vec3::Y, -Vec3::Y, Vec3::Z, -Vec3::Z];
+        let inertia = inertia_tensor(&[1.0; 6], &positions, Vec3::ZERO);
+        assert_eq!(inertia, Mat3::IDENTITY * 4.0);
+    }
+
+    #[test]
+    fn principal_axes_of_a_tilted_rod() {
+        // Masses 1 at ±2 along the diagonal of the xy plane, and a lighter pair along z
+        let axis = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let positions = [2.0 * axis, -2.0 * axis, 0.5 * Vec3::Z, -0.5 * Vec3::Z];
+        let inertia = inertia_tensor(&[1.0, 1.0, 4.0, 4.0], &positions, Vec3::ZERO);
+        let axes = principal_axes(inertia);
+
+        let moments: Vec<f32> = axes.iter().map(|(moment, _)| *moment).collect();
+        let expected = [2.0, 8.0, 10.0];
+        for (moment, expected) in moments.iter().zip(expected.iter()) {
+            assert!((moment - expected).abs() < 1e-4, "{:?}", moments);
+        }
+        // Smallest moment along the rod, only the z pair being off its axis, the intermediate one along z
+        assert!(axes[0].1.dot(axis).abs() > 1.0 - 1e-5);
+        assert!(axes[1].1.dot(Vec3::Z).abs() > 1.0 - 1e-5);
+        assert!(axes[2].1.dot(Vec3::new(1.0, -1.0, 0.0).normalize()).abs() > 1.0 - 1e-5);
+    }
+}
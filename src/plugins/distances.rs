@@ -0,0 +1,481 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::hud::{hud_text, set_hud_text, HudFont};
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{BodyName, Mass, Velocity};
+use super::selection::Selected;
+
+/// Distances from the selected body to all the others, nearest first, exposed in `DistanceList`,
+/// shown in the top right corner and logged every second while enabled, the nearest neighbor of every body in `NearestNeighbors`
+/// and their `LocalDensity`:
+/// * N: toggle the distance list
+pub struct DistanceListPlugin;
+
+impl Plugin for DistanceListPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::ToggleDistanceList,
+            "toggle the list of the distances from the selected body to the others",
+        )
+        .init_resource::<DistanceList>()
+        .init_resource::<HudFont>()
+        .init_resource::<NearestNeighbors>()
+        .init_resource::<DensityEstimate>()
+        .insert_resource(DistanceLogTimer(Timer::from_seconds(1.0, true)))
+        .add_startup_system(spawn_distance_list_text.system())
+        .add_system(toggle_distance_list.system())
+        .add_system(update_distance_list.system().label(DistanceListSystem))
+        .add_system(log_distance_list.system().after(DistanceListSystem))
+        .add_system(show_distance_list.system().after(DistanceListSystem))
+        .add_system(update_nearest_neighbors.system())
+        .add_system(update_local_density.system());
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct DistanceListSystem;
+
+/// Distance and relative speed of a body from the selected one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistanceEntry {
+    pub body: Entity,
+    pub distance: f32,
+    pub relative_speed: f32,
+}
+
+pub struct DistanceList {
+    pub enabled: bool,
+    /// Number of entries shown and logged, all of them are kept in `entries`
+    pub max_logged: usize,
+    /// Empty without selection
+    pub entries: Vec<DistanceEntry>,
+}
+
+impl Default for DistanceList {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_logged: 10,
+            entries: Vec::new(),
+        }
+    }
+}
+
+struct DistanceLogTimer(Timer);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NearestNeighbor {
+    pub body: Entity,
+    pub distance: f32,
+}
+
+/// Nearest other body of each body, updated every frame
+#[derive(Debug, Clone, Default)]
+pub struct NearestNeighbors {
+    pub neighbors: HashMap<Entity, NearestNeighbor>,
+}
+
+impl NearestNeighbors {
+    /// `None` for a lone or despawned body
+    pub fn get(&self, body: Entity) -> Option<NearestNeighbor> {
+        self.neighbors.get(&body).copied()
+    }
+}
+
+/// Number of bodies per unit volume around a body, see `local_density`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LocalDensity(pub f32);
+
+/// Neighbors the `LocalDensity` is estimated over: few of them follow the small clumps
+/// but are noisy, many of them smooth out the density
+pub struct DensityEstimate {
+    pub k: usize,
+}
+
+impl Default for DensityEstimate {
+    fn default() -> Self {
+        Self { k: 8 }
+    }
+}
+
+/// Density of `k` bodies within the sphere reaching the `k`-th nearest neighbor of a body:
+/// ```text
+/// rho = k / (4/3 * π * r_k^3)
+/// ```
+pub fn local_density(k: usize, kth_distance: f32) -> f32 {
+    let volume = 4.0 / 3.0 * std::f32::consts::PI * kth_distance.powi(3);
+    if volume <= 0.0 {
+        return f32::INFINITY;
+    }
+    k as f32 / volume
+}
+
+/// Distance from each body to its `k`-th nearest neighbor, `None` with less than `k` other bodies
+pub fn kth_neighbor_distances(positions: &[Vec3], k: usize) -> Vec<Option<f32>> {
+    if k == 0 || positions.len() <= k {
+        return vec![None; positions.len()];
+    }
+    let mut distances = Vec::with_capacity(positions.len() - 1);
+    positions
+        .iter()
+        .enumerate()
+        .map(|(i, pos)| {
+            distances.clear();
+            distances.extend(
+                positions
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, other)| pos.distance(*other)),
+            );
+            let (_, kth, _) = distances.select_nth_unstable_by(k - 1, |a, b| {
+                a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+            });
+            Some(*kth)
+        })
+        .collect()
+}
+
+/// Distance and relative speed of each of the `(entity, position, velocity)` bodies
+/// from a body at `pos` moving at `vel`, sorted by increasing distance
+pub fn distance_list(pos: Vec3, vel: Vec3, others: &[(Entity, Vec3, Vec3)]) -> Vec<DistanceEntry> {
+    let mut entries: Vec<DistanceEntry> = others
+        .iter()
+        .map(|(body, other_pos, other_vel)| DistanceEntry {
+            body: *body,
+            distance: pos.distance(*other_pos),
+            relative_speed: vel.distance(*other_vel),
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        a.distance
+            .partial_cmp(&b.distance)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    entries
+}
+
+/// Nearest neighbor of each of the `(entity, position)` bodies, none for a lone body.
+/// The bodies are swept along the x-axis, each one only looking at those closer in x than its nearest
+/// neighbor so far, which is much faster than comparing all the pairs unless they are lined up along x.
+pub fn nearest_neighbors(bodies: &[(Entity, Vec3)]) -> HashMap<Entity, NearestNeighbor> {
+    let mut sorted: Vec<&(Entity, Vec3)> = bodies.iter().collect();
+    sorted.sort_by(|a, b| {
+        a.1.x
+            .partial_cmp(&b.1.x)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let mut neighbors = HashMap::default();
+    for (i, (body, pos)) in sorted.iter().enumerate() {
+        let mut nearest: Option<NearestNeighbor> = None;
+        // Whether the bodies further along x may still be nearer
+        let mut visit = |(other, other_pos): &(Entity, Vec3)| {
+            if matches!(nearest, Some(nearest) if (other_pos.x - pos.x).abs() >= nearest.distance) {
+                return false;
+            }
+            let distance = pos.distance(*other_pos);
+            if !matches!(nearest, Some(nearest) if distance >= nearest.distance) {
+                nearest = Some(NearestNeighbor {
+                    body: *other,
+                    distance,
+                });
+            }
+            true
+        };
+        for other in sorted[i + 1..].iter() {
+            if !visit(other) {
+                break;
+            }
+        }
+        for other in sorted[..i].iter().rev() {
+            if !visit(other) {
+                break;
+            }
+        }
+        if let Some(nearest) = nearest {
+            neighbors.insert(*body, nearest);
+        }
+    }
+    neighbors
+}
+
+fn toggle_distance_list(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut list: ResMut<DistanceList>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleDistanceList) {
+        list.enabled = !list.enabled;
+    }
+}
+
+/// Recomputed from the live bodies every frame, so that despawned bodies drop out of the list
+fn update_distance_list(
+    mut list: ResMut<DistanceList>,
+    bodies: Query<(Entity, &Transform, &Velocity, Option<&Selected>), With<Mass>>,
+) {
+    if !list.enabled {
+        list.entries.clear();
+        return;
+    }
+    let mut selected = None;
+    let mut others = Vec::new();
+    for (entity, transform, vel, is_selected) in bodies.iter() {
+        let body = (entity, transform.translation, vel.0);
+        match is_selected {
+            Some(_) if selected.is_none() => selected = Some(body),
+            _ => others.push(body),
+        }
+    }
+    list.entries = match selected {
+        Some((_, pos, vel)) => distance_list(pos, vel, &others),
+        None => Vec::new(),
+    };
+}
+
+/// Rebuilt from the live bodies, so that despawned bodies drop out of the map
+fn update_nearest_neighbors(
+    mut nearest: ResMut<NearestNeighbors>,
+    bodies: Query<(Entity, &Transform), With<Mass>>,
+) {
+    let bodies: Vec<(Entity, Vec3)> = bodies
+        .iter()
+        .map(|(entity, transform)| (entity, transform.translation))
+        .collect();
+    nearest.neighbors = nearest_neighbors(&bodies);
+}
+
+/// The bodies with less than `k` others don't get a density
+fn update_local_density(
+    mut commands: Commands,
+    estimate: Res<DensityEstimate>,
+    mut bodies: Query<(Entity, &Transform, Option<&mut LocalDensity>), With<Mass>>,
+) {
+    let positions: Vec<Vec3> = bodies
+        .iter_mut()
+        .map(|(_, transform, _)| transform.translation)
+        .collect();
+    let distances = kth_neighbor_distances(&positions, estimate.k);
+    for ((entity, _, density), distance) in bodies.iter_mut().zip(distances) {
+        match (density, distance) {
+            (Some(mut density), Some(distance)) => density.0 = local_density(estimate.k, distance),
+            (None, Some(distance)) => {
+                commands
+                    .entity(entity)
+                    .insert(LocalDensity(local_density(estimate.k, distance)));
+            }
+            (Some(_), None) => {
+                commands.entity(entity).remove::<LocalDensity>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+/// Lines of the `max_logged` nearest bodies, named after their `BodyName` if any
+fn entry_lines(list: &DistanceList, names: &Query<&BodyName>) -> Vec<String> {
+    list.entries
+        .iter()
+        .take(list.max_logged)
+        .map(|entry| {
+            let name = match names.get(entry.body) {
+                Ok(name) => name.0.clone(),
+                Err(_) => format!("{:?}", entry.body),
+            };
+            format!(
+                "  {}: {:.4} (relative speed {:.4})",
+                name, entry.distance, entry.relative_speed
+            )
+        })
+        .collect()
+}
+
+fn log_distance_list(
+    time: Res<Time>,
+    list: Res<DistanceList>,
+    mut timer: ResMut<DistanceLogTimer>,
+    names: Query<&BodyName>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() || list.entries.is_empty() {
+        return;
+    }
+    info!("Nearest bodies:\n{}", entry_lines(&list, &names).join("\n"));
+}
+
+/// Text of the distance list
+struct DistanceListText;
+
+fn spawn_distance_list_text(mut commands: Commands, font: Res<HudFont>) {
+    let position = Rect {
+        right: Val::Px(10.0),
+        top: Val::Px(10.0),
+        ..Default::default()
+    };
+    commands
+        .spawn_bundle(hud_text(&font, position))
+        .insert(DistanceListText);
+}
+
+/// Empty while disabled or without selection
+fn show_distance_list(
+    list: Res<DistanceList>,
+    names: Query<&BodyName>,
+    mut texts: Query<&mut Text, With<DistanceListText>>,
+) {
+    let value = if list.entries.is_empty() {
+        String::new()
+    } else {
+        format!("Nearest bodies:\n{}", entry_lines(&list, &names).join("\n"))
+    };
+    for mut text in texts.iter_mut() {
+        set_hud_text(&mut text, value.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::nbody::BodyBundle;
+    use crate::testing::run_system;
+
+    #[test]
+    fn distances_are_sorted_nearest_first() {
+        let (a, b, c) = (Entity::new(0), Entity::new(1), Entity::new(2));
+        let others = [
+            (a, Vec3::new(0.0, 5.0, 0.0), Vec3::ZERO),
+            (b, Vec3::new(-3.0, 0.0, 0.0), Vec3::X),
+            (c, Vec3::new(0.0, 0.0, 4.0), Vec3::new(1.0, 1.0, 0.0)),
+        ];
+        let entries = distance_list(Vec3::ZERO, Vec3::X, &others);
+        let order: Vec<(Entity, f32, f32)> = entries
+            .iter()
+            .map(|entry| (entry.body, entry.distance, entry.relative_speed))
+            .collect();
+        assert_eq!(order, vec![(b, 3.0, 0.0), (c, 4.0, 1.0), (a, 5.0, 1.0)]);
+    }
+
+    #[test]
+    fn list_of_the_selected_body_is_shown() {
+        let mut world = World::default();
+        world.insert_resource(DistanceList {
+            enabled: true,
+            max_logged: 2,
+            entries: Vec::new(),
+        });
+        world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::ZERO, Vec3::ZERO))
+            .insert(Selected);
+        for (name, x) in [("far", 10.0), ("near", 1.0), ("middle", 2.0)].iter() {
+            world
+                .spawn()
+                .insert_bundle(BodyBundle::new(1.0, Vec3::new(*x, 0.0, 0.0), Vec3::Y))
+                .insert(BodyName(name.to_string()));
+        }
+        let text = world
+            .spawn()
+            .insert_bundle((
+                Text::with_section("", Default::default(), Default::default()),
+                DistanceListText,
+            ))
+            .id();
+
+        run_system(&mut world, update_distance_list.system());
+        run_system(&mut world, show_distance_list.system());
+        assert_eq!(
+            world.get_resource::<DistanceList>().unwrap().entries.len(),
+            3
+        );
+        assert_eq!(
+            world.get::<Text>(text).unwrap().sections[0].value,
+            "Nearest bodies:\n  near: 1.0000 (relative speed 1.0000)\n  middle: 2.0000 (relative speed 1.0000)"
+        );
+
+        // Nothing to show once disabled
+        world.get_resource_mut::<DistanceList>().unwrap().enabled = false;
+        run_system(&mut world, update_distance_list.system());
+        run_system(&mut world, show_distance_list.system());
+        assert_eq!(world.get::<Text>(text).unwrap().sections[0].value, "");
+    }
+
+    #[test]
+    fn nearest_neighbors_of_three_bodies() {
+        let mut world = World::default();
+        world.insert_resource(NearestNeighbors::default());
+        let mut spawn = |x: f32, y: f32| {
+            world
+                .spawn()
+                .insert_bundle(BodyBundle::new(1.0, Vec3::new(x, y, 0.0), Vec3::ZERO))
+                .id()
+        };
+        // The x sweep must not stop at `c`, nearer in x to `a` than `b` is
+        let (a, b, c) = (spawn(0.0, 0.0), spawn(1.0, 0.0), spawn(0.4, 3.0));
+
+        run_system(&mut world, update_nearest_neighbors.system());
+        let nearest = world.get_resource::<NearestNeighbors>().unwrap();
+        let neighbor = |body| nearest.get(body).map(|n| (n.body, n.distance));
+        assert_eq!(neighbor(a), Some((b, 1.0)));
+        assert_eq!(neighbor(b), Some((a, 1.0)));
+        assert_eq!(neighbor(c).unwrap().0, a);
+        assert!((neighbor(c).unwrap().1 - 9.16f32.sqrt()).abs() < 1e-6);
+
+        // Despawned bodies drop out, a lone body having no neighbor
+        world.despawn(b);
+        world.despawn(c);
+        run_system(&mut world, update_nearest_neighbors.system());
+        let nearest = world.get_resource::<NearestNeighbors>().unwrap();
+        assert!(nearest.neighbors.is_empty());
+        assert_eq!(nearest.get(a), None);
+    }
+
+    #[test]
+    fn density_within_the_kth_neighbor() {
+        // 3 bodies in a sphere of volume 4π
+        let rho = local_density(3, 3.0f32.cbrt());
+        assert!((rho - 3.0 / (4.0 * std::f32::consts::PI)).abs() < 1e-6);
+        assert_eq!(local_density(2, 0.0), f32::INFINITY);
+
+        let positions = [
+            Vec3::ZERO,
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(0.0, 0.0, -4.0),
+        ];
+        assert_eq!(
+            kth_neighbor_distances(&positions, 2),
+            vec![
+                Some(2.0),
+                Some(5.0f32.sqrt()),
+                Some(5.0f32.sqrt()),
+                Some(17.0f32.sqrt())
+            ]
+        );
+        assert_eq!(kth_neighbor_distances(&positions, 4), vec![None; 4]);
+    }
+
+    #[test]
+    fn bodies_get_a_density_once_they_have_k_neighbors() {
+        let mut world = World::default();
+        world.insert_resource(DensityEstimate { k: 1 });
+        let a = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::ZERO, Vec3::ZERO))
+            .id();
+        run_system(&mut world, update_local_density.system());
+        assert!(world.get::<LocalDensity>(a).is_none());
+
+        let b = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::new(2.0, 0.0, 0.0), Vec3::ZERO))
+            .id();
+        run_system(&mut world, update_local_density.system());
+        let expected = LocalDensity(local_density(1, 2.0));
+        assert_eq!(world.get::<LocalDensity>(a), Some(&expected));
+        assert_eq!(world.get::<LocalDensity>(b), Some(&expected));
+
+        world.despawn(b);
+        run_system(&mut world, update_local_density.system());
+        assert!(world.get::<LocalDensity>(a).is_none());
+    }
+}
@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::nbody::{Mass, PhysicsStep, PhysicsSystem, SimulationTime, TimeStep, Velocity};
+
+/// Minimum separation of each pair of bodies coming closer than a threshold distance,
+/// e.g. to quantify how chaotic a configuration is
+pub struct CloseEncounterPlugin;
+
+impl Plugin for CloseEncounterPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CloseEncounterStats>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(PhysicsStep)
+                    .with_system(record_encounters.system().after(PhysicsSystem::Movement)),
+            )
+            .add_system(finalize_encounters.system());
+    }
+}
+
+/// Closest approach of a pair of bodies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncounterRecord {
+    pub bodies: (Entity, Entity),
+    pub min_distance: f32,
+    /// Simulated time of the closest approach
+    pub time: f32,
+}
+
+#[derive(Default)]
+pub struct CloseEncounterStats {
+    /// Distance under which two bodies are in a close encounter, disabled when `None`
+    pub threshold: Option<f32>,
+    /// Encounters of the pairs of bodies still in the simulation, updated as they come closer
+    active: HashMap<(Entity, Entity), EncounterRecord>,
+    /// Encounters of the pairs of which a body has been despawned
+    pub finalized: Vec<EncounterRecord>,
+}
+
+impl CloseEncounterStats {
+    pub fn new(threshold: f32) -> Self {
+        Self {
+            threshold: Some(threshold),
+            ..Default::default()
+        }
+    }
+
+    /// Every encounter recorded so far, finalized or not
+    pub fn records(&self) -> impl Iterator<Item = &EncounterRecord> {
+        self.finalized.iter().chain(self.active.values())
+    }
+
+    /// Encounter of the pair `a` and `b` if they are still in the simulation
+    pub fn active(&self, a: Entity, b: Entity) -> Option<&EncounterRecord> {
+        self.active.get(&pair(a, b))
+    }
+
+    /// Records that `a` and `b` are `distance` apart at `time`,
+    /// ignored above the threshold or when they already came closer
+    pub fn record(&mut self, a: Entity, b: Entity, distance: f32, time: f32) {
+        match self.threshold {
+            Some(threshold) if distance < threshold => {}
+            _ => return,
+        }
+        let bodies = pair(a, b);
+        let record = self.active.entry(bodies).or_insert(EncounterRecord {
+            bodies,
+            min_distance: distance,
+            time,
+        });
+        if distance < record.min_distance {
+            record.min_distance = distance;
+            record.time = time;
+        }
+    }
+
+    /// Moves the encounters of the despawned `body` to the finalized ones
+    pub fn finalize(&mut self, body: Entity) {
+        let pairs: Vec<(Entity, Entity)> = self
+            .active
+            .keys()
+            .filter(|(a, b)| *a == body || *b == body)
+            .copied()
+            .collect();
+        for bodies in pairs {
+            if let Some(record) = self.active.remove(&bodies) {
+                info!(
+                    "Close encounter of {:?} and {:?}: {} at t = {}",
+                    record.bodies.0, record.bodies.1, record.min_distance, record.time
+                );
+                self.finalized.push(record);
+            }
+        }
+    }
+}
+
+fn pair(a: Entity, b: Entity) -> (Entity, Entity) {
+    if a.id() <= b.id() {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Closest distance between two bodies over the last step of `dt`, assuming straight-line relative motion,
+/// and the time offset (between `-dt` and 0) at which it's reached.
+/// It catches the closest approach in between two sampled positions.
+pub fn closest_approach(rel_pos: Vec3, rel_vel: Vec3, dt: f32) -> (f32, f32) {
+    let speed2 = rel_vel.length_squared();
+    let t = if speed2 > 0.0 {
+        (-rel_pos.dot(rel_vel) / speed2).clamp(-dt, 0.0)
+    } else {
+        0.0
+    };
+    ((rel_pos + t * rel_vel).length(), t)
+}
+
+fn record_encounters(
+    time: Res<SimulationTime>,
+    dt: Res<TimeStep>,
+    mut stats: ResMut<CloseEncounterStats>,
+    query: Query<(Entity, &Transform, &Velocity), With<Mass>>,
+) {
+    if stats.threshold.is_none() {
+        return;
+    }
+
+    let bodies: Vec<(Entity, Vec3, Vec3)> = query
+        .iter()
+        .map(|(entity, transform, vel)| (entity, transform.translation, vel.0))
+        .collect();
+    for (i, (a, pos_a, vel_a)) in bodies.iter().enumerate() {
+        for (b, pos_b, vel_b) in bodies.iter().skip(i + 1) {
+            let (distance, offset) = closest_approach(*pos_b - *pos_a, *vel_b - *vel_a, dt.0);
+            stats.record(*a, *b, distance, time.0 + offset);
+        }
+    }
+}
+
+/// Runs every frame as the removed components are only tracked for the current frame
+fn finalize_encounters(mut stats: ResMut<CloseEncounterStats>, removed: RemovedComponents<Mass>) {
+    for body in removed.iter() {
+        stats.finalize(body);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::canonical_two_body;
+    use crate::plugins::nbody::{Integrator, DT};
+    use crate::testing::run_system;
+
+    #[test]
+    fn minimum_separation_is_the_perihelion() {
+        // The canonical two-body scene slowed down to an eccentric orbit, starting from the aphelion
+        let (mut state, params) = canonical_two_body();
+        state.velocities.iter_mut().for_each(|vel| *vel *= 0.8);
+
+        let rel_pos = state.positions[1] - state.positions[0];
+        let rel_vel = state.velocities[1] - state.velocities[0];
+        let mu = params.g * (state.masses[0] + state.masses[1]);
+        let energy = 0.5 * rel_vel.length_squared() - mu / rel_pos.length();
+        let semi_major_axis = -mu / (2.0 * energy);
+        let h = rel_pos.cross(rel_vel).length();
+        let eccentricity = (1.0 + 2.0 * energy * h * h / (mu * mu)).sqrt();
+        let perihelion = semi_major_axis * (1.0 - eccentricity);
+        let half_period = std::f32::consts::PI * (semi_major_axis.powi(3) / mu).sqrt();
+
+        let mut world = World::default();
+        world.insert_resource(CloseEncounterStats::new(0.9));
+        world.insert_resource(TimeStep::default());
+        let bodies: Vec<Entity> = (0..2)
+            .map(|i| {
+                world
+                    .spawn()
+                    .insert_bundle((
+                        Mass(state.masses[i]),
+                        Transform::from_translation(state.positions[i]),
+                        Velocity(state.velocities[i]),
+                    ))
+                    .id()
+            })
+            .collect();
+        for step in 1..=200 {
+            state.step(&params, Integrator::Leapfrog, DT);
+            for (i, body) in bodies.iter().enumerate() {
+                world.get_mut::<Transform>(*body).unwrap().translation = state.positions[i];
+                world.get_mut::<Velocity>(*body).unwrap().0 = state.velocities[i];
+            }
+            world.insert_resource(SimulationTime(step as f32 * DT));
+            run_system(&mut world, record_encounters.system());
+        }
+
+        let stats = world.get_resource::<CloseEncounterStats>().unwrap();
+        let record = stats.active(bodies[1], bodies[0]).unwrap();
+        assert!(
+            (record.min_distance - perihelion).abs() < 2e-3,
+            "{} != {}",
+            record.min_distance,
+            perihelion
+        );
+        assert!(
+            (record.time - half_period).abs() < 2.0 * DT,
+            "{}",
+            record.time
+        );
+    }
+
+    #[test]
+    fn despawned_bodies_finalize_their_encounters() {
+        let (a, b, c) = (Entity::new(0), Entity::new(1), Entity::new(2));
+        let mut stats = CloseEncounterStats::new(1.0);
+        stats.record(a, b, 0.5, 1.0);
+        stats.record(b, a, 0.2, 2.0);
+        stats.record(a, c, 0.7, 3.0);
+        stats.record(b, c, 1.5, 4.0);
+        assert_eq!(stats.records().count(), 2);
+
+        stats.finalize(b);
+        assert_eq!(
+            stats.finalized,
+            vec![EncounterRecord {
+                bodies: (a, b),
+                min_distance: 0.2,
+                time: 2.0,
+            }]
+        );
+        assert!(stats.active(a, b).is_none());
+        assert!(stats.active(a, c).is_some());
+    }
+}
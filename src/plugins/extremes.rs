@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{Mass, Velocity};
+use super::tint::HighlightTint;
+
+/// Tints the most massive and the fastest body, following them as they change over the run,
+/// through the `HighlightTint` of the `TintPlugin`:
+/// * T: toggle the highlight of the most massive body
+/// * Y: toggle the highlight of the fastest body
+pub struct ExtremesPlugin;
+
+impl Plugin for ExtremesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::HighlightHeaviest,
+            "toggle the highlight of the most massive body",
+        )
+        .register_key_binding(
+            Action::HighlightFastest,
+            "toggle the highlight of the fastest body",
+        )
+        .init_resource::<Extremes>()
+        .add_system(toggle_extremes.system())
+        .add_system(highlight_extremes.system());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Extremes {
+    pub heaviest: bool,
+    pub fastest: bool,
+}
+
+pub const HEAVIEST_COLOR: Color = Color::FUCHSIA;
+pub const FASTEST_COLOR: Color = Color::LIME_GREEN;
+
+/// Item with the largest property, ignoring the NaN ones, the first one on ties
+pub fn max_by_property<T>(items: impl IntoIterator<Item = (T, f32)>) -> Option<T> {
+    items
+        .into_iter()
+        .filter(|(_, value)| !value.is_nan())
+        .fold(None, |max: Option<(T, f32)>, (item, value)| match max {
+            Some((_, max_value)) if max_value >= value => max,
+            _ => Some((item, value)),
+        })
+        .map(|(item, _)| item)
+}
+
+fn toggle_extremes(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut extremes: ResMut<Extremes>,
+) {
+    if bindings.just_pressed(&keys, Action::HighlightHeaviest) {
+        extremes.heaviest = !extremes.heaviest;
+    }
+    if bindings.just_pressed(&keys, Action::HighlightFastest) {
+        extremes.fastest = !extremes.fastest;
+    }
+}
+
+/// The heaviest body keeps its highlight when it is also the fastest
+fn highlight_extremes(
+    mut commands: Commands,
+    extremes: Res<Extremes>,
+    bodies: Query<(Entity, &Mass, &Velocity, Option<&HighlightTint>)>,
+    highlighted: Query<Entity, With<HighlightTint>>,
+) {
+    if !extremes.heaviest && !extremes.fastest {
+        for entity in highlighted.iter() {
+            commands.entity(entity).remove::<HighlightTint>();
+        }
+        return;
+    }
+
+    let heaviest = if extremes.heaviest {
+        max_by_property(bodies.iter().map(|(entity, mass, ..)| (entity, mass.0)))
+    } else {
+        None
+    };
+    let fastest = if extremes.fastest {
+        max_by_property(
+            bodies
+                .iter()
+                .map(|(entity, _, vel, _)| (entity, vel.0.length())),
+        )
+    } else {
+        None
+    };
+
+    for (entity, _, _, tint) in bodies.iter() {
+        let color = if Some(entity) == heaviest {
+            Some(HEAVIEST_COLOR)
+        } else if Some(entity) == fastest {
+            Some(FASTEST_COLOR)
+        } else {
+            None
+        };
+        match (color, tint) {
+            (Some(color), Some(tint)) if tint.0 == color => {}
+            (Some(color), _) => {
+                commands.entity(entity).insert(HighlightTint(color));
+            }
+            // The body is no longer highlighted
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<HighlightTint>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::nbody::BodyBundle;
+    use crate::testing::run_system;
+
+    #[test]
+    fn max_by_property_finds_the_largest_value() {
+        let bodies = [("sun", 1000.0), ("earth", 1.0), ("jupiter", 318.0)];
+        assert_eq!(max_by_property(bodies.iter().copied()), Some("sun"));
+        assert_eq!(
+            max_by_property(vec![("a", 2.0), ("b", f32::NAN), ("c", 2.0), ("d", 1.0)]),
+            Some("a")
+        );
+        assert_eq!(max_by_property(vec![("a", f32::NAN)]), None);
+        assert_eq!(max_by_property(Vec::<(&str, f32)>::new()), None);
+    }
+
+    #[test]
+    fn extremes_are_tinted_until_the_highlight_is_toggled_off() {
+        let mut world = World::default();
+        world.insert_resource(Extremes {
+            heaviest: true,
+            fastest: true,
+        });
+        let mut spawn = |mass: f32, speed: f32| {
+            world
+                .spawn()
+                .insert_bundle(BodyBundle::new(mass, Vec3::ZERO, speed * Vec3::X))
+                .id()
+        };
+        let bodies = [spawn(10.0, 0.0), spawn(1.0, 3.0), spawn(2.0, 1.0)];
+        let tints = |world: &World| -> Vec<Option<Color>> {
+            bodies
+                .iter()
+                .map(|body| world.get::<HighlightTint>(*body).map(|tint| tint.0))
+                .collect()
+        };
+
+        run_system(&mut world, highlight_extremes.system());
+        assert_eq!(
+            tints(&world),
+            vec![Some(HEAVIEST_COLOR), Some(FASTEST_COLOR), None]
+        );
+
+        world.insert_resource(Extremes {
+            heaviest: false,
+            fastest: true,
+        });
+        run_system(&mut world, highlight_extremes.system());
+        assert_eq!(tints(&world), vec![None, Some(FASTEST_COLOR), None]);
+
+        world.insert_resource(Extremes::default());
+        run_system(&mut world, highlight_extremes.system());
+        assert_eq!(tints(&world), vec![None, None, None]);
+    }
+}
@@ -0,0 +1,382 @@
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy::window::{WindowFocused, WindowId, WindowResized};
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{PhysicsPaused, PhysicsThrottle};
+
+/// Runtime control of the render frame rate:
+/// * F: toggle the frame rate cap
+///
+/// The physics runs on its own fixed time step, so the simulated time progresses identically
+/// whatever the frame rate.
+/// With `PauseOnFocusLoss`, the physics is also paused while the window is in the background.
+/// While the window is minimized, the frame rate drops to `MINIMIZED_FRAME_RATE`
+/// and the physics follows the `MinimizedBehavior`.
+pub struct FrameRatePlugin;
+
+impl Plugin for FrameRatePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::ToggleFrameRateCap,
+            "toggle the frame rate cap (60 FPS)",
+        )
+        .init_resource::<FrameRateCap>()
+        .init_resource::<FrameLimiter>()
+        .init_resource::<PauseOnFocusLoss>()
+        .init_resource::<MinimizedBehavior>()
+        .init_resource::<WindowMinimized>()
+        .add_system(toggle_frame_rate_cap.system())
+        .add_system(pause_on_focus_loss.system())
+        .add_system(apply_minimized_behavior.system())
+        .add_system(apply_vsync.system())
+        .add_system_to_stage(CoreStage::Last, limit_frame_rate.system());
+    }
+}
+
+/// Frames per second cap, uncapped (and without vsync) when `None`
+pub struct FrameRateCap(pub Option<u32>);
+
+impl Default for FrameRateCap {
+    fn default() -> Self {
+        Self(Some(DEFAULT_CAP))
+    }
+}
+
+/// Whether the physics is paused while the window is unfocused, to spare the CPU
+#[derive(Default)]
+pub struct PauseOnFocusLoss(pub bool);
+
+/// Physics while the window is minimized, nothing being visible
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MinimizedBehavior {
+    /// The physics keeps its rate, catching up on several steps per frame
+    #[default]
+    Continue,
+    /// The physics runs at most `hz` steps per second, slowing the simulation down
+    Throttle { hz: f32 },
+    /// The physics is paused until the window is restored
+    Pause,
+}
+
+impl std::str::FromStr for MinimizedBehavior {
+    type Err = String;
+
+    /// `continue`, `throttle:<steps per second>` or `pause`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || String::from("Invalid input. Should be: continue, throttle:<hz> or pause");
+        match s.to_lowercase().split_once(':') {
+            Some(("throttle", hz)) => Ok(Self::Throttle {
+                hz: hz.parse().map_err(|_| invalid())?,
+            }),
+            Some(_) => Err(invalid()),
+            None if s.eq_ignore_ascii_case("continue") => Ok(Self::Continue),
+            None if s.eq_ignore_ascii_case("pause") => Ok(Self::Pause),
+            None => Err(invalid()),
+        }
+    }
+}
+
+/// Whether the primary window is minimized, i.e. resized to nothing
+#[derive(Default)]
+pub struct WindowMinimized(pub bool);
+
+/// Cap restored by the toggle
+const DEFAULT_CAP: u32 = 60;
+
+/// Cap while the window is minimized, there being nothing to render
+pub const MINIMIZED_FRAME_RATE: u32 = 5;
+
+#[derive(Default)]
+struct FrameLimiter {
+    last_frame: Option<Instant>,
+}
+
+/// Time left to wait after a frame of duration `elapsed` to respect the `cap`
+pub fn frame_sleep(elapsed: Duration, cap: u32) -> Duration {
+    if cap == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_secs_f64(1.0 / cap as f64).saturating_sub(elapsed)
+}
+
+/// Pause state after the window gets `focused` or unfocused, given whether the physics is `paused`
+/// and whether it was `paused_by_focus`, returned along with the new `paused_by_focus`.
+/// Only the pauses caused by a focus loss are lifted on regaining it, a pause requested meanwhile is kept.
+pub fn focus_transition(paused: bool, paused_by_focus: bool, focused: bool) -> (bool, bool) {
+    match (focused, paused) {
+        (false, false) => (true, true),
+        (false, true) => (true, paused_by_focus),
+        (true, _) if paused_by_focus => (false, false),
+        (true, _) => (paused, false),
+    }
+}
+
+fn pause_on_focus_loss(
+    enabled: Res<PauseOnFocusLoss>,
+    mut events: EventReader<WindowFocused>,
+    mut paused: ResMut<PhysicsPaused>,
+    mut paused_by_focus: Local<bool>,
+) {
+    for event in events.iter() {
+        if !enabled.0 {
+            continue;
+        }
+        let (new_paused, by_focus) = focus_transition(paused.0, *paused_by_focus, event.focused);
+        if new_paused != paused.0 {
+            if new_paused {
+                info!("Window unfocused, simulation paused");
+            } else {
+                info!("Window focused, simulation resumed");
+            }
+            paused.0 = new_paused;
+        }
+        *paused_by_focus = by_focus;
+    }
+}
+
+/// Throttle of the physics while the window is `minimized` with the given `behavior`
+pub fn minimized_throttle(behavior: MinimizedBehavior, minimized: bool) -> Option<f32> {
+    match behavior {
+        MinimizedBehavior::Throttle { hz } if minimized => Some(hz),
+        _ => None,
+    }
+}
+
+fn apply_minimized_behavior(
+    behavior: Res<MinimizedBehavior>,
+    mut events: EventReader<WindowResized>,
+    mut minimized: ResMut<WindowMinimized>,
+    mut paused: ResMut<PhysicsPaused>,
+    mut throttle: ResMut<PhysicsThrottle>,
+    mut paused_by_minimize: Local<bool>,
+) {
+    for event in events
+        .iter()
+        .filter(|event| event.id == WindowId::primary())
+    {
+        let is_minimized = event.width == 0.0 || event.height == 0.0;
+        if is_minimized == minimized.0 {
+            continue;
+        }
+        minimized.0 = is_minimized;
+        throttle.0 = minimized_throttle(*behavior, is_minimized);
+        if *behavior == MinimizedBehavior::Pause {
+            // Same as losing and regaining the focus
+            let (new_paused, by_minimize) =
+                focus_transition(paused.0, *paused_by_minimize, !is_minimized);
+            paused.0 = new_paused;
+            *paused_by_minimize = by_minimize;
+        }
+        if is_minimized {
+            info!("Window minimized, physics: {:?}", *behavior);
+        } else {
+            info!("Window restored");
+        }
+    }
+}
+
+fn toggle_frame_rate_cap(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut cap: ResMut<FrameRateCap>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleFrameRateCap) {
+        cap.0 = match cap.0 {
+            Some(_) => None,
+            None => Some(DEFAULT_CAP),
+        };
+    }
+}
+
+fn apply_vsync(cap: Res<FrameRateCap>, mut windows: ResMut<Windows>) {
+    if !cap.is_changed() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_vsync(cap.0.is_some());
+    }
+}
+
+fn limit_frame_rate(
+    cap: Res<FrameRateCap>,
+    minimized: Res<WindowMinimized>,
+    mut limiter: ResMut<FrameLimiter>,
+) {
+    let cap = match cap.0 {
+        _ if minimized.0 => Some(MINIMIZED_FRAME_RATE),
+        cap => cap,
+    };
+    if let (Some(cap), Some(last_frame)) = (cap, limiter.last_frame) {
+        std::thread::sleep(frame_sleep(last_frame.elapsed(), cap));
+    }
+    limiter.last_frame = Some(Instant::now());
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::core::{FixedTimestep, FixedTimesteps};
+
+    use super::*;
+
+    const STEPS_PER_SECOND: f64 = 1000.0;
+
+    #[derive(Default)]
+    struct Steps(u32);
+
+    fn count_step(mut steps: ResMut<Steps>) {
+        steps.0 += 1;
+    }
+
+    /// Frames and physics steps run over `duration` seconds with the given `cap`,
+    /// along with the time actually elapsed between the first and the last frame
+    fn run_frames(cap: Option<u32>, duration: f64) -> (u32, u32, f64) {
+        let mut world = World::default();
+        world.insert_resource(Time::default());
+        world.insert_resource(FixedTimesteps::default());
+        world.insert_resource(Steps::default());
+        world.insert_resource(FrameRateCap(cap));
+        world.insert_resource(WindowMinimized::default());
+        world.insert_resource(FrameLimiter::default());
+        let mut physics = SystemStage::single_threaded().with_system_set(
+            SystemSet::new()
+                .with_run_criteria(FixedTimestep::steps_per_second(STEPS_PER_SECOND))
+                .with_system(count_step.system()),
+        );
+        let mut render = SystemStage::single_threaded().with_system(limit_frame_rate.system());
+
+        let mut frames = 0;
+        let mut start = None;
+        loop {
+            world.get_resource_mut::<Time>().unwrap().update();
+            physics.run(&mut world);
+            render.run(&mut world);
+            frames += 1;
+            let now = world
+                .get_resource::<Time>()
+                .unwrap()
+                .seconds_since_startup();
+            let elapsed = now - *start.get_or_insert(now);
+            if elapsed >= duration {
+                return (frames, world.get_resource::<Steps>().unwrap().0, elapsed);
+            }
+        }
+    }
+
+    #[test]
+    fn physics_steps_do_not_depend_on_the_frame_rate_cap() {
+        let (capped_frames, capped_steps, capped_elapsed) = run_frames(Some(30), 0.3);
+        let (uncapped_frames, uncapped_steps, uncapped_elapsed) = run_frames(None, 0.3);
+
+        assert!(capped_frames <= 11);
+        assert!(uncapped_frames > capped_frames);
+        for &(steps, elapsed) in [
+            (capped_steps, capped_elapsed),
+            (uncapped_steps, uncapped_elapsed),
+        ]
+        .iter()
+        {
+            let expected = elapsed * STEPS_PER_SECOND;
+            assert!(
+                (steps as f64 - expected).abs() <= 1.0,
+                "{} steps in {}s",
+                steps,
+                elapsed
+            );
+        }
+    }
+
+    #[test]
+    fn focus_loss_pauses_and_focus_gain_resumes() {
+        use bevy::app::Events;
+        use bevy::window::WindowId;
+
+        let mut world = World::default();
+        world.insert_resource(PauseOnFocusLoss(true));
+        world.insert_resource(PhysicsPaused::default());
+        world.insert_resource(Events::<WindowFocused>::default());
+        let mut stage = SystemStage::single_threaded().with_system(pause_on_focus_loss.system());
+        let mut focus = |world: &mut World, focused: bool| {
+            world
+                .get_resource_mut::<Events<WindowFocused>>()
+                .unwrap()
+                .send(WindowFocused {
+                    id: WindowId::primary(),
+                    focused,
+                });
+            stage.run(world);
+            world.get_resource::<PhysicsPaused>().unwrap().0
+        };
+
+        assert!(focus(&mut world, false));
+        assert!(!focus(&mut world, true));
+
+        // A pause requested before losing the focus is kept on regaining it
+        world.insert_resource(PhysicsPaused(true));
+        assert!(focus(&mut world, false));
+        assert!(focus(&mut world, true));
+
+        // Disabled, the focus changes are ignored
+        world.insert_resource(PhysicsPaused(false));
+        world.insert_resource(PauseOnFocusLoss(false));
+        assert!(!focus(&mut world, false));
+
+        assert_eq!(focus_transition(true, false, false), (true, false));
+        assert_eq!(focus_transition(true, false, true), (true, false));
+    }
+
+    #[test]
+    fn minimizing_applies_the_chosen_behavior() {
+        use bevy::app::Events;
+
+        let resize = |world: &mut World, stage: &mut SystemStage, minimized: bool| {
+            let size = if minimized { 0.0 } else { 800.0 };
+            world
+                .get_resource_mut::<Events<WindowResized>>()
+                .unwrap()
+                .send(WindowResized {
+                    id: WindowId::primary(),
+                    width: size,
+                    height: size,
+                });
+            stage.run(world);
+            (
+                world.get_resource::<PhysicsThrottle>().unwrap().0,
+                world.get_resource::<PhysicsPaused>().unwrap().0,
+            )
+        };
+        let run = |behavior: MinimizedBehavior| {
+            let mut world = World::default();
+            world.insert_resource(behavior);
+            world.insert_resource(WindowMinimized::default());
+            world.insert_resource(PhysicsPaused::default());
+            world.insert_resource(PhysicsThrottle::default());
+            world.insert_resource(Events::<WindowResized>::default());
+            let mut stage =
+                SystemStage::single_threaded().with_system(apply_minimized_behavior.system());
+            let minimized = resize(&mut world, &mut stage, true);
+            assert!(world.get_resource::<WindowMinimized>().unwrap().0);
+            let restored = resize(&mut world, &mut stage, false);
+            assert!(!world.get_resource::<WindowMinimized>().unwrap().0);
+            (minimized, restored)
+        };
+
+        assert_eq!(
+            run(MinimizedBehavior::Throttle { hz: 10.0 }),
+            ((Some(10.0), false), (None, false))
+        );
+        assert_eq!(run(MinimizedBehavior::Pause), ((None, true), (None, false)));
+        assert_eq!(
+            run(MinimizedBehavior::Continue),
+            ((None, false), (None, false))
+        );
+
+        assert_eq!(
+            "throttle:10".parse(),
+            Ok(MinimizedBehavior::Throttle { hz: 10.0 })
+        );
+        assert_eq!("Pause".parse(), Ok(MinimizedBehavior::Pause));
+        assert!("throttle".parse::<MinimizedBehavior>().is_err());
+    }
+}
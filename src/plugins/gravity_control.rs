@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::Gravity;
+
+/// Live control of the gravitational constant, e.g. to show how orbits respond to it:
+/// * [ / ]: hold to decrease / increase G
+/// * Backspace: restore the initial G
+///
+/// G is scrubbed on a log scale as it spans many orders of magnitude across the scenes.
+/// Only the future dynamics change, the positions and velocities are kept as is:
+/// the energy is not conserved across a change and the orbits are no longer physical.
+pub struct GravityControlPlugin;
+
+impl Plugin for GravityControlPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::DecreaseGravity,
+            "hold to decrease the gravitational constant",
+        )
+        .register_key_binding(
+            Action::IncreaseGravity,
+            "hold to increase the gravitational constant",
+        )
+        .register_key_binding(Action::RestoreGravity, "restore the gravitational constant")
+        .init_resource::<GravityControl>()
+        .add_system(scrub_gravity.system());
+    }
+}
+
+pub struct GravityControl {
+    /// Factor G is multiplied by per second while a key is held
+    pub rate: f32,
+    /// G of the scene, captured on the first change
+    initial: Option<f32>,
+}
+
+impl Default for GravityControl {
+    fn default() -> Self {
+        Self {
+            rate: 2.0,
+            initial: None,
+        }
+    }
+}
+
+/// `g` moved by `steps` on a log scale, each step multiplying it by `rate`
+pub fn scale_gravity(g: f32, rate: f32, steps: f32) -> f32 {
+    g * rate.powf(steps)
+}
+
+fn scrub_gravity(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut control: ResMut<GravityControl>,
+    mut g: ResMut<Gravity>,
+) {
+    let steps = match (
+        bindings.pressed(&keys, Action::DecreaseGravity),
+        bindings.pressed(&keys, Action::IncreaseGravity),
+    ) {
+        (true, false) => -time.delta_seconds(),
+        (false, true) => time.delta_seconds(),
+        _ => 0.0,
+    };
+
+    if bindings.just_pressed(&keys, Action::RestoreGravity) {
+        if let Some(initial) = control.initial {
+            g.0 = initial;
+            info!("G restored to {:e}", g.0);
+        }
+        return;
+    }
+    if bindings.just_released(&keys, Action::DecreaseGravity)
+        || bindings.just_released(&keys, Action::IncreaseGravity)
+    {
+        info!("G = {:e}", g.0);
+    }
+    if steps == 0.0 {
+        return;
+    }
+
+    if control.initial.is_none() {
+        control.initial = Some(g.0);
+        warn!(
+            "Changing G mid-run breaks the conservation of energy, the orbits are no longer physical"
+        );
+    }
+    g.0 = scale_gravity(g.0, control.rate, steps);
+}
@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::prediction::line_mesh;
+
+/// Faint grid on a reference plane to judge inclinations and distances:
+/// * G: toggle the grid
+pub struct ReferenceGridPlugin;
+
+impl Plugin for ReferenceGridPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(Action::ToggleGrid, "toggle the grid on the orbital plane")
+            .init_resource::<ReferenceGrid>()
+            .add_startup_system(spawn_grid.system())
+            .add_system(toggle_grid.system())
+            .add_system(update_grid.system());
+    }
+}
+
+/// Reference plane going through the origin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Plane {
+    /// Orbital plane of the scenes, e.g. the ecliptic for the Solar System
+    #[default]
+    XY,
+    XZ,
+    YZ,
+}
+
+impl Plane {
+    /// Unit vectors spanning the plane
+    pub fn axes(&self) -> (Vec3, Vec3) {
+        match self {
+            Plane::XY => (Vec3::X, Vec3::Y),
+            Plane::XZ => (Vec3::X, Vec3::Z),
+            Plane::YZ => (Vec3::Y, Vec3::Z),
+        }
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        let (u, v) = self.axes();
+        u.cross(v)
+    }
+}
+
+impl std::str::FromStr for Plane {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "xy" => Ok(Self::XY),
+            "xz" => Ok(Self::XZ),
+            "yz" => Ok(Self::YZ),
+            _ => Err(String::from("Invalid input. Should be: xy, xz or yz")),
+        }
+    }
+}
+
+pub struct ReferenceGrid {
+    /// Distance between two lines
+    pub spacing: f32,
+    /// Half the side length of the grid, centered on the origin
+    pub extent: f32,
+    pub enabled: bool,
+    pub plane: Plane,
+}
+
+impl Default for ReferenceGrid {
+    fn default() -> Self {
+        Self {
+            spacing: 5.0,
+            extent: 50.0,
+            enabled: false,
+            plane: Plane::default(),
+        }
+    }
+}
+
+pub const GRID_COLOR: Color = Color::rgba(0.5, 0.5, 0.5, 0.25);
+
+/// Line list of a square grid of lines `spacing` apart, up to `extent` from the origin in both directions
+pub fn grid_lines(spacing: f32, extent: f32, plane: Plane) -> Vec<[f32; 3]> {
+    if spacing <= 0.0 || extent <= 0.0 {
+        return Vec::new();
+    }
+    let (u, v) = plane.axes();
+    let n = (extent / spacing).floor() as i32;
+    let mut vertices = Vec::with_capacity(4 * (2 * n as usize + 1));
+    for i in -n..=n {
+        let offset = i as f32 * spacing;
+        vertices.push((offset * u - extent * v).into());
+        vertices.push((offset * u + extent * v).into());
+        vertices.push((offset * v - extent * u).into());
+        vertices.push((offset * v + extent * u).into());
+    }
+    vertices
+}
+
+struct GridLines;
+
+fn spawn_grid(
+    mut commands: Commands,
+    grid: Res<ReferenceGrid>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(line_mesh(grid_lines(grid.spacing, grid.extent, grid.plane))),
+            material: materials.add(StandardMaterial {
+                base_color: GRID_COLOR,
+                unlit: true,
+                ..Default::default()
+            }),
+            visible: Visible {
+                is_visible: grid.enabled,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(GridLines);
+}
+
+fn toggle_grid(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut grid: ResMut<ReferenceGrid>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleGrid) {
+        grid.enabled = !grid.enabled;
+    }
+}
+
+fn update_grid(
+    grid: Res<ReferenceGrid>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<(&Handle<Mesh>, &mut Visible), With<GridLines>>,
+) {
+    if !grid.is_changed() {
+        return;
+    }
+    for (mesh, mut visible) in query.iter_mut() {
+        visible.is_visible = grid.enabled;
+        let vertices = grid_lines(grid.spacing, grid.extent, grid.plane);
+        if let (Some(mesh), false) = (meshes.get_mut(mesh), vertices.is_empty()) {
+            *mesh = line_mesh(vertices);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_has_one_segment_per_line() {
+        // 2 * 4 + 1 = 9 lines along each axis, 2 vertices per segment
+        let vertices = grid_lines(2.5, 10.0, Plane::XY);
+        assert_eq!(vertices.len(), 2 * 2 * 9);
+        assert!(vertices.iter().all(|[_, _, z]| *z == 0.0));
+        assert!(vertices
+            .iter()
+            .all(|[x, y, _]| x.abs() <= 10.0 && y.abs() <= 10.0));
+
+        // The lines stop at the last multiple of the spacing within the extent
+        assert_eq!(grid_lines(3.0, 10.0, Plane::XZ).len(), 2 * 2 * 7);
+        assert!(grid_lines(0.0, 10.0, Plane::XY).is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+use bevy::render::texture::{Extent3d, TextureDimension, TextureFormat};
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{Mass, PhysicsStep, PhysicsSystem};
+
+/// Density of the bodies accumulated over time in the orbital plane (z = 0), rendered as an overlay:
+/// * F2: toggle the heatmap
+/// * Shift + F2: reset it
+pub struct HeatmapPlugin;
+
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::ToggleHeatmap,
+            "toggle the heatmap of the bodies in the orbital plane (with Shift to reset it)",
+        )
+        .init_resource::<EncounterHeatmap>()
+        .add_startup_system(spawn_heatmap_overlay.system())
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(PhysicsStep)
+                .with_system(accumulate_heatmap.system().after(PhysicsSystem::Movement)),
+        )
+        .add_system(heatmap_keys.system())
+        .add_system(draw_heatmap.system());
+    }
+}
+
+pub struct EncounterHeatmap {
+    /// Number of cells along each side
+    pub resolution: usize,
+    /// Half the side length of the covered square, centered on the origin
+    pub extent: f32,
+    pub enabled: bool,
+    /// Distance under which the midpoint of two bodies is binned as a close encounter,
+    /// only the positions of the bodies are binned when `None`
+    pub encounter_distance: Option<f32>,
+    counts: Vec<u32>,
+}
+
+impl Default for EncounterHeatmap {
+    fn default() -> Self {
+        Self {
+            resolution: 128,
+            extent: 50.0,
+            enabled: false,
+            encounter_distance: None,
+            counts: Vec::new(),
+        }
+    }
+}
+
+impl EncounterHeatmap {
+    /// Accumulated counts, row by row from the bottom (-y) of the plane
+    pub fn counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    pub fn reset(&mut self) {
+        self.counts = vec![0; self.resolution * self.resolution];
+    }
+
+    /// Increments the cell containing `pos`, ignored outside of the grid
+    pub fn bin(&mut self, pos: Vec3) {
+        if self.counts.len() != self.resolution * self.resolution {
+            self.reset();
+        }
+        if let Some((row, col)) = cell(pos, self.resolution, self.extent) {
+            self.counts[row * self.resolution + col] += 1;
+        }
+    }
+}
+
+/// `(row, column)` of the cell containing the projection of `pos` on the orbital plane,
+/// for a grid of `resolution` cells per side covering `[-extent, extent]` along x and y
+pub fn cell(pos: Vec3, resolution: usize, extent: f32) -> Option<(usize, usize)> {
+    let index = |x: f32| {
+        let t = (x + extent) / (2.0 * extent);
+        if (0.0..1.0).contains(&t) {
+            Some((t * resolution as f32) as usize)
+        } else {
+            None
+        }
+    };
+    Some((index(pos.y)?, index(pos.x)?))
+}
+
+/// Color of a cell: transparent to red to yellow with the count,
+/// on a logarithmic scale so that the overlay keeps intensifying over long runs
+pub fn heat_color(count: u32, max_count: u32) -> [u8; 4] {
+    if count == 0 || max_count == 0 {
+        return [0; 4];
+    }
+    let heat = ((1.0 + count as f32).ln() / (1.0 + max_count as f32).ln()).min(1.0);
+    [
+        255,
+        (255.0 * heat * heat) as u8,
+        0,
+        (64.0 + 191.0 * heat) as u8,
+    ]
+}
+
+struct HeatmapOverlay {
+    texture: Handle<Texture>,
+}
+
+fn heatmap_texture(resolution: usize) -> Texture {
+    Texture::new_fill(
+        Extent3d::new(resolution as u32, resolution as u32, 1),
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn spawn_heatmap_overlay(
+    mut commands: Commands,
+    heatmap: Res<EncounterHeatmap>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut textures: ResMut<Assets<Texture>>,
+) {
+    let texture = textures.add(heatmap_texture(heatmap.resolution));
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Plane { size: 1.0 })),
+            material: materials.add(StandardMaterial {
+                base_color_texture: Some(texture.clone()),
+                unlit: true,
+                ..Default::default()
+            }),
+            // The plane is horizontal (y = 0), turn it into the orbital plane
+            transform: Transform::from_rotation(Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(HeatmapOverlay { texture });
+}
+
+fn accumulate_heatmap(mut heatmap: ResMut<EncounterHeatmap>, query: Query<(&Mass, &Transform)>) {
+    if !heatmap.enabled {
+        return;
+    }
+    let positions: Vec<Vec3> = query
+        .iter()
+        .map(|(_, transform)| transform.translation)
+        .collect();
+    match heatmap.encounter_distance {
+        None => positions.iter().for_each(|pos| heatmap.bin(*pos)),
+        Some(distance) => {
+            for i in 0..positions.len() {
+                for j in (i + 1)..positions.len() {
+                    if positions[i].distance(positions[j]) < distance {
+                        heatmap.bin((positions[i] + positions[j]) / 2.0);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn heatmap_keys(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut heatmap: ResMut<EncounterHeatmap>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleHeatmap) {
+        if keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift) {
+            heatmap.reset();
+        } else {
+            heatmap.enabled = !heatmap.enabled;
+        }
+    }
+}
+
+fn draw_heatmap(
+    heatmap: Res<EncounterHeatmap>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut query: Query<(&HeatmapOverlay, &mut Transform, &mut Visible)>,
+) {
+    if !heatmap.is_changed() {
+        return;
+    }
+    let (overlay, mut transform, mut visible) = match query.single_mut() {
+        Ok(overlay) => overlay,
+        Err(_) => return,
+    };
+    visible.is_visible = heatmap.enabled;
+    transform.scale = Vec3::splat(2.0 * heatmap.extent);
+
+    let texture = match textures.get_mut(&overlay.texture) {
+        Some(texture) => texture,
+        None => return,
+    };
+    if texture.size.width as usize != heatmap.resolution {
+        *texture = heatmap_texture(heatmap.resolution);
+    }
+    let max_count = heatmap.counts.iter().copied().max().unwrap_or(0);
+    if heatmap.counts.len() == texture.data.len() / 4 {
+        for (pixel, count) in texture.data.chunks_exact_mut(4).zip(heatmap.counts.iter()) {
+            pixel.copy_from_slice(&heat_color(*count, max_count));
+        }
+    } else {
+        texture.data.iter_mut().for_each(|byte| *byte = 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binning_increments_the_cell_of_the_position() {
+        let mut heatmap = EncounterHeatmap {
+            resolution: 4,
+            extent: 2.0,
+            ..Default::default()
+        };
+        // Second column from the left, top row
+        heatmap.bin(Vec3::new(-0.5, 1.5, 7.0));
+        heatmap.bin(Vec3::new(-0.9, 1.1, -3.0));
+        // Out of the grid
+        heatmap.bin(Vec3::new(2.5, 0.0, 0.0));
+
+        let mut expected = vec![0; 16];
+        expected[3 * 4 + 1] = 2;
+        assert_eq!(heatmap.counts(), expected.as_slice());
+    }
+}
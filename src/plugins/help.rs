@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+
+use super::hud::{hud_text, set_hud_text, HudFont};
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+
+/// Lists the keyboard controls registered by the plugins in the `KeyBindings` in the top left corner:
+/// * H: toggle the list of the keyboard controls
+pub struct HelpPlugin;
+
+impl Plugin for HelpPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(Action::ShowHelp, "toggle the list of the keyboard controls")
+            .init_resource::<HelpOverlay>()
+            .init_resource::<HudFont>()
+            .add_startup_system(spawn_help_text.system())
+            .add_system(toggle_help.system().label(HelpSystem))
+            .add_system(show_help.system().after(HelpSystem));
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct HelpSystem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct HelpOverlay {
+    pub visible: bool,
+}
+
+struct HelpText;
+
+fn spawn_help_text(mut commands: Commands, font: Res<HudFont>) {
+    let position = Rect {
+        left: Val::Px(10.0),
+        top: Val::Px(10.0),
+        ..Default::default()
+    };
+    commands
+        .spawn_bundle(hud_text(&font, position))
+        .insert(HelpText);
+}
+
+fn toggle_help(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut overlay: ResMut<HelpOverlay>,
+) {
+    if bindings.just_pressed(&keys, Action::ShowHelp) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+fn show_help(
+    overlay: Res<HelpOverlay>,
+    bindings: Res<KeyBindings>,
+    mut texts: Query<&mut Text, With<HelpText>>,
+) {
+    let value = if overlay.visible {
+        format!("Keyboard controls:\n{}", bindings.help_text())
+    } else {
+        String::new()
+    };
+    for mut text in texts.iter_mut() {
+        set_hud_text(&mut text, value.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::run_system;
+
+    #[test]
+    fn help_text_lists_the_registered_bindings() {
+        let mut bindings = KeyBindings::default();
+        bindings.describe(Action::Launch, "launch a projectile");
+        bindings.describe(Action::ShowHelp, "toggle the help");
+        assert_eq!(
+            bindings.help_text(),
+            "* Space: launch a projectile\n* H: toggle the help"
+        );
+
+        bindings.remap(Action::Launch, KeyCode::H);
+        bindings.remap(Action::ShowHelp, KeyCode::Key1);
+        assert_eq!(
+            bindings.help_text(),
+            "* H: launch a projectile\n* Key1: toggle the help (conflicts with the camera bookmarks)"
+        );
+        bindings.remap(Action::ShowHelp, KeyCode::Tab);
+        assert!(bindings
+            .help_text()
+            .ends_with("* Tab: toggle the help (conflicts with select-next)"));
+    }
+
+    #[test]
+    fn help_key_toggles_the_overlay() {
+        let mut world = World::default();
+        let mut bindings = KeyBindings::default();
+        bindings.describe(Action::ShowHelp, "toggle the help");
+        world.insert_resource(bindings);
+        world.insert_resource(HelpOverlay::default());
+        let mut keys = Input::<KeyCode>::default();
+        keys.press(KeyCode::H);
+        world.insert_resource(keys);
+        let text = world
+            .spawn()
+            .insert_bundle((
+                Text::with_section("", Default::default(), Default::default()),
+                HelpText,
+            ))
+            .id();
+
+        run_system(&mut world, toggle_help.system());
+        run_system(&mut world, show_help.system());
+        assert_eq!(
+            world.get::<Text>(text).unwrap().sections[0].value,
+            "Keyboard controls:\n* H: toggle the help"
+        );
+
+        world.get_resource_mut::<Input<KeyCode>>().unwrap().update();
+        run_system(&mut world, toggle_help.system());
+        assert!(world.get_resource::<HelpOverlay>().unwrap().visible);
+
+        let mut keys = world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        keys.release(KeyCode::H);
+        keys.update();
+        keys.press(KeyCode::H);
+        run_system(&mut world, toggle_help.system());
+        run_system(&mut world, show_help.system());
+        assert_eq!(world.get::<Text>(text).unwrap().sections[0].value, "");
+    }
+}
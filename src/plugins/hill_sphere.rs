@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+
+use super::kepler::OrbitalElements;
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{Gravity, Mass, Velocity};
+use super::selection::Selected;
+
+/// Translucent sphere showing the Hill sphere of the selected body around its dominant primary:
+/// * I: toggle the Hill sphere
+pub struct HillSpherePlugin;
+
+impl Plugin for HillSpherePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::ToggleHillSphere,
+            "toggle the Hill sphere of the selected body",
+        )
+        .init_resource::<HillSphere>()
+        .add_startup_system(spawn_hill_sphere.system())
+        .add_system(hill_sphere_keys.system())
+        .add_system(draw_hill_sphere.system());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct HillSphere {
+    pub enabled: bool,
+}
+
+struct HillSphereOverlay;
+
+/// Radius of the region where a body's gravity dominates the tides of its primary:
+/// ```text
+/// r = a*(1 - e) * cbrt(m / 3M)
+/// ```
+pub fn hill_radius(m_body: f32, m_primary: f32, semi_major: f32, eccentricity: f32) -> f32 {
+    semi_major * (1.0 - eccentricity) * (m_body / (3.0 * m_primary)).cbrt()
+}
+
+/// Index of the heavier body exerting the strongest pull on the body at `pos`,
+/// among the `(mass, position)` of the others
+pub fn dominant_primary(mass: f32, pos: Vec3, others: &[(f32, Vec3)]) -> Option<usize> {
+    others
+        .iter()
+        .enumerate()
+        .filter(|(_, (m, p))| *m > mass && *p != pos)
+        .map(|(i, (m, p))| (i, m / p.distance_squared(pos)))
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(i, _)| i)
+}
+
+fn spawn_hill_sphere(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Icosphere {
+                radius: 1.0,
+                subdivisions: 4,
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(0.3, 0.6, 1.0, 0.15),
+                unlit: true,
+                ..Default::default()
+            }),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(HillSphereOverlay);
+}
+
+fn hill_sphere_keys(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut hill_sphere: ResMut<HillSphere>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleHillSphere) {
+        hill_sphere.enabled = !hill_sphere.enabled;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn draw_hill_sphere(
+    hill_sphere: Res<HillSphere>,
+    g: Res<Gravity>,
+    bodies: Query<(&Mass, &Transform, &Velocity, Option<&Selected>), Without<HillSphereOverlay>>,
+    mut overlay: Query<(&mut Transform, &mut Visible), With<HillSphereOverlay>>,
+) {
+    let (mut transform, mut visible) = match overlay.single_mut() {
+        Ok(overlay) => overlay,
+        Err(_) => return,
+    };
+    visible.is_visible = false;
+    if !hill_sphere.enabled {
+        return;
+    }
+
+    let mut selected = None;
+    let mut others = Vec::new();
+    for (mass, body_transform, vel, is_selected) in bodies.iter() {
+        let body = (mass.0, body_transform.translation, vel.0);
+        match is_selected {
+            Some(_) if selected.is_none() => selected = Some(body),
+            _ => others.push(body),
+        }
+    }
+    let (mass, pos, vel) = match selected {
+        Some(body) => body,
+        None => return,
+    };
+    let masses: Vec<(f32, Vec3)> = others.iter().map(|(m, p, _)| (*m, *p)).collect();
+    let (primary_mass, primary_pos, primary_vel) = match dominant_primary(mass, pos, &masses) {
+        Some(i) => others[i],
+        None => return,
+    };
+    // Only bound orbits have a Hill sphere
+    let mu = g.0 * (primary_mass + mass);
+    if let Some(elements) = OrbitalElements::from_state(pos - primary_pos, vel - primary_vel, mu) {
+        transform.translation = pos;
+        transform.scale = Vec3::splat(hill_radius(
+            mass,
+            primary_mass,
+            elements.semi_major_axis,
+            elements.eccentricity,
+        ));
+        visible.is_visible = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::catalog_entry;
+
+    #[test]
+    fn hill_radius_of_the_earth() {
+        // Rendered at 10 units per AU as in the Solar System startup
+        const AU: f32 = 10.0;
+        let sun = catalog_entry("Sun").unwrap();
+        let earth = catalog_entry("Earth").unwrap();
+        let radius = hill_radius(earth.mass, sun.mass, AU, 0.0167);
+        // ~0.01 AU, 1.5 million km
+        assert!((radius / AU - 0.0098).abs() < 2e-4, "{} AU", radius / AU);
+    }
+
+    #[test]
+    fn dominant_primary_pulls_the_most() {
+        let others = [
+            (1000.0, Vec3::new(10.0, 0.0, 0.0)),
+            (100.0, Vec3::new(-2.0, 0.0, 0.0)),
+            (0.5, Vec3::new(0.0, 0.1, 0.0)),
+        ];
+        // 100 / 4 > 1000 / 100, the lighter body is never a primary
+        assert_eq!(dominant_primary(1.0, Vec3::ZERO, &others), Some(1));
+        assert_eq!(dominant_primary(5000.0, Vec3::ZERO, &others), None);
+    }
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+
+/// Camera and font of the text overlays drawn over the scene by the other plugins, e.g. the census
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<HudFont>()
+            .add_startup_system(spawn_ui_camera.system());
+    }
+}
+
+/// Font of the overlays, shipped in the assets.
+/// The plugins drawing an overlay init it too, in case they are added before the `HudPlugin`.
+pub struct HudFont(pub Handle<Font>);
+
+const FONT_PATH: &str = "fonts/Hack-Regular.ttf";
+
+pub const HUD_FONT_SIZE: f32 = 16.0;
+
+impl FromWorld for HudFont {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world
+            .get_resource::<AssetServer>()
+            .expect("the asset server is added with the default plugins");
+        Self(asset_server.load(FONT_PATH))
+    }
+}
+
+/// Empty white text anchored at `position` from the edges of the window
+pub fn hud_text(font: &HudFont, position: Rect<Val>) -> TextBundle {
+    TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position,
+            ..Default::default()
+        },
+        text: Text::with_section(
+            "",
+            TextStyle {
+                font: font.0.clone(),
+                font_size: HUD_FONT_SIZE,
+                color: Color::WHITE,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    }
+}
+
+/// Sets the text of the single section of a `hud_text`, only flagging a change when there is one
+pub fn set_hud_text(text: &mut Mut<Text>, value: String) {
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+fn spawn_ui_camera(mut commands: Commands) {
+    commands.spawn_bundle(UiCameraBundle::default());
+}
@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{Mass, Pinned, Velocity};
+use super::pan_orbit_camera::PanOrbitCamera;
+use super::selection::Selected;
+
+/// Manual nudges of the orbits of the selected bodies:
+/// * Arrow keys: kick the selected bodies up / down / left / right on screen
+///
+/// The opposite impulse is spread over the other bodies so that the total momentum is conserved,
+/// as if the kicks were thrusts against the rest of the system. Pinned bodies are left out.
+pub struct ImpulsePlugin;
+
+impl Plugin for ImpulsePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(Action::KickUp, "kick the selected bodies up on screen")
+            .register_key_binding(Action::KickDown, "kick the selected bodies down on screen")
+            .register_key_binding(Action::KickLeft, "kick the selected bodies left on screen")
+            .register_key_binding(
+                Action::KickRight,
+                "kick the selected bodies right on screen",
+            )
+            .init_resource::<Impulse>()
+            .add_system(kick_selected.system());
+    }
+}
+
+pub struct Impulse {
+    /// Velocity change of a kick
+    pub delta_v: f32,
+    /// Whether the other bodies recoil
+    pub conserve_momentum: bool,
+}
+
+impl Default for Impulse {
+    fn default() -> Self {
+        Self {
+            delta_v: 0.1,
+            conserve_momentum: true,
+        }
+    }
+}
+
+/// Unit direction of a kick in the camera plane, `None` when no direction is given
+pub fn kick_direction(camera: Quat, up: bool, down: bool, left: bool, right: bool) -> Option<Vec3> {
+    let axis = |plus: bool, minus: bool| plus as i32 as f32 - minus as i32 as f32;
+    let direction = camera * Vec3::new(axis(right, left), axis(up, down), 0.0);
+    direction.try_normalize()
+}
+
+/// Applies a velocity change `delta_v` to the kicked bodies.
+/// With `conserve_momentum`, the others all recoil by the same velocity change
+/// so that the total momentum, hence the motion of the center of mass, is unchanged:
+/// ```text
+/// Δv_others = -Σ m_kicked / Σ m_others * Δv
+/// ```
+/// The kick alone is applied if there are no massive bodies to recoil.
+pub fn apply_kick(
+    masses: &[f32],
+    velocities: &mut [Vec3],
+    kicked: &[bool],
+    delta_v: Vec3,
+    conserve_momentum: bool,
+) {
+    let (mut kicked_mass, mut others_mass) = (0.0, 0.0);
+    for (mass, is_kicked) in masses.iter().zip(kicked) {
+        if *is_kicked {
+            kicked_mass += mass;
+        } else {
+            others_mass += mass;
+        }
+    }
+    let recoil = if conserve_momentum && others_mass > 0.0 {
+        -kicked_mass / others_mass * delta_v
+    } else {
+        Vec3::ZERO
+    };
+    for (vel, is_kicked) in velocities.iter_mut().zip(kicked) {
+        *vel += if *is_kicked { delta_v } else { recoil };
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn kick_selected(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    impulse: Res<Impulse>,
+    cameras: Query<&Transform, With<PanOrbitCamera>>,
+    mut bodies: Query<(&Mass, &mut Velocity, Option<&Selected>), Without<Pinned>>,
+) {
+    let camera = match cameras.iter().next() {
+        Some(transform) => transform.rotation,
+        None => return,
+    };
+    let direction = match kick_direction(
+        camera,
+        bindings.just_pressed(&keys, Action::KickUp),
+        bindings.just_pressed(&keys, Action::KickDown),
+        bindings.just_pressed(&keys, Action::KickLeft),
+        bindings.just_pressed(&keys, Action::KickRight),
+    ) {
+        Some(direction) => direction,
+        None => return,
+    };
+
+    let mut masses = Vec::new();
+    let mut velocities = Vec::new();
+    let mut kicked = Vec::new();
+    for (mass, vel, selected) in bodies.iter_mut() {
+        masses.push(mass.0);
+        velocities.push(vel.0);
+        kicked.push(selected.is_some());
+    }
+    if !kicked.contains(&true) {
+        return;
+    }
+
+    let delta_v = direction * impulse.delta_v;
+    apply_kick(
+        &masses,
+        &mut velocities,
+        &kicked,
+        delta_v,
+        impulse.conserve_momentum,
+    );
+    for ((_, mut vel, _), new_vel) in bodies.iter_mut().zip(velocities) {
+        vel.0 = new_vel;
+    }
+    info!("Kicked the selected bodies by {:?}", delta_v);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::nbody::BodyBundle;
+    use crate::testing::run_system;
+
+    #[test]
+    fn kick_changes_the_velocity_along_the_screen_direction() {
+        let mut world = World::default();
+        world.insert_resource(KeyBindings::default());
+        world.insert_resource(Impulse {
+            delta_v: 0.5,
+            conserve_momentum: true,
+        });
+        let mut keys = Input::<KeyCode>::default();
+        keys.press(KeyCode::Right);
+        world.insert_resource(keys);
+        // Camera on the +X axis looking at the origin: right on screen is -Z
+        world.spawn().insert_bundle((
+            PanOrbitCamera::default(),
+            Transform::from_xyz(10.0, 0.0, 0.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ));
+        let selected = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::ZERO, Vec3::Y))
+            .insert(Selected)
+            .id();
+        let other = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(4.0, Vec3::X, Vec3::ZERO))
+            .id();
+
+        run_system(&mut world, kick_selected.system());
+
+        let vel = world.get::<Velocity>(selected).unwrap().0;
+        assert!((vel - Vec3::new(0.0, 1.0, -0.5)).length() < 1e-6, "{}", vel);
+        // The recoil keeps the total momentum unchanged
+        let recoil = world.get::<Velocity>(other).unwrap().0;
+        assert!(
+            (recoil - Vec3::new(0.0, 0.0, 0.125)).length() < 1e-6,
+            "{}",
+            recoil
+        );
+    }
+
+    #[test]
+    fn diagonal_kicks_have_the_same_magnitude() {
+        let direction = kick_direction(Quat::IDENTITY, true, false, true, false).unwrap();
+        assert!((direction - Vec3::new(-1.0, 1.0, 0.0).normalize()).length() < 1e-6);
+        assert_eq!(
+            kick_direction(Quat::IDENTITY, true, true, false, false),
+            None
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+
+use super::hill_sphere::dominant_primary;
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{BodyClass, Mass};
+use super::tint::InfluenceTint;
+
+/// Tints each body by the star whose gravity dominates it, e.g. to follow captures and hand-offs
+/// between the stars of a multiple system, through the `InfluenceTint` of the `TintPlugin`:
+/// * B: toggle the sphere of influence coloring
+pub struct SphereOfInfluencePlugin;
+
+impl Plugin for SphereOfInfluencePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::ToggleInfluence,
+            "toggle the coloring of the bodies by the star dominating their gravity",
+        )
+        .init_resource::<SphereOfInfluence>()
+        .add_system(toggle_influence.system())
+        .add_system(tint_by_influence.system());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SphereOfInfluence {
+    pub enabled: bool,
+}
+
+/// Colors of the stars' domains, in the order of the stars, cycled past the last one
+pub const INFLUENCE_PALETTE: [Color; 6] = [
+    Color::ORANGE_RED,
+    Color::CYAN,
+    Color::LIME_GREEN,
+    Color::FUCHSIA,
+    Color::GOLD,
+    Color::VIOLET,
+];
+
+/// Radius of the region where a body's gravity dominates the perturbation of its primary,
+/// as defined by Laplace for patched conics:
+/// ```text
+/// r = a * (m / M)^(2/5)
+/// ```
+pub fn sphere_of_influence(m_body: f32, m_primary: f32, semi_major: f32) -> f32 {
+    semi_major * (m_body / m_primary).powf(0.4)
+}
+
+fn toggle_influence(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut influence: ResMut<SphereOfInfluence>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleInfluence) {
+        influence.enabled = !influence.enabled;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn tint_by_influence(
+    mut commands: Commands,
+    influence: Res<SphereOfInfluence>,
+    bodies: Query<(
+        Entity,
+        &Mass,
+        &Transform,
+        Option<&BodyClass>,
+        Option<&InfluenceTint>,
+    )>,
+    tinted: Query<Entity, With<InfluenceTint>>,
+) {
+    if !influence.enabled {
+        for entity in tinted.iter() {
+            commands.entity(entity).remove::<InfluenceTint>();
+        }
+        return;
+    }
+
+    let stars: Vec<(f32, Vec3)> = bodies
+        .iter()
+        .filter(|(_, _, _, class, _)| *class == Some(&BodyClass::Star))
+        .map(|(_, mass, transform, _, _)| (mass.0, transform.translation))
+        .collect();
+    for (entity, mass, transform, class, tint) in bodies.iter() {
+        if class == Some(&BodyClass::Star) {
+            continue;
+        }
+        // Bodies heavier than all the stars keep their color
+        let color = dominant_primary(mass.0, transform.translation, &stars)
+            .map(|i| INFLUENCE_PALETTE[i % INFLUENCE_PALETTE.len()]);
+        match (color, tint) {
+            (Some(color), Some(tint)) if tint.0 == color => {}
+            (Some(color), _) => {
+                commands.entity(entity).insert(InfluenceTint(color));
+            }
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<InfluenceTint>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::run_system;
+
+    #[test]
+    fn bodies_are_tinted_by_the_nearest_star() {
+        let mut world = World::default();
+        world.insert_resource(SphereOfInfluence { enabled: true });
+        let spawn = |world: &mut World, mass: f32, x: f32, class: Option<BodyClass>| {
+            let mut body = world.spawn();
+            body.insert_bundle((Mass(mass), Transform::from_xyz(x, 0.0, 0.0)));
+            if let Some(class) = class {
+                body.insert(class);
+            }
+            body.id()
+        };
+        let star = spawn(&mut world, 10.0, -10.0, Some(BodyClass::Star));
+        spawn(&mut world, 5.0, 10.0, Some(BodyClass::Star));
+        // Closer to the lighter star, which pulls it harder
+        let near_light = spawn(&mut world, 0.1, 8.0, Some(BodyClass::Planet));
+        let near_heavy = spawn(&mut world, 0.1, -5.0, None);
+        let tint = |world: &World, body| world.get::<InfluenceTint>(body).copied();
+
+        run_system(&mut world, tint_by_influence.system());
+        assert_eq!(
+            tint(&world, near_light),
+            Some(InfluenceTint(INFLUENCE_PALETTE[1]))
+        );
+        assert_eq!(
+            tint(&world, near_heavy),
+            Some(InfluenceTint(INFLUENCE_PALETTE[0]))
+        );
+        assert_eq!(tint(&world, star), None);
+
+        world.insert_resource(SphereOfInfluence { enabled: false });
+        run_system(&mut world, tint_by_influence.system());
+        assert_eq!(tint(&world, near_light), None);
+        assert_eq!(tint(&world, near_heavy), None);
+    }
+
+    #[test]
+    fn sphere_of_influence_of_the_earth() {
+        // Sun and Earth masses in 10^24 kg, at 1 AU: ~925,000 km
+        let radius = sphere_of_influence(5.97, 1_988_500.0, 1.496e8);
+        assert!((radius / 9.25e5 - 1.0).abs() < 0.01, "{} km", radius);
+    }
+}
@@ -0,0 +1,248 @@
+use bevy::prelude::*;
+
+use super::nbody::{PhysicsStep, PhysicsSystem, TimeStep, Velocity};
+
+/// Moves the `OnRails` bodies along exact Kepler orbits around their primary,
+/// ignoring the perturbations of the other bodies
+pub struct OnRailsPlugin;
+
+impl Plugin for OnRailsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(PhysicsStep)
+                .with_system(move_on_rails.system().after(PhysicsSystem::Movement)),
+        );
+    }
+}
+
+/// Keplerian elements of a bound orbit, angles in radians
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub inclination: f32,
+    pub longitude_of_ascending_node: f32,
+    pub argument_of_periapsis: f32,
+    pub mean_anomaly: f32,
+}
+
+/// Body following the Kepler orbit described by `elements` around the `primary`,
+/// with `mu = G*(M + m)`
+pub struct OnRails {
+    pub primary: Entity,
+    pub elements: OrbitalElements,
+    pub mu: f32,
+}
+
+/// Eccentric anomaly `E` solving Kepler's equation `M = E - e*sin(E)` (Newton's method)
+pub fn solve_kepler(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let e = eccentricity;
+    let mut anomaly = if e < 0.8 {
+        mean_anomaly
+    } else {
+        std::f32::consts::PI
+    };
+    for _ in 0..32 {
+        let delta = (anomaly - e * anomaly.sin() - mean_anomaly) / (1.0 - e * anomaly.cos());
+        anomaly -= delta;
+        if delta.abs() < 1e-7 {
+            break;
+        }
+    }
+    anomaly
+}
+
+/// Specific orbital energy of a body relative to its primary, negative when bound to it:
+/// ```text
+/// ε = v^2/2 - μ/r
+/// ```
+pub fn specific_orbital_energy(rel_pos: Vec3, rel_vel: Vec3, mu: f32) -> f32 {
+    rel_vel.length_squared() / 2.0 - mu / rel_pos.length()
+}
+
+/// Signed angle from `from` to `to` around `axis`
+fn signed_angle(from: Vec3, to: Vec3, axis: Vec3) -> f32 {
+    from.cross(to).dot(axis).atan2(from.dot(to))
+}
+
+impl OrbitalElements {
+    /// Mean motion `n = sqrt(μ / a^3)`
+    pub fn mean_motion(&self, mu: f32) -> f32 {
+        (mu / self.semi_major_axis.powi(3)).sqrt()
+    }
+
+    pub fn period(&self, mu: f32) -> f32 {
+        std::f32::consts::TAU / self.mean_motion(mu)
+    }
+
+    /// Elements advanced by `dt` along the orbit
+    pub fn advanced(&self, mu: f32, dt: f32) -> Self {
+        Self {
+            mean_anomaly: (self.mean_anomaly + self.mean_motion(mu) * dt)
+                .rem_euclid(std::f32::consts::TAU),
+            ..*self
+        }
+    }
+
+    /// Position and velocity relative to the primary
+    pub fn state(&self, mu: f32) -> (Vec3, Vec3) {
+        let a = self.semi_major_axis;
+        let e = self.eccentricity;
+        let anomaly = solve_kepler(self.mean_anomaly, e);
+        let (sin, cos) = anomaly.sin_cos();
+        let b = a * (1.0 - e * e).sqrt();
+        // In the orbital plane, periapsis along x
+        let pos = Vec3::new(a * (cos - e), b * sin, 0.0);
+        let speed = (mu * a).sqrt() / (a * (1.0 - e * cos));
+        let vel = Vec3::new(-a * sin, b * cos, 0.0) * speed / a;
+
+        let rotation = Quat::from_rotation_z(self.longitude_of_ascending_node)
+            * Quat::from_rotation_x(self.inclination)
+            * Quat::from_rotation_z(self.argument_of_periapsis);
+        (rotation * pos, rotation * vel)
+    }
+
+    /// `points` positions evenly spread in eccentric anomaly around the whole orbit, relative to the primary,
+    /// starting at the periapsis
+    pub fn ellipse(&self, points: usize) -> Vec<Vec3> {
+        let a = self.semi_major_axis;
+        let e = self.eccentricity;
+        let b = a * (1.0 - e * e).sqrt();
+        let rotation = Quat::from_rotation_z(self.longitude_of_ascending_node)
+            * Quat::from_rotation_x(self.inclination)
+            * Quat::from_rotation_z(self.argument_of_periapsis);
+        (0..points)
+            .map(|i| {
+                let anomaly = std::f32::consts::TAU * i as f32 / points as f32;
+                let (sin, cos) = anomaly.sin_cos();
+                rotation * Vec3::new(a * (cos - e), b * sin, 0.0)
+            })
+            .collect()
+    }
+
+    /// Elements of a body with the given position and velocity relative to the primary,
+    /// `None` if it isn't bound to it
+    pub fn from_state(pos: Vec3, vel: Vec3, mu: f32) -> Option<Self> {
+        const EPSILON: f32 = 1e-6;
+        let r = pos.length();
+        let energy = specific_orbital_energy(pos, vel, mu);
+        let momentum = pos.cross(vel);
+        if energy >= 0.0 || momentum.length() == 0.0 {
+            return None;
+        }
+        let normal = momentum.normalize();
+        let semi_major_axis = -mu / (2.0 * energy);
+        let eccentricity_vec = ((vel.length_squared() - mu / r) * pos - pos.dot(vel) * vel) / mu;
+        let eccentricity = eccentricity_vec.length();
+
+        // Line of nodes, along x for equatorial orbits
+        let node = Vec3::Z.cross(momentum);
+        let node = if node.length() > EPSILON * momentum.length() {
+            node.normalize()
+        } else {
+            Vec3::X
+        };
+        // Periapsis, at the node for circular orbits
+        let periapsis = if eccentricity > EPSILON {
+            eccentricity_vec / eccentricity
+        } else {
+            node
+        };
+
+        let true_anomaly = signed_angle(periapsis, pos, normal);
+        let (sin, cos) = true_anomaly.sin_cos();
+        let anomaly = ((1.0 - eccentricity * eccentricity).sqrt() * sin).atan2(eccentricity + cos);
+        Some(Self {
+            semi_major_axis,
+            eccentricity,
+            inclination: normal.z.clamp(-1.0, 1.0).acos(),
+            longitude_of_ascending_node: node.y.atan2(node.x),
+            argument_of_periapsis: signed_angle(node, periapsis, normal),
+            mean_anomaly: (anomaly - eccentricity * anomaly.sin())
+                .rem_euclid(std::f32::consts::TAU),
+        })
+    }
+}
+
+fn move_on_rails(
+    mut rails: Query<(&mut OnRails, &mut Transform, &mut Velocity)>,
+    dt: Res<TimeStep>,
+    primaries: Query<(&Transform, &Velocity), Without<OnRails>>,
+) {
+    for (mut on_rails, mut transform, mut vel) in rails.iter_mut() {
+        let (primary_pos, primary_vel) = match primaries.get(on_rails.primary) {
+            Ok((transform, vel)) => (transform.translation, vel.0),
+            Err(_) => continue,
+        };
+        on_rails.elements = on_rails.elements.advanced(on_rails.mu, dt.0);
+        let (pos, rel_vel) = on_rails.elements.state(on_rails.mu);
+        transform.translation = primary_pos + pos;
+        vel.0 = primary_vel + rel_vel;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::nbody::BodyBundle;
+    use crate::plugins::nbody::DT;
+    use crate::testing::run_system;
+
+    #[test]
+    fn on_rails_body_returns_to_its_start_after_a_period() {
+        const STEPS: usize = 1000;
+        let elements = OrbitalElements {
+            semi_major_axis: 2.0,
+            eccentricity: 0.5,
+            inclination: 0.3,
+            longitude_of_ascending_node: 1.0,
+            argument_of_periapsis: 0.5,
+            mean_anomaly: 0.2,
+        };
+        // G such that the period lasts exactly `STEPS` physics steps
+        let n = std::f32::consts::TAU / (STEPS as f32 * DT);
+        let g = n * n * elements.semi_major_axis.powi(3) / 1001.0;
+
+        let mut world = World::default();
+        world.insert_resource(TimeStep::default());
+        let primary_pos = Vec3::new(1.0, 2.0, 3.0);
+        let primary = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1000.0, primary_pos, Vec3::ZERO))
+            .id();
+        let (bundle, on_rails) = BodyBundle::on_rails_from_elements(
+            1.0,
+            elements,
+            (primary, 1000.0, primary_pos, Vec3::ZERO),
+            g,
+        );
+        assert!((on_rails.elements.period(on_rails.mu) - STEPS as f32 * DT).abs() < 1e-4);
+        let body = world.spawn().insert_bundle(bundle).insert(on_rails).id();
+        let start = world.get::<Transform>(body).unwrap().translation;
+
+        let mut farthest: f32 = 0.0;
+        for _ in 0..STEPS {
+            run_system(&mut world, move_on_rails.system());
+            let pos = world.get::<Transform>(body).unwrap().translation;
+            farthest = farthest.max(pos.distance(start));
+        }
+
+        let end = world.get::<Transform>(body).unwrap().translation;
+        assert!(farthest > 2.0);
+        assert!(end.distance(start) < 1e-3, "{} != {}", end, start);
+    }
+
+    #[test]
+    fn specific_energy_of_circular_and_escape_orbits() {
+        let (mu, r) = (3.0f32, 2.0f32);
+        let pos = Vec3::new(r, 0.0, 0.0);
+        let circular = Vec3::new(0.0, (mu / r).sqrt(), 0.0);
+        let energy = specific_orbital_energy(pos, circular, mu);
+        assert!((energy + mu / (2.0 * r)).abs() < 1e-6, "{}", energy);
+
+        let escape = Vec3::new(0.0, 0.0, (2.0 * mu / r).sqrt());
+        assert!(specific_orbital_energy(pos, escape, mu).abs() < 1e-6);
+        assert!(specific_orbital_energy(pos, 1.1 * escape, mu) > 0.0);
+    }
+}
@@ -0,0 +1,482 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use super::camera_bookmarks::BOOKMARK_KEYS;
+
+/// Logical actions triggered from the keyboard, each bound to a key in `KeyBindings`.
+/// Shift stays the modifier of the actions that have a variant, e.g. Shift + `SelectNext`,
+/// and Ctrl the modifier of the actions sharing their key with another one, see `with_ctrl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    SelectNext,
+    ClearSelection,
+    TogglePinned,
+    ToggleFrozen,
+    ToggleLensing,
+    Launch,
+    ToggleFrameRateCap,
+    SaveDiagnostics,
+    ToggleHeatmap,
+    ToggleGrid,
+    ToggleMeasure,
+    ToggleHillSphere,
+    CycleQuality,
+    ToggleContours,
+    ToggleTrailSpeedColors,
+    ExportTrails,
+    DecreaseGravity,
+    IncreaseGravity,
+    RestoreGravity,
+    FaceOnView,
+    EdgeOnView,
+    ToggleDeflectionRings,
+    KickUp,
+    KickDown,
+    KickLeft,
+    KickRight,
+    TogglePause,
+    StepBackward,
+    StepForward,
+    ToggleInfluence,
+    ToggleDistanceList,
+    ToggleOrbitEllipses,
+    HighlightHeaviest,
+    HighlightFastest,
+    CaptureTrail,
+    ResetScene,
+    ToggleBarycenter,
+    RollLeft,
+    RollRight,
+    ShowHelp,
+}
+
+impl Action {
+    pub const ALL: [Action; 40] = [
+        Action::SelectNext,
+        Action::ClearSelection,
+        Action::TogglePinned,
+        Action::ToggleFrozen,
+        Action::ToggleLensing,
+        Action::Launch,
+        Action::ToggleFrameRateCap,
+        Action::SaveDiagnostics,
+        Action::ToggleHeatmap,
+        Action::ToggleGrid,
+        Action::ToggleMeasure,
+        Action::ToggleHillSphere,
+        Action::CycleQuality,
+        Action::ToggleContours,
+        Action::ToggleTrailSpeedColors,
+        Action::ExportTrails,
+        Action::DecreaseGravity,
+        Action::IncreaseGravity,
+        Action::RestoreGravity,
+        Action::FaceOnView,
+        Action::EdgeOnView,
+        Action::ToggleDeflectionRings,
+        Action::KickUp,
+        Action::KickDown,
+        Action::KickLeft,
+        Action::KickRight,
+        Action::TogglePause,
+        Action::StepBackward,
+        Action::StepForward,
+        Action::ToggleInfluence,
+        Action::ToggleDistanceList,
+        Action::ToggleOrbitEllipses,
+        Action::HighlightHeaviest,
+        Action::HighlightFastest,
+        Action::CaptureTrail,
+        Action::ResetScene,
+        Action::ToggleBarycenter,
+        Action::RollLeft,
+        Action::RollRight,
+        Action::ShowHelp,
+    ];
+
+    pub fn default_key(self) -> KeyCode {
+        match self {
+            Action::SelectNext => KeyCode::Tab,
+            Action::ClearSelection => KeyCode::Escape,
+            Action::TogglePinned => KeyCode::P,
+            Action::ToggleFrozen => KeyCode::Z,
+            Action::ToggleLensing => KeyCode::L,
+            Action::Launch => KeyCode::Space,
+            Action::ToggleFrameRateCap => KeyCode::F,
+            Action::SaveDiagnostics => KeyCode::R,
+            Action::ToggleHeatmap => KeyCode::F2,
+            Action::ToggleGrid => KeyCode::G,
+            Action::ToggleMeasure => KeyCode::M,
+            Action::ToggleHillSphere => KeyCode::I,
+            Action::CycleQuality => KeyCode::Q,
+            Action::ToggleContours => KeyCode::C,
+            Action::ToggleTrailSpeedColors => KeyCode::V,
+            Action::ExportTrails => KeyCode::X,
+            Action::DecreaseGravity => KeyCode::LBracket,
+            Action::IncreaseGravity => KeyCode::RBracket,
+            Action::RestoreGravity => KeyCode::Back,
+            Action::FaceOnView => KeyCode::O,
+            Action::EdgeOnView => KeyCode::E,
+            Action::ToggleDeflectionRings => KeyCode::D,
+            Action::KickUp => KeyCode::Up,
+            Action::KickDown => KeyCode::Down,
+            Action::KickLeft => KeyCode::Left,
+            Action::KickRight => KeyCode::Right,
+            Action::TogglePause => KeyCode::K,
+            Action::StepBackward => KeyCode::Comma,
+            Action::StepForward => KeyCode::Period,
+            Action::ToggleInfluence => KeyCode::B,
+            Action::ToggleDistanceList => KeyCode::N,
+            Action::ToggleOrbitEllipses => KeyCode::J,
+            Action::HighlightHeaviest => KeyCode::T,
+            Action::HighlightFastest => KeyCode::Y,
+            Action::CaptureTrail => KeyCode::A,
+            Action::ResetScene => KeyCode::F5,
+            Action::ToggleBarycenter => KeyCode::U,
+            Action::RollLeft => KeyCode::Q,
+            Action::RollRight => KeyCode::E,
+            Action::ShowHelp => KeyCode::H,
+        }
+    }
+
+    /// Whether the action is triggered by its key with Ctrl held, e.g. Ctrl + Q to roll the camera
+    /// while Q alone cycles the quality presets
+    pub fn with_ctrl(self) -> bool {
+        matches!(self, Action::RollLeft | Action::RollRight)
+    }
+
+    /// Kebab case name used on the command line, e.g. `toggle-pause`
+    pub fn name(self) -> String {
+        let mut name = String::new();
+        for (i, c) in format!("{:?}", self).chars().enumerate() {
+            if c.is_ascii_uppercase() && i > 0 {
+                name.push('-');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+        name
+    }
+}
+
+impl std::str::FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| format!("Unknown action {}", s))
+    }
+}
+
+/// Keys that actions can be bound to
+const BINDABLE_KEYS: [KeyCode; 75] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Escape,
+    KeyCode::Tab,
+    KeyCode::Space,
+    KeyCode::Back,
+    KeyCode::Return,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Backslash,
+    KeyCode::Semicolon,
+    KeyCode::Apostrophe,
+    KeyCode::Grave,
+    KeyCode::Minus,
+    KeyCode::Equals,
+    KeyCode::LBracket,
+    KeyCode::RBracket,
+    KeyCode::Pause,
+];
+
+/// Key named as in `KeyCode`, case insensitive, e.g. `f5`, `PageUp` or `LBracket`
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .copied()
+        .find(|key| format!("{:?}", key).eq_ignore_ascii_case(name))
+}
+
+/// Remapping of an action given on the command line as `<action>=<key>`, e.g. `toggle-pause=pause`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRemap {
+    pub action: Action,
+    pub key: KeyCode,
+}
+
+impl std::str::FromStr for KeyRemap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (action, key) = s
+            .split_once('=')
+            .ok_or_else(|| String::from("Invalid input. Should be: <action>=<key>"))?;
+        Ok(Self {
+            action: action.parse()?,
+            key: parse_key(key).ok_or_else(|| format!("Unknown key {}", key))?,
+        })
+    }
+}
+
+/// Key of each action, consulted by the input systems instead of fixed keys,
+/// and the description of the actions registered by the plugins for the help
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    keys: HashMap<Action, KeyCode>,
+    /// In the order they were registered
+    descriptions: Vec<(Action, &'static str)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: Action::ALL
+                .iter()
+                .map(|action| (*action, action.default_key()))
+                .collect(),
+            descriptions: Vec::new(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys
+            .get(&action)
+            .copied()
+            .unwrap_or_else(|| action.default_key())
+    }
+
+    /// Binds `action` to `key`, returning the other actions already bound to it
+    pub fn remap(&mut self, action: Action, key: KeyCode) -> Vec<Action> {
+        self.keys.insert(action, key);
+        Action::ALL
+            .iter()
+            .copied()
+            .filter(|other| *other != action && self.conflict(action, *other))
+            .collect()
+    }
+
+    /// Whether both actions are triggered by the same key and modifier
+    fn conflict(&self, action: Action, other: Action) -> bool {
+        self.key(action) == self.key(other) && action.with_ctrl() == other.with_ctrl()
+    }
+
+    /// Key of the action as shown in the help, e.g. `Ctrl + Q`
+    fn key_name(&self, action: Action) -> String {
+        if action.with_ctrl() {
+            format!("Ctrl + {:?}", self.key(action))
+        } else {
+            format!("{:?}", self.key(action))
+        }
+    }
+
+    /// Whether Ctrl is held as the action expects, the actions without modifier being left to those
+    /// with Ctrl on the same key while it is held
+    fn modifier_matches(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        let ctrl = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+        if action.with_ctrl() {
+            ctrl
+        } else {
+            let key = self.key(action);
+            !ctrl
+                || !Action::ALL
+                    .iter()
+                    .any(|other| other.with_ctrl() && self.key(*other) == key)
+        }
+    }
+
+    pub fn pressed(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        keys.pressed(self.key(action)) && self.modifier_matches(keys, action)
+    }
+
+    pub fn just_pressed(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        keys.just_pressed(self.key(action)) && self.modifier_matches(keys, action)
+    }
+
+    pub fn just_released(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        keys.just_released(self.key(action)) && self.modifier_matches(keys, action)
+    }
+
+    pub fn describe(&mut self, action: Action, description: &'static str) {
+        self.descriptions.push((action, description));
+    }
+
+    /// Described actions with their current key
+    pub fn bindings(&self) -> impl Iterator<Item = (Action, KeyCode, &'static str)> + '_ {
+        self.descriptions
+            .iter()
+            .map(move |(action, description)| (*action, self.key(*action), *description))
+    }
+
+    /// One line per described action, e.g. `* Tab: select the next body`,
+    /// flagging the keys shared with other actions or with the camera bookmarks
+    pub fn help_text(&self) -> String {
+        self.bindings()
+            .map(|(action, key, description)| {
+                let mut line = format!("* {}: {}", self.key_name(action), description);
+                let others: Vec<String> = Action::ALL
+                    .iter()
+                    .filter(|other| **other != action && self.conflict(action, **other))
+                    .map(|other| other.name())
+                    .collect();
+                if !others.is_empty() {
+                    line += &format!(" (conflicts with {})", others.join(", "));
+                }
+                if BOOKMARK_KEYS.contains(&key) {
+                    line += " (conflicts with the camera bookmarks)";
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Describes the actions of a plugin in the `KeyBindings`, for them to show in the help
+pub trait RegisterKeyBinding {
+    fn register_key_binding(&mut self, action: Action, description: &'static str) -> &mut Self;
+}
+
+impl RegisterKeyBinding for AppBuilder {
+    fn register_key_binding(&mut self, action: Action, description: &'static str) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(KeyBindings::default)
+            .describe(action, description);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remaps_are_parsed_from_the_command_line() {
+        assert_eq!(
+            "toggle-pause=pause".parse::<KeyRemap>(),
+            Ok(KeyRemap {
+                action: Action::TogglePause,
+                key: KeyCode::Pause
+            })
+        );
+        assert_eq!(
+            "Launch=f5".parse::<KeyRemap>().map(|remap| remap.key),
+            Ok(KeyCode::F5)
+        );
+        assert!("toggle-pause".parse::<KeyRemap>().is_err());
+        assert!("fly=k".parse::<KeyRemap>().is_err());
+        assert!("launch=LShift".parse::<KeyRemap>().is_err());
+        for action in Action::ALL.iter() {
+            assert_eq!(action.name().parse::<Action>(), Ok(*action));
+        }
+    }
+
+    #[test]
+    fn remapping_reports_the_actions_bound_to_the_same_key() {
+        let mut bindings = KeyBindings::default();
+        assert_eq!(
+            bindings.remap(Action::Launch, KeyCode::K),
+            vec![Action::TogglePause]
+        );
+        assert_eq!(bindings.key(Action::Launch), KeyCode::K);
+        assert!(bindings.remap(Action::Launch, KeyCode::Pause).is_empty());
+    }
+
+    #[test]
+    fn default_keys_are_unique_and_bindable() {
+        let bindings = KeyBindings::default();
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let key = action.default_key();
+            assert!(BINDABLE_KEYS.contains(&key), "{:?}", action);
+            assert!(
+                Action::ALL[i + 1..]
+                    .iter()
+                    .all(|other| !bindings.conflict(*action, *other)),
+                "{:?}",
+                action
+            );
+        }
+    }
+
+    #[test]
+    fn ctrl_actions_take_over_their_key_while_ctrl_is_held() {
+        let bindings = KeyBindings::default();
+        let mut keys = Input::<KeyCode>::default();
+        keys.press(KeyCode::Q);
+        assert!(bindings.just_pressed(&keys, Action::CycleQuality));
+        assert!(!bindings.pressed(&keys, Action::RollLeft));
+
+        keys.press(KeyCode::LControl);
+        assert!(!bindings.just_pressed(&keys, Action::CycleQuality));
+        assert!(bindings.pressed(&keys, Action::RollLeft));
+        // Ctrl doesn't get in the way of the keys without a Ctrl action
+        keys.press(KeyCode::Space);
+        assert!(bindings.just_pressed(&keys, Action::Launch));
+
+        assert_eq!(bindings.key_name(Action::RollRight), "Ctrl + E");
+        assert_eq!(bindings.key_name(Action::EdgeOnView), "E");
+    }
+}
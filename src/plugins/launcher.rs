@@ -0,0 +1,209 @@
+use bevy::prelude::*;
+
+use super::collision::Radius;
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{BodyBundle, Mass};
+use super::pan_orbit_camera::PanOrbitCamera;
+use super::quality::QualityPreset;
+use super::trail::Trail;
+
+/// Shoots a new body from the camera along the view direction to perturb the system:
+/// * Space: launch a projectile
+///
+/// Past `MaxBodies`, the oldest projectiles are despawned to make room for the new ones,
+/// the bodies of the scene are never evicted.
+pub struct LauncherPlugin;
+
+impl Plugin for LauncherPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(Action::Launch, "launch a projectile from the camera")
+            .init_resource::<Launcher>()
+            .init_resource::<MaxBodies>()
+            .init_resource::<SpawnCounter>()
+            .add_system(launch_projectile.system());
+    }
+}
+
+/// Settings of the launched projectiles
+pub struct Launcher {
+    /// Initial speed, in the units of the scene
+    pub speed: f32,
+    pub mass: f32,
+    pub radius: f32,
+    pub color: Color,
+}
+
+impl Default for Launcher {
+    fn default() -> Self {
+        Self {
+            speed: 10.0,
+            mass: 1.0,
+            radius: 0.2,
+            color: Color::WHITE,
+        }
+    }
+}
+
+/// Maximum number of bodies in the simulation, scene bodies included, unlimited when `None`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MaxBodies(pub Option<usize>);
+
+/// Number of bodies spawned by the user so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SpawnCounter(pub u64);
+
+/// Rank of a body spawned by the user, the lowest one being the oldest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SpawnOrder(pub u64);
+
+/// User-spawned bodies to despawn before spawning `count` new ones among `total` bodies,
+/// oldest first, given the `(body, rank)` of the user-spawned ones.
+/// Fewer bodies than needed are returned when the user-spawned ones don't suffice.
+pub fn bodies_to_evict<T: Copy>(
+    total: usize,
+    count: usize,
+    limit: Option<usize>,
+    spawned: &[(T, SpawnOrder)],
+) -> Vec<T> {
+    let excess = match limit {
+        Some(limit) => (total + count).saturating_sub(limit),
+        None => 0,
+    };
+    let mut spawned = spawned.to_vec();
+    spawned.sort_by_key(|(_, order)| *order);
+    spawned.iter().take(excess).map(|(body, _)| *body).collect()
+}
+
+/// Velocity of a projectile launched at `speed` from a camera with the given `transform`,
+/// i.e. along its forward direction `-Z`
+pub fn launch_velocity(transform: &Transform, speed: f32) -> Vec3 {
+    transform.rotation * -Vec3::Z * speed
+}
+
+#[allow(clippy::too_many_arguments)]
+fn launch_projectile(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    launcher: Res<Launcher>,
+    max_bodies: Res<MaxBodies>,
+    mut counter: ResMut<SpawnCounter>,
+    quality: Res<QualityPreset>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    cameras: Query<&Transform, With<PanOrbitCamera>>,
+    bodies: Query<Entity, With<Mass>>,
+    spawned: Query<(Entity, &SpawnOrder)>,
+) {
+    if !bindings.just_pressed(&keys, Action::Launch) {
+        return;
+    }
+    let camera = match cameras.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+
+    let spawned: Vec<(Entity, SpawnOrder)> = spawned
+        .iter()
+        .map(|(entity, order)| (entity, *order))
+        .collect();
+    let total = bodies.iter().count();
+    let evicted = bodies_to_evict(total, 1, max_bodies.0, &spawned);
+    match max_bodies.0 {
+        Some(limit) if total - evicted.len() >= limit => {
+            warn!("The scene alone reaches the limit of {} bodies", limit);
+            return;
+        }
+        _ => {}
+    }
+    for body in evicted {
+        commands.entity(body).despawn();
+    }
+
+    counter.0 += 1;
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Icosphere {
+                radius: launcher.radius,
+                subdivisions: quality.subdivisions().min(3),
+            })),
+            material: materials.add(launcher.color.into()),
+            ..Default::default()
+        })
+        .insert_bundle(BodyBundle::new(
+            launcher.mass,
+            camera.translation,
+            launch_velocity(camera, launcher.speed),
+        ))
+        .insert(Radius(launcher.radius))
+        .insert(Trail::new(300))
+        .insert(SpawnOrder(counter.0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{run_system, world_with_assets};
+
+    #[test]
+    fn projectiles_are_launched_along_the_view_direction() {
+        let camera = Transform::from_xyz(10.0, 0.0, 0.0).looking_at(Vec3::ZERO, Vec3::Y);
+        let velocity = launch_velocity(&camera, 3.0);
+        assert!((velocity - Vec3::new(-3.0, 0.0, 0.0)).length() < 1e-5);
+
+        let camera = Transform::from_xyz(0.0, 5.0, 5.0).looking_at(Vec3::ZERO, Vec3::Y);
+        let velocity = launch_velocity(&camera, 2.0);
+        let expected = -Vec3::new(0.0, 1.0, 1.0).normalize() * 2.0;
+        assert!((velocity - expected).length() < 1e-5);
+    }
+
+    #[test]
+    fn launching_past_the_limit_evicts_the_oldest_projectile() {
+        let mut world = world_with_assets();
+        world.insert_resource(KeyBindings::default());
+        world.insert_resource(Launcher::default());
+        world.insert_resource(MaxBodies(Some(3)));
+        world.insert_resource(SpawnCounter::default());
+        world.insert_resource(QualityPreset::default());
+        world
+            .spawn()
+            .insert_bundle((PanOrbitCamera::default(), Transform::identity()));
+        let scene_body = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::ZERO, Vec3::ZERO))
+            .id();
+
+        for _ in 0..4 {
+            let mut keys = Input::<KeyCode>::default();
+            keys.press(KeyCode::Space);
+            world.insert_resource(keys);
+            run_system(&mut world, launch_projectile.system());
+        }
+        let mut orders: Vec<u64> = world
+            .query::<&SpawnOrder>()
+            .iter(&world)
+            .map(|order| order.0)
+            .collect();
+        orders.sort_unstable();
+
+        // The scene body is exempt, the 2 most recent projectiles remain
+        assert_eq!(world.query::<&Mass>().iter(&world).count(), 3);
+        assert!(world.get::<Mass>(scene_body).is_some());
+        assert_eq!(orders, vec![3, 4]);
+    }
+
+    #[test]
+    fn oldest_spawned_bodies_are_evicted_first() {
+        let spawned = [
+            ("b", SpawnOrder(2)),
+            ("a", SpawnOrder(1)),
+            ("c", SpawnOrder(3)),
+        ];
+        assert_eq!(bodies_to_evict(5, 1, Some(5), &spawned), vec!["a"]);
+        assert_eq!(bodies_to_evict(5, 2, Some(5), &spawned), vec!["a", "b"]);
+        assert!(bodies_to_evict(3, 1, Some(5), &spawned).is_empty());
+        assert!(bodies_to_evict(50, 1, None, &spawned).is_empty());
+        // Not enough spawned bodies to make room
+        assert_eq!(bodies_to_evict(10, 1, Some(5), &spawned).len(), 3);
+    }
+}
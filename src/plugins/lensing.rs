@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+use bevy::render::camera::{Camera, PerspectiveProjection};
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{BodyClass, Mass};
+
+/// Fake gravitational lensing: the background around the stars is pushed away
+/// from their projected centers as if light was bent by their gravity.
+/// Purely cosmetic, it doesn't affect the simulation.
+/// * L: toggle the effect
+pub struct LensingPlugin;
+
+impl Plugin for LensingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::ToggleLensing,
+            "toggle the gravitational lensing effect around the stars",
+        )
+        .init_resource::<LensingEffect>()
+        .add_system(toggle_lensing.system())
+        .add_system(distort_background.system());
+    }
+}
+
+pub struct LensingEffect {
+    pub enabled: bool,
+    /// Einstein radius of the most massive star, in normalized device coordinates
+    pub strength: f32,
+}
+
+impl Default for LensingEffect {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            strength: 0.1,
+        }
+    }
+}
+
+/// Background element distorted by the lensing, e.g. a star of the starfield
+pub struct LensedBackground {
+    /// Undistorted position
+    pub position: Vec3,
+}
+
+/// A lens in screen space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lens {
+    /// Projected center, in normalized device coordinates
+    pub center: Vec2,
+    /// Einstein radius, in normalized device coordinates
+    pub radius: f32,
+}
+
+/// Normalized device coordinates of `position` (x and y in [-1, 1] on screen, z the depth),
+/// `None` if it's behind the camera
+pub fn project(view_proj: Mat4, position: Vec3) -> Option<Vec3> {
+    let clip = view_proj * position.extend(1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    Some(clip.truncate() / clip.w)
+}
+
+/// Inverse of `project`
+pub fn unproject(view_proj: Mat4, ndc: Vec3) -> Vec3 {
+    let world = view_proj.inverse() * ndc.extend(1.0);
+    world.truncate() / world.w
+}
+
+/// Einstein radius of a lens of the given `mass`, which is proportional to the square root of its mass.
+/// The most massive star gets a radius of `strength`.
+pub fn lens_radius(mass: f32, max_mass: f32, strength: f32) -> f32 {
+    if max_mass <= 0.0 {
+        return 0.0;
+    }
+    strength * (mass / max_mass).max(0.0).sqrt()
+}
+
+/// Point lens equation: a source at a distance `r` of the lens appears at
+/// ```text
+/// r' = (r + sqrt(r^2 + 4*θE^2)) / 2
+/// ```
+/// where `θE` is the Einstein radius of the lens.
+/// `aspect_ratio` accounts for the horizontal stretch of the normalized device coordinates.
+pub fn lensed_position(source: Vec2, lens: &Lens, aspect_ratio: f32) -> Vec2 {
+    let stretch = Vec2::new(aspect_ratio, 1.0);
+    let offset = (source - lens.center) * stretch;
+    let r = offset.length();
+    if r == 0.0 {
+        return source;
+    }
+    let lensed_r = (r + (r * r + 4.0 * lens.radius * lens.radius).sqrt()) / 2.0;
+    lens.center + offset * (lensed_r / r) / stretch
+}
+
+fn toggle_lensing(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut effect: ResMut<LensingEffect>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleLensing) {
+        effect.enabled = !effect.enabled;
+    }
+}
+
+fn distort_background(
+    effect: Res<LensingEffect>,
+    cameras: Query<(&Camera, &GlobalTransform, &PerspectiveProjection)>,
+    stars: Query<(&BodyClass, &Mass, &Transform), Without<LensedBackground>>,
+    mut background: Query<(&LensedBackground, &mut Transform)>,
+) {
+    if !effect.enabled {
+        if effect.is_changed() {
+            for (lensed, mut transform) in background.iter_mut() {
+                transform.translation = lensed.position;
+            }
+        }
+        return;
+    }
+    let (view_proj, aspect_ratio) = match cameras.iter().next() {
+        Some((camera, transform, projection)) => (
+            camera.projection_matrix * transform.compute_matrix().inverse(),
+            projection.aspect_ratio,
+        ),
+        None => return,
+    };
+
+    let stars: Vec<(f32, Vec3)> = stars
+        .iter()
+        .filter(|(class, _, _)| **class == BodyClass::Star)
+        .map(|(_, mass, transform)| (mass.0, transform.translation))
+        .collect();
+    let max_mass = stars.iter().map(|(mass, _)| *mass).fold(0.0, f32::max);
+    let lenses: Vec<Lens> = stars
+        .iter()
+        .filter_map(|(mass, position)| {
+            project(view_proj, *position).map(|center| Lens {
+                center: center.truncate(),
+                radius: lens_radius(*mass, max_mass, effect.strength),
+            })
+        })
+        .collect();
+
+    for (lensed, mut transform) in background.iter_mut() {
+        transform.translation = match project(view_proj, lensed.position) {
+            Some(ndc) => {
+                let image = lenses.iter().fold(ndc.truncate(), |image, lens| {
+                    lensed_position(image, lens, aspect_ratio)
+                });
+                unproject(view_proj, image.extend(ndc.z))
+            }
+            None => lensed.position,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::camera::CameraProjection;
+
+    use super::*;
+
+    #[test]
+    fn lens_of_a_star_seen_from_a_known_camera() {
+        let projection = PerspectiveProjection {
+            fov: std::f32::consts::FRAC_PI_2,
+            aspect_ratio: 1.0,
+            ..Default::default()
+        };
+        let camera = Transform::from_xyz(0.0, 0.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y);
+        let view_proj = projection.get_projection_matrix() * camera.compute_matrix().inverse();
+
+        // With a 90° field of view, the edge of the screen is as far from its center as the camera
+        let center = project(view_proj, Vec3::new(5.0, 0.0, 0.0)).unwrap();
+        assert!(center.truncate().abs_diff_eq(Vec2::new(0.5, 0.0), 1e-5));
+        assert!(project(view_proj, Vec3::new(0.0, 0.0, 20.0)).is_none());
+        let back = unproject(view_proj, center);
+        assert!(back.abs_diff_eq(Vec3::new(5.0, 0.0, 0.0), 1e-3));
+
+        // A quarter of the heaviest mass gets half its radius
+        let lens = Lens {
+            center: center.truncate(),
+            radius: lens_radius(1.0, 4.0, 0.2),
+        };
+        assert!((lens.radius - 0.1).abs() < 1e-6);
+
+        // A source on the Einstein ring appears pushed away by the golden ratio
+        let source = lens.center + Vec2::new(0.0, 0.1);
+        let image = lensed_position(source, &lens, 1.0);
+        let golden = (1.0 + 5.0f32.sqrt()) / 2.0;
+        assert!(image.abs_diff_eq(lens.center + Vec2::new(0.0, 0.1 * golden), 1e-5));
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+
+use super::nbody::{
+    ExternalField, ForceCutoff, Gravity, Integrator, Mass, PhysicsStep, PhysicsSystem,
+    SimulationTime, SofteningKernel, TimeStep, Velocity,
+};
+use crate::headless::{ForceParams, LyapunovRun, SystemState};
+
+/// Estimate of the largest Lyapunov exponent of the scene, logged every second:
+/// the scene and a perturbed shadow copy of it are integrated headlessly along the simulation,
+/// see `LyapunovRun`. Both restart from the bodies of the simulation when their number changes,
+/// e.g. after a collision or a launch.
+pub struct LyapunovPlugin;
+
+impl Plugin for LyapunovPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<LyapunovEstimator>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(PhysicsStep)
+                    .with_system(step_shadow.system().after(PhysicsSystem::Movement)),
+            )
+            .add_system(log_exponent.system());
+    }
+}
+
+pub struct LyapunovEstimator {
+    pub enabled: bool,
+    /// Number of steps between two renormalizations of the shadow copy
+    pub renorm_interval: usize,
+    /// Offset of the shadow copy, small enough for its separation to grow exponentially,
+    /// large enough not to be swamped by the rounding errors
+    pub initial_delta: f32,
+    run: Option<LyapunovRun>,
+    steps: usize,
+    timer: Timer,
+}
+
+impl Default for LyapunovEstimator {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            renorm_interval: 100,
+            initial_delta: 1e-4,
+            run: None,
+            steps: 0,
+            timer: Timer::from_seconds(1.0, true),
+        }
+    }
+}
+
+impl LyapunovEstimator {
+    pub fn new(renorm_interval: usize, initial_delta: f32) -> Self {
+        Self {
+            enabled: true,
+            renorm_interval,
+            initial_delta,
+            ..Default::default()
+        }
+    }
+
+    /// Running estimate of the exponent, `None` until the shadow copy is first renormalized
+    pub fn exponent(&self) -> Option<f32> {
+        self.run.as_ref().and_then(LyapunovRun::exponent)
+    }
+
+    /// Steps integrated since the estimation last (re)started
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn step_shadow(
+    mut estimator: ResMut<LyapunovEstimator>,
+    g: Res<Gravity>,
+    softening: Res<SofteningKernel>,
+    cutoff: Res<ForceCutoff>,
+    external: Res<ExternalField>,
+    integrator: Res<Integrator>,
+    dt: Res<TimeStep>,
+    bodies: Query<(&Mass, &Transform, &Velocity)>,
+) {
+    if !estimator.enabled {
+        return;
+    }
+    let len = bodies.iter().count();
+    if len == 0 {
+        return;
+    }
+    if estimator.run.as_ref().map(LyapunovRun::len) != Some(len) {
+        let mut state = SystemState::default();
+        for (mass, transform, vel) in bodies.iter() {
+            state.push(mass.0, transform.translation, vel.0);
+        }
+        if estimator.run.is_some() {
+            info!("Lyapunov exponent estimation restarted with {} bodies", len);
+        }
+        estimator.run = Some(LyapunovRun::new(state, estimator.initial_delta));
+        estimator.steps = 0;
+        return;
+    }
+
+    let params = ForceParams {
+        g: g.0,
+        softening: *softening,
+        cutoff: cutoff.0,
+        external: *external,
+    };
+    let estimator = &mut *estimator;
+    if let Some(run) = estimator.run.as_mut() {
+        run.step(&params, *integrator, dt.0);
+        estimator.steps += 1;
+        if estimator
+            .steps
+            .is_multiple_of(estimator.renorm_interval.max(1))
+        {
+            run.renormalize();
+        }
+    }
+}
+
+fn log_exponent(
+    time: Res<Time>,
+    sim_time: Res<SimulationTime>,
+    mut estimator: ResMut<LyapunovEstimator>,
+) {
+    if !estimator.enabled || !estimator.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    if let Some(exponent) = estimator.exponent() {
+        info!(
+            "Largest Lyapunov exponent at t = {:.2}: {:.4}",
+            sim_time.0, exponent
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use bevy::prelude::*;
+use bevy::render::camera::Camera;
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::lensing::unproject;
+use super::pan_orbit_camera::PanOrbitCamera;
+use super::prediction::line_mesh;
+
+/// Distance between two points clicked on the focal plane of the camera:
+/// * M: toggle the measurement mode
+/// * left click (measurement mode): pick a point
+pub struct MeasurePlugin;
+
+impl Plugin for MeasurePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::ToggleMeasure,
+            "toggle the measurement mode, left-click two points to measure their distance",
+        )
+        .init_resource::<MeasureState>()
+        .init_resource::<UnitSystem>()
+        .add_startup_system(spawn_measure_line.system())
+        .add_system(toggle_measure.system())
+        .add_system(pick_point.system())
+        .add_system(draw_measure.system());
+    }
+}
+
+#[derive(Default)]
+pub struct MeasureState {
+    pub enabled: bool,
+    /// Picked points, the second one is cleared when picking a new first point
+    pub points: [Option<Vec3>; 2],
+}
+
+/// Physical unit of the distances, for a scene rendered at some scale, and of the simulated time
+pub struct UnitSystem {
+    pub length_unit: String,
+    /// Render units per length unit
+    pub render_scale: f32,
+    /// Unit of the simulated time of the scene, `None` for the dimensionless scenes
+    pub time_unit: Option<TimeUnit>,
+    /// Unit the simulated time is displayed in, the `time_unit` of the scene when `None`
+    pub display_time_unit: Option<TimeUnit>,
+}
+
+impl Default for UnitSystem {
+    fn default() -> Self {
+        Self {
+            length_unit: String::from("units"),
+            render_scale: 1.0,
+            time_unit: None,
+            display_time_unit: None,
+        }
+    }
+}
+
+impl UnitSystem {
+    /// Simulated `time` in the display unit with its symbol, e.g. `11.86 yr`,
+    /// or as is for the dimensionless scenes
+    pub fn format_time(&self, time: f32) -> String {
+        match (self.time_unit, self.display_time_unit.or(self.time_unit)) {
+            (Some(unit), Some(display)) => {
+                format!(
+                    "{:.2} {}",
+                    convert_time(time, unit, display),
+                    display.symbol()
+                )
+            }
+            _ => format!("{:.2}", time),
+        }
+    }
+}
+
+/// Unit of time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Seconds,
+    Hours,
+    Days,
+    /// Julian year of 365.25 days
+    Years,
+}
+
+impl TimeUnit {
+    pub fn seconds(&self) -> f64 {
+        match self {
+            TimeUnit::Seconds => 1.0,
+            TimeUnit::Hours => 3600.0,
+            TimeUnit::Days => 86_400.0,
+            TimeUnit::Years => 365.25 * 86_400.0,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TimeUnit::Seconds => "s",
+            TimeUnit::Hours => "h",
+            TimeUnit::Days => "d",
+            TimeUnit::Years => "yr",
+        }
+    }
+}
+
+impl std::str::FromStr for TimeUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "seconds" | "s" => Ok(Self::Seconds),
+            "hours" | "h" => Ok(Self::Hours),
+            "days" | "d" => Ok(Self::Days),
+            "years" | "yr" => Ok(Self::Years),
+            _ => Err(String::from(
+                "Invalid input. Should be: seconds, hours, days or years",
+            )),
+        }
+    }
+}
+
+/// `time` in the unit `from` converted to the unit `to`
+pub fn convert_time(time: f32, from: TimeUnit, to: TimeUnit) -> f32 {
+    (time as f64 * from.seconds() / to.seconds()) as f32
+}
+
+/// Distance between `a` and `b`, in the length unit of `units`
+pub fn measured_distance(a: Vec3, b: Vec3, units: &UnitSystem) -> f32 {
+    a.distance(b) / units.render_scale
+}
+
+/// Intersection of the ray going through `cursor` (in normalized device coordinates)
+/// with the plane through `focus` facing the camera
+pub fn pick_on_focal_plane(
+    view_proj: Mat4,
+    cursor: Vec2,
+    focus: Vec3,
+    forward: Vec3,
+) -> Option<Vec3> {
+    let near = unproject(view_proj, cursor.extend(0.0));
+    let far = unproject(view_proj, cursor.extend(0.5));
+    let direction = (far - near).try_normalize()?;
+    let denominator = direction.dot(forward);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let t = (focus - near).dot(forward) / denominator;
+    if t < 0.0 {
+        return None;
+    }
+    Some(near + t * direction)
+}
+
+struct MeasureLine;
+
+fn spawn_measure_line(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(line_mesh(vec![[0.0; 3]; 2])),
+            material: materials.add(StandardMaterial {
+                base_color: Color::CYAN,
+                unlit: true,
+                ..Default::default()
+            }),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: false,
+            },
+            ..Default::default()
+        })
+        .insert(MeasureLine);
+}
+
+fn toggle_measure(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut state: ResMut<MeasureState>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleMeasure) {
+        state.enabled = !state.enabled;
+        state.points = [None, None];
+    }
+}
+
+fn pick_point(
+    windows: Res<Windows>,
+    mouse: Res<Input<MouseButton>>,
+    units: Res<UnitSystem>,
+    mut state: ResMut<MeasureState>,
+    cameras: Query<(&Camera, &GlobalTransform, &PanOrbitCamera)>,
+) {
+    if !state.enabled || !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let (cursor, (camera, transform, pan_orbit)) =
+        match (window.cursor_position(), cameras.iter().next()) {
+            (Some(cursor), Some(camera)) => (cursor, camera),
+            _ => return,
+        };
+
+    // The cursor position starts at the bottom left of the window
+    let ndc = 2.0 * cursor / Vec2::new(window.width(), window.height()) - Vec2::ONE;
+    let view_proj = camera.projection_matrix * transform.compute_matrix().inverse();
+    let forward = transform.rotation * -Vec3::Z;
+    let point = match pick_on_focal_plane(view_proj, ndc, pan_orbit.focus, forward) {
+        Some(point) => point,
+        None => return,
+    };
+
+    state.points = match state.points {
+        [Some(first), None] => [Some(first), Some(point)],
+        _ => [Some(point), None],
+    };
+    if let [Some(a), Some(b)] = state.points {
+        info!(
+            "Distance: {:.4} {}",
+            measured_distance(a, b, &units),
+            units.length_unit
+        );
+    }
+}
+
+fn draw_measure(
+    state: Res<MeasureState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<(&Handle<Mesh>, &mut Visible), With<MeasureLine>>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    for (mesh, mut visible) in query.iter_mut() {
+        visible.is_visible = false;
+        if let (true, [Some(a), Some(b)]) = (state.enabled, state.points) {
+            if let Some(mesh) = meshes.get_mut(mesh) {
+                *mesh = line_mesh(vec![a.into(), b.into()]);
+                visible.is_visible = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances_are_measured_in_the_length_unit() {
+        let a = Vec3::new(10.0, 0.0, 0.0);
+        let b = Vec3::new(10.0, 30.0, 40.0);
+        assert_eq!(measured_distance(a, b, &UnitSystem::default()), 50.0);
+
+        // The Solar System is rendered at 10 units per AU
+        let solar = UnitSystem {
+            length_unit: String::from("AU"),
+            render_scale: 10.0,
+            ..Default::default()
+        };
+        assert_eq!(measured_distance(a, b, &solar), 5.0);
+    }
+
+    #[test]
+    fn times_are_displayed_in_the_display_unit() {
+        assert_eq!(convert_time(36.0, TimeUnit::Hours, TimeUnit::Days), 1.5);
+        let units = UnitSystem {
+            time_unit: Some(TimeUnit::Days),
+            display_time_unit: Some(TimeUnit::Years),
+            ..Default::default()
+        };
+        assert_eq!(units.format_time(730.5), "2.00 yr");
+        assert_eq!(UnitSystem::default().format_time(1.234_5), "1.23");
+    }
+
+    #[test]
+    fn seconds_are_converted_to_years() {
+        assert_eq!(
+            convert_time(31_557_600.0, TimeUnit::Seconds, TimeUnit::Years),
+            1.0
+        );
+        // Orbital period of Jupiter
+        let units = UnitSystem {
+            time_unit: Some(TimeUnit::Seconds),
+            display_time_unit: Some("years".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(units.format_time(374_335_776.0), "11.86 yr");
+        assert_eq!("yr".parse(), Ok(TimeUnit::Years));
+        assert!("weeks".parse::<TimeUnit>().is_err());
+    }
+}
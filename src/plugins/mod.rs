@@ -1,2 +1,51 @@
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod axis_gizmo;
+pub mod barnes_hut;
+pub mod barycenter;
+pub mod camera_bookmarks;
+pub mod census;
+pub mod chaos;
+pub mod collision;
+pub mod comet;
+pub mod contours;
+#[cfg(feature = "ui")]
+pub mod dashboard;
+pub mod deflection;
+pub mod diagnostics;
+pub mod distances;
+pub mod encounters;
+pub mod extremes;
+pub mod frame_rate;
+pub mod gravity_control;
+pub mod grid;
+pub mod heatmap;
+pub mod help;
+pub mod hill_sphere;
+pub mod hud;
+pub mod impulse;
+pub mod influence;
+pub mod kepler;
+pub mod key_bindings;
+pub mod launcher;
+pub mod lensing;
+pub mod lyapunov;
+pub mod measure;
+pub mod nbody;
+pub mod orbit_ellipses;
+pub mod orbit_info;
+pub mod orbit_view;
 pub mod pan_orbit_camera;
-pub mod nbody;
\ No newline at end of file
+pub mod performance;
+pub mod prediction;
+pub mod quality;
+pub mod render_radius;
+pub mod reset;
+pub mod resonance;
+pub mod restricted_three_body;
+pub mod selection;
+pub mod self_check;
+pub mod star_light;
+pub mod timeline;
+pub mod tint;
+pub mod trail;
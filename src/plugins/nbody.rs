@@ -1,7 +1,18 @@
-use bevy::{core::FixedTimestep, prelude::*};
+use bevy::{
+    core::FixedTimestep,
+    ecs::{component::Component, schedule::ShouldRun, system::EntityCommands},
+    prelude::*,
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+use super::barnes_hut::{barnes_hut_accelerations, MultipoleOrder};
+use super::collision::Radius;
+use super::kepler::{OnRails, OrbitalElements};
 
 pub const G: f32 = 6.67430e-11_f32;
-const DT: f32 = 0.01;
+/// Default time step of the simulation, see `TimeStep`
+pub const DT: f32 = 0.01;
 
 // Resources
 pub struct Gravity(pub f32);
@@ -12,6 +23,391 @@ impl Default for Gravity {
     }
 }
 
+/// Simulated time of a physics step, e.g. set by a scene.
+/// It has to be inserted before the `NBody` plugin, which runs the steps at `speed_factor / dt` per second
+/// of real time, for the simulation to keep its speed whatever the step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeStep(pub f32);
+
+impl Default for TimeStep {
+    fn default() -> Self {
+        Self(DT)
+    }
+}
+
+/// Simulated time elapsed since the start, in the units of the scene
+#[derive(Default)]
+pub struct SimulationTime(pub f32);
+
+/// Number of threads of Bevy's task pools, running the systems in parallel, e.g. to spare the cores
+/// of a shared machine. Defaults to all the cores.
+/// It has to be applied before the `DefaultPlugins` create the pools, see `task_pool_options`.
+/// The forces are computed on a single thread, so the results don't depend on it.
+pub struct PhysicsThreads(pub usize);
+
+impl Default for PhysicsThreads {
+    fn default() -> Self {
+        Self(std::thread::available_parallelism().map_or(1, |threads| threads.get()))
+    }
+}
+
+impl PhysicsThreads {
+    pub fn task_pool_options(&self) -> DefaultTaskPoolOptions {
+        DefaultTaskPoolOptions::with_num_threads(self.0.max(1))
+    }
+}
+
+/// Suspends the physics steps, and every system running on the `PhysicsStep` criteria along with them
+#[derive(Default)]
+pub struct PhysicsPaused(pub bool);
+
+/// Maximum number of physics steps per second of real time, slowing the simulation down when below
+/// the rate of the fixed time step
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PhysicsThrottle(pub Option<f32>);
+
+/// Regularization of the force law at short separations, keeping the forces finite during close encounters
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum SofteningKernel {
+    /// Newtonian gravity, diverging as the bodies get closer
+    #[default]
+    None,
+    /// The distance is replaced by `sqrt(r^2 + eps^2)`, which slightly weakens the force at any separation
+    Plummer { eps: f32 },
+    /// Cubic spline kernel (Monaghan & Lattanzio), as in GADGET-2:
+    /// the mass is smoothed over a sphere of radius `h`, the force is exactly Newtonian beyond it.
+    CubicSpline { h: f32 },
+}
+
+impl std::str::FromStr for SofteningKernel {
+    type Err = String;
+
+    /// `none`, a Plummer length e.g. `0.05`, or `plummer:0.05` and `spline:0.1`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            String::from(
+                "Invalid input. Should be: none, <length>, plummer:<length> or spline:<length>",
+            )
+        };
+        let s = s.to_lowercase();
+        let (kind, length) = match s.split_once(':') {
+            Some((kind, length)) => (kind, length),
+            None if s == "none" => return Ok(Self::None),
+            None => ("plummer", s.as_str()),
+        };
+        let length: f32 = length.parse().map_err(|_| invalid())?;
+        match kind {
+            "plummer" => Ok(Self::Plummer { eps: length }),
+            "spline" => Ok(Self::CubicSpline { h: length }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl SofteningKernel {
+    /// Magnitude of the acceleration caused by a unit mass at a squared distance `dist2`, for `G = 1`,
+    /// i.e. `1/r^2` without softening
+    pub fn force(&self, dist2: f32) -> f32 {
+        match *self {
+            SofteningKernel::None => plummer_force(dist2, 0.0),
+            SofteningKernel::Plummer { eps } => plummer_force(dist2, eps * eps),
+            SofteningKernel::CubicSpline { h } => {
+                let r = dist2.sqrt();
+                if r >= h {
+                    return 1.0 / dist2;
+                }
+                let u = r / h;
+                let f = if u < 0.5 {
+                    32.0 / 3.0 + u * u * (32.0 * u - 38.4)
+                } else {
+                    64.0 / 3.0 - 48.0 * u + 38.4 * u * u
+                        - 32.0 / 3.0 * u * u * u
+                        - 1.0 / 15.0 / (u * u * u)
+                };
+                r * f / (h * h * h)
+            }
+        }
+    }
+
+    /// Potential of a unit mass at a squared distance `dist2`, for `G = 1`, i.e. `-1/r` without softening
+    pub fn potential(&self, dist2: f32) -> f32 {
+        match *self {
+            SofteningKernel::None => -1.0 / dist2.sqrt(),
+            SofteningKernel::Plummer { eps } => -1.0 / (dist2 + eps * eps).sqrt(),
+            SofteningKernel::CubicSpline { h } => {
+                let r = dist2.sqrt();
+                if r >= h {
+                    return -1.0 / r;
+                }
+                let u = r / h;
+                let w = if u < 0.5 {
+                    -14.0 / 5.0 + u * u * (16.0 / 3.0 + u * u * (6.4 * u - 9.6))
+                } else {
+                    -3.2 + 1.0 / 15.0 / u
+                        + u * u * (32.0 / 3.0 + u * (-16.0 + u * (9.6 - 32.0 / 15.0 * u)))
+                };
+                w / h
+            }
+        }
+    }
+
+    /// Same as `force` for a uniform sphere of radius `radius`, see `compute_extended_accelerations`:
+    /// linear inside the sphere, matching the outer force law at its surface
+    pub fn sphere_force(&self, dist2: f32, radius: f32) -> f32 {
+        if dist2 < radius * radius {
+            self.force(radius * radius) * dist2.sqrt() / radius
+        } else {
+            self.force(dist2)
+        }
+    }
+
+    /// Potential matching `sphere_force`, continuous at the surface of the sphere:
+    /// ```text
+    /// φ(r) = φ(R) - F(R)/2R * (R^2 - r^2)    for r < R
+    /// ```
+    /// i.e. `-(3R^2 - r^2)/2R^3` without softening
+    pub fn sphere_potential(&self, dist2: f32, radius: f32) -> f32 {
+        let radius2 = radius * radius;
+        if dist2 < radius2 {
+            self.potential(radius2) - self.force(radius2) / (2.0 * radius) * (radius2 - dist2)
+        } else {
+            self.potential(dist2)
+        }
+    }
+}
+
+/// `r / (r^2 + eps^2)^(3/2)`
+fn plummer_force(dist2: f32, eps2: f32) -> f32 {
+    let softened = dist2 + eps2;
+    dist2.sqrt() / (softened * softened.sqrt())
+}
+
+/// Optional cap on the magnitude of each body's acceleration.
+/// It prevents numerical blowups during close encounters but breaks the conservation laws.
+#[derive(Default)]
+pub struct MaxAcceleration(pub Option<f32>);
+
+/// Optional distance beyond which pairs of bodies are assumed not to interact.
+/// It's an approximation for large sparse simulations, not appropriate when long-range forces dominate.
+#[derive(Default)]
+pub struct ForceCutoff(pub Option<f32>);
+
+/// Whether the bodies with a `Radius` are treated as uniform-density spheres instead of point masses.
+/// Inside a body the force then decreases linearly down to zero at its center, e.g. for bodies resting
+/// on or falling through a planet, rather than diverging.
+#[derive(Default)]
+pub struct ExtendedBody(pub bool);
+
+/// Optional normal of the plane the physics is confined to, e.g. for clean closed orbits still viewed in 3D.
+/// The components of the velocities and accelerations along it are removed each step,
+/// each body then staying in the plane through its initial position.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ConstrainToPlane(pub Option<Vec3>);
+
+impl ConstrainToPlane {
+    /// `v` without its component along the normal of the plane
+    pub fn project(&self, v: Vec3) -> Vec3 {
+        match self.0.and_then(|normal| normal.try_normalize()) {
+            Some(normal) => v - v.dot(normal) * normal,
+            None => v,
+        }
+    }
+}
+
+/// External gravitational field acting on every body on top of their mutual attraction,
+/// e.g. the tides of a distant massive object:
+/// ```text
+/// a = uniform + tidal * r
+/// ```
+/// A uniform field accelerates all the bodies alike, leaving their relative motion unchanged
+/// (equivalence principle) unless some of them are pinned.
+/// Its potential is left out of the energies of the diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExternalField {
+    pub uniform: Vec3,
+    /// Tidal tensor, symmetric for the field to derive from a potential
+    pub tidal: Mat3,
+}
+
+impl Default for ExternalField {
+    fn default() -> Self {
+        Self {
+            uniform: Vec3::ZERO,
+            tidal: Mat3::ZERO,
+        }
+    }
+}
+
+impl ExternalField {
+    pub fn uniform(acceleration: Vec3) -> Self {
+        Self {
+            uniform: acceleration,
+            ..Default::default()
+        }
+    }
+
+    /// Linearized tides of a point mass `mass` at `offset` from the origin,
+    /// valid for the bodies much closer to the origin than to the mass:
+    /// ```text
+    /// T = G*M/d^3 * (3*n*n^T - I)
+    /// ```
+    /// where `n` is the unit vector away from the mass, along which the bodies are stretched.
+    pub fn point_mass_tides(g: f32, mass: f32, offset: Vec3) -> Mat3 {
+        let d = offset.length();
+        if d == 0.0 {
+            return Mat3::ZERO;
+        }
+        let n = offset / d;
+        let outer = Mat3::from_cols(n * n.x, n * n.y, n * n.z);
+        (outer * 3.0 - Mat3::IDENTITY) * (g * mass / (d * d * d))
+    }
+
+    pub fn acceleration(&self, pos: Vec3) -> Vec3 {
+        self.uniform + self.tidal * pos
+    }
+
+    /// Adds the field to the `accelerations` of the bodies at `positions`
+    pub fn apply(&self, positions: &[Vec3], accelerations: &mut [Vec3]) {
+        if *self == Self::default() {
+            return;
+        }
+        for (acc, pos) in accelerations.iter_mut().zip(positions) {
+            *acc += self.acceleration(*pos);
+        }
+    }
+}
+
+impl std::str::FromStr for ExternalField {
+    type Err = String;
+
+    /// Uniform field as `<x>,<y>,<z>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || String::from("Invalid input. Should be: <x>,<y>,<z>");
+        let components = s
+            .split(',')
+            .map(|c| c.trim().parse::<f32>().map_err(|_| invalid()))
+            .collect::<Result<Vec<f32>, String>>()?;
+        match components[..] {
+            [x, y, z] => Ok(Self::uniform(Vec3::new(x, y, z))),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// How the forces are computed, e.g. switched by the `AdaptivePerformance` to keep up with the frame rate
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ForceMode {
+    /// All pairs of bodies interact, up to the optional `ForceCutoff`
+    #[default]
+    Direct,
+    /// Pairs of bodies further apart than the given distance are skipped
+    Cutoff(f32),
+    /// Barnes-Hut tree code, the groups of bodies seen under an angle smaller than `theta` being
+    /// approximated by their multipole expansion up to `multipole_order`.
+    /// The `ForceCutoff` doesn't apply, bodies with a `GravityCharge` fall back to the direct computation.
+    BarnesHut {
+        theta: f32,
+        multipole_order: MultipoleOrder,
+    },
+}
+
+impl ForceMode {
+    /// Cutoff distance of the mode combined with the `ForceCutoff` one
+    pub fn cutoff(&self, cutoff: Option<f32>) -> Option<f32> {
+        match *self {
+            ForceMode::Direct | ForceMode::BarnesHut { .. } => cutoff,
+            ForceMode::Cutoff(distance) => Some(cutoff.map_or(distance, |c| c.min(distance))),
+        }
+    }
+}
+
+/// Time integration scheme of the physics systems
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Integrator {
+    /// Velocities are updated first, then the positions with the new velocities (symplectic Euler)
+    #[default]
+    SemiImplicitEuler,
+    /// Kick-drift-kick leapfrog, second order.
+    /// Between two steps, the velocities of the bodies are half a step ahead of their positions.
+    Leapfrog,
+    /// Velocity Verlet, second order, the same trajectories as `Leapfrog` with synchronized velocities.
+    /// The physics systems step it as `Leapfrog`, only the headless runner keeps the velocities in sync.
+    VelocityVerlet,
+    /// Classic fourth order Runge-Kutta, not symplectic and four force computations per step
+    RungeKutta4,
+    /// Velocity Verlet with individual block time steps, see `block_step`:
+    /// each body is advanced with a power-of-two subdivision of the step fitting its local dynamics,
+    /// down to `max_levels` subdivisions and a step of `min_dt`,
+    /// e.g. for a close binary among distant planets.
+    BlockStep { min_dt: f32, max_levels: u32 },
+    /// Implicit midpoint, second order and symplectic, its equations solved by `iterations` fixed-point
+    /// iterations per step, each one a force computation, see `implicit_midpoint_step`,
+    /// e.g. for a stiff close binary.
+    ImplicitMidpoint { iterations: u32 },
+}
+
+impl Integrator {
+    /// Whether the scheme is stepped as a whole by its own system, in place of `update_velocity` and `movement`
+    pub fn steps_whole(&self) -> bool {
+        matches!(
+            self,
+            Self::RungeKutta4 | Self::BlockStep { .. } | Self::ImplicitMidpoint { .. }
+        )
+    }
+}
+
+impl std::str::FromStr for Integrator {
+    type Err = String;
+
+    /// `euler`, `leapfrog`, `verlet`, `rk4`, `block` with optional maximum levels and minimum step,
+    /// e.g. `block:8:1e-6`, or `midpoint` with optional iterations, e.g. `midpoint:6`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            String::from(
+                "Invalid input. Should be: euler, leapfrog, verlet, rk4, block[:<max levels>[:<min dt>]] or midpoint[:<iterations>]",
+            )
+        };
+        let s = s.to_lowercase();
+        let mut parts = s.split(':');
+        match parts.next().unwrap_or_default() {
+            "euler" => Ok(Self::SemiImplicitEuler),
+            "leapfrog" => Ok(Self::Leapfrog),
+            "verlet" => Ok(Self::VelocityVerlet),
+            "rk4" => Ok(Self::RungeKutta4),
+            "block" => {
+                let (default_min_dt, default_max_levels) = (DT / 1024.0, 8);
+                let max_levels = match parts.next() {
+                    Some(levels) => levels.parse().map_err(|_| invalid())?,
+                    None => default_max_levels,
+                };
+                let min_dt = match parts.next() {
+                    Some(min_dt) => min_dt.parse().map_err(|_| invalid())?,
+                    None => default_min_dt,
+                };
+                Ok(Self::BlockStep { min_dt, max_levels })
+            }
+            "midpoint" => {
+                let iterations = match parts.next() {
+                    Some(iterations) => iterations.parse().map_err(|_| invalid())?,
+                    None => 4,
+                };
+                Ok(Self::ImplicitMidpoint { iterations })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Relaxes orbits around the `central` body toward circular ones by damping the radial velocity relative to it,
+/// a crude stand-in for the gas drag of a protoplanetary disk.
+/// The angular momentum about the central body is left unchanged.
+pub struct CircularizationDamping {
+    /// Decay rate of the radial velocity, per unit of simulated time
+    pub rate: f32,
+    pub central: Entity,
+    pub enabled: bool,
+}
+
 // Plugin
 pub struct NBody {
     pub speed_factor: f32,
@@ -25,36 +421,93 @@ impl Default for NBody {
 
 impl Plugin for NBody {
     fn build(&self, app: &mut AppBuilder) {
-        app.init_resource::<Gravity>().add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::steps_per_second(
-                    (self.speed_factor / DT) as f64,
-                ))
-                .with_system(
-                    update_acceleration
-                        .system()
-                        .label(PhysicsSystem::UpdateAcceleration),
-                )
-                .with_system(
-                    update_velocity
-                        .system()
-                        .label(PhysicsSystem::UpdateVelocity)
-                        .after(PhysicsSystem::UpdateAcceleration),
-                )
-                .with_system(
-                    movement
-                        .system()
-                        .label(PhysicsSystem::Movement)
-                        .after(PhysicsSystem::UpdateVelocity),
-                ),
-        );
+        let dt = app
+            .world_mut()
+            .get_resource_or_insert_with(TimeStep::default)
+            .0;
+        app.init_resource::<Gravity>()
+            .init_resource::<SofteningKernel>()
+            .init_resource::<MaxAcceleration>()
+            .init_resource::<ForceCutoff>()
+            .init_resource::<ExtendedBody>()
+            .init_resource::<ConstrainToPlane>()
+            .init_resource::<ExternalField>()
+            .init_resource::<GroupIsolation>()
+            .init_resource::<ForceMode>()
+            .init_resource::<Integrator>()
+            .init_resource::<SimulationTime>()
+            .init_resource::<PhysicsPaused>()
+            .init_resource::<PhysicsThrottle>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(
+                        FixedTimestep::steps_per_second((self.speed_factor / dt) as f64)
+                            .chain(unless_paused.system())
+                            .chain(throttled.system())
+                            .label(PhysicsStep),
+                    )
+                    .with_system(
+                        update_acceleration
+                            .system()
+                            .label(PhysicsSystem::UpdateAcceleration),
+                    )
+                    .with_system(
+                        constrain_to_plane
+                            .system()
+                            .label(PhysicsSystem::PlaneConstraint)
+                            .after(PhysicsSystem::UpdateAcceleration),
+                    )
+                    .with_system(
+                        update_velocity
+                            .system()
+                            .label(PhysicsSystem::UpdateVelocity)
+                            .after(PhysicsSystem::PlaneConstraint),
+                    )
+                    .with_system(
+                        circularize_orbits
+                            .system()
+                            .label(PhysicsSystem::Circularization)
+                            .after(PhysicsSystem::UpdateVelocity),
+                    )
+                    .with_system(
+                        movement
+                            .system()
+                            .label(PhysicsSystem::Movement)
+                            .after(PhysicsSystem::Circularization),
+                    )
+                    .with_system(
+                        runge_kutta_step
+                            .system()
+                            .label(PhysicsSystem::Movement)
+                            .after(PhysicsSystem::Circularization),
+                    )
+                    .with_system(
+                        block_time_step
+                            .system()
+                            .label(PhysicsSystem::Movement)
+                            .after(PhysicsSystem::Circularization),
+                    )
+                    .with_system(
+                        implicit_midpoint_time_step
+                            .system()
+                            .label(PhysicsSystem::Movement)
+                            .after(PhysicsSystem::Circularization),
+                    )
+                    .with_system(advance_time.system().after(PhysicsSystem::Movement)),
+            );
     }
 }
 
+/// Run criteria of the physics systems, for systems which need to run once per physics step
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RunCriteriaLabel)]
+pub struct PhysicsStep;
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
 pub enum PhysicsSystem {
     UpdateAcceleration,
+    PlaneConstraint,
     UpdateVelocity,
+    Circularization,
     Movement,
 }
 
@@ -62,12 +515,71 @@ pub enum PhysicsSystem {
 struct Position(Vec3);
 
 #[derive(Default)]
-struct Velocity(Vec3);
+pub struct Velocity(pub Vec3);
 #[derive(Default)]
-struct Acceleration(Vec3);
+pub struct Acceleration(pub Vec3);
+
+pub struct Mass(pub f32);
+
+/// Gravitational coupling of a body, multiplying its mass in the force law (1 for Newtonian gravity),
+/// e.g. to model a dark matter coupling differently
+pub struct GravityCharge(pub f32);
 
-struct Mass(f32);
+impl Default for GravityCharge {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Bodies only attract the bodies of the same universe, those without one belonging to the universe 0,
+/// e.g. to run perturbed replicas of a scene side by side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Universe(pub u32);
+
+/// Whether the gravity is confined to each `Universe`, e.g. disabled to see how the side by side
+/// systems would perturb each other. The collisions stay within each universe either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupIsolation(pub bool);
+
+impl Default for GroupIsolation {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+impl GroupIsolation {
+    /// Universe the gravity of a body is computed in, all of them being merged without isolation
+    pub fn universe(&self, universe: Option<&Universe>) -> Universe {
+        match universe {
+            Some(universe) if self.0 => *universe,
+            _ => Universe::default(),
+        }
+    }
+}
+
+/// Pinned bodies still attract the others but stay in place
+pub struct Pinned;
 
+/// Frozen bodies are suspended in time while the others evolve: they keep attracting the others
+/// from their frozen position, but neither move nor have their velocity updated.
+/// Once unfrozen, they resume their motion with the velocity they had when frozen.
+pub struct Frozen;
+
+/// Tags a body whose velocity is half a step ahead of its position, see `Integrator::Leapfrog`
+struct HalfStepVelocity;
+
+/// Kind of celestial body, e.g. stars carry their own light
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BodyClass {
+    Star,
+    Planet,
+    /// Grows a tail pointing away from the nearest star, see `CometPlugin`
+    Comet,
+}
+
+/// Components of a body required by the physics systems.
+/// Any other component can be inserted alongside, e.g. a catalog id, the physics systems only query their own,
+/// see `BodyBundle::builder`.
 #[derive(Bundle)]
 pub struct BodyBundle {
     mass: Mass,
@@ -85,10 +597,265 @@ impl BodyBundle {
             acc: Acceleration::default(),
         }
     }
+
+    /// Body set up fluently, with optional render and user fields spawned along with it:
+    /// ```ignore
+    /// struct CatalogId(u32);
+    ///
+    /// BodyBundle::builder()
+    ///     .mass(5.97)
+    ///     .position(Vec3::X)
+    ///     .radius(0.1)
+    ///     .color(Color::BLUE)
+    ///     .name("Earth")
+    ///     .with(CatalogId(399))
+    ///     .spawn(&mut commands);
+    /// ```
+    pub fn builder() -> BodyBuilder {
+        BodyBuilder::default()
+    }
+
+    /// Body on the Kepler orbit described by `elements` around a primary moving at `primary_vel`,
+    /// e.g. a moon of an orbiting planet: its state relative to the primary is added to the primary's one
+    pub fn orbit_around(
+        mass: f32,
+        primary_mass: f32,
+        primary_pos: Vec3,
+        primary_vel: Vec3,
+        elements: OrbitalElements,
+        g: f32,
+    ) -> Self {
+        let (pos, vel) = elements.state(g * (primary_mass + mass));
+        Self::new(mass, primary_pos + pos, primary_vel + vel)
+    }
+
+    /// Body moving along the Kepler orbit described by `elements` around the `primary`,
+    /// given as `(entity, mass, position, velocity)`
+    pub fn on_rails_from_elements(
+        mass: f32,
+        elements: OrbitalElements,
+        primary: (Entity, f32, Vec3, Vec3),
+        g: f32,
+    ) -> (Self, OnRails) {
+        let (primary, primary_mass, primary_pos, primary_vel) = primary;
+        let mu = g * (primary_mass + mass);
+        (
+            Self::orbit_around(mass, primary_mass, primary_pos, primary_vel, elements, g),
+            OnRails {
+                primary,
+                elements,
+                mu,
+            },
+        )
+    }
+}
+
+/// Name of a body, e.g. from a scene file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyName(pub String);
+
+/// Render-only fields of a body built with `BodyBundle::builder`, the physics doesn't depend on them
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BodyAppearance {
+    /// Rendered and collision radius, bodies without one don't collide
+    pub radius: Option<f32>,
+    pub color: Option<Color>,
+    /// Surface image mapped over the icosphere, tinted by the `color`
+    pub texture: Option<Handle<Texture>>,
+    pub name: Option<String>,
+    pub class: Option<BodyClass>,
+}
+
+/// Radius of the icosphere of bodies without a radius, as for scene bodies
+pub const DEFAULT_RENDER_RADIUS: f32 = 0.1;
+
+impl BodyAppearance {
+    /// Icosphere of the body, with an emissive material for stars
+    pub fn pbr_bundle(
+        &self,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<StandardMaterial>,
+        subdivisions: usize,
+    ) -> PbrBundle {
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Icosphere {
+                radius: self.radius.unwrap_or(DEFAULT_RENDER_RADIUS),
+                subdivisions,
+            })),
+            material: materials.add(self.material()),
+            ..Default::default()
+        }
+    }
+
+    /// Flat color unless textured, emissive for stars
+    pub fn material(&self) -> StandardMaterial {
+        let color = self.color.unwrap_or(Color::WHITE);
+        StandardMaterial {
+            base_color: color,
+            base_color_texture: self.texture.clone(),
+            emissive: if self.class == Some(BodyClass::Star) {
+                color
+            } else {
+                Color::BLACK
+            },
+            ..Default::default()
+        }
+    }
+}
+
+type InsertExtra = Box<dyn FnOnce(&mut EntityCommands) + Send + Sync>;
+
+/// Builder of a body, see `BodyBundle::builder`.
+/// Defaults to a unit mass at rest at the origin, without radius, color, name nor class.
+pub struct BodyBuilder {
+    mass: f32,
+    position: Vec3,
+    velocity: Vec3,
+    density: Option<f32>,
+    appearance: BodyAppearance,
+    extras: Vec<InsertExtra>,
+}
+
+impl Default for BodyBuilder {
+    fn default() -> Self {
+        Self {
+            mass: 1.0,
+            position: Vec3::ZERO,
+            velocity: Vec3::ZERO,
+            density: None,
+            appearance: BodyAppearance::default(),
+            extras: Vec::new(),
+        }
+    }
+}
+
+impl BodyBuilder {
+    pub fn mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    pub fn position(mut self, position: Vec3) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn velocity(mut self, velocity: Vec3) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.appearance.radius = Some(radius);
+        self
+    }
+
+    /// Sets the radius of a homogeneous sphere of the mass, unless a radius is given
+    pub fn density(mut self, density: f32) -> Self {
+        self.density = Some(density);
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.appearance.color = Some(color);
+        self
+    }
+
+    /// Image of the surface, e.g. loaded with the `AssetServer`, the icospheres being UV-mapped
+    pub fn texture(mut self, texture: Handle<Texture>) -> Self {
+        self.appearance.texture = Some(texture);
+        self
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.appearance.name = Some(name.into());
+        self
+    }
+
+    pub fn class(mut self, class: BodyClass) -> Self {
+        self.appearance.class = Some(class);
+        self
+    }
+
+    /// Extra component of the caller's own, the physics systems ignore it
+    pub fn with<C: Component>(mut self, component: C) -> Self {
+        self.extras.push(Box::new(move |entity| {
+            entity.insert(component);
+        }));
+        self
+    }
+
+    pub fn with_bundle<B: Bundle>(mut self, bundle: B) -> Self {
+        self.extras.push(Box::new(move |entity| {
+            entity.insert_bundle(bundle);
+        }));
+        self
+    }
+
+    /// Physics bundle and render fields, without the extra components
+    pub fn build(&self) -> (BodyBundle, BodyAppearance) {
+        let mut appearance = self.appearance.clone();
+        if let (None, Some(density)) = (appearance.radius, self.density) {
+            // m = 4/3 * π * r^3 * ρ
+            appearance.radius = Some(
+                (3.0 * self.mass / (4.0 * std::f32::consts::PI * density))
+                    .max(0.0)
+                    .cbrt(),
+            );
+        }
+        (
+            BodyBundle::new(self.mass, self.position, self.velocity),
+            appearance,
+        )
+    }
+
+    /// Spawns the body with its `Radius`, `BodyClass`, `BodyName` and extra components, without rendering
+    pub fn spawn(self, commands: &mut Commands) -> Entity {
+        let (bundle, appearance) = self.build();
+        let mut entity = commands.spawn();
+        entity.insert_bundle(bundle);
+        Self::insert_components(&mut entity, appearance, self.extras)
+    }
+
+    /// Same as `spawn`, rendered as an icosphere of the body's radius and color
+    pub fn spawn_rendered(
+        self,
+        commands: &mut Commands,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<StandardMaterial>,
+        subdivisions: usize,
+    ) -> Entity {
+        let (bundle, appearance) = self.build();
+        // The body's transform overrides the default one of the render bundle
+        let mut entity =
+            commands.spawn_bundle(appearance.pbr_bundle(meshes, materials, subdivisions));
+        entity.insert_bundle(bundle);
+        Self::insert_components(&mut entity, appearance, self.extras)
+    }
+
+    fn insert_components(
+        entity: &mut EntityCommands,
+        appearance: BodyAppearance,
+        extras: Vec<InsertExtra>,
+    ) -> Entity {
+        if let Some(radius) = appearance.radius {
+            entity.insert(Radius(radius));
+        }
+        if let Some(class) = appearance.class {
+            entity.insert(class);
+        }
+        if let Some(name) = appearance.name {
+            entity.insert(BodyName(name));
+        }
+        for insert in extras {
+            insert(entity);
+        }
+        entity.id()
+    }
 }
 
 /// Newton's law of universal gravitation
-/// ```
+/// ```text
 /// F = G*m1*m2/r^2
 /// ```
 /// where:
@@ -96,36 +863,1677 @@ impl BodyBundle {
 /// - `G` is the gravitational constant
 /// - `m1` and `m2` are the masses of the objects
 /// - `r` is the distance between the centers of their masses
-fn update_acceleration(g: Res<Gravity>, mut query: Query<(&Mass, &Transform, &mut Acceleration)>) {
-    let mut bodies: Vec<(&Mass, &Transform, Mut<Acceleration>)> = Vec::new();
-    for (mass, transform, mut acc) in query.iter_mut() {
-        acc.0 = Vec3::ZERO;
-        for (other_mass, other_pos, other_acc) in bodies.iter_mut() {
-            let diff = other_pos.translation - transform.translation;
-            if let Some(mut force) = diff.try_normalize() {
-                let magnitude = g.0 * mass.0 * other_mass.0 / diff.length_squared();
-                force *= magnitude;
-                acc.0 += force;
-                other_acc.0 -= force;
+///
+/// At short separations, the force law is regularized by the `softening` kernel
+/// to keep the force finite during close encounters, see `SofteningKernel`.
+/// Coincident bodies don't exert any force on each other.
+/// Massless bodies (test particles) feel the gravity of the others without attracting them.
+/// Pairs further apart than the optional `cutoff` distance are skipped.
+pub fn compute_accelerations(
+    masses: &[f32],
+    positions: &[Vec3],
+    g: f32,
+    softening: SofteningKernel,
+    cutoff: Option<f32>,
+) -> Vec<Vec3> {
+    pairwise_accelerations(masses, None, None, positions, g, softening, cutoff)
+}
+
+/// Same as `compute_accelerations` with a `GravityCharge` per body, the force between two bodies becoming:
+/// ```text
+/// F = G*(m1*q1)*(m2*q2)/r^2
+/// ```
+pub fn compute_charged_accelerations(
+    masses: &[f32],
+    charges: &[f32],
+    positions: &[Vec3],
+    g: f32,
+    softening: SofteningKernel,
+    cutoff: Option<f32>,
+) -> Vec<Vec3> {
+    pairwise_accelerations(masses, Some(charges), None, positions, g, softening, cutoff)
+}
+
+/// Same as `compute_accelerations` with the bodies being uniform-density spheres of radii `radii`.
+/// Within the radius `R` of a pair, the larger of their radii so that the forces stay opposite,
+/// the enclosed mass shrinks as `r^3` and the force grows linearly with the distance:
+/// ```text
+/// F = G*m1*m2/r^2      for r >= R
+/// F = G*m1*m2*r/R^3    for r < R
+/// ```
+/// which is continuous at the surface. Point masses have a zero radius.
+pub fn compute_extended_accelerations(
+    masses: &[f32],
+    radii: &[f32],
+    positions: &[Vec3],
+    g: f32,
+    softening: SofteningKernel,
+    cutoff: Option<f32>,
+) -> Vec<Vec3> {
+    pairwise_accelerations(masses, None, Some(radii), positions, g, softening, cutoff)
+}
+
+fn pairwise_accelerations(
+    masses: &[f32],
+    charges: Option<&[f32]>,
+    radii: Option<&[f32]>,
+    positions: &[Vec3],
+    g: f32,
+    softening: SofteningKernel,
+    cutoff: Option<f32>,
+) -> Vec<Vec3> {
+    let charge = |i: usize| charges.map_or(1.0, |charges| charges[i]);
+    let pair_radius = |i: usize, j: usize| radii.map_or(0.0, |radii| radii[i].max(radii[j]));
+    let cutoff2 = cutoff.map_or(f32::INFINITY, |cutoff| cutoff * cutoff);
+    let mut accelerations = vec![Vec3::ZERO; positions.len()];
+    for i in 0..positions.len() {
+        for j in (i + 1)..positions.len() {
+            let diff = positions[j] - positions[i];
+            let dist2 = diff.length_squared();
+            if dist2 > cutoff2 {
+                continue;
+            }
+            if let Some(direction) = diff.try_normalize() {
+                // Newton's second law of motion: `F = ma => a = F/m`
+                // a body's own mass cancels out, which lets massless test particles move too
+                let force = softening.sphere_force(dist2, pair_radius(i, j));
+                let field = direction * g * charge(i) * charge(j) * force;
+                accelerations[i] += masses[j] * field;
+                accelerations[j] -= masses[i] * field;
+            }
+        }
+    }
+    accelerations
+}
+
+/// Acceleration a test mass at `point` would feel from the `(mass, position)` bodies,
+/// a body sitting exactly at `point` is treated as the test mass itself and ignored
+pub fn acceleration_at(
+    point: Vec3,
+    bodies: &[(f32, Vec3)],
+    g: f32,
+    softening: SofteningKernel,
+) -> Vec3 {
+    bodies
+        .iter()
+        .filter_map(|&(mass, position)| {
+            let diff = position - point;
+            let dist2 = diff.length_squared();
+            diff.try_normalize()
+                .map(|direction| direction * g * mass * softening.force(dist2))
+        })
+        .fold(Vec3::ZERO, |acc, field| acc + field)
+}
+
+/// Same as `compute_accelerations` for the `active` bodies only, in their order
+pub fn accelerations_of(
+    masses: &[f32],
+    positions: &[Vec3],
+    active: &[usize],
+    g: f32,
+    softening: SofteningKernel,
+    cutoff: Option<f32>,
+) -> Vec<Vec3> {
+    let cutoff2 = cutoff.map_or(f32::INFINITY, |cutoff| cutoff * cutoff);
+    active
+        .iter()
+        .map(|&i| {
+            (0..positions.len())
+                .filter_map(|j| {
+                    let diff = positions[j] - positions[i];
+                    let dist2 = diff.length_squared();
+                    if dist2 > cutoff2 {
+                        return None;
+                    }
+                    diff.try_normalize()
+                        .map(|direction| direction * g * masses[j] * softening.force(dist2))
+                })
+                .fold(Vec3::ZERO, |acc, field| acc + field)
+        })
+        .collect()
+}
+
+/// Fraction of its local dynamical time a body is stepped by with `Integrator::BlockStep`
+pub const BLOCK_STEP_ACCURACY: f32 = 0.05;
+
+/// Level of each body for `block_step`, its step being `dt / 2^level`.
+/// The step of a body should not exceed a fraction of its local dynamical time:
+/// ```text
+/// dt_i = BLOCK_STEP_ACCURACY * sqrt(d_i / |a_i|)
+/// ```
+/// where `d_i` is the distance to its nearest neighbour, i.e. `r/v` on a circular orbit.
+/// The levels are capped at `max_levels` and at the step `min_dt`.
+pub fn block_levels(
+    positions: &[Vec3],
+    accelerations: &[Vec3],
+    dt: f32,
+    min_dt: f32,
+    max_levels: u32,
+) -> Vec<u32> {
+    // Beyond 2^30 substeps per step, the step counts would overflow
+    let max_level = max_levels
+        .min((dt / min_dt).log2().floor().max(0.0) as u32)
+        .min(30);
+    (0..positions.len())
+        .map(|i| {
+            let nearest = (0..positions.len())
+                .filter(|j| *j != i)
+                .map(|j| positions[i].distance(positions[j]))
+                .fold(f32::INFINITY, f32::min);
+            let acc = accelerations[i].length();
+            let target = BLOCK_STEP_ACCURACY * (nearest / acc).sqrt();
+            if !target.is_finite() || target >= dt {
+                return 0;
+            }
+            ((dt / target).log2().ceil() as u32).min(max_level)
+        })
+        .collect()
+}
+
+/// Step of `dt` of the velocity Verlet (kick-drift-kick) scheme with block time steps:
+/// each body is advanced with its own step `dt / 2^level`, from its `levels`,
+/// so that the bodies in close encounters take many small steps while the others take few large ones.
+/// All the bodies drift at every substep, only those starting or ending a step of theirs get a kick,
+/// and `eval(positions, active)` returns the accelerations of the `active` bodies only.
+/// The `fixed` bodies don't move.
+///
+/// `accelerations` holds the accelerations at the start of the step, and those at its end on return,
+/// all the bodies being synchronized again at the end of the step.
+/// Returns the number of steps each body took.
+pub fn block_step(
+    positions: &mut [Vec3],
+    velocities: &mut [Vec3],
+    accelerations: &mut [Vec3],
+    levels: &[u32],
+    fixed: &[bool],
+    dt: f32,
+    mut eval: impl FnMut(&[Vec3], &[usize]) -> Vec<Vec3>,
+) -> Vec<u32> {
+    let top = levels.iter().copied().max().unwrap_or(0);
+    let substeps = 1u32 << top;
+    let h = dt / substeps as f32;
+    // Substeps per step of each body
+    let span = |i: usize| 1u32 << (top - levels[i]);
+    let mut steps = vec![0; positions.len()];
+    for substep in 0..substeps {
+        for i in 0..positions.len() {
+            if !fixed[i] && substep % span(i) == 0 {
+                velocities[i] += accelerations[i] * span(i) as f32 * h / 2.0;
+            }
+        }
+        for (pos, (vel, fixed)) in positions.iter_mut().zip(velocities.iter().zip(fixed)) {
+            if !fixed {
+                *pos += *vel * h;
             }
         }
-        bodies.push((mass, transform, acc));
+        let active: Vec<usize> = (0..positions.len())
+            .filter(|i| (substep + 1) % span(*i) == 0)
+            .collect();
+        let new_accelerations = eval(positions, &active);
+        for (i, acc) in active.into_iter().zip(new_accelerations) {
+            accelerations[i] = acc;
+            if !fixed[i] {
+                velocities[i] += acc * span(i) as f32 * h / 2.0;
+            }
+            steps[i] += 1;
+        }
     }
+    steps
+}
 
-    // Newton's second law of motion: `F = ma => a = F/m`
-    for (mass, _, acc) in bodies.iter_mut() {
-        acc.0 /= mass.0;
+/// Step of `dt` of the implicit midpoint scheme:
+/// ```text
+/// x' = x + dt * (v + v') / 2
+/// v' = v + dt * a((x + x') / 2)
+/// ```
+/// The implicit equations are solved by fixed-point iterations from an explicit Euler guess
+/// starting from the `accelerations`, `eval(positions)` returning the accelerations at the midpoints.
+/// At least one iteration is made. The `fixed` bodies don't move, the others feeling them
+/// where they stay during the iterations, whatever their velocity.
+///
+/// Solved exactly, the scheme is stable whatever the step, but the iterations only converge for
+/// `dt * sqrt(|∂a/∂x|) < 2`, i.e. `dt * sqrt(2GM / r³) < 2` at a distance `r` of a mass `M`,
+/// the closest approaches setting the limit.
+/// Beyond it they diverge rather than damp the motion, and with too few of them the step is not
+/// symmetric anymore and the energy drifts as with an explicit scheme.
+pub fn implicit_midpoint_step(
+    positions: &mut [Vec3],
+    velocities: &mut [Vec3],
+    accelerations: &[Vec3],
+    fixed: &[bool],
+    dt: f32,
+    iterations: u32,
+    mut eval: impl FnMut(&[Vec3]) -> Vec<Vec3>,
+) {
+    let mut new_positions: Vec<Vec3> = (0..positions.len())
+        .map(|i| {
+            if fixed[i] {
+                positions[i]
+            } else {
+                positions[i] + velocities[i] * dt
+            }
+        })
+        .collect();
+    let mut new_velocities: Vec<Vec3> = (0..positions.len())
+        .map(|i| {
+            if fixed[i] {
+                velocities[i]
+            } else {
+                velocities[i] + accelerations[i] * dt
+            }
+        })
+        .collect();
+    for _ in 0..iterations.max(1) {
+        let midpoints: Vec<Vec3> = positions
+            .iter()
+            .zip(&new_positions)
+            .map(|(pos, new_pos)| (*pos + *new_pos) / 2.0)
+            .collect();
+        let midpoint_accelerations = eval(&midpoints);
+        for i in 0..positions.len() {
+            if fixed[i] {
+                continue;
+            }
+            new_velocities[i] = velocities[i] + midpoint_accelerations[i] * dt;
+            new_positions[i] = positions[i] + (velocities[i] + new_velocities[i]) * dt / 2.0;
+        }
+    }
+    for i in 0..positions.len() {
+        if !fixed[i] {
+            positions[i] = new_positions[i];
+            velocities[i] = new_velocities[i];
+        }
     }
 }
 
-fn update_velocity(mut query: Query<(&mut Velocity, &Acceleration)>) {
-    for (mut vel, acc) in query.iter_mut() {
-        vel.0 += acc.0 * DT;
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_acceleration(
+    g: Res<Gravity>,
+    softening: Res<SofteningKernel>,
+    cutoff: Res<ForceCutoff>,
+    mode: Res<ForceMode>,
+    max_acc: Res<MaxAcceleration>,
+    extended: Res<ExtendedBody>,
+    isolation: Res<GroupIsolation>,
+    external: Res<ExternalField>,
+    mut query: Query<(
+        &Mass,
+        &Transform,
+        &mut Acceleration,
+        Option<&GravityCharge>,
+        Option<&Universe>,
+        Option<&Radius>,
+    )>,
+) {
+    let mut masses = Vec::new();
+    let mut positions = Vec::new();
+    let mut charges = Vec::new();
+    let mut universes = Vec::new();
+    let mut radii = Vec::new();
+    for (mass, transform, _, charge, universe, radius) in query.iter_mut() {
+        masses.push(mass.0);
+        positions.push(transform.translation);
+        charges.push(charge.map_or(1.0, |charge| charge.0));
+        universes.push(isolation.universe(universe));
+        radii.push(radius.map_or(0.0, |radius| radius.0));
+    }
+
+    let radii = if extended.0 { Some(&radii[..]) } else { None };
+    let mut accelerations = field(
+        &masses, &charges, radii, &universes, &positions, g.0, *softening, *mode, cutoff.0,
+    );
+    debug_assert!(
+        is_torque_free(&masses, &positions, &accelerations),
+        "gravitational forces should not exert any net torque on the system"
+    );
+    external.apply(&positions, &mut accelerations);
+    for ((_, _, mut acc, _, _, _), new_acc) in query.iter_mut().zip(accelerations) {
+        acc.0 = match max_acc.0 {
+            Some(max) => clamp_magnitude(new_acc, max),
+            None => new_acc,
+        };
     }
 }
 
-fn movement(mut query: Query<(&mut Transform, &Velocity)>) {
+/// Accelerations of the bodies, each universe on its own
+#[allow(clippy::too_many_arguments)]
+fn field(
+    masses: &[f32],
+    charges: &[f32],
+    radii: Option<&[f32]>,
+    universes: &[Universe],
+    positions: &[Vec3],
+    g: f32,
+    softening: SofteningKernel,
+    mode: ForceMode,
+    cutoff: Option<f32>,
+) -> Vec<Vec3> {
+    if universes
+        .iter()
+        .all(|universe| *universe == Universe::default())
+    {
+        return universe_field(
+            masses, charges, radii, positions, g, softening, mode, cutoff,
+        );
+    }
+    let mut groups: HashMap<Universe, Vec<usize>> = HashMap::default();
+    for (i, universe) in universes.iter().enumerate() {
+        groups.entry(*universe).or_default().push(i);
+    }
+    let mut accelerations = vec![Vec3::ZERO; positions.len()];
+    for indices in groups.values() {
+        let gather = |values: &[f32]| indices.iter().map(|i| values[*i]).collect::<Vec<f32>>();
+        let group_positions: Vec<Vec3> = indices.iter().map(|i| positions[*i]).collect();
+        let group_radii = radii.map(gather);
+        let group_accelerations = universe_field(
+            &gather(masses),
+            &gather(charges),
+            group_radii.as_deref(),
+            &group_positions,
+            g,
+            softening,
+            mode,
+            cutoff,
+        );
+        for (i, acc) in indices.iter().zip(group_accelerations) {
+            accelerations[*i] = acc;
+        }
+    }
+    accelerations
+}
+
+#[allow(clippy::too_many_arguments)]
+fn universe_field(
+    masses: &[f32],
+    charges: &[f32],
+    radii: Option<&[f32]>,
+    positions: &[Vec3],
+    g: f32,
+    softening: SofteningKernel,
+    mode: ForceMode,
+    cutoff: Option<f32>,
+) -> Vec<Vec3> {
+    let cutoff = mode.cutoff(cutoff);
+    let charged = !charges.iter().all(|charge| *charge == 1.0);
+    if charged || radii.is_some() {
+        // Tree codes only handle point masses
+        let charges = if charged { Some(charges) } else { None };
+        pairwise_accelerations(masses, charges, radii, positions, g, softening, cutoff)
+    } else if let ForceMode::BarnesHut {
+        theta,
+        multipole_order,
+    } = mode
+    {
+        barnes_hut_accelerations(masses, positions, g, softening, theta, multipole_order)
+    } else {
+        compute_accelerations(masses, positions, g, softening, cutoff)
+    }
+}
+
+/// Scales `v` down so that its length doesn't exceed `max`
+pub fn clamp_magnitude(v: Vec3, max: f32) -> Vec3 {
+    let length = v.length();
+    if length > max {
+        v * (max / length)
+    } else {
+        v
+    }
+}
+
+/// Mass-weighted average of the positions
+pub fn center_of_mass(masses: &[f32], positions: &[Vec3]) -> Vec3 {
+    let total_mass: f32 = masses.iter().sum();
+    if total_mass == 0.0 {
+        return Vec3::ZERO;
+    }
+    masses
+        .iter()
+        .zip(positions)
+        .fold(Vec3::ZERO, |acc, (m, pos)| acc + *m * *pos)
+        / total_mass
+}
+
+/// Net torque about the center of mass:
+/// ```text
+/// τ = Σ (r_i - r_cm) x m_i*a_i
+/// ```
+pub fn net_torque(masses: &[f32], positions: &[Vec3], accelerations: &[Vec3]) -> Vec3 {
+    let com = center_of_mass(masses, positions);
+    masses
+        .iter()
+        .zip(positions)
+        .zip(accelerations)
+        .fold(Vec3::ZERO, |torque, ((m, pos), acc)| {
+            torque + (*pos - com).cross(*m * *acc)
+        })
+}
+
+/// Central forces conserve the angular momentum, i.e. the net torque vanishes.
+/// It is compared to the largest torque the forces could exert to account for rounding errors.
+pub fn is_torque_free(masses: &[f32], positions: &[Vec3], accelerations: &[Vec3]) -> bool {
+    const TOLERANCE: f32 = 1e-3;
+    let com = center_of_mass(masses, positions);
+    let scale: f32 = masses
+        .iter()
+        .zip(positions)
+        .zip(accelerations)
+        .map(|((m, pos), acc)| (*pos - com).length() * (*m * *acc).length())
+        .sum();
+    net_torque(masses, positions, accelerations).length() <= TOLERANCE * scale
+}
+
+/// Removes the components along the normal of the `ConstrainToPlane`,
+/// the later stages of `Integrator::RungeKutta4` and `Integrator::BlockStep` projecting their accelerations too
+fn constrain_to_plane(
+    constraint: Res<ConstrainToPlane>,
+    mut query: Query<(&mut Velocity, &mut Acceleration)>,
+) {
+    if constraint.0.is_none() {
+        return;
+    }
+    for (mut vel, mut acc) in query.iter_mut() {
+        vel.0 = constraint.project(vel.0);
+        acc.0 = constraint.project(acc.0);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_velocity(
+    mut commands: Commands,
+    integrator: Res<Integrator>,
+    dt: Res<TimeStep>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Velocity,
+            &Acceleration,
+            Option<&HalfStepVelocity>,
+        ),
+        (Without<Pinned>, Without<Frozen>),
+    >,
+) {
+    if integrator.steps_whole() {
+        return;
+    }
+    for (entity, mut vel, acc, half_step) in query.iter_mut() {
+        match (*integrator, half_step) {
+            // The closing half kick of a leapfrog step and the opening one of the next step add up to a full kick,
+            // only the very first step starts with a half kick
+            (Integrator::Leapfrog, None) | (Integrator::VelocityVerlet, None) => {
+                vel.0 += acc.0 * dt.0 / 2.0;
+                commands.entity(entity).insert(HalfStepVelocity);
+            }
+            _ => vel.0 += acc.0 * dt.0,
+        }
+    }
+}
+
+fn circularize_orbits(
+    damping: Option<Res<CircularizationDamping>>,
+    dt: Res<TimeStep>,
+    mut query: Query<(Entity, &Transform, &mut Velocity, Option<&Frozen>)>,
+) {
+    let damping = match damping {
+        Some(damping) if damping.enabled => damping,
+        _ => return,
+    };
+    let (center, center_vel) = match query.get_mut(damping.central) {
+        Ok((_, transform, vel, _)) => (transform.translation, vel.0),
+        Err(_) => return,
+    };
+
+    // Fraction of the radial velocity removed during a step
+    let decay = 1.0 - (-damping.rate * dt.0).exp();
+    for (entity, transform, mut vel, frozen) in query.iter_mut() {
+        if entity == damping.central || frozen.is_some() {
+            continue;
+        }
+        if let Some(radial_dir) = (transform.translation - center).try_normalize() {
+            let radial_vel = (vel.0 - center_vel).dot(radial_dir) * radial_dir;
+            vel.0 -= decay * radial_vel;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn movement(
+    integrator: Res<Integrator>,
+    dt: Res<TimeStep>,
+    mut query: Query<(&mut Transform, &Velocity), (Without<Pinned>, Without<Frozen>)>,
+) {
+    if integrator.steps_whole() {
+        return;
+    }
     for (mut transform, vel) in query.iter_mut() {
-        transform.translation += vel.0 * DT;
+        transform.translation += vel.0 * dt.0;
+    }
+}
+
+/// Whole `Integrator::RungeKutta4` step, in place of `update_velocity` and `movement`.
+/// The first stage reuses the accelerations of `update_acceleration`.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn runge_kutta_step(
+    integrator: Res<Integrator>,
+    dt: Res<TimeStep>,
+    g: Res<Gravity>,
+    softening: Res<SofteningKernel>,
+    cutoff: Res<ForceCutoff>,
+    mode: Res<ForceMode>,
+    max_acc: Res<MaxAcceleration>,
+    extended: Res<ExtendedBody>,
+    isolation: Res<GroupIsolation>,
+    constraint: Res<ConstrainToPlane>,
+    external: Res<ExternalField>,
+    mut query: Query<(
+        &Mass,
+        Option<&GravityCharge>,
+        &Acceleration,
+        &mut Transform,
+        &mut Velocity,
+        Option<&Pinned>,
+        Option<&Frozen>,
+        Option<&Universe>,
+        Option<&Radius>,
+    )>,
+) {
+    if *integrator != Integrator::RungeKutta4 {
+        return;
+    }
+    let mut masses = Vec::new();
+    let mut charges = Vec::new();
+    let mut universes = Vec::new();
+    let mut positions = Vec::new();
+    let mut velocities = Vec::new();
+    let mut accelerations = Vec::new();
+    let mut pinned = Vec::new();
+    let mut radii = Vec::new();
+    for (mass, charge, acc, transform, vel, pin, frozen, universe, radius) in query.iter_mut() {
+        masses.push(mass.0);
+        radii.push(radius.map_or(0.0, |radius| radius.0));
+        charges.push(charge.map_or(1.0, |charge| charge.0));
+        universes.push(isolation.universe(universe));
+        positions.push(transform.translation);
+        velocities.push(vel.0);
+        accelerations.push(acc.0);
+        // Frozen bodies keep their velocity, they are otherwise stepped as pinned ones
+        pinned.push(pin.is_some() || frozen.is_some());
+    }
+
+    let radii = if extended.0 { Some(&radii[..]) } else { None };
+    let eval = |positions: &[Vec3]| -> Vec<Vec3> {
+        let mut accelerations = field(
+            &masses, &charges, radii, &universes, positions, g.0, *softening, *mode, cutoff.0,
+        );
+        external.apply(positions, &mut accelerations);
+        accelerations
+            .into_iter()
+            .map(|acc| {
+                let acc = constraint.project(acc);
+                match max_acc.0 {
+                    Some(max) => clamp_magnitude(acc, max),
+                    None => acc,
+                }
+            })
+            .collect()
+    };
+    // `base + h * derivative`, pinned bodies staying in place
+    let advance = |base: &[Vec3], derivative: &[Vec3], h: f32| -> Vec<Vec3> {
+        base.iter()
+            .zip(derivative)
+            .zip(&pinned)
+            .map(|((x, dx), pinned)| if *pinned { *x } else { *x + *dx * h })
+            .collect()
+    };
+
+    let (x1, v1, a1) = (&positions, &velocities, &accelerations);
+    let (x2, v2) = (advance(x1, v1, dt.0 / 2.0), advance(v1, a1, dt.0 / 2.0));
+    let a2 = eval(&x2);
+    let (x3, v3) = (advance(x1, &v2, dt.0 / 2.0), advance(v1, &a2, dt.0 / 2.0));
+    let a3 = eval(&x3);
+    let (x4, v4) = (advance(x1, &v3, dt.0), advance(v1, &a3, dt.0));
+    let a4 = eval(&x4);
+
+    let weighted = |k1: &[Vec3], k2: &[Vec3], k3: &[Vec3], k4: &[Vec3]| -> Vec<Vec3> {
+        (0..k1.len())
+            .map(|i| (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]) / 6.0)
+            .collect()
+    };
+    let new_positions = advance(x1, &weighted(v1, &v2, &v3, &v4), dt.0);
+    let new_velocities = advance(v1, &weighted(a1, &a2, &a3, &a4), dt.0);
+    for ((_, _, _, mut transform, mut vel, _, _, _, _), (pos, new_vel)) in query
+        .iter_mut()
+        .zip(new_positions.into_iter().zip(new_velocities))
+    {
+        transform.translation = pos;
+        vel.0 = new_vel;
+    }
+}
+
+/// The steps due while paused are dropped rather than caught up on resume
+fn unless_paused(In(should_run): In<ShouldRun>, paused: Res<PhysicsPaused>) -> ShouldRun {
+    if paused.0 {
+        ShouldRun::No
+    } else {
+        should_run
+    }
+}
+
+/// Steps over the `PhysicsThrottle` rate are dropped, not caught up.
+/// The run criteria is checked again after each step of a frame: `last_frame` tells the first check.
+fn throttled(
+    In(should_run): In<ShouldRun>,
+    throttle: Res<PhysicsThrottle>,
+    time: Res<Time>,
+    mut budget: Local<f32>,
+    mut last_frame: Local<f64>,
+) -> ShouldRun {
+    let hz = match throttle.0 {
+        Some(hz) => hz,
+        None => return should_run,
+    };
+    if *last_frame != time.seconds_since_startup() {
+        *last_frame = time.seconds_since_startup();
+        // At most one step left over from the previous frames
+        *budget = budget.min(1.0) + hz * time.delta_seconds();
+    }
+    match should_run {
+        ShouldRun::Yes | ShouldRun::YesAndCheckAgain if *budget >= 1.0 => {
+            *budget -= 1.0;
+            should_run
+        }
+        _ => ShouldRun::No,
+    }
+}
+
+/// Whole `Integrator::BlockStep` step, in place of `update_velocity` and `movement`.
+/// It starts from the accelerations of `update_acceleration`.
+/// The forces of the active bodies are computed for all the bodies, as with the other integrators,
+/// e.g. to account for their charges and universes.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn block_time_step(
+    integrator: Res<Integrator>,
+    dt: Res<TimeStep>,
+    g: Res<Gravity>,
+    softening: Res<SofteningKernel>,
+    cutoff: Res<ForceCutoff>,
+    mode: Res<ForceMode>,
+    max_acc: Res<MaxAcceleration>,
+    extended: Res<ExtendedBody>,
+    isolation: Res<GroupIsolation>,
+    constraint: Res<ConstrainToPlane>,
+    external: Res<ExternalField>,
+    mut query: Query<(
+        &Mass,
+        Option<&GravityCharge>,
+        &mut Acceleration,
+        &mut Transform,
+        &mut Velocity,
+        Option<&Pinned>,
+        Option<&Frozen>,
+        Option<&Universe>,
+        Option<&Radius>,
+    )>,
+) {
+    let (min_dt, max_levels) = match *integrator {
+        Integrator::BlockStep { min_dt, max_levels } => (min_dt, max_levels),
+        _ => return,
+    };
+    let mut masses = Vec::new();
+    let mut charges = Vec::new();
+    let mut universes = Vec::new();
+    let mut positions = Vec::new();
+    let mut velocities = Vec::new();
+    let mut accelerations = Vec::new();
+    let mut fixed = Vec::new();
+    let mut radii = Vec::new();
+    for (mass, charge, acc, transform, vel, pin, frozen, universe, radius) in query.iter_mut() {
+        masses.push(mass.0);
+        charges.push(charge.map_or(1.0, |charge| charge.0));
+        universes.push(isolation.universe(universe));
+        positions.push(transform.translation);
+        velocities.push(vel.0);
+        accelerations.push(acc.0);
+        radii.push(radius.map_or(0.0, |radius| radius.0));
+        // Frozen bodies keep their velocity, they are otherwise stepped as pinned ones
+        fixed.push(pin.is_some() || frozen.is_some());
+    }
+
+    let radii = if extended.0 { Some(&radii[..]) } else { None };
+    let eval = |positions: &[Vec3], active: &[usize]| -> Vec<Vec3> {
+        let accelerations = field(
+            &masses, &charges, radii, &universes, positions, g.0, *softening, *mode, cutoff.0,
+        );
+        active
+            .iter()
+            .map(|i| {
+                let acc = accelerations[*i] + external.acceleration(positions[*i]);
+                let acc = constraint.project(acc);
+                match max_acc.0 {
+                    Some(max) => clamp_magnitude(acc, max),
+                    None => acc,
+                }
+            })
+            .collect()
+    };
+    let levels = block_levels(&positions, &accelerations, dt.0, min_dt, max_levels);
+    block_step(
+        &mut positions,
+        &mut velocities,
+        &mut accelerations,
+        &levels,
+        &fixed,
+        dt.0,
+        eval,
+    );
+    for ((_, _, mut acc, mut transform, mut vel, _, _, _, _), ((pos, new_vel), new_acc)) in query
+        .iter_mut()
+        .zip(positions.into_iter().zip(velocities).zip(accelerations))
+    {
+        transform.translation = pos;
+        vel.0 = new_vel;
+        acc.0 = new_acc;
+    }
+}
+
+/// Whole `Integrator::ImplicitMidpoint` step, in place of `update_velocity` and `movement`.
+/// The initial guess uses the accelerations of `update_acceleration`.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn implicit_midpoint_time_step(
+    integrator: Res<Integrator>,
+    dt: Res<TimeStep>,
+    g: Res<Gravity>,
+    softening: Res<SofteningKernel>,
+    cutoff: Res<ForceCutoff>,
+    mode: Res<ForceMode>,
+    max_acc: Res<MaxAcceleration>,
+    extended: Res<ExtendedBody>,
+    isolation: Res<GroupIsolation>,
+    constraint: Res<ConstrainToPlane>,
+    external: Res<ExternalField>,
+    mut query: Query<(
+        &Mass,
+        Option<&GravityCharge>,
+        &Acceleration,
+        &mut Transform,
+        &mut Velocity,
+        Option<&Pinned>,
+        Option<&Frozen>,
+        Option<&Universe>,
+        Option<&Radius>,
+    )>,
+) {
+    let iterations = match *integrator {
+        Integrator::ImplicitMidpoint { iterations } => iterations,
+        _ => return,
+    };
+    let mut masses = Vec::new();
+    let mut charges = Vec::new();
+    let mut universes = Vec::new();
+    let mut positions = Vec::new();
+    let mut velocities = Vec::new();
+    let mut accelerations = Vec::new();
+    let mut fixed = Vec::new();
+    let mut radii = Vec::new();
+    for (mass, charge, acc, transform, vel, pin, frozen, universe, radius) in query.iter_mut() {
+        masses.push(mass.0);
+        charges.push(charge.map_or(1.0, |charge| charge.0));
+        universes.push(isolation.universe(universe));
+        positions.push(transform.translation);
+        velocities.push(vel.0);
+        accelerations.push(acc.0);
+        radii.push(radius.map_or(0.0, |radius| radius.0));
+        // Frozen bodies keep their velocity, they are otherwise stepped as pinned ones
+        fixed.push(pin.is_some() || frozen.is_some());
+    }
+
+    let radii = if extended.0 { Some(&radii[..]) } else { None };
+    let eval = |positions: &[Vec3]| -> Vec<Vec3> {
+        let mut accelerations = field(
+            &masses, &charges, radii, &universes, positions, g.0, *softening, *mode, cutoff.0,
+        );
+        external.apply(positions, &mut accelerations);
+        accelerations
+            .into_iter()
+            .map(|acc| {
+                let acc = constraint.project(acc);
+                match max_acc.0 {
+                    Some(max) => clamp_magnitude(acc, max),
+                    None => acc,
+                }
+            })
+            .collect()
+    };
+    implicit_midpoint_step(
+        &mut positions,
+        &mut velocities,
+        &accelerations,
+        &fixed,
+        dt.0,
+        iterations,
+        eval,
+    );
+    for ((_, _, _, mut transform, mut vel, _, _, _, _), (pos, new_vel)) in
+        query.iter_mut().zip(positions.into_iter().zip(velocities))
+    {
+        transform.translation = pos;
+        vel.0 = new_vel;
+    }
+}
+
+fn advance_time(dt: Res<TimeStep>, mut time: ResMut<SimulationTime>) {
+    time.0 += dt.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::HandleId;
+
+    use super::*;
+    use crate::testing::run_system;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn two_bodies_attract_each_other_symmetrically() {
+        let masses = [2.0, 3.0];
+        let positions = [Vec3::ZERO, Vec3::new(2.0, 0.0, 0.0)];
+        let acc = compute_accelerations(&masses, &positions, 1.0, SofteningKernel::None, None);
+        assert_close(acc[0], Vec3::new(3.0 / 4.0, 0.0, 0.0));
+        assert_close(acc[1], Vec3::new(-2.0 / 4.0, 0.0, 0.0));
+        // Equal and opposite forces
+        assert_close(masses[0] * acc[0], -masses[1] * acc[1]);
+    }
+
+    #[test]
+    fn three_collinear_bodies() {
+        let masses = [1.0, 1.0, 1.0];
+        let positions = [
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::ZERO,
+            Vec3::new(1.0, 0.0, 0.0),
+        ];
+        let acc = compute_accelerations(&masses, &positions, 1.0, SofteningKernel::None, None);
+        // The middle body is pulled equally both ways, the outer ones toward the center
+        assert_close(acc[1], Vec3::ZERO);
+        assert_close(acc[0], Vec3::new(1.0 + 1.0 / 4.0, 0.0, 0.0));
+        assert_close(acc[2], -acc[0]);
+    }
+
+    #[test]
+    fn a_single_body_is_not_accelerated() {
+        let acc = compute_accelerations(&[5.0], &[Vec3::ONE], 1.0, SofteningKernel::None, None);
+        assert_eq!(acc, vec![Vec3::ZERO]);
+    }
+
+    #[test]
+    fn central_forces_exert_no_net_torque() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(105);
+        let masses: Vec<f32> = (0..20).map(|_| rng.gen_range(0.1..10.0)).collect();
+        let positions: Vec<Vec3> = (0..20)
+            .map(|_| Vec3::new(rng.gen(), rng.gen(), rng.gen()) * 10.0)
+            .collect();
+        let acc = compute_accelerations(&masses, &positions, 1.0, SofteningKernel::None, None);
+        assert!(net_torque(&masses, &positions, &acc).length() < 1e-2);
+        assert!(is_torque_free(&masses, &positions, &acc));
+
+        // Pushing a single body sideways does exert a torque
+        let mut pushed = acc;
+        pushed[0] += Vec3::new(0.0, 100.0, 0.0);
+        assert!(!is_torque_free(&masses, &positions, &pushed));
+    }
+
+    /// World with the default physics resources and `G = 1`
+    fn physics_world() -> World {
+        let mut world = World::default();
+        world.insert_resource(Gravity(1.0));
+        world.insert_resource(SofteningKernel::default());
+        world.insert_resource(ForceCutoff::default());
+        world.insert_resource(ForceMode::default());
+        world.insert_resource(MaxAcceleration::default());
+        world.insert_resource(ExtendedBody::default());
+        world.insert_resource(GroupIsolation::default());
+        world.insert_resource(ExternalField::default());
+        world.insert_resource(TimeStep::default());
+        world
+    }
+
+    #[test]
+    fn close_encounter_acceleration_is_clamped() {
+        let mut world = physics_world();
+        world.insert_resource(MaxAcceleration(Some(10.0)));
+        let close = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::ZERO, Vec3::ZERO))
+            .id();
+        world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::new(1e-3, 0.0, 0.0), Vec3::ZERO));
+        let far = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(
+                1e-6,
+                Vec3::new(100.0, 0.0, 0.0),
+                Vec3::ZERO,
+            ))
+            .id();
+
+        run_system(&mut world, update_acceleration.system());
+        // Unclamped, the acceleration would be 1e6
+        let acc = world.get::<Acceleration>(close).unwrap().0;
+        assert!((acc.length() - 10.0).abs() < 1e-4);
+        assert!(acc.x > 0.0);
+        // Accelerations under the limit are left as is
+        let acc = world.get::<Acceleration>(far).unwrap().0;
+        assert!((acc.length() - 2.0 / 100.0f32.powi(2)).abs() < 1e-6);
+
+        assert_eq!(
+            clamp_magnitude(Vec3::new(3.0, 4.0, 0.0), 1.0),
+            Vec3::new(0.6, 0.8, 0.0)
+        );
+    }
+
+    /// Physics systems of a step with the default integrator, in the order of the `NBody` plugin
+    fn physics_stage() -> SystemStage {
+        physics_systems(SystemStage::single_threaded())
+    }
+
+    fn physics_systems(stage: SystemStage) -> SystemStage {
+        use PhysicsSystem::*;
+        stage
+            .with_system(update_acceleration.system().label(UpdateAcceleration))
+            .with_system(
+                constrain_to_plane
+                    .system()
+                    .label(PlaneConstraint)
+                    .after(UpdateAcceleration),
+            )
+            .with_system(
+                update_velocity
+                    .system()
+                    .label(UpdateVelocity)
+                    .after(PlaneConstraint),
+            )
+            .with_system(
+                circularize_orbits
+                    .system()
+                    .label(Circularization)
+                    .after(UpdateVelocity),
+            )
+            .with_system(movement.system().after(Circularization))
+    }
+
+    fn step_world(world: &mut World, stage: &mut SystemStage, steps: usize) {
+        for _ in 0..steps {
+            stage.run(world);
+        }
+    }
+
+    #[test]
+    fn damping_circularizes_an_eccentric_orbit() {
+        let mut world = physics_world();
+        world.insert_resource(Integrator::default());
+        world.insert_resource(ConstrainToPlane::default());
+        let star = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::ZERO, Vec3::ZERO))
+            .insert(Pinned)
+            .id();
+        let particle = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(0.0, Vec3::X, Vec3::new(0.3, 0.8, 0.0)))
+            .id();
+        world.insert_resource(CircularizationDamping {
+            rate: 0.5,
+            central: star,
+            enabled: true,
+        });
+
+        let mut stage = physics_stage();
+        let state = |world: &World| {
+            let pos = world.get::<Transform>(particle).unwrap().translation;
+            let vel = world.get::<Velocity>(particle).unwrap().0;
+            (vel.dot(pos.normalize()).abs(), pos.cross(vel).length())
+        };
+        let max_radial_speed = |world: &mut World, stage: &mut SystemStage| {
+            (0..300).fold(0.0f32, |max, _| {
+                step_world(world, stage, 1);
+                max.max(state(world).0)
+            })
+        };
+        let (_, initial_momentum) = state(&world);
+        let initial_radial_speed = max_radial_speed(&mut world, &mut stage);
+        step_world(&mut world, &mut stage, 3000);
+        let final_radial_speed = max_radial_speed(&mut world, &mut stage);
+        let (_, final_momentum) = state(&world);
+
+        assert!(final_radial_speed < 0.1 * initial_radial_speed);
+        assert!((final_momentum - initial_momentum).abs() < 0.01 * initial_momentum);
+    }
+
+    /// Four bodies at the corners of a square, attracting each other, the first `pinned` ones being pinned
+    fn square_of_bodies(world: &mut World, pinned: usize) -> Vec<Entity> {
+        let corners = [Vec3::X, Vec3::Y, -Vec3::X, -Vec3::Y];
+        corners
+            .iter()
+            .enumerate()
+            .map(|(i, pos)| {
+                let mut body = world.spawn();
+                body.insert_bundle(BodyBundle::new(1.0, *pos, Vec3::ZERO));
+                if i < pinned {
+                    body.insert(Pinned);
+                }
+                body.id()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_unpinned_bodies_move() {
+        let mut world = physics_world();
+        world.insert_resource(Integrator::default());
+        world.insert_resource(ConstrainToPlane::default());
+        let bodies = square_of_bodies(&mut world, 2);
+        let initial: Vec<Vec3> = bodies
+            .iter()
+            .map(|body| world.get::<Transform>(*body).unwrap().translation)
+            .collect();
+
+        step_world(&mut world, &mut physics_stage(), 1);
+        for (i, (body, initial)) in bodies.iter().zip(initial).enumerate() {
+            let pos = world.get::<Transform>(*body).unwrap().translation;
+            assert_eq!(pos == initial, i < 2, "body {} at {}", i, pos);
+        }
+    }
+
+    #[test]
+    fn frozen_bodies_stay_put_but_still_attract() {
+        let mut world = physics_world();
+        world.insert_resource(Integrator::default());
+        world.insert_resource(ConstrainToPlane::default());
+        let frozen = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::ZERO, Vec3::Y))
+            .insert(Frozen)
+            .id();
+        let other = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::X, Vec3::ZERO))
+            .id();
+
+        step_world(&mut world, &mut physics_stage(), 1);
+        assert_eq!(
+            world.get::<Transform>(frozen).unwrap().translation,
+            Vec3::ZERO
+        );
+        assert_eq!(world.get::<Velocity>(frozen).unwrap().0, Vec3::Y);
+        let vel = world.get::<Velocity>(other).unwrap().0;
+        assert!(vel.x < 0.0 && vel.y == 0.0, "{}", vel);
+        assert!(world.get::<Transform>(other).unwrap().translation.x < 1.0);
+    }
+
+    #[test]
+    fn bodies_with_extra_components_are_simulated() {
+        use bevy::ecs::system::CommandQueue;
+
+        #[derive(Debug, PartialEq)]
+        struct CatalogId(u32);
+
+        let mut world = physics_world();
+        world.insert_resource(Integrator::default());
+        world.insert_resource(ConstrainToPlane::default());
+        world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::ZERO, Vec3::ZERO));
+        let mut queue = CommandQueue::default();
+        let body = BodyBundle::builder()
+            .position(Vec3::X)
+            .with(CatalogId(399))
+            .spawn(&mut Commands::new(&mut queue, &world));
+        queue.apply(&mut world);
+
+        step_world(&mut world, &mut physics_stage(), 1);
+        assert_eq!(world.get::<CatalogId>(body), Some(&CatalogId(399)));
+        assert!(world.get::<Transform>(body).unwrap().translation.x < 1.0);
+    }
+
+    #[test]
+    fn builder_sets_every_field() {
+        let (bundle, appearance) = BodyBundle::builder()
+            .mass(5.0)
+            .position(Vec3::X)
+            .velocity(Vec3::Y)
+            .radius(0.2)
+            .color(Color::BLUE)
+            .name("Earth")
+            .class(BodyClass::Planet)
+            .build();
+        assert_eq!(bundle.mass.0, 5.0);
+        assert_eq!(bundle.transform.translation, Vec3::X);
+        assert_eq!(bundle.vel.0, Vec3::Y);
+        assert_eq!(
+            appearance,
+            BodyAppearance {
+                radius: Some(0.2),
+                color: Some(Color::BLUE),
+                texture: None,
+                name: Some("Earth".to_string()),
+                class: Some(BodyClass::Planet),
+            }
+        );
+
+        // Same defaults as `BodyBundle::new`
+        let (bundle, appearance) = BodyBundle::builder().build();
+        assert_eq!(bundle.mass.0, 1.0);
+        assert_eq!(bundle.transform, Transform::identity());
+        assert_eq!(bundle.vel.0, Vec3::ZERO);
+        assert_eq!(appearance, BodyAppearance::default());
+
+        // A homogeneous sphere of density 3/(4π) has the radius of the cube root of its mass
+        let (_, appearance) = BodyBundle::builder()
+            .mass(8.0)
+            .density(3.0 / (4.0 * std::f32::consts::PI))
+            .build();
+        assert!((appearance.radius.unwrap() - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn pairs_beyond_the_cutoff_do_not_interact() {
+        let masses = [1.0, 2.0, 3.0];
+        let positions = [
+            Vec3::ZERO,
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(10.0, 0.0, 0.0),
+        ];
+        let full = compute_accelerations(&masses, &positions, 1.0, SofteningKernel::None, None);
+        let cut = compute_accelerations(&masses, &positions, 1.0, SofteningKernel::None, Some(5.0));
+
+        // The close pair alone
+        let pair = compute_accelerations(
+            &masses[..2],
+            &positions[..2],
+            1.0,
+            SofteningKernel::None,
+            None,
+        );
+        assert_eq!(cut[..2], pair[..]);
+        // The far body is out of reach of both
+        assert_eq!(cut[2], Vec3::ZERO);
+        assert_ne!(full[2], Vec3::ZERO);
+
+        // All the pairs within the cutoff match the full computation
+        let within =
+            compute_accelerations(&masses, &positions, 1.0, SofteningKernel::None, Some(20.0));
+        assert_eq!(within, full);
+    }
+
+    #[test]
+    fn a_charge_of_two_doubles_the_force_exerted_and_felt() {
+        let masses = [1.0, 3.0];
+        let positions = [Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0)];
+        let newtonian = compute_charged_accelerations(
+            &masses,
+            &[1.0, 1.0],
+            &positions,
+            1.0,
+            SofteningKernel::None,
+            None,
+        );
+        assert_close(
+            newtonian[0],
+            compute_accelerations(&masses, &positions, 1.0, SofteningKernel::None, None)[0],
+        );
+
+        let charged = compute_charged_accelerations(
+            &masses,
+            &[2.0, 1.0],
+            &positions,
+            1.0,
+            SofteningKernel::None,
+            None,
+        );
+        // F = m * a on each side, still equal and opposite
+        assert_close(masses[0] * charged[0], 2.0 * masses[0] * newtonian[0]);
+        assert_close(masses[1] * charged[1], 2.0 * masses[1] * newtonian[1]);
+        assert_close(masses[0] * charged[0], -masses[1] * charged[1]);
+        assert_close(charged[0], Vec3::new(6.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn acceleration_at_points_toward_a_single_mass() {
+        let bodies = [(5.0, Vec3::new(0.0, 2.0, 0.0))];
+        // G*M/r^2 = 3 * 5 / 4
+        assert_close(
+            acceleration_at(Vec3::ZERO, &bodies, 3.0, SofteningKernel::None),
+            Vec3::new(0.0, 3.75, 0.0),
+        );
+        // No self-interaction
+        assert_eq!(
+            acceleration_at(
+                Vec3::new(0.0, 2.0, 0.0),
+                &bodies,
+                3.0,
+                SofteningKernel::None
+            ),
+            Vec3::ZERO
+        );
+    }
+
+    #[test]
+    fn acceleration_at_vanishes_between_two_equal_masses() {
+        let bodies = [
+            (2.0, Vec3::new(-1.0, 1.0, 0.0)),
+            (2.0, Vec3::new(1.0, -1.0, 0.0)),
+        ];
+        assert_close(
+            acceleration_at(Vec3::ZERO, &bodies, 1.0, SofteningKernel::None),
+            Vec3::ZERO,
+        );
+    }
+
+    #[test]
+    fn accelerations_scale_with_the_gravitational_constant() {
+        let mut world = physics_world();
+        let bodies = square_of_bodies(&mut world, 0);
+        let state = |world: &World| {
+            bodies
+                .iter()
+                .map(|body| {
+                    (
+                        world.get::<Acceleration>(*body).unwrap().0,
+                        world.get::<Transform>(*body).unwrap().translation,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        run_system(&mut world, update_acceleration.system());
+        let before = state(&world);
+
+        world.insert_resource(Gravity(2.5));
+        run_system(&mut world, update_acceleration.system());
+        for ((acc, pos), (acc_before, pos_before)) in state(&world).into_iter().zip(before) {
+            assert_close(acc, 2.5 * acc_before);
+            assert_eq!(pos, pos_before);
+        }
+    }
+
+    #[test]
+    fn kernels_are_newtonian_at_large_separation() {
+        let kernels = [
+            SofteningKernel::None,
+            SofteningKernel::Plummer { eps: 0.1 },
+            SofteningKernel::CubicSpline { h: 0.5 },
+        ];
+        for kernel in kernels.iter() {
+            for r in [10.0f32, 100.0].iter() {
+                let force = kernel.force(r * r);
+                let potential = kernel.potential(r * r);
+                assert!(
+                    (force * r * r - 1.0).abs() < 1e-3,
+                    "{:?}: {}",
+                    kernel,
+                    force
+                );
+                assert!(
+                    (potential * r + 1.0).abs() < 1e-3,
+                    "{:?}: {}",
+                    kernel,
+                    potential
+                );
+            }
+        }
+        // The spline is exactly Newtonian beyond its smoothing length
+        let spline = SofteningKernel::CubicSpline { h: 0.5 };
+        assert_eq!(spline.force(0.36), 1.0 / 0.36);
+        assert_eq!(spline.potential(0.36), -1.0 / 0.6);
+    }
+
+    #[test]
+    fn softened_kernels_are_finite_at_zero_separation() {
+        let kernels = [
+            SofteningKernel::Plummer { eps: 0.1 },
+            SofteningKernel::CubicSpline { h: 0.5 },
+        ];
+        for kernel in kernels.iter() {
+            assert_eq!(kernel.force(0.0), 0.0, "{:?}", kernel);
+            assert!(kernel.potential(0.0).is_finite(), "{:?}", kernel);
+            // Bounded all the way down
+            let max = (1..=100)
+                .map(|i| kernel.force((i as f32 * 0.01).powi(2)))
+                .fold(0.0, f32::max);
+            assert!(max.is_finite() && max < 1e3, "{:?}: {}", kernel, max);
+        }
+        // The spline is continuous where its pieces meet
+        let spline = SofteningKernel::CubicSpline { h: 1.0 };
+        for r in [0.5f32, 1.0].iter() {
+            let (below, above) = ((r - 1e-4).powi(2), (r + 1e-4).powi(2));
+            assert!((spline.force(below) - spline.force(above)).abs() < 1e-2);
+            assert!((spline.potential(below) - spline.potential(above)).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn force_inside_a_uniform_sphere_is_linear() {
+        let radius = 2.0f32;
+        for kernel in [SofteningKernel::None, SofteningKernel::Plummer { eps: 0.1 }].iter() {
+            let surface = kernel.force(radius * radius);
+            for r in [0.5f32, 1.0, 1.5].iter() {
+                let force = kernel.sphere_force(r * r, radius);
+                assert!((force - surface * r / radius).abs() < 1e-6, "{:?}", kernel);
+            }
+            // Continuous at the surface, the outer force law beyond it
+            let below = kernel.sphere_force((radius - 1e-4).powi(2), radius);
+            assert!((below - surface).abs() < 1e-3 * surface);
+            assert_eq!(kernel.sphere_force(9.0, radius), kernel.force(9.0));
+            assert_eq!(kernel.sphere_force(0.0, radius), 0.0);
+        }
+    }
+
+    #[test]
+    fn satellite_moves_along_with_its_primary() {
+        let elements = OrbitalElements {
+            semi_major_axis: 2.0,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            longitude_of_ascending_node: 0.0,
+            argument_of_periapsis: 0.0,
+            mean_anomaly: 0.0,
+        };
+        let (primary_pos, primary_vel) = (Vec3::new(10.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 3.0));
+        let bundle = BodyBundle::orbit_around(1.0, 7.0, primary_pos, primary_vel, elements, 0.25);
+
+        // Circular speed sqrt(G*(M + m) / a) = 1 around the primary, in the plane of the orbit
+        let local_pos = bundle.transform.translation - primary_pos;
+        let local_vel = bundle.vel.0 - primary_vel;
+        assert!((local_pos.length() - 2.0).abs() < 1e-5);
+        assert!((local_vel.length() - 1.0).abs() < 1e-5);
+        assert!(local_pos.dot(local_vel).abs() < 1e-5);
+        let (pos, vel) = elements.state(0.25 * 8.0);
+        assert!((bundle.transform.translation - (primary_pos + pos)).length() < 1e-6);
+        assert!((bundle.vel.0 - (primary_vel + vel)).length() < 1e-6);
+    }
+
+    #[test]
+    fn results_do_not_depend_on_the_thread_count() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let run = |threads: usize| {
+            let mut world = physics_world();
+            world.insert_resource(Integrator::default());
+            world.insert_resource(ConstrainToPlane::default());
+            PhysicsThreads(threads)
+                .task_pool_options()
+                .create_default_pools(&mut world);
+            let mut rng = StdRng::seed_from_u64(3);
+            for _ in 0..50 {
+                let mut vec3 = || Vec3::new(rng.gen(), rng.gen(), rng.gen());
+                let (pos, vel) = (vec3(), vec3());
+                world
+                    .spawn()
+                    .insert_bundle(BodyBundle::new(1.0, 10.0 * pos, vel));
+            }
+            step_world(
+                &mut world,
+                &mut physics_systems(SystemStage::parallel()),
+                20,
+            );
+            let mut states: Vec<(u32, Vec3, Vec3)> = world
+                .query::<(Entity, &Transform, &Velocity)>()
+                .iter(&world)
+                .map(|(entity, transform, vel)| (entity.id(), transform.translation, vel.0))
+                .collect();
+            states.sort_by_key(|(id, ..)| *id);
+            states
+        };
+
+        assert_eq!(run(1), run(4));
+    }
+
+    #[test]
+    fn plane_constraint_keeps_the_bodies_at_their_height() {
+        let run = |constraint: ConstrainToPlane| {
+            let mut world = physics_world();
+            world.insert_resource(Integrator::default());
+            world.insert_resource(constraint);
+            let bodies = [
+                (1.0, Vec3::new(0.0, 0.0, 0.5), Vec3::new(0.0, -0.2, 0.3)),
+                (0.5, Vec3::new(2.0, 0.0, -0.5), Vec3::new(0.0, 0.7, 0.1)),
+                (0.1, Vec3::new(-1.0, 1.0, 1.0), Vec3::new(0.3, 0.0, -0.2)),
+            ];
+            for &(mass, pos, vel) in bodies.iter() {
+                world.spawn().insert_bundle(BodyBundle::new(mass, pos, vel));
+            }
+            step_world(&mut world, &mut physics_stage(), 200);
+            world
+                .query::<&Transform>()
+                .iter(&world)
+                .map(|transform| transform.translation)
+                .collect::<Vec<_>>()
+        };
+
+        let free = run(ConstrainToPlane::default());
+        let constrained = run(ConstrainToPlane(Some(2.0 * Vec3::Z)));
+        for (pos, z) in constrained.iter().zip([0.5, -0.5, 1.0].iter()) {
+            assert_eq!(pos.z, *z);
+        }
+        // The bodies still move in the plane, and out of it without the constraint
+        assert!(constrained[1].y > 0.5);
+        assert!(free
+            .iter()
+            .zip(&constrained)
+            .all(|(free, pos)| free.z != pos.z));
+
+        assert_eq!(
+            ConstrainToPlane(Some(Vec3::Y)).project(Vec3::new(1.0, 2.0, 3.0)),
+            Vec3::new(1.0, 0.0, 3.0)
+        );
+        assert_eq!(ConstrainToPlane(None).project(Vec3::ONE), Vec3::ONE);
+    }
+
+    #[test]
+    fn isolated_groups_do_not_attract_each_other() {
+        let run = |isolation: GroupIsolation| {
+            let mut world = physics_world();
+            world.insert_resource(isolation);
+            let mut spawn = |pos: Vec3, universe: u32| {
+                world
+                    .spawn()
+                    .insert_bundle(BodyBundle::new(1.0, pos, Vec3::ZERO))
+                    .insert(Universe(universe))
+                    .id()
+            };
+            let a = spawn(Vec3::ZERO, 0);
+            let b = spawn(Vec3::X, 1);
+            let c = spawn(-2.0 * Vec3::X, 1);
+            run_system(&mut world, update_acceleration.system());
+            let acc = |body| world.get::<Acceleration>(body).unwrap().0;
+            (acc(a), acc(b), acc(c))
+        };
+
+        let (a, b, c) = run(GroupIsolation(true));
+        assert_eq!(a, Vec3::ZERO);
+        // Only attracted by each other, 3 apart
+        assert!((b - Vec3::new(-1.0 / 9.0, 0.0, 0.0)).length() < 1e-6);
+        assert!((c + b).length() < 1e-6);
+
+        let (a, ..) = run(GroupIsolation(false));
+        assert!((a - Vec3::new(0.75, 0.0, 0.0)).length() < 1e-6);
+    }
+
+    #[test]
+    fn textured_bodies_get_the_texture_in_their_material() {
+        let texture = Handle::<Texture>::weak(HandleId::random::<Texture>());
+        let (_, appearance) = BodyBundle::builder()
+            .color(Color::ORANGE)
+            .texture(texture.clone())
+            .build();
+        let material = appearance.material();
+        assert_eq!(material.base_color_texture, Some(texture));
+        // Tinted by the color
+        assert_eq!(material.base_color, Color::ORANGE);
+
+        let (_, appearance) = BodyBundle::builder()
+            .color(Color::YELLOW)
+            .class(BodyClass::Star)
+            .build();
+        let material = appearance.material();
+        assert_eq!(material.base_color_texture, None);
+        assert_eq!(material.emissive, Color::YELLOW);
+    }
+
+    #[test]
+    fn barycenter_marker_takes_no_part_in_gravity() {
+        use crate::plugins::barycenter::BarycenterMarker;
+
+        let run = |with_marker: bool| {
+            let mut world = physics_world();
+            world.insert_resource(Integrator::default());
+            world.insert_resource(ConstrainToPlane::default());
+            let a = world
+                .spawn()
+                .insert_bundle(BodyBundle::new(1.0, -Vec3::X, 0.5 * Vec3::Y))
+                .id();
+            let b = world
+                .spawn()
+                .insert_bundle(BodyBundle::new(1.0, Vec3::X, -0.5 * Vec3::Y))
+                .id();
+            // Right next to a body, where it would pull the hardest
+            let marker = world
+                .spawn()
+                .insert_bundle((Transform::from_xyz(-0.9, 0.0, 0.0), BarycenterMarker))
+                .id();
+            if !with_marker {
+                world.despawn(marker);
+            }
+            step_world(&mut world, &mut physics_stage(), 100);
+            let position = |entity| world.get::<Transform>(entity).map(|t| t.translation);
+            (position(a), position(b), position(marker))
+        };
+
+        let (a, b, marker) = run(true);
+        assert_eq!((a, b), (run(false).0, run(false).1));
+        // Not moved by the physics either
+        assert_eq!(marker, Some(Vec3::new(-0.9, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn uniform_field_shifts_every_acceleration_equally() {
+        let positions = [Vec3::ZERO, Vec3::X, Vec3::new(0.0, 2.0, 1.0)];
+        let run = |field: ExternalField| {
+            let mut world = physics_world();
+            world.insert_resource(field);
+            let bodies: Vec<Entity> = positions
+                .iter()
+                .map(|pos| {
+                    world
+                        .spawn()
+                        .insert_bundle(BodyBundle::new(1.0, *pos, Vec3::ZERO))
+                        .id()
+                })
+                .collect();
+            run_system(&mut world, update_acceleration.system());
+            bodies
+                .iter()
+                .map(|body| world.get::<Acceleration>(*body).unwrap().0)
+                .collect::<Vec<_>>()
+        };
+
+        let field = Vec3::new(0.5, -1.0, 2.0);
+        let free = run(ExternalField::default());
+        let shifted = run(ExternalField::uniform(field));
+        for (free, shifted) in free.iter().zip(&shifted) {
+            assert!((*shifted - *free - field).length() < 1e-5);
+        }
+        // The relative accelerations, hence the internal separations, are unchanged
+        let relative = |acc: &[Vec3]| (acc[1] - acc[0], acc[2] - acc[0]);
+        let (a, b) = relative(&free);
+        let (c, d) = relative(&shifted);
+        assert!((a - c).length() < 1e-5 && (b - d).length() < 1e-5);
+    }
+
+    #[test]
+    fn tidal_field_accelerates_proportionally_to_the_position() {
+        let tidal = ExternalField::point_mass_tides(1.0, 1000.0, 100.0 * Vec3::X);
+        // Stretched along the axis towards the mass, squeezed across it
+        assert!((tidal * Vec3::X - Vec3::new(2e-3, 0.0, 0.0)).length() < 1e-7);
+        assert!((tidal * Vec3::Y + Vec3::new(0.0, 1e-3, 0.0)).length() < 1e-7);
+
+        let mut world = physics_world();
+        world.insert_resource(ExternalField {
+            uniform: Vec3::ZERO,
+            tidal,
+        });
+        // Massless test particles, feeling only the field
+        let positions = [Vec3::new(3.0, 0.0, 0.0), Vec3::new(1.0, -2.0, 4.0)];
+        let bodies: Vec<Entity> = positions
+            .iter()
+            .map(|pos| {
+                world
+                    .spawn()
+                    .insert_bundle(BodyBundle::new(0.0, *pos, Vec3::ZERO))
+                    .id()
+            })
+            .collect();
+        run_system(&mut world, update_acceleration.system());
+        for (body, pos) in bodies.iter().zip(&positions) {
+            let acc = world.get::<Acceleration>(*body).unwrap().0;
+            assert!((acc - tidal * *pos).length() < 1e-7, "{} at {}", acc, pos);
+        }
+    }
+
+    #[test]
+    fn frozen_bodies_stay_in_place_during_the_implicit_midpoint_iterations() {
+        let masses = [1.0, 0.5, 0.5];
+        let run = |heavy_velocity: Vec3| {
+            let mut positions = vec![Vec3::ZERO, Vec3::X, Vec3::new(0.0, 2.0, 0.0)];
+            let mut velocities = vec![heavy_velocity, Vec3::Y, -0.7 * Vec3::X];
+            let fixed = [true, false, false];
+            for _ in 0..100 {
+                let eval = |positions: &[Vec3]| {
+                    compute_accelerations(&masses, positions, 1.0, SofteningKernel::None, None)
+                };
+                let accelerations = eval(&positions);
+                implicit_midpoint_step(
+                    &mut positions,
+                    &mut velocities,
+                    &accelerations,
+                    &fixed,
+                    DT,
+                    4,
+                    eval,
+                );
+            }
+            (positions, velocities)
+        };
+
+        // Frozen with its velocity kept, against pinned at rest
+        let (frozen, frozen_velocities) = run(Vec3::new(3.0, -2.0, 1.0));
+        let (pinned, _) = run(Vec3::ZERO);
+        assert_eq!(frozen[0], Vec3::ZERO);
+        assert_eq!(frozen_velocities[0], Vec3::new(3.0, -2.0, 1.0));
+        assert_eq!(frozen[1..], pinned[1..]);
+    }
+
+    #[test]
+    fn physics_steps_advance_by_the_time_step() {
+        let mut world = physics_world();
+        world.insert_resource(TimeStep(0.05));
+        world.insert_resource(Integrator::default());
+        world.insert_resource(ConstrainToPlane::default());
+        world.insert_resource(SimulationTime::default());
+        let body = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::ZERO, Vec3::X))
+            .id();
+
+        let mut stage = physics_stage().with_system(advance_time.system());
+        step_world(&mut world, &mut stage, 4);
+        let position = world.get::<Transform>(body).unwrap().translation;
+        assert!((position - 0.2 * Vec3::X).length() < 1e-6);
+        assert!((world.get_resource::<SimulationTime>().unwrap().0 - 0.2).abs() < 1e-6);
     }
 }
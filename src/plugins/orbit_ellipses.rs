@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+
+use super::hill_sphere::dominant_primary;
+use super::kepler::OrbitalElements;
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{Gravity, Mass, Velocity};
+use super::prediction::line_mesh;
+
+/// Full Kepler ellipse of each bound body around its dominant primary, from its current orbital elements,
+/// i.e. the orbit it would follow without perturbations, unlike the trails showing the past path:
+/// * J: toggle the orbit ellipses
+pub struct OrbitEllipsesPlugin;
+
+impl Plugin for OrbitEllipsesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::ToggleOrbitEllipses,
+            "toggle the osculating orbit of the bodies around their primary",
+        )
+        .init_resource::<OrbitEllipses>()
+        .add_startup_system(spawn_orbit_ellipses.system())
+        .add_system(toggle_orbit_ellipses.system())
+        .add_system(draw_orbit_ellipses.system());
+    }
+}
+
+pub struct OrbitEllipses {
+    pub enabled: bool,
+    /// Number of segments of each ellipse
+    pub segments: usize,
+}
+
+impl Default for OrbitEllipses {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            segments: 128,
+        }
+    }
+}
+
+pub const ORBIT_ELLIPSE_COLOR: Color = Color::rgba(0.4, 0.8, 1.0, 0.4);
+
+struct OrbitEllipseLines;
+
+/// Line list of the closed curve going through `points`
+pub fn closed_line_vertices(points: &[Vec3]) -> Vec<[f32; 3]> {
+    if points.len() < 2 {
+        return Vec::new();
+    }
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .flat_map(|(a, b)| vec![(*a).into(), (*b).into()])
+        .collect()
+}
+
+/// Ellipse of the body at `pos` moving at `vel` around its dominant primary among the `(mass, position, velocity)`
+/// of the `others`, `None` if it isn't bound to one
+pub fn orbit_ellipse(
+    mass: f32,
+    pos: Vec3,
+    vel: Vec3,
+    others: &[(f32, Vec3, Vec3)],
+    g: f32,
+    segments: usize,
+) -> Option<Vec<Vec3>> {
+    let masses: Vec<(f32, Vec3)> = others.iter().map(|(m, p, _)| (*m, *p)).collect();
+    let (primary_mass, primary_pos, primary_vel) = others[dominant_primary(mass, pos, &masses)?];
+    let mu = g * (primary_mass + mass);
+    let elements = OrbitalElements::from_state(pos - primary_pos, vel - primary_vel, mu)?;
+    Some(
+        elements
+            .ellipse(segments)
+            .into_iter()
+            .map(|point| primary_pos + point)
+            .collect(),
+    )
+}
+
+fn spawn_orbit_ellipses(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(line_mesh(vec![[0.0; 3]; 2])),
+            material: materials.add(StandardMaterial {
+                base_color: ORBIT_ELLIPSE_COLOR,
+                unlit: true,
+                ..Default::default()
+            }),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(OrbitEllipseLines);
+}
+
+fn toggle_orbit_ellipses(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut ellipses: ResMut<OrbitEllipses>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleOrbitEllipses) {
+        ellipses.enabled = !ellipses.enabled;
+    }
+}
+
+/// Redrawn every frame as the elements evolve under the perturbations of the other bodies
+fn draw_orbit_ellipses(
+    ellipses: Res<OrbitEllipses>,
+    g: Res<Gravity>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    bodies: Query<(&Mass, &Transform, &Velocity)>,
+    mut lines: Query<(&Handle<Mesh>, &mut Visible), With<OrbitEllipseLines>>,
+) {
+    let (mesh, mut visible) = match lines.single_mut() {
+        Ok(lines) => lines,
+        Err(_) => return,
+    };
+    visible.is_visible = false;
+    if !ellipses.enabled {
+        return;
+    }
+
+    let bodies: Vec<(f32, Vec3, Vec3)> = bodies
+        .iter()
+        .map(|(mass, transform, vel)| (mass.0, transform.translation, vel.0))
+        .collect();
+    let vertices: Vec<[f32; 3]> = bodies
+        .iter()
+        .filter_map(|(mass, pos, vel)| {
+            orbit_ellipse(*mass, *pos, *vel, &bodies, g.0, ellipses.segments)
+        })
+        .flat_map(|ellipse| closed_line_vertices(&ellipse))
+        .collect();
+    if vertices.is_empty() {
+        return;
+    }
+    if let Some(mesh) = meshes.get_mut(mesh) {
+        *mesh = line_mesh(vertices);
+    }
+    visible.is_visible = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circular_orbit_is_drawn_as_a_circle() {
+        // Light body at 2 from a star of mass 8, on a circular orbit of speed 2 tilted around x
+        let star = (8.0, Vec3::new(1.0, -1.0, 0.5), Vec3::new(0.0, 0.0, 0.1));
+        let normal = Vec3::new(0.0, -0.6, 0.8);
+        let pos = star.1 + 2.0 * Vec3::X;
+        let vel = star.2 + 2.0 * normal.cross(Vec3::X);
+
+        let ellipse = orbit_ellipse(1e-6, pos, vel, &[star], 1.0, 64).unwrap();
+        assert_eq!(ellipse.len(), 64);
+        for point in ellipse.iter() {
+            let rel = *point - star.1;
+            assert!((rel.length() - 2.0).abs() < 1e-4, "{}", rel.length());
+            assert!(rel.dot(normal).abs() < 1e-4);
+        }
+        assert!(ellipse.iter().any(|point| point.distance(pos) < 1e-4));
+    }
+
+    #[test]
+    fn unbound_bodies_have_no_ellipse() {
+        let star = (1.0, Vec3::ZERO, Vec3::ZERO);
+        assert!(orbit_ellipse(0.0, Vec3::X, 1.5 * Vec3::Y, &[star], 1.0, 16).is_none());
+        assert!(orbit_ellipse(0.0, Vec3::X, 1.4 * Vec3::Y, &[star], 1.0, 16).is_some());
+    }
+
+    #[test]
+    fn closed_line_joins_the_last_point_to_the_first() {
+        let points = [Vec3::X, Vec3::Y, Vec3::Z];
+        assert_eq!(
+            closed_line_vertices(&points),
+            vec![
+                [1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [0.0, 0.0, 1.0],
+                [0.0, 0.0, 1.0],
+                [1.0, 0.0, 0.0],
+            ]
+        );
+        assert!(closed_line_vertices(&points[..1]).is_empty());
+    }
+}
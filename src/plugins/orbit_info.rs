@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+
+use super::hill_sphere::dominant_primary;
+use super::kepler::{specific_orbital_energy, OrbitalElements};
+use super::measure::UnitSystem;
+use super::nbody::{Gravity, Mass, Velocity};
+use super::selection::Selected;
+
+/// Orbit of the selected body around its dominant primary, exposed in `SelectedOrbit`
+/// and logged every second while selected
+pub struct OrbitInfoPlugin;
+
+impl Plugin for OrbitInfoPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SelectedOrbit>()
+            .init_resource::<UnitSystem>()
+            .insert_resource(OrbitLogTimer(Timer::from_seconds(1.0, true)))
+            .add_system(update_selected_orbit.system().label(OrbitInfoSystem))
+            .add_system(log_selected_orbit.system().after(OrbitInfoSystem));
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct OrbitInfoSystem;
+
+/// Orbit of a body relative to its primary
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrbitInfo {
+    pub body: Entity,
+    pub primary: Entity,
+    /// `μ = G*(M + m)`
+    pub mu: f32,
+    /// Negative when bound to the primary, see `specific_orbital_energy`
+    pub specific_energy: f32,
+    /// `None` when unbound
+    pub elements: Option<OrbitalElements>,
+}
+
+impl OrbitInfo {
+    pub fn is_bound(&self) -> bool {
+        self.specific_energy < 0.0
+    }
+}
+
+/// Orbit of the selected body, `None` without selection or primary
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SelectedOrbit(pub Option<OrbitInfo>);
+
+struct OrbitLogTimer(Timer);
+
+#[allow(clippy::type_complexity)]
+fn update_selected_orbit(
+    g: Res<Gravity>,
+    mut orbit: ResMut<SelectedOrbit>,
+    bodies: Query<(Entity, &Mass, &Transform, &Velocity, Option<&Selected>)>,
+) {
+    let mut selected = None;
+    let mut others = Vec::new();
+    for (entity, mass, transform, vel, is_selected) in bodies.iter() {
+        let body = (entity, mass.0, transform.translation, vel.0);
+        match is_selected {
+            Some(_) if selected.is_none() => selected = Some(body),
+            _ => others.push(body),
+        }
+    }
+    orbit.0 = selected.and_then(|(body, mass, pos, vel)| {
+        let masses: Vec<(f32, Vec3)> = others.iter().map(|(_, m, p, _)| (*m, *p)).collect();
+        let (primary, primary_mass, primary_pos, primary_vel) =
+            others[dominant_primary(mass, pos, &masses)?];
+        let mu = g.0 * (primary_mass + mass);
+        let (rel_pos, rel_vel) = (pos - primary_pos, vel - primary_vel);
+        Some(OrbitInfo {
+            body,
+            primary,
+            mu,
+            specific_energy: specific_orbital_energy(rel_pos, rel_vel, mu),
+            elements: OrbitalElements::from_state(rel_pos, rel_vel, mu),
+        })
+    });
+}
+
+fn log_selected_orbit(
+    time: Res<Time>,
+    orbit: Res<SelectedOrbit>,
+    units: Res<UnitSystem>,
+    mut timer: ResMut<OrbitLogTimer>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    let orbit = match orbit.0 {
+        Some(orbit) => orbit,
+        None => return,
+    };
+    match orbit.elements {
+        Some(elements) => info!(
+            "{:?} bound to {:?}: specific energy {:.4e}, a = {:.4}, e = {:.4}, period {}",
+            orbit.body,
+            orbit.primary,
+            orbit.specific_energy,
+            elements.semi_major_axis,
+            elements.eccentricity,
+            units.format_time(elements.period(orbit.mu))
+        ),
+        None => info!(
+            "{:?} unbound from {:?}: specific energy {:.4e}",
+            orbit.body, orbit.primary, orbit.specific_energy
+        ),
+    }
+}
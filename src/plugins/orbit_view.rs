@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+
+use super::hill_sphere::dominant_primary;
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{Mass, Velocity};
+use super::pan_orbit_camera::PanOrbitCamera;
+use super::selection::Selected;
+
+/// Snaps the camera onto the orbit of the selected body around its dominant primary,
+/// e.g. to read its eccentricity or inclination:
+/// * O: view the orbital plane face-on, looking down the orbit normal
+/// * E: view the orbital plane edge-on
+///
+/// The camera keeps its distance and orbits around the primary afterwards.
+pub struct OrbitViewPlugin;
+
+impl Plugin for OrbitViewPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::FaceOnView,
+            "view the orbit of the selected body face-on",
+        )
+        .register_key_binding(
+            Action::EdgeOnView,
+            "view the orbit of the selected body edge-on",
+        )
+        .add_system(snap_to_orbit.system());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrbitView {
+    FaceOn,
+    EdgeOn,
+}
+
+/// Unit normal of the orbital plane, along the specific angular momentum `r × v` relative to the primary,
+/// `None` for a radial or degenerate motion
+pub fn orbit_normal(rel_pos: Vec3, rel_vel: Vec3) -> Option<Vec3> {
+    rel_pos.cross(rel_vel).try_normalize()
+}
+
+/// Any unit vector perpendicular to the unit vector `v`
+fn perpendicular(v: Vec3) -> Vec3 {
+    let other = if v.x.abs() < 0.9 { Vec3::X } else { Vec3::Y };
+    v.cross(other).normalize()
+}
+
+/// Rotation of a camera looking along `-back`, with its up axis as close as possible to `up`
+fn look_rotation(back: Vec3, up: Vec3) -> Quat {
+    let right = match up.cross(back).try_normalize() {
+        Some(right) => right,
+        // `up` is parallel to the view direction, any perpendicular axis does
+        None => perpendicular(back),
+    };
+    Quat::from_rotation_mat3(&Mat3::from_cols(right, back.cross(right), back))
+}
+
+/// Camera rotation viewing the orbit of normal `normal` as `view`.
+/// Face-on, the camera looks down the normal so that the body orbits counterclockwise on screen.
+/// Edge-on, the camera looks along the body's direction `rel_pos` from the primary, with the normal pointing up.
+pub fn orbit_view_rotation(view: OrbitView, normal: Vec3, rel_pos: Vec3) -> Quat {
+    match view {
+        OrbitView::FaceOn => {
+            let up = if normal.cross(Vec3::Y).length_squared() > 1e-6 {
+                Vec3::Y
+            } else {
+                Vec3::Z
+            };
+            look_rotation(normal, up)
+        }
+        OrbitView::EdgeOn => {
+            // Projected in the plane in case of rounding errors
+            let back = (rel_pos - rel_pos.dot(normal) * normal)
+                .try_normalize()
+                .unwrap_or_else(|| perpendicular(normal));
+            look_rotation(back, normal)
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn snap_to_orbit(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    bodies: Query<(&Mass, &Transform, &Velocity, Option<&Selected>), Without<PanOrbitCamera>>,
+    mut cameras: Query<(&mut PanOrbitCamera, &mut Transform)>,
+) {
+    let view = if bindings.just_pressed(&keys, Action::FaceOnView) {
+        OrbitView::FaceOn
+    } else if bindings.just_pressed(&keys, Action::EdgeOnView) {
+        OrbitView::EdgeOn
+    } else {
+        return;
+    };
+
+    let mut selected = None;
+    let mut others = Vec::new();
+    for (mass, transform, vel, is_selected) in bodies.iter() {
+        let body = (mass.0, transform.translation, vel.0);
+        match is_selected {
+            Some(_) if selected.is_none() => selected = Some(body),
+            _ => others.push(body),
+        }
+    }
+    let (mass, pos, vel) = match selected {
+        Some(body) => body,
+        None => return,
+    };
+    let masses: Vec<(f32, Vec3)> = others.iter().map(|(m, p, _)| (*m, *p)).collect();
+    let (_, primary_pos, primary_vel) = match dominant_primary(mass, pos, &masses) {
+        Some(i) => others[i],
+        None => return,
+    };
+    let normal = match orbit_normal(pos - primary_pos, vel - primary_vel) {
+        Some(normal) => normal,
+        None => {
+            warn!("The selected body has no orbital plane around its primary");
+            return;
+        }
+    };
+
+    let rotation = orbit_view_rotation(view, normal, pos - primary_pos);
+    for (mut pan_orbit, mut transform) in cameras.iter_mut() {
+        pan_orbit.focus = primary_pos;
+        pan_orbit.upside_down = false;
+        transform.rotation = rotation;
+        transform.translation = primary_pos + rotation * Vec3::new(0.0, 0.0, pan_orbit.radius);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn views_of_an_orbit_in_the_xy_plane() {
+        let (rel_pos, rel_vel) = (Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.5, 0.0));
+        let normal = orbit_normal(rel_pos, rel_vel).unwrap();
+        assert_close(normal, Vec3::Z);
+        assert_eq!(orbit_normal(rel_pos, -rel_pos), None);
+
+        // Looking down the normal, the body moves counterclockwise: from the right to the top of the screen
+        let face_on = orbit_view_rotation(OrbitView::FaceOn, normal, rel_pos);
+        assert_close(face_on * -Vec3::Z, -normal);
+        assert_close(face_on.conjugate() * rel_pos.normalize(), Vec3::X);
+        assert_close(face_on.conjugate() * rel_vel.normalize(), Vec3::Y);
+
+        // Looking at the primary from behind the body, the plane of the orbit is a horizontal line
+        let edge_on = orbit_view_rotation(OrbitView::EdgeOn, normal, rel_pos);
+        assert_close(edge_on * -Vec3::Z, -Vec3::X);
+        assert_close(edge_on * Vec3::Y, normal);
+    }
+}
@@ -2,14 +2,50 @@ use bevy::input::mouse::{MouseMotion, MouseWheel};
 use bevy::prelude::*;
 use bevy::render::camera::PerspectiveProjection;
 
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+
+/// Orbits the camera around the `WorldUp` axis and its focus, pans and zooms it with the mouse,
+/// and rolls it:
+/// * Ctrl + Q / Ctrl + E: hold to roll the camera left / right
 pub struct PanOrbitCameraPlugin;
 
 impl Plugin for PanOrbitCameraPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system(pan_orbit_camera.system());
+        app.register_key_binding(Action::RollLeft, "hold to roll the camera left")
+            .register_key_binding(Action::RollRight, "hold to roll the camera right")
+            .init_resource::<WorldUp>()
+            .add_system(pan_orbit_camera.system())
+            .add_system(roll_camera.system());
+    }
+}
+
+/// Axis the camera orbits around when dragged horizontally, e.g. `Vec3::Z` to turn around
+/// the normal of the ecliptic for the scenes in the xy plane
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldUp(pub Vec3);
+
+impl Default for WorldUp {
+    fn default() -> Self {
+        Self(Vec3::Y)
+    }
+}
+
+impl std::str::FromStr for WorldUp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "x" => Ok(Self(Vec3::X)),
+            "y" => Ok(Self(Vec3::Y)),
+            "z" => Ok(Self(Vec3::Z)),
+            _ => Err(String::from("Invalid input. Should be: x, y or z")),
+        }
     }
 }
 
+/// Radians per second the camera rolls by while the roll keys are held
+pub const ROLL_SPEED: f32 = std::f32::consts::FRAC_PI_2;
+
 /// SOURCE: https://bevy-cheatbook.github.io/cookbook/pan-orbit-camera.html
 /// Tags an entity as capable of panning and orbiting.
 pub struct PanOrbitCamera {
@@ -29,9 +65,39 @@ impl Default for PanOrbitCamera {
     }
 }
 
+/// Right, up and back axes of a camera of the given rotation, the camera looking along `-back`
+pub fn camera_basis(rotation: Quat) -> (Vec3, Vec3, Vec3) {
+    (rotation * Vec3::X, rotation * Vec3::Y, rotation * Vec3::Z)
+}
+
+/// Whether the up axis of the camera points away from the `world_up`,
+/// dragging horizontally then turning the camera the other way around
+pub fn is_upside_down(rotation: Quat, world_up: Vec3) -> bool {
+    let (_, up, _) = camera_basis(rotation);
+    up.dot(world_up) <= 0.0
+}
+
+/// Rotation of the camera after turning it by `yaw`, then tilting it by `pitch` around its own horizontal axis.
+/// With the default `WorldUp`, the camera turns around its own up axis as it always did,
+/// otherwise around the `world_up` axis, like a turntable.
+pub fn orbit_rotation(rotation: Quat, world_up: Vec3, yaw: f32, pitch: f32) -> Quat {
+    let turned = if world_up == WorldUp::default().0 {
+        rotation * Quat::from_rotation_y(yaw)
+    } else {
+        Quat::from_axis_angle(world_up, yaw) * rotation
+    };
+    turned * Quat::from_rotation_x(pitch)
+}
+
+/// Rotation of the camera after rolling it counterclockwise by `angle` around its view axis
+pub fn roll_rotation(rotation: Quat, angle: f32) -> Quat {
+    rotation * Quat::from_rotation_z(angle)
+}
+
 /// Pan the camera with middle mouse click, zoom with scroll wheel, orbit with right mouse click.
 fn pan_orbit_camera(
     windows: Res<Windows>,
+    world_up: Res<WorldUp>,
     mut ev_motion: EventReader<MouseMotion>,
     mut ev_scroll: EventReader<MouseWheel>,
     input_mouse: Res<Input<MouseButton>>,
@@ -67,8 +133,7 @@ fn pan_orbit_camera(
         if orbit_button_changed {
             // only check for upside down when orbiting started or ended this frame
             // if the camera is "upside" down, panning horizontally would be inverted, so invert the input to make it correct
-            let up = transform.rotation * Vec3::Y;
-            pan_orbit.upside_down = up.y <= 0.0;
+            pan_orbit.upside_down = is_upside_down(transform.rotation, world_up.0);
         }
 
         let mut any = false;
@@ -84,10 +149,8 @@ fn pan_orbit_camera(
                 }
             };
             let delta_y = rotation_move.y / window.y * std::f32::consts::PI;
-            let yaw = Quat::from_rotation_y(-delta_x);
-            let pitch = Quat::from_rotation_x(-delta_y);
-            transform.rotation *= yaw; // rotate around global y axis
-            transform.rotation *= pitch; // rotate around local x axis
+            // rotate around the up axis, then around the local x axis
+            transform.rotation = orbit_rotation(transform.rotation, world_up.0, -delta_x, -delta_y);
         } else if pan.length_squared() > 0.0 {
             any = true;
             // make panning distance independent of resolution and FOV,
@@ -117,8 +180,87 @@ fn pan_orbit_camera(
     }
 }
 
+/// Rolling leaves the camera in place, looking at its focus
+fn roll_camera(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut query: Query<&mut Transform, With<PanOrbitCamera>>,
+) {
+    let direction = match (
+        bindings.pressed(&keys, Action::RollLeft),
+        bindings.pressed(&keys, Action::RollRight),
+    ) {
+        (true, false) => 1.0,
+        (false, true) => -1.0,
+        _ => return,
+    };
+    let angle = direction * ROLL_SPEED * time.delta_seconds();
+    for mut transform in query.iter_mut() {
+        transform.rotation = roll_rotation(transform.rotation, angle);
+    }
+}
+
 fn get_primary_window_size(windows: &Res<Windows>) -> Vec2 {
     let window = windows.get_primary().unwrap();
     Vec2::new(window.width(), window.height())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(a.distance(b) < 1e-5, "{} vs {}", a, b);
+    }
+
+    #[test]
+    fn default_up_turns_the_camera_around_its_own_up_axis() {
+        let tilted = Quat::from_rotation_z(0.5);
+        let rotation = orbit_rotation(tilted, Vec3::Y, 0.3, 0.0);
+        assert_eq!(rotation, tilted * Quat::from_rotation_y(0.3));
+        let (_, up, _) = camera_basis(rotation);
+        assert_near(up, tilted * Vec3::Y);
+    }
+
+    #[test]
+    fn camera_turns_around_the_world_up_axis() {
+        // Looking down at the xy plane from +z
+        let rotation = Quat::IDENTITY;
+        let turned = orbit_rotation(rotation, Vec3::Z, std::f32::consts::FRAC_PI_2, 0.0);
+        let (right, up, back) = camera_basis(turned);
+        // The view axis stays along z while the camera turns in the plane
+        assert_near(back, Vec3::Z);
+        assert_near(right, Vec3::Y);
+        assert_near(up, -Vec3::X);
+
+        // Camera on the side of the xy plane, its up along z
+        let side = Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+        let (_, up, back) = camera_basis(side);
+        assert_near(up, Vec3::Z);
+        assert_near(back, -Vec3::Y);
+        assert!(!is_upside_down(side, Vec3::Z));
+        assert!(is_upside_down(side, Vec3::Y));
+        let (_, up, back) = camera_basis(orbit_rotation(side, Vec3::Z, std::f32::consts::PI, 0.0));
+        assert_near(up, Vec3::Z);
+        assert_near(back, Vec3::Y);
+    }
+
+    #[test]
+    fn rolling_keeps_the_view_axis() {
+        let rotation = Quat::from_rotation_y(0.4);
+        let rolled = roll_rotation(rotation, std::f32::consts::FRAC_PI_2);
+        let (right, up, back) = camera_basis(rolled);
+        let (old_right, old_up, old_back) = camera_basis(rotation);
+        assert_near(back, old_back);
+        assert_near(up, -old_right);
+        assert_near(right, old_up);
+    }
+
+    #[test]
+    fn world_up_from_str() {
+        assert_eq!("z".parse::<WorldUp>(), Ok(WorldUp(Vec3::Z)));
+        assert_eq!("Y".parse::<WorldUp>(), Ok(WorldUp::default()));
+        assert!("w".parse::<WorldUp>().is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use std::time::Instant;
+
+use bevy::prelude::*;
+
+use super::nbody::{ForceMode, Mass, PhysicsStep, PhysicsSystem};
+
+/// Trades accuracy for frame rate: when the direct computation of the forces is estimated to exceed
+/// the time budget, pairs of distant bodies are skipped with a force cutoff
+pub struct AdaptivePerformancePlugin;
+
+impl Plugin for AdaptivePerformancePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AdaptivePerformance>()
+            .init_resource::<ForceStepTiming>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(PhysicsStep)
+                    .with_system(
+                        start_force_timer
+                            .system()
+                            .before(PhysicsSystem::UpdateAcceleration),
+                    )
+                    .with_system(
+                        adjust_force_mode
+                            .system()
+                            .after(PhysicsSystem::UpdateAcceleration),
+                    ),
+            );
+    }
+}
+
+pub struct AdaptivePerformance {
+    pub enabled: bool,
+    /// Time budget of the force computation, in milliseconds per physics step
+    pub target_ms: f32,
+    /// Cutoff distance applied when over budget
+    pub cutoff: f32,
+}
+
+impl Default for AdaptivePerformance {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_ms: 4.0,
+            cutoff: 50.0,
+        }
+    }
+}
+
+/// Fraction of the budget below which the direct computation is restored, to avoid switching back and forth
+const HYSTERESIS: f32 = 0.8;
+
+#[derive(Default)]
+struct ForceStepTiming {
+    start: Option<Instant>,
+    /// Measured duration of the direct computation, per pair of bodies
+    pair_cost_ms: Option<f32>,
+}
+
+/// Number of interacting pairs in the direct computation
+pub fn pair_count(bodies: usize) -> usize {
+    bodies * bodies.saturating_sub(1) / 2
+}
+
+/// Mode of the next force computation given the measured cost of a pair in direct mode
+pub fn choose_force_mode(
+    pair_cost_ms: f32,
+    bodies: usize,
+    settings: &AdaptivePerformance,
+    current: ForceMode,
+) -> ForceMode {
+    let estimated_ms = pair_cost_ms * pair_count(bodies) as f32;
+    let budget_ms = match current {
+        ForceMode::Direct | ForceMode::BarnesHut { .. } => settings.target_ms,
+        ForceMode::Cutoff(_) => HYSTERESIS * settings.target_ms,
+    };
+    if estimated_ms > budget_ms {
+        ForceMode::Cutoff(settings.cutoff)
+    } else {
+        ForceMode::Direct
+    }
+}
+
+fn start_force_timer(settings: Res<AdaptivePerformance>, mut timing: ResMut<ForceStepTiming>) {
+    timing.start = if settings.enabled {
+        Some(Instant::now())
+    } else {
+        None
+    };
+}
+
+fn adjust_force_mode(
+    settings: Res<AdaptivePerformance>,
+    mut timing: ResMut<ForceStepTiming>,
+    mut mode: ResMut<ForceMode>,
+    bodies: Query<&Mass>,
+) {
+    // The tree code already scales, it's left as is
+    if let ForceMode::BarnesHut { .. } = *mode {
+        return;
+    }
+    if !settings.enabled {
+        if *mode != ForceMode::Direct {
+            *mode = ForceMode::Direct;
+        }
+        return;
+    }
+    let elapsed_ms = match timing.start.take() {
+        Some(start) => start.elapsed().as_secs_f32() * 1000.0,
+        None => return,
+    };
+
+    let n = bodies.iter().count();
+    // Only the direct computation tells the cost of all the pairs
+    if *mode == ForceMode::Direct && n > 1 {
+        timing.pair_cost_ms = Some(elapsed_ms / pair_count(n) as f32);
+    }
+    if let Some(pair_cost_ms) = timing.pair_cost_ms {
+        let next = choose_force_mode(pair_cost_ms, n, &settings, *mode);
+        if next != *mode {
+            *mode = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn force_mode_follows_the_estimated_cost() {
+        let settings = AdaptivePerformance {
+            enabled: true,
+            target_ms: 4.0,
+            cutoff: 20.0,
+        };
+        // 1000 bodies: 499 500 pairs
+        assert_eq!(
+            choose_force_mode(1e-5, 1000, &settings, ForceMode::Direct),
+            ForceMode::Cutoff(20.0)
+        );
+        assert_eq!(
+            choose_force_mode(1e-5, 500, &settings, ForceMode::Direct),
+            ForceMode::Direct
+        );
+        // Within the budget but above the hysteresis threshold: the cutoff is kept
+        assert_eq!(
+            choose_force_mode(1e-5, 850, &settings, ForceMode::Cutoff(20.0)),
+            ForceMode::Cutoff(20.0)
+        );
+        assert_eq!(
+            choose_force_mode(1e-5, 850, &settings, ForceMode::Direct),
+            ForceMode::Direct
+        );
+        assert_eq!(
+            choose_force_mode(1e-5, 700, &settings, ForceMode::Cutoff(20.0)),
+            ForceMode::Direct
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use bevy::prelude::*;
+use bevy::render::pipeline::PrimitiveTopology;
+
+use super::hill_sphere::dominant_primary;
+use super::kepler::OrbitalElements;
+use super::nbody::{
+    ExternalField, ForceCutoff, Gravity, Integrator, Mass, SofteningKernel, TimeStep, Velocity,
+};
+use super::selection::Selected;
+use crate::headless::{run_headless, EndCondition, ForceParams, SystemState};
+
+/// Dashed line showing the predicted path of the selected body, see `TrajectoryPrediction`
+pub struct PredictionPlugin;
+
+impl Plugin for PredictionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<TrajectoryPrediction>()
+            .add_startup_system(spawn_prediction_line.system())
+            .add_system(predict_trajectory.system());
+    }
+}
+
+/// How the path of the selected body is predicted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PredictionModel {
+    /// Integration of all the massive bodies, including the perturbations of the others
+    #[default]
+    NBody,
+    /// Kepler orbit around the dominant primary, ignoring the other bodies, see `predict_two_body_path`
+    TwoBody,
+}
+
+pub struct TrajectoryPrediction {
+    pub enabled: bool,
+    pub model: PredictionModel,
+    /// Number of physics steps integrated ahead
+    pub steps: usize,
+    /// Number of physics steps between two points of the predicted path
+    pub sample_every: usize,
+    /// The prediction is only refreshed periodically as it's costly
+    pub refresh: Timer,
+}
+
+impl Default for TrajectoryPrediction {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            model: PredictionModel::default(),
+            steps: 5000,
+            sample_every: 25,
+            refresh: Timer::from_seconds(0.5, true),
+        }
+    }
+}
+
+impl TrajectoryPrediction {
+    /// Predicts the path over the simulated time `horizon` with `points` points, for steps of `dt`
+    pub fn with_horizon(mut self, horizon: f32, points: usize, dt: f32) -> Self {
+        self.steps = (horizon / dt).ceil() as usize;
+        self.sample_every = (self.steps / points.max(1)).max(1);
+        self
+    }
+}
+
+struct PredictionLine;
+
+/// Forward-integrates a copy of `state` with steps of `dt` and returns the positions of the body
+/// at `index`, sampled every `sample_every` steps and starting with its current position
+pub fn predict_path(
+    mut state: SystemState,
+    index: usize,
+    params: &ForceParams,
+    dt: f32,
+    steps: usize,
+    sample_every: usize,
+) -> Vec<Vec3> {
+    let mut path = vec![state.positions[index]];
+    // Even with the leapfrog, the physics systems move the bodies like a semi-implicit Euler scheme
+    // with velocities half a step ahead
+    run_headless(
+        &mut state,
+        params,
+        Integrator::SemiImplicitEuler,
+        dt,
+        &EndCondition::Steps(steps),
+        |step, state| {
+            if step % sample_every == 0 {
+                path.push(state.positions[index]);
+            }
+        },
+    );
+    path
+}
+
+/// Same as `predict_path` along the Kepler orbit of the body at `index` around its dominant primary,
+/// the primary moving in a straight line. `None` if the body isn't bound to a primary.
+/// Unlike `predict_path`, the other bodies are ignored: both match for an isolated pair of bodies.
+pub fn predict_two_body_path(
+    state: &SystemState,
+    index: usize,
+    params: &ForceParams,
+    dt: f32,
+    steps: usize,
+    sample_every: usize,
+) -> Option<Vec<Vec3>> {
+    let (mass, pos, vel) = (
+        state.masses[index],
+        state.positions[index],
+        state.velocities[index],
+    );
+    let others: Vec<usize> = (0..state.len()).filter(|i| *i != index).collect();
+    let masses: Vec<(f32, Vec3)> = others
+        .iter()
+        .map(|i| (state.masses[*i], state.positions[*i]))
+        .collect();
+    let primary = others[dominant_primary(mass, pos, &masses)?];
+    let (primary_pos, primary_vel) = (state.positions[primary], state.velocities[primary]);
+    let mu = params.g * (state.masses[primary] + mass);
+    let elements = OrbitalElements::from_state(pos - primary_pos, vel - primary_vel, mu)?;
+
+    let mut path = vec![pos];
+    for step in (sample_every..=steps).step_by(sample_every) {
+        let t = step as f32 * dt;
+        let (rel_pos, _) = elements.advanced(mu, t).state(mu);
+        path.push(primary_pos + primary_vel * t + rel_pos);
+    }
+    Some(path)
+}
+
+/// Line list of every other segment of `path`
+pub fn dashed_line_vertices(path: &[Vec3]) -> Vec<[f32; 3]> {
+    path.chunks_exact(2)
+        .flat_map(|dash| vec![dash[0].into(), dash[1].into()])
+        .collect()
+}
+
+pub(crate) fn line_mesh(vertices: Vec<[f32; 3]>) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    let count = vertices.len();
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
+    // Unlit, the normals don't matter
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; count]);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; count]);
+    mesh
+}
+
+fn spawn_prediction_line(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(line_mesh(vec![[0.0; 3]; 2])),
+            material: materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                unlit: true,
+                ..Default::default()
+            }),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: false,
+            },
+            ..Default::default()
+        })
+        .insert(PredictionLine);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn predict_trajectory(
+    time: Res<Time>,
+    mut prediction: ResMut<TrajectoryPrediction>,
+    g: Res<Gravity>,
+    softening: Res<SofteningKernel>,
+    cutoff: Res<ForceCutoff>,
+    external: Res<ExternalField>,
+    dt: Res<TimeStep>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    bodies: Query<(Entity, &Mass, &Transform, &Velocity)>,
+    selected: Query<Entity, With<Selected>>,
+    mut line: Query<(&Handle<Mesh>, &mut Visible), With<PredictionLine>>,
+) {
+    if !prediction.refresh.tick(time.delta()).just_finished() {
+        return;
+    }
+    let (mesh, mut visible) = match line.single_mut() {
+        Ok(line) => line,
+        Err(_) => return,
+    };
+
+    let selected = selected.iter().next();
+    visible.is_visible = prediction.enabled && selected.is_some();
+    let selected = match selected {
+        Some(selected) if prediction.enabled => selected,
+        _ => return,
+    };
+
+    // Massless bodies don't affect the selected one
+    let mut state = SystemState::default();
+    let mut index = None;
+    for (entity, mass, transform, vel) in bodies.iter() {
+        if entity == selected {
+            index = Some(state.len());
+        } else if mass.0 == 0.0 {
+            continue;
+        }
+        state.push(mass.0, transform.translation, vel.0);
+    }
+    let index = match index {
+        Some(index) => index,
+        None => return,
+    };
+
+    let params = ForceParams {
+        g: g.0,
+        softening: *softening,
+        cutoff: cutoff.0,
+        external: *external,
+    };
+    let sample_every = prediction.sample_every.max(1);
+    let path = match prediction.model {
+        PredictionModel::NBody => {
+            predict_path(state, index, &params, dt.0, prediction.steps, sample_every)
+        }
+        PredictionModel::TwoBody => {
+            match predict_two_body_path(
+                &state,
+                index,
+                &params,
+                dt.0,
+                prediction.steps,
+                sample_every,
+            ) {
+                Some(path) => path,
+                None => return,
+            }
+        }
+    };
+    let vertices = dashed_line_vertices(&path);
+    if vertices.is_empty() {
+        return;
+    }
+    if let Some(mesh) = meshes.get_mut(mesh) {
+        *mesh = line_mesh(vertices);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::nbody::DT;
+
+    fn params() -> ForceParams {
+        ForceParams {
+            g: 1.0,
+            softening: SofteningKernel::None,
+            cutoff: None,
+            external: ExternalField::default(),
+        }
+    }
+
+    /// Massless body on a circular orbit of radius 1 around a unit mass at rest, of period 2π
+    fn kepler_orbit() -> SystemState {
+        let mut state = SystemState::default();
+        state.push(1.0, Vec3::ZERO, Vec3::ZERO);
+        state.push(0.0, Vec3::X, Vec3::Y);
+        state
+    }
+
+    #[test]
+    fn kepler_orbit_prediction_is_a_closed_loop() {
+        let period = (std::f32::consts::TAU / DT).round() as usize;
+        let path = predict_path(kepler_orbit(), 1, &params(), DT, period, 1);
+
+        assert_eq!(path.len(), period + 1);
+        assert!(path[0].distance(*path.last().unwrap()) < 0.02);
+        // Around the star rather than back and forth
+        assert!(path.iter().all(|pos| (pos.length() - 1.0).abs() < 0.02));
+        assert!(path.iter().any(|pos| pos.distance(-Vec3::X) < 0.02));
+    }
+
+    /// Massless body on an orbit of eccentricity 0.44 around a unit mass at rest, starting at the periapsis
+    fn eccentric_orbit() -> SystemState {
+        let mut state = SystemState::default();
+        state.push(1.0, Vec3::ZERO, Vec3::ZERO);
+        state.push(0.0, Vec3::X, 1.2 * Vec3::Y);
+        state
+    }
+
+    fn max_distance(a: &[Vec3], b: &[Vec3]) -> f32 {
+        assert_eq!(a.len(), b.len());
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| a.distance(*b))
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn full_prediction_of_a_pair_matches_the_analytic_orbit() {
+        let state = eccentric_orbit();
+        let steps = 1500;
+        let path = predict_path(state.clone(), 1, &params(), DT, steps, 10);
+        let analytic = predict_two_body_path(&state, 1, &params(), DT, steps, 10).unwrap();
+
+        assert_eq!(path.len(), steps / 10 + 1);
+        assert!(max_distance(&path, &analytic) < 0.05);
+    }
+
+    #[test]
+    fn full_prediction_of_a_perturbed_pair_diverges_from_the_two_body_one() {
+        let mut state = eccentric_orbit();
+        // Lighter than the star to keep it the primary, but close to the apoapsis
+        state.push(0.2, Vec3::new(-4.0, 0.0, 0.0), Vec3::ZERO);
+        let steps = 1500;
+        let path = predict_path(state.clone(), 1, &params(), DT, steps, 10);
+        let two_body = predict_two_body_path(&state, 1, &params(), DT, steps, 10).unwrap();
+
+        assert!(max_distance(&path, &two_body) > 0.5);
+    }
+}
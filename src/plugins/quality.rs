@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+
+/// Rendering quality, for lighter rendering on integrated GPUs:
+/// * Q: cycle through the presets
+///
+/// The MSAA samples and the starfield are only set at startup,
+/// the bodies spawned after a change use the new mesh subdivisions.
+pub struct QualityPlugin;
+
+impl Plugin for QualityPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(Action::CycleQuality, "cycle the rendering quality presets")
+            .init_resource::<QualityPreset>()
+            .add_system(cycle_quality.system());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl std::str::FromStr for QualityPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            _ => Err(String::from(
+                "Invalid input. Should be: low, medium or high",
+            )),
+        }
+    }
+}
+
+impl QualityPreset {
+    pub fn msaa_samples(&self) -> u32 {
+        match self {
+            QualityPreset::Low => 1,
+            QualityPreset::Medium => 2,
+            QualityPreset::High => 4,
+        }
+    }
+
+    /// Icosphere subdivisions of the bodies
+    pub fn subdivisions(&self) -> usize {
+        match self {
+            QualityPreset::Low => 2,
+            QualityPreset::Medium => 3,
+            QualityPreset::High => 5,
+        }
+    }
+
+    /// Number of stars of the background
+    pub fn star_count(&self) -> usize {
+        match self {
+            QualityPreset::Low => 250,
+            QualityPreset::Medium => 500,
+            QualityPreset::High => 1000,
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            QualityPreset::Low => QualityPreset::Medium,
+            QualityPreset::Medium => QualityPreset::High,
+            QualityPreset::High => QualityPreset::Low,
+        }
+    }
+}
+
+fn cycle_quality(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut quality: ResMut<QualityPreset>,
+) {
+    if bindings.just_pressed(&keys, Action::CycleQuality) {
+        *quality = quality.next();
+        info!(
+            "Quality preset: {:?} (MSAA and starfield unchanged until restart)",
+            *quality
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_set_the_msaa_and_the_subdivisions() {
+        let settings = |preset: &str| {
+            let preset: QualityPreset = preset.parse().unwrap();
+            (preset.msaa_samples(), preset.subdivisions())
+        };
+        assert_eq!(settings("low"), (1, 2));
+        assert_eq!(settings("Medium"), (2, 3));
+        assert_eq!(settings("HIGH"), (4, 5));
+        assert!(QualityPreset::Low.star_count() < QualityPreset::High.star_count());
+    }
+
+    #[test]
+    fn cycling_goes_through_every_preset() {
+        let mut world = World::default();
+        world.insert_resource(KeyBindings::default());
+        world.insert_resource(QualityPreset::Medium);
+        let mut stage = SystemStage::single_threaded().with_system(cycle_quality.system());
+        let mut presets = Vec::new();
+        for _ in 0..3 {
+            let mut keys = Input::<KeyCode>::default();
+            keys.press(KeyCode::Q);
+            world.insert_resource(keys);
+            stage.run(&mut world);
+            presets.push(*world.get_resource::<QualityPreset>().unwrap());
+        }
+        assert_eq!(
+            presets,
+            vec![
+                QualityPreset::High,
+                QualityPreset::Low,
+                QualityPreset::Medium
+            ]
+        );
+    }
+
+    #[test]
+    fn remapped_action_triggers_on_the_new_key() {
+        let mut world = World::default();
+        let mut bindings = KeyBindings::default();
+        bindings.remap(Action::CycleQuality, KeyCode::F3);
+        world.insert_resource(bindings);
+        world.insert_resource(QualityPreset::Medium);
+        let mut stage = SystemStage::single_threaded().with_system(cycle_quality.system());
+
+        let mut keys = Input::<KeyCode>::default();
+        keys.press(KeyCode::Q);
+        world.insert_resource(keys);
+        stage.run(&mut world);
+        assert_eq!(
+            *world.get_resource::<QualityPreset>().unwrap(),
+            QualityPreset::Medium
+        );
+
+        let mut keys = Input::<KeyCode>::default();
+        keys.press(KeyCode::F3);
+        world.insert_resource(keys);
+        stage.run(&mut world);
+        assert_eq!(
+            *world.get_resource::<QualityPreset>().unwrap(),
+            QualityPreset::High
+        );
+    }
+}
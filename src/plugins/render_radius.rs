@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+
+use super::collision::Radius;
+
+/// Floor on the rendered radius of the bodies, for the asteroids and debris to stay visible
+/// and clickable when zoomed out. Only the meshes are enlarged, the physics keeps the `Radius`.
+pub struct RenderRadiusPlugin;
+
+impl Plugin for RenderRadiusPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MinRenderRadius>()
+            // After the collisions have resized the merged bodies
+            .add_system_to_stage(CoreStage::PostUpdate, clamp_render_radius.system());
+    }
+}
+
+/// Smallest rendered radius, 0 to render the bodies at their physical radius
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MinRenderRadius(pub f32);
+
+/// Radius of the body's mesh at scale 1, from its radius and scale before being clamped
+struct MeshRadius(f32);
+
+/// Scale of a mesh of radius `mesh_radius` rendering a body of radius `radius`:
+/// ```text
+/// scale = max(radius, min_render_radius) / mesh_radius
+/// ```
+pub fn render_scale(radius: f32, mesh_radius: f32, min_render_radius: f32) -> f32 {
+    if mesh_radius <= 0.0 {
+        return 1.0;
+    }
+    radius.max(min_render_radius) / mesh_radius
+}
+
+fn clamp_render_radius(
+    mut commands: Commands,
+    min_radius: Res<MinRenderRadius>,
+    mut bodies: Query<(Entity, &Radius, &mut Transform, Option<&MeshRadius>)>,
+) {
+    for (entity, radius, mut transform, mesh_radius) in bodies.iter_mut() {
+        let mesh_radius = match mesh_radius {
+            Some(mesh_radius) => mesh_radius.0,
+            None => {
+                // The bodies are spawned with a mesh of their radius, the collisions scaling it along
+                let mesh_radius = radius.0 / transform.scale.x;
+                commands.entity(entity).insert(MeshRadius(mesh_radius));
+                mesh_radius
+            }
+        };
+        let scale = render_scale(radius.0, mesh_radius, min_radius.0);
+        if transform.scale != Vec3::splat(scale) {
+            transform.scale = Vec3::splat(scale);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::run_system;
+
+    #[test]
+    fn tiny_bodies_are_rendered_at_the_minimum_radius() {
+        assert_eq!(render_scale(0.01, 0.01, 0.5), 50.0);
+        assert_eq!(render_scale(2.0, 1.0, 0.5), 2.0);
+        assert_eq!(render_scale(2.0, 0.0, 0.5), 1.0);
+
+        let mut world = World::default();
+        world.insert_resource(MinRenderRadius(0.5));
+        let mut spawn = |radius: f32| {
+            world
+                .spawn()
+                .insert_bundle((Radius(radius), Transform::default()))
+                .id()
+        };
+        let (asteroid, planet) = (spawn(0.01), spawn(2.0));
+
+        // Stable over the frames, the mesh radius being kept
+        for _ in 0..2 {
+            run_system(&mut world, clamp_render_radius.system());
+            assert_eq!(
+                world.get::<Transform>(asteroid).unwrap().scale,
+                Vec3::splat(50.0)
+            );
+            assert_eq!(world.get::<Transform>(planet).unwrap().scale, Vec3::ONE);
+        }
+        // The physics is unaffected
+        assert_eq!(world.get::<Radius>(asteroid).unwrap().0, 0.01);
+    }
+}
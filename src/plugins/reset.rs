@@ -0,0 +1,249 @@
+use bevy::prelude::*;
+
+use super::collision::Radius;
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{
+    Acceleration, BodyBundle, BodyClass, BodyName, Gravity, Mass, SimulationTime, Velocity,
+};
+use super::timeline::Timeline;
+
+/// Restores the scene as it was spawned, e.g. after perturbing it, without restarting:
+/// * F5: reset the scene
+///
+/// The bodies still alive get back their initial state and keep their other components,
+/// those spawned since are despawned, and those despawned since, e.g. merged, are respawned
+/// with their mesh, material, name and class only.
+pub struct SceneResetPlugin;
+
+impl Plugin for SceneResetPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(Action::ResetScene, "reset the scene to its initial state")
+            .add_event::<ResetScene>()
+            .add_startup_system_to_stage(StartupStage::PostStartup, capture_initial_scene.system())
+            .add_system(reset_scene_keys.system())
+            .add_system(reset_scene.system());
+    }
+}
+
+/// Request to restore the `InitialScene`
+pub struct ResetScene;
+
+/// State of a body when the scene was spawned
+#[derive(Debug, Clone)]
+pub struct BodySnapshot {
+    pub entity: Entity,
+    pub mass: f32,
+    pub transform: Transform,
+    pub velocity: Vec3,
+    pub radius: Option<f32>,
+    pub mesh: Option<Handle<Mesh>>,
+    pub material: Option<Handle<StandardMaterial>>,
+    pub name: Option<BodyName>,
+    pub class: Option<BodyClass>,
+}
+
+/// Bodies and settings captured once the startup systems have spawned the scene
+#[derive(Debug, Clone, Default)]
+pub struct InitialScene {
+    pub time: f32,
+    pub gravity: f32,
+    pub bodies: Vec<BodySnapshot>,
+}
+
+#[allow(clippy::type_complexity)]
+fn capture_initial_scene(
+    mut commands: Commands,
+    time: Res<SimulationTime>,
+    gravity: Res<Gravity>,
+    bodies: Query<(
+        Entity,
+        &Mass,
+        &Transform,
+        &Velocity,
+        Option<&Radius>,
+        Option<&Handle<Mesh>>,
+        Option<&Handle<StandardMaterial>>,
+        Option<&BodyName>,
+        Option<&BodyClass>,
+    )>,
+) {
+    commands.insert_resource(InitialScene {
+        time: time.0,
+        gravity: gravity.0,
+        bodies: bodies
+            .iter()
+            .map(
+                |(entity, mass, transform, vel, radius, mesh, material, name, class)| {
+                    BodySnapshot {
+                        entity,
+                        mass: mass.0,
+                        transform: *transform,
+                        velocity: vel.0,
+                        radius: radius.map(|radius| radius.0),
+                        mesh: mesh.cloned(),
+                        material: material.cloned(),
+                        name: name.cloned(),
+                        class: class.copied(),
+                    }
+                },
+            )
+            .collect(),
+    });
+}
+
+fn reset_scene_keys(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut resets: EventWriter<ResetScene>,
+) {
+    if bindings.just_pressed(&keys, Action::ResetScene) {
+        resets.send(ResetScene);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn reset_scene(
+    mut commands: Commands,
+    mut resets: EventReader<ResetScene>,
+    scene: Option<ResMut<InitialScene>>,
+    mut time: ResMut<SimulationTime>,
+    mut gravity: ResMut<Gravity>,
+    timeline: Option<ResMut<Timeline>>,
+    mut bodies: Query<(
+        Entity,
+        &mut Mass,
+        &mut Transform,
+        &mut Velocity,
+        &mut Acceleration,
+        Option<&mut Radius>,
+    )>,
+) {
+    let mut scene = match (resets.iter().last(), scene) {
+        (Some(_), Some(scene)) => scene,
+        _ => return,
+    };
+
+    let mut alive = Vec::new();
+    for (entity, mut mass, mut transform, mut vel, mut acc, radius) in bodies.iter_mut() {
+        let snapshot = match scene.bodies.iter().find(|body| body.entity == entity) {
+            Some(snapshot) => snapshot,
+            None => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        mass.0 = snapshot.mass;
+        *transform = snapshot.transform;
+        vel.0 = snapshot.velocity;
+        acc.0 = Vec3::ZERO;
+        if let (Some(mut radius), Some(initial)) = (radius, snapshot.radius) {
+            radius.0 = initial;
+        }
+        alive.push(entity);
+    }
+
+    for snapshot in scene
+        .bodies
+        .iter_mut()
+        .filter(|body| !alive.contains(&body.entity))
+    {
+        let mut entity = match (&snapshot.mesh, &snapshot.material) {
+            (Some(mesh), Some(material)) => commands.spawn_bundle(PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                ..Default::default()
+            }),
+            _ => commands.spawn_bundle((GlobalTransform::default(),)),
+        };
+        entity
+            .insert_bundle(BodyBundle::new(
+                snapshot.mass,
+                snapshot.transform.translation,
+                snapshot.velocity,
+            ))
+            .insert(snapshot.transform);
+        if let Some(radius) = snapshot.radius {
+            entity.insert(Radius(radius));
+        }
+        if let Some(name) = &snapshot.name {
+            entity.insert(name.clone());
+        }
+        if let Some(class) = snapshot.class {
+            entity.insert(class);
+        }
+        // For the next reset to find it
+        snapshot.entity = entity.id();
+    }
+
+    time.0 = scene.time;
+    gravity.0 = scene.gravity;
+    // The recorded frames belong to the discarded history
+    if let Some(mut timeline) = timeline {
+        timeline.clear();
+    }
+    info!("Scene reset");
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::Events;
+
+    use super::*;
+    use crate::testing::run_system;
+
+    fn states(world: &mut World) -> Vec<(f32, Vec3, Vec3, Option<f32>)> {
+        let mut states: Vec<_> = world
+            .query::<(&Mass, &Transform, &Velocity, Option<&Radius>)>()
+            .iter(world)
+            .map(|(mass, transform, vel, radius)| {
+                (mass.0, transform.translation, vel.0, radius.map(|r| r.0))
+            })
+            .collect();
+        states.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        states
+    }
+
+    #[test]
+    fn reset_restores_the_captured_scene() {
+        let mut world = World::default();
+        world.insert_resource(SimulationTime(0.0));
+        world.insert_resource(Gravity(1.0));
+        world.insert_resource(Events::<ResetScene>::default());
+        let star = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(10.0, Vec3::ZERO, Vec3::ZERO))
+            .insert(Radius(1.0))
+            .id();
+        let planet = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::X * 5.0, Vec3::Y))
+            .insert_bundle((Radius(0.1), BodyName(String::from("planet"))))
+            .id();
+        run_system(&mut world, capture_initial_scene.system());
+        let initial = states(&mut world);
+
+        // The star moves and grows, the planet merges into it and a projectile is launched
+        world.get_mut::<Transform>(star).unwrap().translation = Vec3::ONE;
+        world.get_mut::<Velocity>(star).unwrap().0 = Vec3::X;
+        world.get_mut::<Mass>(star).unwrap().0 = 11.0;
+        world.get_mut::<Radius>(star).unwrap().0 = 1.1;
+        world.despawn(planet);
+        world
+            .spawn()
+            .insert_bundle(BodyBundle::new(0.5, Vec3::Z, Vec3::Z));
+        world.insert_resource(SimulationTime(42.0));
+        world.insert_resource(Gravity(3.0));
+
+        world
+            .get_resource_mut::<Events<ResetScene>>()
+            .unwrap()
+            .send(ResetScene);
+        run_system(&mut world, reset_scene.system());
+
+        assert_eq!(states(&mut world), initial);
+        assert_eq!(world.get_resource::<SimulationTime>().unwrap().0, 0.0);
+        assert_eq!(world.get_resource::<Gravity>().unwrap().0, 1.0);
+        let names: Vec<&BodyName> = world.query::<&BodyName>().iter(&world).collect();
+        assert_eq!(names, vec![&BodyName(String::from("planet"))]);
+    }
+}
@@ -0,0 +1,182 @@
+use std::cmp::Ordering;
+
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use super::nbody::{Gravity, Mass, Velocity};
+
+/// Periodically reports the mean-motion resonances between the bodies orbiting the most massive one
+pub struct ResonancePlugin;
+
+impl Plugin for ResonancePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ResonanceDetection>()
+            .add_system(report_resonances.system());
+    }
+}
+
+pub struct ResonanceDetection {
+    /// Relative tolerance on the period ratio
+    pub tolerance: f32,
+    pub timer: Timer,
+    /// Resonances already reported
+    reported: HashSet<(Entity, Entity, u32, u32)>,
+}
+
+impl Default for ResonanceDetection {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.02,
+            timer: Timer::from_seconds(5.0, true),
+            reported: HashSet::default(),
+        }
+    }
+}
+
+/// Largest term of the period ratios considered, e.g. 5:2 but not 7:3
+pub const MAX_RATIO_TERM: u32 = 5;
+
+/// Period of a body of `mass` orbiting a `central_mass` on a Keplerian orbit (Kepler's third law):
+/// ```text
+/// T = 2π * sqrt(a^3 / μ)
+/// ```
+/// where `μ = G*(M + m)` and the semi-major axis `a` comes from the vis-viva equation:
+/// ```text
+/// 1/a = 2/r - v^2/μ
+/// ```
+/// `rel_pos` and `rel_vel` are relative to the central body, `None` if the body isn't bound to it.
+pub fn orbital_period(
+    g: f32,
+    central_mass: f32,
+    mass: f32,
+    rel_pos: Vec3,
+    rel_vel: Vec3,
+) -> Option<f32> {
+    let mu = g * (central_mass + mass);
+    let r = rel_pos.length();
+    if mu <= 0.0 || r == 0.0 {
+        return None;
+    }
+    let inv_a = 2.0 / r - rel_vel.length_squared() / mu;
+    if inv_a <= 0.0 {
+        return None;
+    }
+    Some(std::f32::consts::TAU * (inv_a.powi(-3) / mu).sqrt())
+}
+
+/// Simplest `p:q` ratio with `p > q` matching `ratio` within the relative `tolerance`
+pub fn small_integer_ratio(ratio: f32, tolerance: f32) -> Option<(u32, u32)> {
+    (1..=MAX_RATIO_TERM)
+        .flat_map(|q| ((q + 1)..=MAX_RATIO_TERM).map(move |p| (p, q)))
+        .filter(|(p, q)| gcd(*p, *q) == 1)
+        .find(|(p, q)| {
+            let exact = *p as f32 / *q as f32;
+            ((ratio - exact) / exact).abs() <= tolerance
+        })
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Pairs of bodies bound to the most massive one whose periods are close to a small-integer ratio.
+/// Bodies are given as `(entity, mass, position, velocity)`,
+/// each resonance as `(inner, outer, p, q)` where `T_outer / T_inner ≈ p / q`.
+pub fn detect_resonances(
+    bodies: &[(Entity, f32, Vec3, Vec3)],
+    g: f32,
+    tolerance: f32,
+) -> Vec<(Entity, Entity, u32, u32)> {
+    let central = match bodies
+        .iter()
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+    {
+        Some(central) => *central,
+        None => return Vec::new(),
+    };
+    let (central_entity, central_mass, central_pos, central_vel) = central;
+
+    let mut periods: Vec<(Entity, f32)> = bodies
+        .iter()
+        .filter(|(entity, ..)| *entity != central_entity)
+        .filter_map(|(entity, mass, pos, vel)| {
+            orbital_period(
+                g,
+                central_mass,
+                *mass,
+                *pos - central_pos,
+                *vel - central_vel,
+            )
+            .map(|period| (*entity, period))
+        })
+        .collect();
+    periods.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+
+    let mut resonances = Vec::new();
+    for (i, (inner, inner_period)) in periods.iter().enumerate() {
+        for (outer, outer_period) in periods.iter().skip(i + 1) {
+            if let Some((p, q)) = small_integer_ratio(outer_period / inner_period, tolerance) {
+                resonances.push((*inner, *outer, p, q));
+            }
+        }
+    }
+    resonances
+}
+
+fn report_resonances(
+    time: Res<Time>,
+    g: Res<Gravity>,
+    mut detection: ResMut<ResonanceDetection>,
+    query: Query<(Entity, &Mass, &Transform, &Velocity)>,
+) {
+    if !detection.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let bodies: Vec<(Entity, f32, Vec3, Vec3)> = query
+        .iter()
+        .map(|(entity, mass, transform, vel)| (entity, mass.0, transform.translation, vel.0))
+        .collect();
+
+    for resonance in detect_resonances(&bodies, g.0, detection.tolerance) {
+        if detection.reported.insert(resonance) {
+            let (inner, outer, p, q) = resonance;
+            info!("{:?} and {:?} are in {}:{} resonance", inner, outer, p, q);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_two_to_one_resonance_is_detected() {
+        let mut world = World::default();
+        let (star, inner, outer) = (world.spawn().id(), world.spawn().id(), world.spawn().id());
+        let (g, star_mass) = (1.0, 1000.0);
+        // Circular orbits, the outer one twice as long as the inner one by Kepler's third law
+        let circular = |entity, r: f32| {
+            let speed = (g * star_mass / r).sqrt();
+            (
+                entity,
+                0.0,
+                Vec3::new(r, 0.0, 0.0),
+                Vec3::new(0.0, speed, 0.0),
+            )
+        };
+        let bodies = [
+            (star, star_mass, Vec3::ZERO, Vec3::ZERO),
+            circular(inner, 1.0),
+            circular(outer, 2.0f32.powf(2.0 / 3.0)),
+        ];
+
+        assert_eq!(
+            detect_resonances(&bodies, g, 0.01),
+            vec![(inner, outer, 2, 1)]
+        );
+    }
+}
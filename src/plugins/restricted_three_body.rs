@@ -0,0 +1,294 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use super::nbody::{Mass, Velocity};
+use super::selection::Selected;
+
+pub struct RestrictedThreeBodyPlugin;
+
+impl Plugin for RestrictedThreeBodyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(JacobiLogTimer(Timer::from_seconds(1.0, true)))
+            .add_system(mark_escapers.system())
+            .add_system(log_jacobi_constant.system());
+    }
+}
+
+/// Tags a massless body whose survival in the system is tracked
+pub struct TestParticle;
+
+/// Tags a test particle which went past the `EscapeRadius`
+pub struct Escaped;
+
+/// Distance from the origin (the barycenter) beyond which a test particle is considered escaped
+pub struct EscapeRadius(pub f32);
+
+/// The two massive bodies on circular orbits, the heaviest first
+pub struct Primaries(pub Entity, pub Entity);
+
+struct JacobiLogTimer(Timer);
+
+pub const ESCAPED_COLOR: Color = Color::RED;
+
+/// Velocity of a point at rest in the frame rotating at `omega` around the origin
+pub fn corotating_velocity(omega: Vec3, pos: Vec3) -> Vec3 {
+    omega.cross(pos)
+}
+
+/// Triangular Lagrange points L4 and L5 of a `secondary` body orbiting a `primary` one,
+/// `normal` being the direction of their orbital angular momentum.
+/// They complete equilateral triangles on the primaries, L4 leading the secondary by 60° and L5 trailing it.
+pub fn triangular_points(primary: Vec3, secondary: Vec3, normal: Vec3) -> (Vec3, Vec3) {
+    let arm = secondary - primary;
+    let rotation = |angle| Quat::from_axis_angle(normal.normalize(), angle);
+    (
+        primary + rotation(std::f32::consts::FRAC_PI_3) * arm,
+        primary + rotation(-std::f32::consts::FRAC_PI_3) * arm,
+    )
+}
+
+/// `(position, velocity)` of `count` test particles scattered around the triangular point `point`
+/// of primaries rotating at `omega` around the origin: up to `angle` (radians) ahead or behind it along the orbit
+/// and `radial_spread` closer or farther from the origin.
+/// They co-rotate with the primaries up to a random velocity offset of at most `speed_spread` per axis.
+/// The offsets must be small, especially the radial one, for the particles to librate around the point
+/// rather than leave it for a horseshoe orbit or escape.
+pub fn trojan_swarm(
+    point: Vec3,
+    omega: Vec3,
+    count: usize,
+    angle: f32,
+    radial_spread: f32,
+    speed_spread: f32,
+    rng: &mut impl Rng,
+) -> Vec<(Vec3, Vec3)> {
+    let axis = omega.normalize();
+    (0..count)
+        .map(|_| {
+            let along = Quat::from_axis_angle(axis, rng.gen_range(-angle..=angle)) * point;
+            let pos = along + along.normalize() * rng.gen_range(-radial_spread..=radial_spread);
+            let radial = pos.normalize();
+            let kick = speed_spread
+                * (rng.gen_range(-1.0..=1.0) * radial
+                    + rng.gen_range(-1.0..=1.0) * axis.cross(radial));
+            (pos, corotating_velocity(omega, pos) + kick)
+        })
+        .collect()
+}
+
+fn mark_escapers(
+    mut commands: Commands,
+    radius: Option<Res<EscapeRadius>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<(Entity, &Transform, &Handle<StandardMaterial>, &TestParticle), Without<Escaped>>,
+) {
+    let radius = match radius {
+        Some(radius) => radius.0,
+        None => return,
+    };
+
+    for (entity, transform, material, _) in query.iter() {
+        if transform.translation.length() > radius {
+            if let Some(material) = materials.get_mut(material) {
+                material.base_color = ESCAPED_COLOR;
+            }
+            commands.entity(entity).insert(Escaped);
+        }
+    }
+}
+
+/// Jacobi integral of a test particle in the circular restricted three-body problem:
+/// ```text
+/// C = ω^2*(x^2 + y^2) + 2*ω^2*((1 - μ)/r1 + μ/r2) - v^2
+/// ```
+/// where:
+/// - `μ = m2 / (m1 + m2)` is the mass parameter of the primaries
+/// - `pos` and `vel` are the position and velocity of the particle in the frame co-rotating with the primaries
+///   around the z-axis at `ω`, the primaries being fixed at `(-μ, 0, 0)` and `(1 - μ, 0, 0)`
+/// - distances are in units of the primaries' separation
+/// - `r1` and `r2` are the distances to the primaries
+///
+/// It is conserved along the particle's trajectory.
+pub fn jacobi_constant(mu: f32, pos: Vec3, vel: Vec3, omega: f32) -> f32 {
+    let r1 = pos.distance(Vec3::new(-mu, 0.0, 0.0));
+    let r2 = pos.distance(Vec3::new(1.0 - mu, 0.0, 0.0));
+    let omega2 = omega * omega;
+    omega2 * (pos.x * pos.x + pos.y * pos.y) + 2.0 * omega2 * ((1.0 - mu) / r1 + mu / r2)
+        - vel.length_squared()
+}
+
+/// Converts the state of a test particle from the simulation frame to the co-rotating frame of `jacobi_constant`.
+/// Each body is given as `(mass, position, velocity)`, returns `(μ, position, velocity, ω)`.
+pub fn to_corotating_frame(
+    primary1: (f32, Vec3, Vec3),
+    primary2: (f32, Vec3, Vec3),
+    pos: Vec3,
+    vel: Vec3,
+) -> (f32, Vec3, Vec3, f32) {
+    let (m1, pos1, vel1) = primary1;
+    let (m2, pos2, vel2) = primary2;
+    let mu = m2 / (m1 + m2);
+    let barycenter = (m1 * pos1 + m2 * pos2) / (m1 + m2);
+    let barycenter_vel = (m1 * vel1 + m2 * vel2) / (m1 + m2);
+
+    let separation = pos2 - pos1;
+    let distance = separation.length();
+    // Angular velocity of the primaries around each other
+    let omega = separation.cross(vel2 - vel1) / (distance * distance);
+
+    let x = separation / distance;
+    let z = omega.normalize();
+    let y = z.cross(x);
+    let rotation = Mat3::from_cols(x, y, z).transpose();
+
+    let rel_pos = pos - barycenter;
+    let rel_vel = vel - barycenter_vel - omega.cross(rel_pos);
+    (
+        mu,
+        rotation * rel_pos / distance,
+        rotation * rel_vel / distance,
+        omega.length(),
+    )
+}
+
+fn log_jacobi_constant(
+    time: Res<Time>,
+    primaries: Option<Res<Primaries>>,
+    mut timer: ResMut<JacobiLogTimer>,
+    bodies: Query<(&Mass, &Transform, &Velocity)>,
+    selected: Query<(Entity, &Transform, &Velocity, &TestParticle), With<Selected>>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    let primaries = match primaries {
+        Some(primaries) => primaries,
+        None => return,
+    };
+    let state = |entity| {
+        bodies
+            .get(entity)
+            .map(|(mass, transform, vel)| (mass.0, transform.translation, vel.0))
+    };
+    let (primary1, primary2) = match (state(primaries.0), state(primaries.1)) {
+        (Ok(primary1), Ok(primary2)) => (primary1, primary2),
+        _ => return,
+    };
+
+    for (entity, transform, vel, _) in selected.iter() {
+        let (mu, pos, vel, omega) =
+            to_corotating_frame(primary1, primary2, transform.translation, vel.0);
+        info!(
+            "{:?} Jacobi constant: {:.6}",
+            entity,
+            jacobi_constant(mu, pos, vel, omega)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn corotating_velocity_at_a_sample_point() {
+        let omega = Vec3::new(0.0, 0.0, 2.0);
+        assert_eq!(
+            corotating_velocity(omega, Vec3::new(1.5, 0.0, 0.0)),
+            Vec3::new(0.0, 3.0, 0.0)
+        );
+        // Points on the axis of rotation stay at rest
+        assert_eq!(corotating_velocity(omega, Vec3::Z), Vec3::ZERO);
+    }
+
+    #[test]
+    fn jacobi_constant_is_conserved() {
+        use crate::headless::{ForceParams, SystemState};
+        use crate::plugins::nbody::{ExternalField, Integrator, SofteningKernel};
+
+        // Primaries of separation 1 on circular orbits around their barycenter at the origin, `G = 1`
+        let mu = 0.1;
+        let omega = 1.0;
+        let mut state = SystemState::default();
+        state.push(1.0 - mu, Vec3::new(-mu, 0.0, 0.0), Vec3::new(0.0, -mu, 0.0));
+        state.push(
+            mu,
+            Vec3::new(1.0 - mu, 0.0, 0.0),
+            Vec3::new(0.0, 1.0 - mu, 0.0),
+        );
+        // Test particle between the primaries, slightly off its co-rotating velocity
+        let pos = Vec3::new(0.5, 0.3, 0.0);
+        state.push(
+            0.0,
+            pos,
+            corotating_velocity(Vec3::Z * omega, pos) + Vec3::new(0.05, 0.0, 0.0),
+        );
+        let params = ForceParams {
+            g: 1.0,
+            softening: SofteningKernel::None,
+            cutoff: None,
+            external: ExternalField::default(),
+        };
+
+        let jacobi = |state: &SystemState| {
+            let body = |i: usize| (state.masses[i], state.positions[i], state.velocities[i]);
+            let (mu, pos, vel, omega) =
+                to_corotating_frame(body(0), body(1), state.positions[2], state.velocities[2]);
+            jacobi_constant(mu, pos, vel, omega)
+        };
+        let initial = jacobi(&state);
+        for _ in 0..2000 {
+            state.step(&params, Integrator::RungeKutta4, 1e-3);
+            let c = jacobi(&state);
+            assert!(
+                (c - initial).abs() < 1e-3 * initial.abs(),
+                "{} != {}",
+                c,
+                initial
+            );
+        }
+        // The particle did move in the rotating frame
+        assert!(state.positions[2].distance(Quat::from_rotation_z(2.0) * pos) > 1e-2);
+    }
+
+    #[test]
+    fn triangular_points_complete_equilateral_triangles() {
+        let (primary, secondary) = (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(4.0, 0.0, 0.0));
+        let (l4, l5) = triangular_points(primary, secondary, Vec3::Z);
+        for point in [l4, l5].iter() {
+            assert!((point.distance(primary) - 5.0).abs() < 1e-5);
+            assert!((point.distance(secondary) - 5.0).abs() < 1e-5);
+            assert_eq!(point.z, 0.0);
+        }
+        // L4 leads the secondary along its counterclockwise orbit
+        assert!(l4.y > 0.0 && l5.y < 0.0);
+        assert!((l4.x - 1.5).abs() < 1e-5 && (l5.x - 1.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn trojan_swarm_is_centered_on_its_point() {
+        let (l4, l5) = triangular_points(Vec3::ZERO, Vec3::new(100.0, 0.0, 0.0), Vec3::Z);
+        let omega = 0.1 * Vec3::Z;
+        let mut rng = StdRng::seed_from_u64(0);
+        for point in [l4, l5].iter() {
+            let swarm = trojan_swarm(*point, omega, 500, 0.2, 0.5, 0.05, &mut rng);
+            assert_eq!(swarm.len(), 500);
+            let (pos, vel) = swarm
+                .iter()
+                .fold((Vec3::ZERO, Vec3::ZERO), |(p, v), (pos, vel)| {
+                    (p + *pos, v + *vel)
+                });
+            let (pos, vel) = (pos / 500.0, vel / 500.0);
+            // The swarm spreads over ±11° along the orbit, within about 6 of the point
+            assert!(pos.distance(*point) < 2.0, "{} from {}", pos, point);
+            assert!(vel.distance(corotating_velocity(omega, *point)) < 0.2);
+            for (pos, _) in swarm.iter() {
+                assert!((pos.length() - 100.0).abs() <= 0.5 + 1e-3);
+                assert!(pos.angle_between(*point) <= 0.2 + 1e-3);
+            }
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use bevy::prelude::*;
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::nbody::{Frozen, Mass, Pinned};
+
+/// Keyboard selection of bodies:
+/// * Tab: select the next body
+/// * Shift + Tab: add the next body to the selection
+/// * Escape: clear the selection
+/// * P: pin/unpin the selected bodies
+/// * Z: freeze/unfreeze the selected bodies in time
+///
+/// Selected bodies blink so that they are easy to spot.
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::SelectNext,
+            "select the next body (with Shift to add it to the selection)",
+        )
+        .register_key_binding(Action::ClearSelection, "clear the selection")
+        .register_key_binding(Action::TogglePinned, "pin/unpin the selected bodies")
+        .register_key_binding(
+            Action::ToggleFrozen,
+            "freeze/unfreeze the selected bodies in time",
+        )
+        .add_system(cycle_selection.system())
+        .add_system(clear_selection.system())
+        .add_system(toggle_pinned.system())
+        .add_system(toggle_frozen.system())
+        .add_system(blink_selected.system())
+        .add_system(restore_deselected.system());
+    }
+}
+
+/// Tags a selected body
+pub struct Selected;
+
+/// Duration of a blink, in seconds
+const BLINK_PERIOD: f32 = 1.0;
+
+/// Emissive color of a blinking body before it got selected
+struct Blinking {
+    original_emissive: Color,
+}
+
+/// Intensity of the blink at time `t`, going smoothly from 0 to 1 and back over a `period`
+pub fn pulse_intensity(t: f32, period: f32) -> f32 {
+    0.5 * (1.0 - (std::f32::consts::TAU * t / period).cos())
+}
+
+fn cycle_selection(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    bodies: Query<(Entity, Option<&Selected>), With<Mass>>,
+) {
+    if !bindings.just_pressed(&keys, Action::SelectNext) {
+        return;
+    }
+    let extend = keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift);
+
+    // Stable ordering so that Tab goes through every body
+    let mut bodies: Vec<(Entity, bool)> = bodies
+        .iter()
+        .map(|(entity, selected)| (entity, selected.is_some()))
+        .collect();
+    bodies.sort_by_key(|(entity, _)| entity.id());
+
+    let next = match bodies.iter().rposition(|(_, selected)| *selected) {
+        Some(last) => (last + 1) % bodies.len(),
+        None if bodies.is_empty() => return,
+        None => 0,
+    };
+
+    if !extend {
+        for (entity, _) in bodies.iter().filter(|(_, selected)| *selected) {
+            commands.entity(*entity).remove::<Selected>();
+        }
+    }
+    commands.entity(bodies[next].0).insert(Selected);
+}
+
+fn clear_selection(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    selected: Query<Entity, With<Selected>>,
+) {
+    if bindings.just_pressed(&keys, Action::ClearSelection) {
+        for entity in selected.iter() {
+            commands.entity(entity).remove::<Selected>();
+        }
+    }
+}
+
+/// Pins all the selected bodies, or unpins them if they all are already pinned
+fn toggle_pinned(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    selected: Query<(Entity, Option<&Pinned>), With<Selected>>,
+) {
+    if !bindings.just_pressed(&keys, Action::TogglePinned) {
+        return;
+    }
+
+    let all_pinned = selected.iter().all(|(_, pinned)| pinned.is_some());
+    for (entity, _) in selected.iter() {
+        if all_pinned {
+            commands.entity(entity).remove::<Pinned>();
+        } else {
+            commands.entity(entity).insert(Pinned);
+        }
+    }
+}
+
+/// Freezes all the selected bodies, or unfreezes them if they all are already frozen
+fn toggle_frozen(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    selected: Query<(Entity, Option<&Frozen>), With<Selected>>,
+) {
+    if !bindings.just_pressed(&keys, Action::ToggleFrozen) {
+        return;
+    }
+
+    let all_frozen = selected.iter().all(|(_, frozen)| frozen.is_some());
+    for (entity, _) in selected.iter() {
+        if all_frozen {
+            commands.entity(entity).remove::<Frozen>();
+        } else {
+            commands.entity(entity).insert(Frozen);
+        }
+    }
+}
+
+fn blink_selected(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<(
+        Entity,
+        &Handle<StandardMaterial>,
+        Option<&Blinking>,
+        &Selected,
+    )>,
+) {
+    let intensity = pulse_intensity(time.seconds_since_startup() as f32, BLINK_PERIOD);
+    for (entity, handle, blinking, _) in query.iter() {
+        let material = match materials.get_mut(handle) {
+            Some(material) => material,
+            None => continue,
+        };
+        let original = match blinking {
+            Some(blinking) => blinking.original_emissive,
+            None => {
+                commands.entity(entity).insert(Blinking {
+                    original_emissive: material.emissive,
+                });
+                material.emissive
+            }
+        };
+        material.emissive = Color::rgba(
+            original.r() + (1.0 - original.r()) * intensity,
+            original.g() + (1.0 - original.g()) * intensity,
+            original.b() + (1.0 - original.b()) * intensity,
+            original.a(),
+        );
+    }
+}
+
+fn restore_deselected(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<(Entity, &Handle<StandardMaterial>, &Blinking), Without<Selected>>,
+) {
+    for (entity, handle, blinking) in query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.emissive = blinking.original_emissive;
+        }
+        commands.entity(entity).remove::<Blinking>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinning_toggles_on_the_selected_bodies_only() {
+        let mut world = World::default();
+        world.insert_resource(KeyBindings::default());
+        let mut keys = Input::<KeyCode>::default();
+        keys.press(KeyCode::P);
+        world.insert_resource(keys);
+        let bodies: Vec<Entity> = (0..4)
+            .map(|i| {
+                let mut body = world.spawn();
+                body.insert(Mass(1.0));
+                if i < 2 {
+                    body.insert(Selected);
+                }
+                body.id()
+            })
+            .collect();
+
+        let mut stage = SystemStage::single_threaded().with_system(toggle_pinned.system());
+        stage.run(&mut world);
+        let pinned = |world: &World| -> Vec<bool> {
+            bodies
+                .iter()
+                .map(|body| world.get::<Pinned>(*body).is_some())
+                .collect()
+        };
+        assert_eq!(pinned(&world), vec![true, true, false, false]);
+
+        // Pressed again, the selection gets unpinned
+        let mut keys = world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        keys.update();
+        keys.release(KeyCode::P);
+        keys.press(KeyCode::P);
+        stage.run(&mut world);
+        assert_eq!(pinned(&world), vec![false; 4]);
+    }
+
+    #[test]
+    fn pulse_goes_up_and_down_over_a_period() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-6;
+        assert!(close(pulse_intensity(0.0, 2.0), 0.0));
+        assert!(close(pulse_intensity(0.5, 2.0), 0.5));
+        assert!(close(pulse_intensity(1.0, 2.0), 1.0));
+        assert!(close(pulse_intensity(1.5, 2.0), 0.5));
+        assert!(close(pulse_intensity(2.0, 2.0), 0.0));
+        assert!((0..100).all(|i| (0.0..=1.0).contains(&pulse_intensity(i as f32 * 0.037, 2.0))));
+    }
+}
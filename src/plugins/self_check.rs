@@ -0,0 +1,117 @@
+use std::cmp::Ordering;
+
+use bevy::prelude::*;
+
+use super::nbody::{
+    ExternalField, ForceCutoff, Gravity, Integrator, Mass, SofteningKernel, TimeStep, Velocity,
+};
+use super::resonance::orbital_period;
+use crate::headless::{estimate_energy_drift, ForceParams, SystemState};
+
+/// Checks once the bodies are spawned that the time step is small enough for the scene,
+/// by integrating its massive bodies over the shortest orbital period around the most massive one
+pub struct SelfCheckPlugin;
+
+impl Plugin for SelfCheckPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<IntegratorSelfCheck>()
+            .add_system(check_energy_drift.system());
+    }
+}
+
+pub struct IntegratorSelfCheck {
+    pub enabled: bool,
+    /// Relative energy drift above which a warning is logged
+    pub threshold: f32,
+    /// Limit on the number of steps integrated, the check is skipped for longer periods
+    pub max_steps: usize,
+}
+
+impl Default for IntegratorSelfCheck {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 0.01,
+            max_steps: 100_000,
+        }
+    }
+}
+
+/// Shortest orbital period around the most massive body
+pub fn shortest_period(state: &SystemState, g: f32) -> Option<f32> {
+    let central = (0..state.len()).max_by(|i, j| {
+        state.masses[*i]
+            .partial_cmp(&state.masses[*j])
+            .unwrap_or(Ordering::Equal)
+    })?;
+    (0..state.len())
+        .filter(|i| *i != central)
+        .filter_map(|i| {
+            orbital_period(
+                g,
+                state.masses[central],
+                state.masses[i],
+                state.positions[i] - state.positions[central],
+                state.velocities[i] - state.velocities[central],
+            )
+        })
+        .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_energy_drift(
+    mut done: Local<bool>,
+    check: Res<IntegratorSelfCheck>,
+    g: Res<Gravity>,
+    softening: Res<SofteningKernel>,
+    cutoff: Res<ForceCutoff>,
+    integrator: Res<Integrator>,
+    dt: Res<TimeStep>,
+    bodies: Query<(&Mass, &Transform, &Velocity)>,
+) {
+    if *done || !check.enabled {
+        return;
+    }
+    // Massless bodies don't change the energy
+    let mut state = SystemState::default();
+    for (mass, transform, vel) in bodies.iter().filter(|(mass, ..)| mass.0 > 0.0) {
+        state.push(mass.0, transform.translation, vel.0);
+    }
+    if state.is_empty() {
+        return;
+    }
+    *done = true;
+
+    let period = match shortest_period(&state, g.0) {
+        Some(period) => period,
+        None => return,
+    };
+    let steps = (period / dt.0).ceil() as usize;
+    if steps > check.max_steps {
+        info!("Self-check skipped: {} steps per orbital period", steps);
+        return;
+    }
+    let params = ForceParams {
+        g: g.0,
+        softening: *softening,
+        cutoff: cutoff.0,
+        // Its potential is left out of the energy
+        external: ExternalField::default(),
+    };
+    let drift = estimate_energy_drift(&state, &params, *integrator, dt.0, steps);
+    if drift > check.threshold {
+        warn!(
+            "Energy drift of {:.2}% over an orbital period of {} steps with {:?}: the time step may be too large for this scene",
+            100.0 * drift,
+            steps,
+            *integrator
+        );
+    } else {
+        info!(
+            "Energy drift of {:.4}% over an orbital period of {} steps with {:?}",
+            100.0 * drift,
+            steps,
+            *integrator
+        );
+    }
+}
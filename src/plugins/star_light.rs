@@ -0,0 +1,229 @@
+use std::cmp::Ordering;
+
+use bevy::prelude::*;
+
+use super::nbody::{BodyClass, Mass};
+
+/// Number of lights supported by Bevy's PBR shader, any light past that is ignored.
+pub const MAX_LIGHTS: usize = 10;
+
+pub struct StarLightPlugin;
+
+impl Plugin for StarLightPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<StarLights>()
+            .add_system(update_star_lights.system())
+            .add_system(apply_star_temperature.system());
+    }
+}
+
+/// Lighting of the `BodyClass::Star` bodies.
+/// Each star carries its own `Light` so that it moves along with the body.
+pub struct StarLights {
+    /// Intensity of the most massive star, the others are scaled with the mass-luminosity relation
+    pub intensity: f32,
+    pub range: f32,
+    /// Only the brightest stars get a light to respect the renderer's limit
+    pub max_lights: usize,
+}
+
+impl Default for StarLights {
+    fn default() -> Self {
+        let light = Light::default();
+        Self {
+            intensity: light.intensity,
+            range: light.range,
+            max_lights: MAX_LIGHTS,
+        }
+    }
+}
+
+/// Mass-luminosity relation of main sequence stars, relative to a reference star:
+/// ```text
+/// L / L_ref = (M / M_ref)^3.5
+/// ```
+pub fn relative_luminosity(mass: f32, reference_mass: f32) -> f32 {
+    (mass / reference_mass).powf(3.5)
+}
+
+/// Surface temperature of a star in Kelvin, setting the color and emission of its material
+pub struct StarTemperature(pub f32);
+
+/// Effective temperature of the Sun, in Kelvin
+pub const SOLAR_TEMPERATURE: f32 = 5772.0;
+
+/// Approximate temperature of a main sequence star of `mass`, in the same units as `solar_mass`,
+/// from the mass-luminosity relation and a radius scaling as `M^0.8`:
+/// ```text
+/// T / T_sun = (M / M_sun)^0.475
+/// ```
+pub fn main_sequence_temperature(mass: f32, solar_mass: f32) -> f32 {
+    SOLAR_TEMPERATURE * (mass / solar_mass).powf(0.475)
+}
+
+/// Color of a blackbody at `kelvin`, from red below 2000K to blue above 10000K.
+/// Fit of the CIE 1964 color matching functions by Tanner Helland, valid from 1000K to 40000K.
+pub fn temperature_to_color(kelvin: f32) -> Color {
+    let t = kelvin.clamp(1000.0, 40_000.0) / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+    let green = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_17 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+    let channel = |c: f32| c.clamp(0.0, 255.0) / 255.0;
+    Color::rgb(channel(red), channel(green), channel(blue))
+}
+
+fn apply_star_temperature(
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<(&StarTemperature, &Handle<StandardMaterial>), Changed<StarTemperature>>,
+) {
+    for (temperature, handle) in query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            let color = temperature_to_color(temperature.0);
+            material.base_color = color;
+            material.emissive = color;
+        }
+    }
+}
+
+fn update_star_lights(
+    mut commands: Commands,
+    settings: Res<StarLights>,
+    mut query: Query<(Entity, &BodyClass, &Mass, Option<&mut Light>)>,
+) {
+    let mut stars: Vec<(Entity, &Mass, Option<Mut<Light>>)> = query
+        .iter_mut()
+        .filter(|(_, class, _, _)| **class == BodyClass::Star)
+        .map(|(entity, _, mass, light)| (entity, mass, light))
+        .collect();
+
+    // Brightest (i.e. most massive) first
+    stars.sort_by(|(_, m1, _), (_, m2, _)| m2.0.partial_cmp(&m1.0).unwrap_or(Ordering::Equal));
+
+    let reference_mass = match stars.first() {
+        Some((_, mass, _)) => mass.0,
+        None => return,
+    };
+
+    for (i, (entity, mass, light)) in stars.into_iter().enumerate() {
+        if i >= settings.max_lights {
+            if light.is_some() {
+                commands.entity(entity).remove::<Light>();
+            }
+            continue;
+        }
+
+        let intensity = settings.intensity * relative_luminosity(mass.0, reference_mass);
+        match light {
+            Some(mut light) => {
+                if light.intensity != intensity || light.range != settings.range {
+                    light.intensity = intensity;
+                    light.range = settings.range;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(Light {
+                    intensity,
+                    range: settings.range,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::nbody::BodyBundle;
+
+    fn spawn_star(world: &mut World, mass: f32, pos: Vec3) -> Entity {
+        world
+            .spawn()
+            .insert_bundle(BodyBundle::new(mass, pos, Vec3::ZERO))
+            .insert(BodyClass::Star)
+            .id()
+    }
+
+    #[test]
+    fn every_star_carries_a_light_moving_along() {
+        let mut world = World::default();
+        world.insert_resource(StarLights::default());
+        let sun = spawn_star(&mut world, 1.0, Vec3::ZERO);
+        let companion = spawn_star(&mut world, 0.5, Vec3::new(5.0, 0.0, 0.0));
+        world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1e-3, Vec3::X, Vec3::ZERO))
+            .insert(BodyClass::Planet);
+
+        let mut stage = SystemStage::single_threaded().with_system(update_star_lights.system());
+        stage.run(&mut world);
+
+        let mut lit: Vec<Entity> = world
+            .query_filtered::<Entity, With<Light>>()
+            .iter(&world)
+            .collect();
+        lit.sort();
+        let mut stars = vec![sun, companion];
+        stars.sort();
+        assert_eq!(lit, stars);
+        let intensity = |entity| world.get::<Light>(entity).unwrap().intensity;
+        assert!(intensity(companion) < intensity(sun));
+
+        // The light is a component of the body, rendered at its transform
+        world.get_mut::<Transform>(companion).unwrap().translation = Vec3::new(0.0, 7.0, 0.0);
+        stage.run(&mut world);
+        assert!(world.get::<Light>(companion).is_some());
+        assert_eq!(
+            world.get::<Transform>(companion).unwrap().translation,
+            Vec3::new(0.0, 7.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn lights_beyond_the_limit_are_removed() {
+        let mut world = World::default();
+        world.insert_resource(StarLights {
+            max_lights: 1,
+            ..Default::default()
+        });
+        let sun = spawn_star(&mut world, 1.0, Vec3::ZERO);
+        let dwarf = spawn_star(&mut world, 0.1, Vec3::X);
+
+        let mut stage = SystemStage::single_threaded().with_system(update_star_lights.system());
+        stage.run(&mut world);
+        assert!(world.get::<Light>(sun).is_some());
+        assert!(world.get::<Light>(dwarf).is_none());
+    }
+
+    #[test]
+    fn hotter_stars_are_bluer() {
+        let [red_dwarf, sun, blue_giant] = [3000.0, 5800.0, 20_000.0].map(temperature_to_color);
+        // Toward red when cold
+        assert_eq!(red_dwarf.r(), 1.0);
+        assert!(red_dwarf.b() < 0.5 * red_dwarf.r());
+        // Toward blue when hot
+        assert_eq!(blue_giant.b(), 1.0);
+        assert!(blue_giant.r() < blue_giant.b());
+        let blueness = |color: Color| color.b() / color.r();
+        assert!(blueness(red_dwarf) < blueness(sun));
+        assert!(blueness(sun) < blueness(blue_giant));
+
+        // More massive stars are hotter
+        assert!(main_sequence_temperature(0.5, 1.0) < main_sequence_temperature(1.0, 1.0));
+        assert!(main_sequence_temperature(1.0, 1.0) < main_sequence_temperature(10.0, 1.0));
+    }
+}
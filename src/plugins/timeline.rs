@@ -0,0 +1,316 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::measure::UnitSystem;
+use super::nbody::{Mass, PhysicsPaused, PhysicsStep, PhysicsSystem, SimulationTime, Velocity};
+
+/// Records the state of the bodies as the simulation runs and scrubs through it like a video:
+/// * K: pause / resume the simulation
+/// * , / .: step one recorded frame backward / forward (Shift to step 10 frames)
+///
+/// While paused, the bodies are moved to the recorded frame under the cursor.
+/// Resuming restarts the live physics from that frame, discarding the frames recorded after it.
+/// Bodies spawned after the frame are left where they are.
+pub struct TimelinePlugin;
+
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(Action::TogglePause, "pause / resume the simulation")
+            .register_key_binding(
+                Action::StepBackward,
+                "step one recorded frame backward while paused (with Shift to step 10 frames)",
+            )
+            .register_key_binding(
+                Action::StepForward,
+                "step one recorded frame forward while paused (with Shift to step 10 frames)",
+            )
+            .init_resource::<Timeline>()
+            .init_resource::<UnitSystem>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(PhysicsStep)
+                    .with_system(record_frame.system().after(PhysicsSystem::Movement)),
+            )
+            .add_system(scrub_timeline.system());
+    }
+}
+
+/// State of the bodies at a given time
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineFrame {
+    pub time: f32,
+    /// Position and velocity of each body
+    pub bodies: Vec<(Entity, Vec3, Vec3)>,
+}
+
+/// Control of the timeline, from the keyboard or the dashboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineControl {
+    /// Pauses on the current state, or resumes from the frame under the cursor
+    TogglePause,
+    /// Moves the cursor by a number of frames
+    Step(isize),
+    /// Moves the cursor to a frame
+    Seek(usize),
+}
+
+/// Most recent frames, recorded every `interval` physics steps
+pub struct Timeline {
+    pub interval: u64,
+    capacity: usize,
+    steps: u64,
+    frames: VecDeque<TimelineFrame>,
+    /// Index of the frame shown while paused
+    cursor: Option<usize>,
+    /// Control requested by another system, applied along with the keyboard ones
+    requested: Option<TimelineControl>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new(10, 1000)
+    }
+}
+
+impl Timeline {
+    pub fn new(interval: u64, capacity: usize) -> Self {
+        Self {
+            interval,
+            capacity,
+            steps: 0,
+            frames: VecDeque::with_capacity(capacity),
+            cursor: None,
+            requested: None,
+        }
+    }
+
+    /// Number of recorded frames, i.e. the range of the cursor
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn frames(&self) -> &VecDeque<TimelineFrame> {
+        &self.frames
+    }
+
+    pub fn cursor(&self) -> Option<usize> {
+        self.cursor
+    }
+
+    pub fn is_scrubbing(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    pub fn push(&mut self, frame: TimelineFrame) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// Moves the cursor to the frame `index`, clamped to the recorded range, and returns that frame
+    pub fn seek(&mut self, index: usize) -> Option<&TimelineFrame> {
+        let index = index.min(self.frames.len().checked_sub(1)?);
+        self.cursor = Some(index);
+        self.frames.get(index)
+    }
+
+    /// Moves the cursor by `offset` frames, starting from the last frame if not scrubbing yet
+    pub fn step(&mut self, offset: isize) -> Option<&TimelineFrame> {
+        let last = self.frames.len().checked_sub(1)?;
+        let current = self.cursor.unwrap_or(last);
+        let index = if offset < 0 {
+            current.saturating_sub(offset.unsigned_abs())
+        } else {
+            current + offset as usize
+        };
+        self.seek(index)
+    }
+
+    /// Requests a control, e.g. from a UI panel, applied on the next frame
+    pub fn request(&mut self, control: TimelineControl) {
+        self.requested = Some(control);
+    }
+
+    /// Drops all the frames, e.g. when the scene is reset
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.cursor = None;
+        self.steps = 0;
+    }
+
+    /// Leaves the scrubbing mode, the frames after the cursor being discarded as the history diverges from them.
+    /// Returns the frame the simulation resumes from.
+    pub fn resume(&mut self) -> Option<&TimelineFrame> {
+        let cursor = self.cursor.take()?;
+        self.frames.truncate(cursor + 1);
+        self.steps = 0;
+        self.frames.back()
+    }
+}
+
+/// Moves the bodies to their state in `frame`, the bodies missing from it are left untouched
+pub fn restore_frame<'a>(
+    frame: &TimelineFrame,
+    bodies: impl Iterator<Item = (Entity, Mut<'a, Transform>, Mut<'a, Velocity>)>,
+) {
+    for (entity, mut transform, mut vel) in bodies {
+        if let Some((_, pos, frame_vel)) = frame.bodies.iter().find(|(e, _, _)| *e == entity) {
+            transform.translation = *pos;
+            vel.0 = *frame_vel;
+        }
+    }
+}
+
+fn record_frame(
+    time: Res<SimulationTime>,
+    mut timeline: ResMut<Timeline>,
+    query: Query<(Entity, &Transform, &Velocity), With<Mass>>,
+) {
+    timeline.steps += 1;
+    if !timeline.steps.is_multiple_of(timeline.interval.max(1)) {
+        return;
+    }
+    let frame = TimelineFrame {
+        time: time.0,
+        bodies: query
+            .iter()
+            .map(|(entity, transform, vel)| (entity, transform.translation, vel.0))
+            .collect(),
+    };
+    timeline.push(frame);
+}
+
+fn scrub_timeline(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut timeline: ResMut<Timeline>,
+    mut paused: ResMut<PhysicsPaused>,
+    mut time: ResMut<SimulationTime>,
+    units: Res<UnitSystem>,
+    mut query: Query<(Entity, &mut Transform, &mut Velocity), With<Mass>>,
+) {
+    let frames = if keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift) {
+        10
+    } else {
+        1
+    };
+    let control = if bindings.just_pressed(&keys, Action::TogglePause) {
+        Some(TimelineControl::TogglePause)
+    } else if bindings.just_pressed(&keys, Action::StepBackward) {
+        Some(TimelineControl::Step(-frames))
+    } else if bindings.just_pressed(&keys, Action::StepForward) {
+        Some(TimelineControl::Step(frames))
+    } else {
+        None
+    };
+    // Not to flag the resource as changed on every frame
+    let requested = if timeline.requested.is_some() {
+        timeline.requested.take()
+    } else {
+        None
+    };
+    let control = match requested.or(control) {
+        Some(control) => control,
+        None => return,
+    };
+
+    if control == TimelineControl::TogglePause && timeline.is_scrubbing() {
+        if let Some(frame) = timeline.resume() {
+            time.0 = frame.time;
+        }
+        paused.0 = false;
+        info!("Simulation resumed at t = {}", units.format_time(time.0));
+        return;
+    }
+
+    // Pausing records the current state so that resuming right away continues seamlessly
+    if !timeline.is_scrubbing() {
+        timeline.push(TimelineFrame {
+            time: time.0,
+            bodies: query
+                .iter_mut()
+                .map(|(entity, transform, vel)| (entity, transform.translation, vel.0))
+                .collect(),
+        });
+    }
+    paused.0 = true;
+    let len = timeline.len();
+    let frame = match control {
+        TimelineControl::Seek(index) => timeline.seek(index),
+        TimelineControl::Step(offset) => timeline.step(offset),
+        TimelineControl::TogglePause => timeline.step(0),
+    };
+    if let Some(frame) = frame {
+        time.0 = frame.time;
+        restore_frame(frame, query.iter_mut());
+    }
+    if let Some(cursor) = timeline.cursor() {
+        info!(
+            "Frame {}/{} at t = {}",
+            cursor + 1,
+            len,
+            units.format_time(time.0)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::nbody::BodyBundle;
+    use crate::testing::run_system;
+
+    #[test]
+    fn seeking_restores_the_recorded_frame() {
+        let mut world = World::default();
+        world.insert_resource(Input::<KeyCode>::default());
+        world.insert_resource(KeyBindings::default());
+        world.insert_resource(PhysicsPaused::default());
+        world.insert_resource(SimulationTime(3.0));
+        world.insert_resource(UnitSystem::default());
+        let body = world
+            .spawn()
+            .insert_bundle(BodyBundle::new(1.0, Vec3::new(3.0, 0.0, 0.0), Vec3::X))
+            .id();
+        let mut timeline = Timeline::new(1, 10);
+        for i in 0..3 {
+            timeline.push(TimelineFrame {
+                time: i as f32,
+                bodies: vec![(body, Vec3::new(i as f32, 0.0, 0.0), Vec3::Y * i as f32)],
+            });
+        }
+        timeline.request(TimelineControl::Seek(1));
+        world.insert_resource(timeline);
+
+        run_system(&mut world, scrub_timeline.system());
+        assert_eq!(world.get::<Transform>(body).unwrap().translation, Vec3::X);
+        assert_eq!(world.get::<Velocity>(body).unwrap().0, Vec3::Y);
+        assert_eq!(world.get_resource::<SimulationTime>().unwrap().0, 1.0);
+        assert!(world.get_resource::<PhysicsPaused>().unwrap().0);
+        // The live state was recorded as the last frame before seeking
+        let timeline = world.get_resource::<Timeline>().unwrap();
+        assert_eq!(timeline.len(), 4);
+        assert_eq!(timeline.cursor(), Some(1));
+
+        // Resuming discards the frames after the cursor
+        world
+            .get_resource_mut::<Timeline>()
+            .unwrap()
+            .request(TimelineControl::TogglePause);
+        run_system(&mut world, scrub_timeline.system());
+        assert!(!world.get_resource::<PhysicsPaused>().unwrap().0);
+        let timeline = world.get_resource::<Timeline>().unwrap();
+        assert_eq!(timeline.len(), 2);
+        assert!(!timeline.is_scrubbing());
+    }
+}
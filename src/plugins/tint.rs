@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+
+/// Single owner of the `base_color` of the bodies tinted by the other plugins, which only set their
+/// tint layer on the bodies: the topmost layer shows, and the original color comes back once no
+/// layer is left. The material is only written when its color changes.
+pub struct TintPlugin;
+
+impl Plugin for TintPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // After the commands of the `Update` stage inserting and removing the tints
+        app.add_system_to_stage(CoreStage::PostUpdate, apply_tints.system());
+    }
+}
+
+/// Tint of the sphere of influence coloring, under the `HighlightTint`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InfluenceTint(pub Color);
+
+/// Tint of the highlighted extremes, over the `InfluenceTint`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HighlightTint(pub Color);
+
+/// Color of a tinted body before it got tinted
+struct OriginalColor(Color);
+
+#[allow(clippy::type_complexity)]
+fn apply_tints(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    bodies: Query<
+        (
+            Entity,
+            &Handle<StandardMaterial>,
+            Option<&InfluenceTint>,
+            Option<&HighlightTint>,
+            Option<&OriginalColor>,
+        ),
+        Or<(
+            With<InfluenceTint>,
+            With<HighlightTint>,
+            With<OriginalColor>,
+        )>,
+    >,
+) {
+    for (entity, handle, influence, highlight, original) in bodies.iter() {
+        let current = match materials.get(handle) {
+            Some(material) => material.base_color,
+            None => continue,
+        };
+        let tint = highlight
+            .map(|tint| tint.0)
+            .or_else(|| influence.map(|tint| tint.0));
+        let color = match (tint, original) {
+            (Some(tint), Some(_)) => tint,
+            (Some(tint), None) => {
+                commands.entity(entity).insert(OriginalColor(current));
+                tint
+            }
+            (None, Some(original)) => {
+                commands.entity(entity).remove::<OriginalColor>();
+                original.0
+            }
+            (None, None) => continue,
+        };
+        if color != current {
+            if let Some(material) = materials.get_mut(handle) {
+                material.base_color = color;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::Events;
+
+    use super::*;
+    use crate::testing::{run_system, world_with_assets};
+
+    #[test]
+    fn topmost_tint_shows_until_the_original_color_is_restored() {
+        let mut world = world_with_assets();
+        let material = world
+            .get_resource_mut::<Assets<StandardMaterial>>()
+            .unwrap()
+            .add(Color::WHITE.into());
+        let body = world.spawn().insert(material.clone()).id();
+        let color = |world: &World| {
+            let materials = world.get_resource::<Assets<StandardMaterial>>().unwrap();
+            materials.get(&material).unwrap().base_color
+        };
+
+        world.entity_mut(body).insert(InfluenceTint(Color::CYAN));
+        run_system(&mut world, apply_tints.system());
+        assert_eq!(color(&world), Color::CYAN);
+
+        world.entity_mut(body).insert(HighlightTint(Color::FUCHSIA));
+        run_system(&mut world, apply_tints.system());
+        assert_eq!(color(&world), Color::FUCHSIA);
+
+        // Back to the influence, not to the color under the highlight when it was set
+        world.entity_mut(body).remove::<HighlightTint>();
+        run_system(&mut world, apply_tints.system());
+        assert_eq!(color(&world), Color::CYAN);
+
+        world.entity_mut(body).remove::<InfluenceTint>();
+        run_system(&mut world, apply_tints.system());
+        assert_eq!(color(&world), Color::WHITE);
+        assert!(world.get::<OriginalColor>(body).is_none());
+    }
+
+    /// Number of `Modified` events of the materials since the last call
+    fn modified_materials(world: &mut World) -> usize {
+        run_system(
+            world,
+            Assets::<StandardMaterial>::asset_event_system.system(),
+        );
+        let mut events = world
+            .get_resource_mut::<Events<AssetEvent<StandardMaterial>>>()
+            .unwrap();
+        events
+            .drain()
+            .filter(|event| matches!(event, AssetEvent::Modified { .. }))
+            .count()
+    }
+
+    #[test]
+    fn materials_are_only_written_when_their_color_changes() {
+        let mut world = world_with_assets();
+        let material = world
+            .get_resource_mut::<Assets<StandardMaterial>>()
+            .unwrap()
+            .add(Color::WHITE.into());
+        let tinted = world.spawn().insert(material).id();
+        let material = world
+            .get_resource_mut::<Assets<StandardMaterial>>()
+            .unwrap()
+            .add(Color::WHITE.into());
+        world.spawn().insert(material);
+        modified_materials(&mut world);
+
+        run_system(&mut world, apply_tints.system());
+        assert_eq!(modified_materials(&mut world), 0);
+
+        world.entity_mut(tinted).insert(InfluenceTint(Color::CYAN));
+        run_system(&mut world, apply_tints.system());
+        assert_eq!(modified_materials(&mut world), 1);
+        run_system(&mut world, apply_tints.system());
+        assert_eq!(modified_materials(&mut world), 0);
+    }
+}
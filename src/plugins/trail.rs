@@ -0,0 +1,752 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::ops::Range;
+
+use bevy::prelude::*;
+use bevy::render::{
+    camera::{Camera, PerspectiveProjection},
+    pipeline::PrimitiveTopology,
+};
+
+use super::key_bindings::{Action, KeyBindings, RegisterKeyBinding};
+use super::lensing::project;
+use super::nbody::{PhysicsStep, PhysicsSystem, Velocity};
+use super::prediction::line_mesh;
+use super::selection::Selected;
+
+/// Orbit trails of the bodies with a `Trail`, and the paths captured on demand in a `ManualTrail`:
+/// * V: toggle the coloring of the trails by speed
+/// * X: export the trails as seen from the camera to an SVG file, see `TrailExport`
+/// * A: hold to capture the path of the selected bodies
+/// * Shift + A: clear the captured paths
+pub struct TrailPlugin;
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_key_binding(
+            Action::ToggleTrailSpeedColors,
+            "toggle the coloring of the trails by speed",
+        )
+        .register_key_binding(
+            Action::ExportTrails,
+            "export the trails as seen from the camera to `trails.svg`",
+        )
+        .register_key_binding(
+            Action::CaptureTrail,
+            "hold to capture the path of the selected bodies (with Shift to clear the captured paths)",
+        )
+        .init_resource::<TrailStyle>()
+        .init_resource::<TrailExport>()
+        .add_startup_system(spawn_manual_trail_lines.system())
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(PhysicsStep)
+                .with_system(record_trails.system().after(PhysicsSystem::Movement))
+                .with_system(record_manual_trails.system().after(PhysicsSystem::Movement)),
+        )
+        .add_system(capture_trails.system())
+        .add_system(draw_manual_trails.system())
+        .add_system(spawn_trail_segments.system())
+        .add_system(draw_trails.system())
+        .add_system(toggle_color_mode.system())
+        .add_system(export_trails.system())
+        .add_system(update_trail_materials.system())
+        .add_system(despawn_orphan_segments.system());
+    }
+}
+
+/// Number of segments a trail is split into, each with its own transparency
+const FADE_SEGMENTS: usize = 8;
+
+/// When a trail records a new point
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TrailSampling {
+    /// At every physics step: fast bodies get sparse trails and slow ones dense trails
+    #[default]
+    PerStep,
+    /// Once the body moved `min_spacing` away from the last point, for a uniform spatial resolution.
+    /// The trail then lags up to `min_spacing` behind the body.
+    ByDistance { min_spacing: f32 },
+}
+
+/// Past positions of a body and its speed at each of them, sampled as set by its `TrailSampling`
+pub struct Trail {
+    points: VecDeque<Vec3>,
+    speeds: VecDeque<f32>,
+    max_points: usize,
+    sampling: TrailSampling,
+}
+
+impl Trail {
+    pub fn new(max_points: usize) -> Self {
+        Self {
+            points: VecDeque::with_capacity(max_points),
+            speeds: VecDeque::with_capacity(max_points),
+            max_points,
+            sampling: TrailSampling::default(),
+        }
+    }
+
+    pub fn with_sampling(mut self, sampling: TrailSampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    pub fn sampling(&self) -> TrailSampling {
+        self.sampling
+    }
+
+    /// Recorded positions, the oldest first
+    pub fn points(&self) -> &VecDeque<Vec3> {
+        &self.points
+    }
+
+    /// Speed of the body at each of the `points`
+    pub fn speeds(&self) -> &VecDeque<f32> {
+        &self.speeds
+    }
+
+    /// Records `point` unless it's too close to the last one for the sampling, returns whether it was recorded
+    pub fn push(&mut self, point: Vec3, speed: f32) -> bool {
+        if let (TrailSampling::ByDistance { min_spacing }, Some(last)) =
+            (self.sampling, self.points.back())
+        {
+            if last.distance_squared(point) < min_spacing * min_spacing {
+                return false;
+            }
+        }
+        if self.points.len() == self.max_points {
+            self.points.pop_front();
+            self.speeds.pop_front();
+        }
+        self.points.push_back(point);
+        self.speeds.push_back(speed);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.speeds.clear();
+    }
+}
+
+/// Path of a body recorded only while `recording`, e.g. over an interval of interest,
+/// and kept until cleared, unlike the rolling `Trail`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ManualTrail {
+    pub recording: bool,
+    points: Vec<Vec3>,
+}
+
+impl ManualTrail {
+    /// Recorded positions, the oldest first
+    pub fn points(&self) -> &[Vec3] {
+        &self.points
+    }
+
+    /// Records `point` if `recording`, returns whether it was recorded
+    pub fn record(&mut self, point: Vec3) -> bool {
+        if self.recording {
+            self.points.push(point);
+        }
+        self.recording
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+}
+
+pub const MANUAL_TRAIL_COLOR: Color = Color::rgba(1.0, 0.85, 0.3, 0.8);
+
+struct ManualTrailLines;
+
+/// Coloring of the trails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailColorMode {
+    /// The `TrailStyle` color, or the color of each body
+    #[default]
+    Solid,
+    /// Speed of the body along its trail, from blue when slowest to red when fastest.
+    /// Each segment of a trail has a single material, colored by the mean speed over the segment.
+    BySpeed,
+}
+
+/// Rendering settings shared by all the trails
+pub struct TrailStyle {
+    /// Apparent width, relative to the distance to the camera
+    pub width: f32,
+    /// Fade from opaque at the body to transparent at the tail
+    pub fade: bool,
+    /// Color of all the trails, defaults to the color of each body
+    pub color: Option<Color>,
+    pub color_mode: TrailColorMode,
+}
+
+impl Default for TrailStyle {
+    fn default() -> Self {
+        Self {
+            width: 0.002,
+            fade: true,
+            color: None,
+            color_mode: TrailColorMode::default(),
+        }
+    }
+}
+
+/// Destination of the SVG export of the trails
+pub struct TrailExport {
+    pub path: String,
+}
+
+impl Default for TrailExport {
+    fn default() -> Self {
+        Self {
+            path: String::from("trails.svg"),
+        }
+    }
+}
+
+/// Part of the trail of `owner`, rendered as a ribbon facing the camera
+struct TrailSegment {
+    owner: Entity,
+    index: usize,
+    body_color: Color,
+}
+
+/// Opacity of the `index`-th segment out of `count`, counting from the tail
+pub fn segment_alpha(index: usize, count: usize, fade: bool) -> f32 {
+    if fade {
+        (index + 1) as f32 / count as f32
+    } else {
+        1.0
+    }
+}
+
+/// Points of the `index`-th segment out of `count` for a trail of `len` points.
+/// Consecutive segments share their boundary point so that the trail is continuous.
+pub fn segment_range(index: usize, count: usize, len: usize) -> Range<usize> {
+    let bound = |i: usize| i * len.saturating_sub(1) / count;
+    bound(index)..(bound(index + 1) + 1).min(len)
+}
+
+/// Colormap of the speeds: blue at `min`, through cyan, green and yellow, to red at `max`
+pub fn speed_color(speed: f32, min: f32, max: f32) -> Color {
+    let t = if max > min {
+        ((speed - min) / (max - min)).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    // Piecewise linear over the 4 quarters of the range
+    let x = 4.0 * t;
+    let (r, g, b) = match x {
+        x if x < 1.0 => (0.0, x, 1.0),
+        x if x < 2.0 => (0.0, 1.0, 2.0 - x),
+        x if x < 3.0 => (x - 2.0, 1.0, 0.0),
+        x => (1.0, 4.0 - x, 0.0),
+    };
+    Color::rgb(r, g, b)
+}
+
+/// Triangle strip of a ribbon of the given `width` going through `points` and facing the `camera`
+pub fn ribbon_vertices<'a>(
+    points: impl Iterator<Item = &'a Vec3> + Clone,
+    camera: Vec3,
+    width: f32,
+) -> Vec<[f32; 3]> {
+    let next_points = points
+        .clone()
+        .skip(1)
+        .map(Some)
+        .chain(std::iter::once(None));
+    let mut previous: Option<Vec3> = None;
+    let mut vertices = Vec::new();
+    for (point, next) in points.zip(next_points) {
+        let tangent = match (previous, next) {
+            (_, Some(next)) => *next - *point,
+            (Some(previous), None) => *point - previous,
+            (None, None) => Vec3::ZERO,
+        };
+        let to_camera = camera - *point;
+        // Counter-clockwise when seen from the camera
+        let side = to_camera.cross(tangent).normalize_or_zero() * width * to_camera.length() / 2.0;
+        vertices.push((*point + side).into());
+        vertices.push((*point - side).into());
+        previous = Some(*point);
+    }
+    vertices
+}
+
+/// `#rrggbb` code of the color, ignoring its alpha
+fn svg_color(color: Color) -> String {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.r()),
+        channel(color.g()),
+        channel(color.b())
+    )
+}
+
+/// SVG document of `width` x `height` pixels with a polyline per trail, given as its points
+/// in normalized device coordinates and its color. Trails with less than 2 points are skipped.
+pub fn trails_svg(trails: &[(Vec<Vec2>, Color)], width: f32, height: f32) -> String {
+    let mut svg = String::new();
+    // Writing to a String can't fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height
+    );
+    for (points, color) in trails.iter().filter(|(points, _)| points.len() >= 2) {
+        let points: Vec<String> = points
+            .iter()
+            .map(|ndc| {
+                // The SVG y-axis points down
+                let x = (ndc.x + 1.0) / 2.0 * width;
+                let y = (1.0 - ndc.y) / 2.0 * height;
+                format!("{:.2},{:.2}", x, y)
+            })
+            .collect();
+        let _ = writeln!(
+            svg,
+            r#"  <polyline points="{}" fill="none" stroke="{}" stroke-width="1"/>"#,
+            points.join(" "),
+            svg_color(*color)
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn record_trails(mut query: Query<(&Transform, Option<&Velocity>, &mut Trail)>) {
+    for (transform, vel, mut trail) in query.iter_mut() {
+        trail.push(transform.translation, vel.map_or(0.0, |vel| vel.0.length()));
+    }
+}
+
+/// Line list of the open curve going through `points`
+pub fn polyline_vertices(points: &[Vec3]) -> Vec<[f32; 3]> {
+    points
+        .windows(2)
+        .flat_map(|pair| vec![pair[0].into(), pair[1].into()])
+        .collect()
+}
+
+fn record_manual_trails(mut query: Query<(&Transform, &mut ManualTrail)>) {
+    for (transform, mut trail) in query.iter_mut() {
+        if trail.recording {
+            trail.record(transform.translation);
+        }
+    }
+}
+
+/// Records the selected bodies while the key is held, a body keeping its captured path once deselected
+fn capture_trails(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut trails: Query<(Entity, &mut ManualTrail, Option<&Selected>)>,
+    uncaptured: Query<Entity, (With<Selected>, Without<ManualTrail>)>,
+) {
+    let shift = keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift);
+    if shift && bindings.just_pressed(&keys, Action::CaptureTrail) {
+        for (entity, _, _) in trails.iter_mut() {
+            commands.entity(entity).remove::<ManualTrail>();
+        }
+        return;
+    }
+
+    let held = !shift && bindings.pressed(&keys, Action::CaptureTrail);
+    for (_, mut trail, selected) in trails.iter_mut() {
+        let recording = held && selected.is_some();
+        if trail.recording != recording {
+            trail.recording = recording;
+        }
+    }
+    if held {
+        for entity in uncaptured.iter() {
+            commands.entity(entity).insert(ManualTrail {
+                recording: true,
+                ..Default::default()
+            });
+        }
+    }
+}
+
+fn spawn_manual_trail_lines(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(line_mesh(vec![[0.0; 3]; 2])),
+            material: materials.add(StandardMaterial {
+                base_color: MANUAL_TRAIL_COLOR,
+                unlit: true,
+                ..Default::default()
+            }),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(ManualTrailLines);
+}
+
+fn draw_manual_trails(
+    mut meshes: ResMut<Assets<Mesh>>,
+    trails: Query<&ManualTrail>,
+    mut lines: Query<(&Handle<Mesh>, &mut Visible), With<ManualTrailLines>>,
+) {
+    let (mesh, mut visible) = match lines.single_mut() {
+        Ok(lines) => lines,
+        Err(_) => return,
+    };
+    let vertices: Vec<[f32; 3]> = trails
+        .iter()
+        .flat_map(|trail| polyline_vertices(trail.points()))
+        .collect();
+    visible.is_visible = !vertices.is_empty();
+    if vertices.is_empty() {
+        return;
+    }
+    if let Some(mesh) = meshes.get_mut(mesh) {
+        *mesh = line_mesh(vertices);
+    }
+}
+
+fn ribbon_mesh(vertices: Vec<[f32; 3]>) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleStrip);
+    let count = vertices.len();
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
+    // Unlit, the normals don't matter
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; count]);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; count]);
+    mesh
+}
+
+fn trail_material(color: Color, alpha: f32) -> StandardMaterial {
+    let mut base_color = color;
+    base_color.set_a(alpha);
+    StandardMaterial {
+        base_color,
+        unlit: true,
+        ..Default::default()
+    }
+}
+
+fn spawn_trail_segments(
+    mut commands: Commands,
+    style: Res<TrailStyle>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<(Entity, Option<&Handle<StandardMaterial>>), Added<Trail>>,
+) {
+    for (owner, body_material) in query.iter() {
+        let body_color = body_material
+            .and_then(|handle| materials.get(handle))
+            .map(|material| material.base_color)
+            .unwrap_or(Color::WHITE);
+
+        for index in 0..FADE_SEGMENTS {
+            commands
+                .spawn_bundle(PbrBundle {
+                    // Degenerate until the body has moved
+                    mesh: meshes.add(ribbon_mesh(vec![[0.0; 3]; 4])),
+                    material: materials.add(trail_material(
+                        style.color.unwrap_or(body_color),
+                        segment_alpha(index, FADE_SEGMENTS, style.fade),
+                    )),
+                    visible: Visible {
+                        is_visible: true,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(TrailSegment {
+                    owner,
+                    index,
+                    body_color,
+                });
+        }
+    }
+}
+
+fn draw_trails(
+    style: Res<TrailStyle>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    cameras: Query<&GlobalTransform, With<PerspectiveProjection>>,
+    trails: Query<&Trail>,
+    segments: Query<(&TrailSegment, &Handle<Mesh>)>,
+) {
+    let camera = match cameras.iter().next() {
+        Some(transform) => transform.translation,
+        None => return,
+    };
+
+    for (segment, mesh) in segments.iter() {
+        let trail = match trails.get(segment.owner) {
+            Ok(trail) => trail,
+            Err(_) => continue,
+        };
+        let range = segment_range(segment.index, FADE_SEGMENTS, trail.points.len());
+        let vertices = if range.len() < 2 {
+            vec![[0.0; 3]; 4]
+        } else {
+            ribbon_vertices(trail.points.range(range), camera, style.width)
+        };
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            *mesh = ribbon_mesh(vertices);
+        }
+    }
+}
+
+fn toggle_color_mode(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut style: ResMut<TrailStyle>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleTrailSpeedColors) {
+        style.color_mode = match style.color_mode {
+            TrailColorMode::Solid => TrailColorMode::BySpeed,
+            TrailColorMode::BySpeed => TrailColorMode::Solid,
+        };
+    }
+}
+
+/// Writes the trails projected on the screen, the points behind the camera being left out
+#[allow(clippy::too_many_arguments)]
+fn export_trails(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    style: Res<TrailStyle>,
+    export: Res<TrailExport>,
+    windows: Res<Windows>,
+    materials: Res<Assets<StandardMaterial>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<PerspectiveProjection>>,
+    trails: Query<(&Trail, Option<&Handle<StandardMaterial>>)>,
+) {
+    if !bindings.just_pressed(&keys, Action::ExportTrails) {
+        return;
+    }
+    let (window, (camera, transform)) = match (windows.get_primary(), cameras.iter().next()) {
+        (Some(window), Some(camera)) => (window, camera),
+        _ => return,
+    };
+    let view_proj = camera.projection_matrix * transform.compute_matrix().inverse();
+
+    let projected: Vec<(Vec<Vec2>, Color)> = trails
+        .iter()
+        .map(|(trail, material)| {
+            let points = trail
+                .points
+                .iter()
+                .filter_map(|point| project(view_proj, *point).map(|ndc| ndc.truncate()))
+                .collect();
+            let body_color = material
+                .and_then(|handle| materials.get(handle))
+                .map_or(Color::WHITE, |material| material.base_color);
+            (points, style.color.unwrap_or(body_color))
+        })
+        .collect();
+    let svg = trails_svg(&projected, window.width(), window.height());
+    match std::fs::write(&export.path, svg) {
+        Ok(()) => info!("Trails exported to {}", export.path),
+        Err(e) => error!("Cannot export the trails to {}: {}", export.path, e),
+    }
+}
+
+/// Color of a segment by the mean speed over its points, relative to the range of speeds of the whole trail
+fn segment_speed_color(trail: &Trail, index: usize) -> Option<Color> {
+    let range = segment_range(index, FADE_SEGMENTS, trail.speeds.len());
+    if range.is_empty() {
+        return None;
+    }
+    let mean = trail.speeds.range(range.clone()).sum::<f32>() / range.len() as f32;
+    let (min, max) = trail
+        .speeds
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), speed| {
+            (min.min(*speed), max.max(*speed))
+        });
+    Some(speed_color(mean, min, max))
+}
+
+fn update_trail_materials(
+    style: Res<TrailStyle>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    trails: Query<&Trail>,
+    segments: Query<(&TrailSegment, &Handle<StandardMaterial>)>,
+) {
+    // The speeds change along with the trails
+    if !style.is_changed() && style.color_mode == TrailColorMode::Solid {
+        return;
+    }
+    for (segment, handle) in segments.iter() {
+        let color = match style.color_mode {
+            TrailColorMode::Solid => style.color.unwrap_or(segment.body_color),
+            TrailColorMode::BySpeed => match trails
+                .get(segment.owner)
+                .ok()
+                .and_then(|trail| segment_speed_color(trail, segment.index))
+            {
+                Some(color) => color,
+                None => continue,
+            },
+        };
+        if let Some(material) = materials.get_mut(handle) {
+            *material = trail_material(
+                color,
+                segment_alpha(segment.index, FADE_SEGMENTS, style.fade),
+            );
+        }
+    }
+}
+
+fn despawn_orphan_segments(
+    mut commands: Commands,
+    trails: Query<&Trail>,
+    segments: Query<(Entity, &TrailSegment)>,
+) {
+    for (entity, segment) in segments.iter() {
+        if trails.get(segment.owner).is_err() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::run_system;
+
+    #[test]
+    fn segments_fade_toward_the_tail() {
+        let alphas: Vec<f32> = (0..4).map(|i| segment_alpha(i, 4, true)).collect();
+        assert_eq!(alphas, vec![0.25, 0.5, 0.75, 1.0]);
+        assert!((0..4).all(|i| segment_alpha(i, 4, false) == 1.0));
+    }
+
+    #[test]
+    fn segments_share_their_boundary_points() {
+        let ranges: Vec<Range<usize>> = (0..3).map(|i| segment_range(i, 3, 7)).collect();
+        assert_eq!(ranges, vec![0..3, 2..5, 4..7]);
+        // Shorter trails than the number of segments leave some of them empty
+        assert!(segment_range(0, 8, 2).len() < 2);
+        assert_eq!(segment_range(7, 8, 2), 0..2);
+    }
+
+    #[test]
+    fn speeds_map_from_blue_to_red() {
+        assert_eq!(speed_color(1.0, 1.0, 5.0), Color::rgb(0.0, 0.0, 1.0));
+        assert_eq!(speed_color(3.0, 1.0, 5.0), Color::rgb(0.0, 1.0, 0.0));
+        assert_eq!(speed_color(5.0, 1.0, 5.0), Color::rgb(1.0, 0.0, 0.0));
+        // Out of range speeds are clamped, a single speed is drawn blue
+        assert_eq!(speed_color(9.0, 1.0, 5.0), Color::rgb(1.0, 0.0, 0.0));
+        assert_eq!(speed_color(2.0, 2.0, 2.0), Color::rgb(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn trail_keeps_a_speed_per_point() {
+        let mut trail = Trail::new(2);
+        for i in 0..3 {
+            trail.push(Vec3::new(i as f32, 0.0, 0.0), 10.0 * i as f32);
+        }
+        assert_eq!(trail.points().len(), 2);
+        assert_eq!(
+            trail.speeds().iter().copied().collect::<Vec<_>>(),
+            vec![10.0, 20.0]
+        );
+    }
+
+    #[test]
+    fn slow_bodies_add_fewer_points_by_distance() {
+        let mut per_step = Trail::new(1000);
+        let mut by_distance =
+            Trail::new(1000).with_sampling(TrailSampling::ByDistance { min_spacing: 0.1 });
+        // 100 steps of 0.01
+        for i in 0..100 {
+            let point = Vec3::new(0.01 * i as f32, 0.0, 0.0);
+            per_step.push(point, 1.0);
+            by_distance.push(point, 1.0);
+        }
+        assert_eq!(per_step.points().len(), 100);
+        assert_eq!(by_distance.points().len(), 10);
+        assert!(by_distance
+            .points()
+            .iter()
+            .zip(by_distance.points().iter().skip(1))
+            .all(|(a, b)| a.distance(*b) >= 0.1 - 1e-6));
+    }
+
+    #[test]
+    fn svg_has_a_polyline_per_trail() {
+        let trails = vec![
+            (vec![Vec2::new(-1.0, 1.0), Vec2::new(1.0, -1.0)], Color::RED),
+            (vec![Vec2::ZERO, Vec2::X, Vec2::Y], Color::BLUE),
+            // Not enough points for a line
+            (vec![Vec2::ZERO], Color::GREEN),
+        ];
+        let svg = trails_svg(&trails, 200.0, 100.0);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<polyline ").count(), 2);
+        assert_eq!(svg.matches("/>").count(), 2);
+        // Corners of the screen, with the y axis pointing down
+        assert!(svg.contains(r#"points="0.00,0.00 200.00,100.00""#));
+        assert!(svg.contains(r#"points="100.00,50.00 200.00,50.00 100.00,0.00""#));
+        assert!(svg.contains(r##"stroke="#ff0000""##) && svg.contains(r##"stroke="#0000ff""##));
+    }
+
+    #[test]
+    fn manual_trail_records_only_while_recording() {
+        let mut trail = ManualTrail::default();
+        assert!(!trail.record(Vec3::X));
+        trail.recording = true;
+        assert!(trail.record(Vec3::Y));
+        assert!(trail.record(Vec3::Z));
+        trail.recording = false;
+        assert!(!trail.record(Vec3::ONE));
+        assert_eq!(trail.points(), &[Vec3::Y, Vec3::Z]);
+        trail.clear();
+        assert!(trail.points().is_empty());
+    }
+
+    #[test]
+    fn selected_bodies_are_captured_while_the_key_is_held() {
+        let mut world = World::default();
+        world.insert_resource(KeyBindings::default());
+        let selected = world
+            .spawn()
+            .insert_bundle((Transform::default(), Selected))
+            .id();
+        let other = world.spawn().insert(Transform::default()).id();
+        let step = |world: &mut World, held: bool, x: f32| {
+            let mut keys = Input::<KeyCode>::default();
+            if held {
+                keys.press(KeyCode::A);
+            }
+            world.insert_resource(keys);
+            run_system(world, capture_trails.system());
+            world.get_mut::<Transform>(selected).unwrap().translation.x = x;
+            run_system(world, record_manual_trails.system());
+        };
+
+        step(&mut world, true, 1.0);
+        step(&mut world, true, 2.0);
+        step(&mut world, false, 3.0);
+        step(&mut world, false, 4.0);
+        let trail = world.get::<ManualTrail>(selected).unwrap();
+        assert!(!trail.recording);
+        assert_eq!(trail.points(), &[Vec3::X, 2.0 * Vec3::X]);
+        assert_eq!(
+            polyline_vertices(trail.points()),
+            vec![[1.0, 0.0, 0.0], [2.0, 0.0, 0.0]]
+        );
+        assert!(world.get::<ManualTrail>(other).is_none());
+    }
+}
@@ -0,0 +1,669 @@
+//! Scenes described in RON or JSON files, e.g.
+//! ```text
+//! (
+//!     simulation: Some((g: Some(1.0), softening: Some(0.05))),
+//!     bodies: [
+//!         (mass: 1.0, position: (0.97, -0.24, 0.0), velocity: (0.47, 0.43, 0.0), color: Some((0.0, 0.0, 1.0))),
+//!         ...
+//!     ],
+//! )
+//! ```
+use std::{fmt, fs, path::Path};
+
+use bevy::math::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::plugins::collision::{CollisionMode, ContinuousCollision, TidalDisruption};
+use crate::plugins::nbody::{Gravity, Integrator, SofteningKernel, TimeStep};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneDescription {
+    /// Overrides of the simulation parameters, the current ones are kept when missing
+    #[serde(default)]
+    pub simulation: Option<SimulationParams>,
+    pub bodies: Vec<BodyDescription>,
+}
+
+/// Simulation parameters of a scene, missing fields fall back to the current values
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SimulationParams {
+    /// Gravitational constant, in the units of the scene
+    #[serde(default)]
+    pub g: Option<f32>,
+    /// Plummer softening length
+    #[serde(default)]
+    pub softening: Option<f32>,
+    /// Softening kernel, e.g. `Some(CubicSpline(h: 0.1))`, taking precedence over the Plummer `softening`
+    #[serde(default)]
+    pub softening_kernel: Option<SofteningKernel>,
+    /// Rendering scale applied to the positions and velocities of the bodies
+    #[serde(default)]
+    pub scale: Option<f32>,
+    /// Simulated time of a physics step, see `TimeStep`
+    #[serde(default)]
+    pub dt: Option<f32>,
+    #[serde(default)]
+    pub integrator: Option<Integrator>,
+    /// Outcome of the bodies touching each other, e.g. `Some(Fragment(threshold: 0.5, pieces: 8))`
+    #[serde(default)]
+    pub collisions: Option<CollisionMode>,
+    /// Detect the collisions along the paths of the bodies during a step, see `ContinuousCollision`
+    #[serde(default)]
+    pub continuous_collision: Option<bool>,
+    /// Break up of the bodies crossing a Roche limit, e.g. `Some((enabled: true, pieces: 8))`
+    #[serde(default)]
+    pub tidal_disruption: Option<TidalDisruption>,
+}
+
+impl SimulationParams {
+    /// Overrides the resources with the parameters set in the scene
+    pub fn apply(
+        &self,
+        g: &mut Gravity,
+        softening: &mut SofteningKernel,
+        integrator: &mut Integrator,
+    ) {
+        if let Some(value) = self.g {
+            g.0 = value;
+        }
+        if let Some(eps) = self.softening {
+            *softening = SofteningKernel::Plummer { eps };
+        }
+        if let Some(kernel) = self.softening_kernel {
+            *softening = kernel;
+        }
+        if let Some(value) = self.integrator {
+            *integrator = value;
+        }
+    }
+
+    /// Time step set in the scene, to be inserted before the `NBody` plugin fixes the rate of the steps
+    pub fn time_step(&self) -> Option<TimeStep> {
+        self.dt.map(TimeStep)
+    }
+
+    /// Overrides the collision resources with the parameters set in the scene
+    pub fn apply_collisions(
+        &self,
+        mode: &mut CollisionMode,
+        continuous: &mut ContinuousCollision,
+        tidal_disruption: &mut TidalDisruption,
+    ) {
+        if let Some(value) = self.collisions {
+            *mode = value;
+        }
+        if let Some(value) = self.continuous_collision {
+            continuous.0 = value;
+        }
+        if let Some(value) = self.tidal_disruption {
+            *tidal_disruption = value;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BodyDescription {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub mass: f32,
+    pub position: (f32, f32, f32),
+    #[serde(default)]
+    pub velocity: (f32, f32, f32),
+    /// Rendered and collision radius
+    #[serde(default = "default_radius")]
+    pub radius: f32,
+    /// RGB color
+    #[serde(default)]
+    pub color: Option<(f32, f32, f32)>,
+    /// Image of the surface relative to the assets directory, e.g. `textures/earth.png` (PNG or HDR), tinted by the color
+    #[serde(default)]
+    pub texture: Option<String>,
+    /// Stars carry their own light
+    #[serde(default)]
+    pub star: bool,
+    /// Comets grow a tail pointing away from the nearest star
+    #[serde(default)]
+    pub comet: bool,
+    /// Absorbs every body touching it, see `CentralAccretor`
+    #[serde(default)]
+    pub accretor: bool,
+    /// Length of the trail, in physics steps
+    #[serde(default)]
+    pub trail: Option<usize>,
+    /// Surface temperature in Kelvin, overriding the color with the blackbody one
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+fn default_radius() -> f32 {
+    0.1
+}
+
+impl BodyDescription {
+    /// Absorbs `other`, conserving mass, momentum and volume
+    fn merge(&mut self, other: &BodyDescription) {
+        let (pos, vel) = self.scaled_state(1.0);
+        let (other_pos, other_vel) = other.scaled_state(1.0);
+        let mass = self.mass + other.mass;
+        let (pos, vel) = if mass > 0.0 {
+            (
+                (self.mass * pos + other.mass * other_pos) / mass,
+                (self.mass * vel + other.mass * other_vel) / mass,
+            )
+        } else {
+            ((pos + other_pos) / 2.0, (vel + other_vel) / 2.0)
+        };
+        self.mass = mass;
+        self.position = pos.into();
+        self.velocity = vel.into();
+        self.radius = (self.radius.powi(3) + other.radius.powi(3)).cbrt();
+        self.star |= other.star;
+        self.comet |= other.comet;
+        self.accretor |= other.accretor;
+        self.trail = self.trail.max(other.trail);
+        self.temperature = self.temperature.or(other.temperature);
+    }
+
+    /// Initial position and velocity, at the rendering `scale`
+    pub fn scaled_state(&self, scale: f32) -> (Vec3, Vec3) {
+        let (x, y, z) = self.position;
+        let (vx, vy, vz) = self.velocity;
+        (scale * Vec3::new(x, y, z), scale * Vec3::new(vx, vy, vz))
+    }
+}
+
+/// Handling of bodies loaded at (nearly) the same position, whose forces would blow up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Duplicates are merged into one body, conserving mass, momentum and volume
+    #[default]
+    Merge,
+    /// Duplicates are kept as is
+    Warn,
+    /// The scene is rejected
+    Error,
+}
+
+impl std::str::FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "merge" => Ok(Self::Merge),
+            "warn" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            _ => Err(String::from(
+                "Invalid input. Should be: merge, warn or error",
+            )),
+        }
+    }
+}
+
+/// Distance under which two bodies of a scene are considered duplicates
+pub const DUPLICATE_EPSILON: f32 = 1e-6;
+
+/// Net momentum, relative to the sum of the momenta magnitudes, above which the scene is likely wrong
+pub const NET_MOMENTUM_TOLERANCE: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Suspicious, the scene can still be simulated
+    Warning,
+    /// The simulation would produce garbage
+    Error,
+}
+
+/// Physical sanity issue of a scene, bodies are referred to by index
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    NegativeMass(usize),
+    /// Massless bodies are test particles, which don't attract the others
+    ZeroMass(usize),
+    /// NaN or infinite position or velocity
+    NonFinite(usize),
+    /// Bodies closer than `DUPLICATE_EPSILON`, handled by the `DuplicatePolicy`
+    Coincident(usize, usize),
+    /// Net momentum relative to the sum of the momenta magnitudes,
+    /// most scenes are given in their barycentric frame
+    NetMomentum(f32),
+    /// Time step of the simulation parameters which is not a positive number
+    InvalidTimeStep(f32),
+}
+
+impl ValidationIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            ValidationIssue::NegativeMass(_)
+            | ValidationIssue::NonFinite(_)
+            | ValidationIssue::InvalidTimeStep(_) => Severity::Error,
+            ValidationIssue::ZeroMass(_)
+            | ValidationIssue::Coincident(..)
+            | ValidationIssue::NetMomentum(_) => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationIssue::NegativeMass(i) => write!(f, "body {} has a negative mass", i),
+            ValidationIssue::ZeroMass(i) => {
+                write!(f, "body {} is massless and won't attract the others", i)
+            }
+            ValidationIssue::NonFinite(i) => {
+                write!(f, "body {} has a NaN or infinite position or velocity", i)
+            }
+            ValidationIssue::Coincident(i, j) => {
+                write!(f, "bodies {} and {} are at the same position", i, j)
+            }
+            ValidationIssue::NetMomentum(relative) => write!(
+                f,
+                "the net momentum is {:.0}% of the total, the scene may not be in its barycentric frame",
+                100.0 * relative
+            ),
+            ValidationIssue::InvalidTimeStep(dt) => {
+                write!(f, "the time step {} is not a positive number", dt)
+            }
+        }
+    }
+}
+
+/// Checks a scene for common data errors before simulating it
+pub fn validate_scene(scene: &SceneDescription) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    for (i, body) in scene.bodies.iter().enumerate() {
+        if body.mass < 0.0 {
+            issues.push(ValidationIssue::NegativeMass(i));
+        } else if body.mass == 0.0 {
+            issues.push(ValidationIssue::ZeroMass(i));
+        }
+        let (pos, vel) = body.scaled_state(1.0);
+        if !body.mass.is_finite() || !pos.is_finite() || !vel.is_finite() {
+            issues.push(ValidationIssue::NonFinite(i));
+        }
+    }
+    issues.extend(
+        scene
+            .duplicates(DUPLICATE_EPSILON)
+            .into_iter()
+            .map(|(i, j)| ValidationIssue::Coincident(i, j)),
+    );
+
+    let (momentum, magnitudes) = scene
+        .bodies
+        .iter()
+        .map(|body| body.mass * body.scaled_state(1.0).1)
+        .filter(|p| p.is_finite())
+        .fold((Vec3::ZERO, 0.0), |(momentum, magnitudes), p| {
+            (momentum + p, magnitudes + p.length())
+        });
+    if magnitudes > 0.0 && momentum.length() / magnitudes > NET_MOMENTUM_TOLERANCE {
+        issues.push(ValidationIssue::NetMomentum(momentum.length() / magnitudes));
+    }
+    if let Some(dt) = scene.simulation.as_ref().and_then(|params| params.dt) {
+        // NaN fails the comparison too
+        if !(dt > 0.0 && dt.is_finite()) {
+            issues.push(ValidationIssue::InvalidTimeStep(dt));
+        }
+    }
+    issues
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Ron(ron::Error),
+    Json(serde_json::Error),
+    UnknownFormat(String),
+    /// Indices of two bodies at the same position
+    Duplicates(usize, usize),
+    /// First `Severity::Error` issue found by `validate_scene`
+    Invalid(ValidationIssue),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "cannot read the scene: {}", e),
+            SceneError::Ron(e) => write!(f, "invalid RON scene: {}", e),
+            SceneError::Json(e) => write!(f, "invalid JSON scene: {}", e),
+            SceneError::UnknownFormat(ext) => {
+                write!(f, "unknown scene format {:?}, should be ron or json", ext)
+            }
+            SceneError::Duplicates(i, j) => {
+                write!(f, "bodies {} and {} are at the same position", i, j)
+            }
+            SceneError::Invalid(issue) => write!(f, "invalid scene: {}", issue),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl SceneDescription {
+    pub fn from_ron(s: &str) -> Result<Self, SceneError> {
+        ron::from_str(s).map_err(SceneError::Ron)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, SceneError> {
+        serde_json::from_str(s).map_err(SceneError::Json)
+    }
+
+    /// Loads a `.ron` or `.json` scene file, rejected if `validate_scene` finds any error
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(SceneError::Io)?;
+        let scene = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ron") => Self::from_ron(&content),
+            Some("json") => Self::from_json(&content),
+            ext => Err(SceneError::UnknownFormat(
+                ext.unwrap_or_default().to_string(),
+            )),
+        }?;
+        match validate_scene(&scene)
+            .into_iter()
+            .find(|issue| issue.severity() == Severity::Error)
+        {
+            Some(issue) => Err(SceneError::Invalid(issue)),
+            None => Ok(scene),
+        }
+    }
+
+    /// Pairs of bodies closer than `epsilon` to each other, by index
+    pub fn duplicates(&self, epsilon: f32) -> Vec<(usize, usize)> {
+        let mut duplicates = Vec::new();
+        for (i, a) in self.bodies.iter().enumerate() {
+            for (j, b) in self.bodies.iter().enumerate().skip(i + 1) {
+                if a.scaled_state(1.0).0.distance(b.scaled_state(1.0).0) < epsilon {
+                    duplicates.push((i, j));
+                }
+            }
+        }
+        duplicates
+    }
+
+    /// Applies the `policy` to the bodies closer than `epsilon` to each other
+    /// and returns the duplicates found, by index in the scene as loaded.
+    /// Merged bodies keep the description of the first one.
+    pub fn sanitize(
+        &mut self,
+        epsilon: f32,
+        policy: DuplicatePolicy,
+    ) -> Result<Vec<(usize, usize)>, SceneError> {
+        let duplicates = self.duplicates(epsilon);
+        match (policy, duplicates.first()) {
+            (_, None) | (DuplicatePolicy::Warn, _) => return Ok(duplicates),
+            (DuplicatePolicy::Error, Some((i, j))) => return Err(SceneError::Duplicates(*i, *j)),
+            (DuplicatePolicy::Merge, _) => {}
+        }
+
+        let mut bodies: Vec<Option<BodyDescription>> = self.bodies.drain(..).map(Some).collect();
+        for (i, j) in duplicates.iter() {
+            // Skip the bodies already merged into another one
+            if bodies[*i].is_none() {
+                continue;
+            }
+            if let Some(b) = bodies[*j].take() {
+                if let Some(a) = bodies[*i].as_mut() {
+                    a.merge(&b);
+                }
+            }
+        }
+        self.bodies = bodies.into_iter().flatten().collect();
+        Ok(duplicates)
+    }
+
+    /// Rendering scale of the scene, 1 by default
+    pub fn scale(&self) -> f32 {
+        self.simulation
+            .as_ref()
+            .and_then(|params| params.scale)
+            .unwrap_or(1.0)
+    }
+
+    /// Copy of the scene with the position of the `body`-th body offset by `delta`, in the units of the scene,
+    /// e.g. to study the sensitivity to the initial conditions. `None` if there's no such body.
+    pub fn perturbed(&self, body: usize, delta: Vec3) -> Option<Self> {
+        let mut scene = self.clone();
+        let perturbed = scene.bodies.get_mut(body)?;
+        let (x, y, z) = perturbed.position;
+        perturbed.position = (x + delta.x, y + delta.y, z + delta.z);
+        Some(scene)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(mass: f32, position: (f32, f32, f32), velocity: (f32, f32, f32)) -> BodyDescription {
+        BodyDescription {
+            name: None,
+            mass,
+            position,
+            velocity,
+            radius: default_radius(),
+            color: None,
+            texture: None,
+            star: false,
+            comet: false,
+            accretor: false,
+            trail: None,
+            temperature: None,
+        }
+    }
+
+    #[test]
+    fn scene_parameters_override_the_resources() {
+        let path = std::env::temp_dir().join("nbody_scene_parameters.ron");
+        fs::write(
+            &path,
+            "(
+                simulation: Some((g: Some(1.0), dt: Some(0.005), integrator: Some(Leapfrog))),
+                bodies: [(mass: 1.0, position: (0.0, 0.0, 0.0))],
+            )",
+        )
+        .unwrap();
+        let scene = SceneDescription::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            scene.simulation.as_ref().unwrap().time_step(),
+            Some(TimeStep(0.005))
+        );
+
+        let mut g = Gravity::default();
+        let mut softening = SofteningKernel::Plummer { eps: 0.1 };
+        let mut integrator = Integrator::default();
+        scene
+            .simulation
+            .unwrap()
+            .apply(&mut g, &mut softening, &mut integrator);
+        assert_eq!(g.0, 1.0);
+        assert_eq!(integrator, Integrator::Leapfrog);
+        // Missing parameters are left unchanged
+        assert_eq!(softening, SofteningKernel::Plummer { eps: 0.1 });
+        assert_eq!(
+            scene.bodies,
+            vec![body(1.0, (0.0, 0.0, 0.0), (0.0, 0.0, 0.0))]
+        );
+    }
+
+    #[test]
+    fn scene_collision_parameters_override_the_resources() {
+        let scene = SceneDescription::from_ron(
+            "(
+                simulation: Some((
+                    collisions: Some(Fragment(threshold: 0.5, pieces: 8)),
+                    continuous_collision: Some(true),
+                    tidal_disruption: Some((enabled: true, pieces: 3)),
+                )),
+                bodies: [],
+            )",
+        )
+        .unwrap();
+
+        let mut mode = CollisionMode::default();
+        let mut continuous = ContinuousCollision::default();
+        let mut tidal_disruption = TidalDisruption::default();
+        scene.simulation.unwrap().apply_collisions(
+            &mut mode,
+            &mut continuous,
+            &mut tidal_disruption,
+        );
+        assert_eq!(
+            mode,
+            CollisionMode::Fragment {
+                threshold: 0.5,
+                pieces: 8
+            }
+        );
+        assert_eq!(continuous, ContinuousCollision(true));
+        assert_eq!(
+            tidal_disruption,
+            TidalDisruption {
+                enabled: true,
+                pieces: 3,
+                spread: 1.0
+            }
+        );
+    }
+
+    #[test]
+    fn coincident_bodies_follow_the_duplicate_policy() {
+        let scene = SceneDescription::from_ron(
+            "(bodies: [
+                (mass: 1.0, position: (0.0, 0.0, 0.0), velocity: (1.0, 0.0, 0.0)),
+                (mass: 3.0, position: (0.0, 0.0, 1e-7), velocity: (-1.0, 0.0, 0.0)),
+                (mass: 1.0, position: (5.0, 0.0, 0.0)),
+            ])",
+        )
+        .unwrap();
+
+        let mut merged = scene.clone();
+        assert_eq!(
+            merged
+                .sanitize(DUPLICATE_EPSILON, DuplicatePolicy::Merge)
+                .unwrap(),
+            vec![(0, 1)]
+        );
+        assert_eq!(merged.bodies.len(), 2);
+        assert_eq!(merged.bodies[0].mass, 4.0);
+        assert_eq!(merged.bodies[0].velocity, (-0.5, 0.0, 0.0));
+        assert_eq!(merged.bodies[1], scene.bodies[2]);
+
+        let mut kept = scene.clone();
+        assert_eq!(
+            kept.sanitize(DUPLICATE_EPSILON, DuplicatePolicy::Warn)
+                .unwrap(),
+            vec![(0, 1)]
+        );
+        assert_eq!(kept, scene);
+
+        let mut rejected = scene.clone();
+        assert!(matches!(
+            rejected.sanitize(DUPLICATE_EPSILON, DuplicatePolicy::Error),
+            Err(SceneError::Duplicates(0, 1))
+        ));
+    }
+
+    fn issues(bodies: Vec<BodyDescription>) -> Vec<ValidationIssue> {
+        validate_scene(&SceneDescription {
+            simulation: None,
+            bodies,
+        })
+    }
+
+    #[test]
+    fn validation_reports_each_issue() {
+        // Two bodies orbiting their barycenter
+        let sane = || {
+            vec![
+                body(1.0, (-1.0, 0.0, 0.0), (0.0, -0.5, 0.0)),
+                body(1.0, (1.0, 0.0, 0.0), (0.0, 0.5, 0.0)),
+            ]
+        };
+        assert!(issues(sane()).is_empty());
+
+        let mut bodies = sane();
+        bodies.push(body(-1.0, (5.0, 0.0, 0.0), (0.0, 0.0, 0.0)));
+        bodies.push(body(0.0, (0.0, 5.0, 0.0), (0.0, 0.0, 0.0)));
+        assert_eq!(
+            issues(bodies),
+            vec![
+                ValidationIssue::NegativeMass(2),
+                ValidationIssue::ZeroMass(3)
+            ]
+        );
+
+        let mut bodies = sane();
+        bodies[1].position.1 = f32::NAN;
+        assert_eq!(issues(bodies), vec![ValidationIssue::NonFinite(1)]);
+
+        let mut bodies = sane();
+        bodies.push(body(0.0, (1.0, 0.0, 0.0), (0.0, 0.0, 0.0)));
+        assert_eq!(
+            issues(bodies),
+            vec![
+                ValidationIssue::ZeroMass(2),
+                ValidationIssue::Coincident(1, 2)
+            ]
+        );
+
+        let mut bodies = sane();
+        bodies[0].velocity.1 = 0.5;
+        assert_eq!(issues(bodies), vec![ValidationIssue::NetMomentum(1.0)]);
+
+        let scene = SceneDescription {
+            simulation: Some(SimulationParams {
+                dt: Some(-0.01),
+                ..Default::default()
+            }),
+            bodies: sane(),
+        };
+        let expected = ValidationIssue::InvalidTimeStep(-0.01);
+        assert_eq!(validate_scene(&scene), vec![expected.clone()]);
+        assert_eq!(expected.severity(), Severity::Error);
+    }
+
+    #[test]
+    fn only_errors_reject_a_scene() {
+        assert_eq!(ValidationIssue::NegativeMass(0).severity(), Severity::Error);
+        assert_eq!(ValidationIssue::NonFinite(0).severity(), Severity::Error);
+        assert_eq!(ValidationIssue::ZeroMass(0).severity(), Severity::Warning);
+        assert_eq!(
+            ValidationIssue::Coincident(0, 1).severity(),
+            Severity::Warning
+        );
+        assert_eq!(
+            ValidationIssue::NetMomentum(1.0).severity(),
+            Severity::Warning
+        );
+    }
+
+    #[test]
+    fn perturbed_scene_differs_by_delta_only() {
+        let scene = SceneDescription {
+            simulation: Some(SimulationParams {
+                g: Some(2.0),
+                ..Default::default()
+            }),
+            bodies: vec![
+                body(1.0, (0.5, 0.0, 0.0), (0.0, 1.0, 0.0)),
+                body(2.0, (-1.0, 0.25, 0.0), (0.0, -0.5, 0.0)),
+            ],
+        };
+        let delta = Vec3::new(0.125, -0.0625, 1.0 / 1024.0);
+        let replica = scene.perturbed(1, delta).unwrap();
+
+        assert_eq!(replica.simulation, scene.simulation);
+        assert_eq!(replica.bodies[0], scene.bodies[0]);
+        let mut twin = replica.bodies[1].clone();
+        assert_eq!(
+            Vec3::from(twin.position) - Vec3::from(scene.bodies[1].position),
+            delta
+        );
+        twin.position = scene.bodies[1].position;
+        assert_eq!(twin, scene.bodies[1]);
+        assert_eq!(scene.perturbed(2, delta), None);
+    }
+}
@@ -0,0 +1,22 @@
+//! Helpers of the unit tests running systems on a bare `World`, without Bevy's render loop
+use bevy::asset::AssetPlugin;
+use bevy::ecs::schedule::SystemDescriptor;
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, TaskPool};
+
+/// World with the `Assets` of the meshes and materials, for the systems spawning rendered bodies
+pub fn world_with_assets() -> World {
+    let mut app = App::build();
+    app.insert_resource(IoTaskPool(TaskPool::new()))
+        .add_plugin(AssetPlugin)
+        .add_asset::<Mesh>()
+        .add_asset::<StandardMaterial>();
+    std::mem::take(&mut app.app.world)
+}
+
+/// Runs the system once, applying its commands
+pub fn run_system(world: &mut World, system: impl Into<SystemDescriptor>) {
+    SystemStage::single_threaded()
+        .with_system(system)
+        .run(world);
+}